@@ -1,4 +1,5 @@
 use chrono::NaiveDateTime;
+use repository::NotificationPriority;
 use serde::Serialize;
 use service::{
     notification::{
@@ -51,6 +52,14 @@ pub struct ColdchainAlert {
     pub old_status: Option<SensorStatus>,
 }
 
+/// A fridge that's too hot or cold needs someone now, the others can wait their turn
+pub fn alert_priority(alert_type: &AlertType) -> NotificationPriority {
+    match alert_type {
+        AlertType::High | AlertType::Low => NotificationPriority::Critical,
+        AlertType::Ok | AlertType::NoData => NotificationPriority::Normal,
+    }
+}
+
 // Later this function probably won't exist, but serves as a reminder/POC...
 pub fn queue_temperature_alert(
     ctx: &ServiceContext,
@@ -81,6 +90,7 @@ pub fn queue_temperature_alert(
         AlertType::NoData => TemplateDefinition::TemplateName("coldchain/no_data.md".to_string()),
     };
 
+    let priority = alert_priority(&alert.alert_type);
     let notification = NotificationContext {
         title_template,
         body_template,
@@ -91,6 +101,9 @@ pub fn queue_temperature_alert(
                 e
             ))
         })?,
+        attachments: vec![],
+        email_options: Default::default(),
+        priority,
     };
 
     create_notification_events(ctx, config_id, notification)
@@ -103,7 +116,6 @@ mod tests {
     use repository::{
         mock::MockDataInserts, test_db::setup_all, NotificationEventRowRepository, NotificationType,
     };
-    use service::test_utils::email_test::send_test_emails;
     use service::test_utils::get_test_settings;
     use service::test_utils::telegram_test::get_default_telegram_chat_id;
     use service::test_utils::telegram_test::send_test_notifications;
@@ -181,7 +193,6 @@ mod tests {
             .contains(&example_alert.store_name));
 
         send_test_notifications(&context).await;
-        send_test_emails(&context);
     }
 
     #[tokio::test]
@@ -249,7 +260,6 @@ mod tests {
             .contains(&example_alert.store_name));
 
         send_test_notifications(&context).await;
-        send_test_emails(&context);
     }
 
     #[tokio::test]
@@ -317,6 +327,5 @@ mod tests {
             .contains(&example_alert.store_name));
 
         send_test_notifications(&context).await;
-        send_test_emails(&context);
     }
 }
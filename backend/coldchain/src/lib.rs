@@ -1,16 +1,16 @@
 use service::{
-    plugin::{PluginError, PluginTrait},
+    notification_config::query::NotificationConfig,
+    plugin::{PluginAlarm, PluginError, PluginTrait},
     service_provider::ServiceContext,
 };
 
 mod test;
 
 pub mod alerts;
-pub mod latest_temperature;
 pub mod parse;
 pub mod process;
-pub mod sensor_info;
 pub mod sensor_state;
+pub mod status;
 
 const PLUGIN_NAME: &str = "ColdChain";
 
@@ -56,6 +56,15 @@ impl PluginTrait for ColdChainPlugin {
             }
         }
     }
+
+    fn current_alarms(
+        &self,
+        ctx: &ServiceContext,
+        config: &NotificationConfig,
+    ) -> Result<Vec<PluginAlarm>, PluginError> {
+        status::current_alarms(ctx, config)
+            .map_err(|e| PluginError::UnableToGetAlarms(format!("{:?}", e)))
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +99,70 @@ mod tests {
         let result = plugin.tick(&ctx);
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn cold_chain_plugin_current_alarms() {
+        let (_, _, connection_manager, _) =
+            setup_all("cold_chain_plugin_current_alarms", MockDataInserts::none()).await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let ctx = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let config = NotificationConfig {
+            id: "config_id".to_string(),
+            kind: repository::NotificationConfigKind::ColdChain,
+            configuration_data: r#"{"sensorIds": ["sensor_ok", "sensor_hot", "sensor_unchecked"]}"#
+                .to_string(),
+            ..Default::default()
+        };
+        let since_utc =
+            chrono::NaiveDateTime::parse_from_str("2024-03-25 01:30:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap();
+        for (sensor_id, status) in [
+            ("sensor_ok", sensor_state::SensorStatus::Ok),
+            ("sensor_hot", sensor_state::SensorStatus::HighTemp),
+        ] {
+            let state = sensor_state::SensorState {
+                sensor_id: sensor_id.to_string(),
+                status,
+                temperature: Some(9.46),
+                status_start_utc: since_utc,
+                ..Default::default()
+            };
+            ctx.service_provider
+                .plugin_service
+                .set_value(
+                    &ctx,
+                    PLUGIN_NAME.to_string(),
+                    process::sensor_status_key(sensor_id, &config.id),
+                    state.to_json_string().unwrap(),
+                )
+                .unwrap();
+        }
+
+        let plugin = ColdChainPlugin::new();
+        let alarms = plugin.current_alarms(&ctx, &config).unwrap();
+        // Without sensor info from the datasource, sensors are named by their id
+        assert_eq!(
+            alarms,
+            vec![PluginAlarm {
+                name: "sensor_hot".to_string(),
+                description: "High temperature (9.5°C)".to_string(),
+                since_utc,
+            }]
+        );
+
+        // Other kinds of notification config don't have cold chain alarms
+        let scheduled_config = NotificationConfig {
+            kind: repository::NotificationConfigKind::Scheduled,
+            ..config
+        };
+        assert!(plugin
+            .current_alarms(&ctx, &scheduled_config)
+            .unwrap()
+            .is_empty());
+    }
 }
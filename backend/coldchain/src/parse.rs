@@ -67,6 +67,9 @@ pub struct ColdChainPluginConfig {
     pub reminder_interval: u32,
     #[serde(default = "default_reminder_units")]
     pub reminder_units: IntervalUnits,
+    /// Named datasource the sensors are read from, None uses the default datasource
+    #[serde(default)]
+    pub datasource_id: Option<String>,
 }
 
 fn default_low_temp_limit() -> f64 {
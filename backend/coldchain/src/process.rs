@@ -1,4 +1,5 @@
 use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use datasource::{LatestTemperatureRow, SensorInfoRow};
 use repository::{
     NotificationConfigKind, NotificationConfigRowRepository, NotificationConfigStatus,
 };
@@ -8,10 +9,8 @@ use service::{
 };
 
 use crate::{
-    alerts::{queue_temperature_alert, AlertType, ColdchainAlert},
-    latest_temperature::{self, latest_temperature},
+    alerts::{alert_priority, queue_temperature_alert, AlertType, ColdchainAlert},
     parse::ColdChainPluginConfig,
-    sensor_info::{sensor_info, SensorInfoRow},
     sensor_state::{SensorState, SensorStatus},
     ColdChainError, PLUGIN_NAME,
 };
@@ -59,6 +58,11 @@ pub fn process_coldchain_alerts(
     Ok(num_configs)
 }
 
+/// Plugin store key for the sensor's state in a cold chain config
+pub fn sensor_status_key(sensor_id: &str, notification_config_id: &str) -> String {
+    format!("sensor_status_{}_{}", sensor_id, notification_config_id)
+}
+
 enum ProcessingResult {
     Success,
 }
@@ -93,15 +97,16 @@ fn try_process_coldchain_notifications(
     // Loop through checking the current status for each sensor
     for sensor_id in config.sensor_ids.clone() {
         // Get the latest temperature for the sensor
-        let mut connection = ctx
+        let mut datasource_connection = ctx
             .service_provider
             .datasource_service
-            .get_connection_pool()
-            .pool
+            .get_connection_pool(config.datasource_id.clone())
+            .map_err(|e| ColdChainError::InternalError(format!("{:?}", e)))?
             .get()
             .map_err(|e| ColdChainError::InternalError(format!("{:?}", e)))?;
 
-        let latest_temperature_row = latest_temperature(&mut connection, sensor_id.clone())
+        let latest_temperature_row = datasource_connection
+            .latest_temperature(&sensor_id)
             .map_err(|e| {
                 ColdChainError::InternalError(format!(
                     "Failed to get latest temperature for sensor {}: {:?}",
@@ -113,7 +118,7 @@ fn try_process_coldchain_notifications(
         // This means that the same sensor can alarm in two different configs
         // And duplicate notifications would be sent, e.g. if your email address is in two configuration & you have the same sensor in both
         // Future deduplication efforts could be considered for this...
-        let sensor_status_key = format!("sensor_status_{}_{}", sensor_id, notification_config.id);
+        let sensor_status_key = sensor_status_key(&sensor_id, &notification_config.id);
 
         // Check if the status has changed since the last time we checked
         let prev_sensor_state = ctx
@@ -152,7 +157,7 @@ fn try_process_coldchain_notifications(
         };
 
         // Get Sensor information from the database to use in alerts
-        let sensor_row = sensor_info(&mut connection, sensor_id.clone()).map_err(|e| {
+        let sensor_row = datasource_connection.sensor_info(&sensor_id).map_err(|e| {
             ColdChainError::InternalError(format!(
                 "Failed to get sensor info from the database {}: {:?}",
                 sensor_id, e
@@ -209,23 +214,24 @@ fn try_process_coldchain_notifications(
     // TODO: Suppress too many notifications in a short period of time
     // https://github.com/openmsupply/notify/issues/177
 
-    // look up the recipients for the notification config
-    let notification_targets = get_notification_targets(
-        ctx,
-        &notification_config,
-        serde_json::Value::Null,
-    )
-    .map_err(|e| {
-        ColdChainError::InternalError(format!("Failed to get notification targets: {:?}", e))
-    })?;
-
     for alert in alerts {
+        // look up the recipients for the notification config, muted recipients still get critical alerts so they depend on the alert
+        let notification_targets = get_notification_targets(
+            ctx,
+            &notification_config,
+            serde_json::Value::Null,
+            alert_priority(&alert.alert_type),
+        )
+        .map_err(|e| {
+            ColdChainError::InternalError(format!("Failed to get notification targets: {:?}", e))
+        })?;
+
         // Send the notifications
         let result = queue_temperature_alert(
             ctx,
             Some(notification_config.id.clone()),
             alert,
-            notification_targets.clone(),
+            notification_targets,
         );
         match result {
             Ok(_) => {
@@ -245,7 +251,7 @@ pub fn try_process_sensor_notification(
     prev_sensor_state: Option<SensorState>,
     sensor_row: SensorInfoRow,
     now_local: NaiveDateTime,
-    latest_temperature_row: Option<latest_temperature::LatestTemperatureRow>,
+    latest_temperature_row: Option<LatestTemperatureRow>,
 ) -> (SensorState, Option<ColdchainAlert>) {
     // If we don't have a previous state, we'll assume the sensor was previously in the `Ok` state
     let prev_sensor_state = match prev_sensor_state {
@@ -447,7 +453,7 @@ pub fn try_process_sensor_notification(
 
 pub fn evaluate_sensor_status(
     now: NaiveDateTime,
-    latest_temperature_row: Option<latest_temperature::LatestTemperatureRow>,
+    latest_temperature_row: Option<LatestTemperatureRow>,
     high_temp_threshold: f64,
     low_temp_threshold: f64,
     max_age: chrono::Duration,
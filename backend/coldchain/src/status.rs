@@ -0,0 +1,91 @@
+use repository::NotificationConfigKind;
+use service::{
+    notification_config::query::NotificationConfig, plugin::PluginAlarm,
+    service_provider::ServiceContext,
+};
+
+use crate::{
+    parse::ColdChainPluginConfig,
+    process::sensor_status_key,
+    sensor_state::{SensorState, SensorStatus},
+    ColdChainError, PLUGIN_NAME,
+};
+
+/// Sensors that aren't Ok, based on the state saved the last time the config was processed
+pub fn current_alarms(
+    ctx: &ServiceContext,
+    notification_config: &NotificationConfig,
+) -> Result<Vec<PluginAlarm>, ColdChainError> {
+    if notification_config.kind != NotificationConfigKind::ColdChain {
+        return Ok(vec![]);
+    }
+    let config = ColdChainPluginConfig::from_string(&notification_config.configuration_data)?;
+
+    let mut alarms = Vec::new();
+    for sensor_id in config.sensor_ids {
+        let sensor_state = ctx
+            .service_provider
+            .plugin_service
+            .get_value(
+                ctx,
+                PLUGIN_NAME.to_string(),
+                sensor_status_key(&sensor_id, &notification_config.id),
+            )
+            .map_err(|e| {
+                ColdChainError::InternalError(format!(
+                    "Failed to get state for sensor {}: {:?}",
+                    sensor_id, e
+                ))
+            })?;
+        // Sensors without a saved state haven't been checked yet
+        let sensor_state = match sensor_state {
+            Some(sensor_state) => SensorState::from_string(&sensor_state)?,
+            None => continue,
+        };
+
+        let description = match sensor_state.status {
+            SensorStatus::Ok => continue,
+            SensorStatus::HighTemp => "High temperature",
+            SensorStatus::LowTemp => "Low temperature",
+            SensorStatus::NoData => "No data",
+        };
+        let description = match sensor_state.temperature {
+            Some(temperature) if sensor_state.status != SensorStatus::NoData => {
+                format!("{} ({:.1}°C)", description, temperature)
+            }
+            _ => description.to_string(),
+        };
+
+        alarms.push(PluginAlarm {
+            name: sensor_name(ctx, &config.datasource_id, &sensor_id),
+            description,
+            since_utc: sensor_state.status_start_utc,
+        });
+    }
+
+    Ok(alarms)
+}
+
+/// Looks up the sensor's name in the datasource, falling back to its id if that isn't possible
+fn sensor_name(ctx: &ServiceContext, datasource_id: &Option<String>, sensor_id: &str) -> String {
+    let sensor_row = ctx
+        .service_provider
+        .datasource_service
+        .get_connection_pool(datasource_id.clone())
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|pool| pool.get().map_err(|e| format!("{:?}", e)))
+        .and_then(|mut connection| {
+            connection
+                .sensor_info(sensor_id)
+                .map_err(|e| format!("{:?}", e))
+        });
+
+    match sensor_row {
+        Ok(Some(sensor_row)) => format!("{} ({})", sensor_row.sensor_name, sensor_row.store_name),
+        Ok(None) => sensor_id.to_string(),
+        Err(e) => {
+            log::error!("Failed to get sensor info for {}: {}", sensor_id, e);
+            sensor_id.to_string()
+        }
+    }
+}
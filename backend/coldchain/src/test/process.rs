@@ -1,11 +1,10 @@
 use chrono::{NaiveDateTime, Utc};
+use datasource::{LatestTemperatureRow, SensorInfoRow};
 
 use crate::{
     alerts::AlertType,
-    latest_temperature::LatestTemperatureRow,
     parse::ColdChainPluginConfig,
     process::{evaluate_sensor_status, try_process_sensor_notification},
-    sensor_info::SensorInfoRow,
     sensor_state::{SensorState, SensorStatus},
 };
 
@@ -147,6 +146,7 @@ fn test_try_process_sensor_notification_prev_ok() {
         remind: true,
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     // Sensor Data
@@ -302,6 +302,7 @@ fn test_try_process_sensor_notification_prev_high() {
         remind: true,
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     // Sensor Data
@@ -455,6 +456,7 @@ fn test_try_process_sensor_notification_prev_low() {
         remind: true,
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     // Sensor Data
@@ -608,6 +610,7 @@ fn test_try_process_sensor_notification_prev_no_data() {
         remind: true,
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     // Sensor Data
@@ -752,6 +755,7 @@ fn test_try_process_sensor_notification_no_data_reminder() {
         remind: true,
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     // Sensor Data
@@ -832,6 +836,7 @@ fn test_try_process_sensor_notification_no_data_reminder() {
         remind: false, // Reminders disabled!
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     /*
@@ -878,6 +883,7 @@ fn test_try_process_sensor_notification_high_temp_reminder() {
         remind: true,
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     // Sensor Data
@@ -1038,6 +1044,7 @@ fn test_try_process_sensor_notification_high_temp_reminder() {
         remind: false, // Reminders disabled!
         reminder_interval: 1,
         reminder_units: service::notification_config::intervals::IntervalUnits::Hours,
+        datasource_id: None,
     };
 
     /*
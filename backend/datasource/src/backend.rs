@@ -0,0 +1,244 @@
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use diesel::sql_query;
+use diesel::sql_types::Text;
+
+use crate::coldchain::{
+    latest_temperature_query, sensor_info_query, LatestTemperatureRow, SensorInfoRow,
+};
+use crate::query_limits::{collect_json_rows, map_query_error, QueryError, QueryLimits};
+
+/// The database specific parts of running an admin supplied query against a datasource
+pub trait DatasourceBackendConnection: Connection {
+    /// Stops queries on this connection from modifying the datasource
+    fn set_read_only(&mut self) -> Result<(), DieselError>;
+
+    /// Called at the start of the transaction each query runs in
+    fn set_statement_timeout(&mut self, timeout_seconds: Option<u32>) -> Result<(), DieselError>;
+
+    /// Runs the query, returning each row as a json object.
+    /// Rows are read one at a time, so the query is stopped as soon as it's over `max_result_bytes`
+    fn load_json_rows(
+        &mut self,
+        sql_select_query: &str,
+        row_limit_clause: &str,
+        limits: &QueryLimits,
+    ) -> Result<Vec<serde_json::Value>, QueryError>;
+
+    /// The sensor's most recent temperature log, for cold chain monitoring
+    fn latest_temperature(
+        &mut self,
+        sensor_id: &str,
+    ) -> Result<Option<LatestTemperatureRow>, DieselError>;
+
+    /// The sensor's name, store and location, for cold chain monitoring
+    fn sensor_info(&mut self, sensor_id: &str) -> Result<Option<SensorInfoRow>, DieselError>;
+
+    /// Runs the provided function in a transaction with the configured statement timeout
+    fn with_statement_timeout<T, F>(&mut self, limits: &QueryLimits, f: F) -> Result<T, QueryError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, QueryError>,
+    {
+        self.transaction(|connection| {
+            connection
+                .set_statement_timeout(limits.timeout_seconds)
+                .map_err(|error| map_query_error(error, limits))?;
+            f(connection)
+        })
+    }
+}
+
+/// Wraps the provided query so each row can be loaded as a `JsonObjectRow`
+#[cfg(any(feature = "sqlite", feature = "mysql"))]
+fn json_object_rows_query(sql_select_query: &str, row_limit_clause: &str) -> String {
+    format!(
+        "WITH provided_query AS(
+        {}
+        ) SELECT * FROM provided_query {};",
+        sql_select_query, row_limit_clause
+    )
+}
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::*;
+    use crate::json_query::JsonDataRow;
+    use diesel::connection::SimpleConnection;
+    use diesel::pg::{PgConnection, PgRowByRowLoadingMode};
+
+    impl DatasourceBackendConnection for PgConnection {
+        fn set_read_only(&mut self) -> Result<(), DieselError> {
+            self.batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY;")
+        }
+
+        fn set_statement_timeout(
+            &mut self,
+            timeout_seconds: Option<u32>,
+        ) -> Result<(), DieselError> {
+            if let Some(timeout_seconds) = timeout_seconds {
+                // SET LOCAL only lasts until the end of the transaction, so pooled connections are left untouched
+                self.batch_execute(&format!(
+                    "SET LOCAL statement_timeout = {};",
+                    u64::from(timeout_seconds) * 1000
+                ))?;
+            }
+            Ok(())
+        }
+
+        fn load_json_rows(
+            &mut self,
+            sql_select_query: &str,
+            row_limit_clause: &str,
+            limits: &QueryLimits,
+        ) -> Result<Vec<serde_json::Value>, QueryError> {
+            let json_row_sql_query = format!(
+                "WITH provided_query AS(
+        {}
+        ) SELECT row_to_json(provided_query) as data FROM provided_query {};",
+                sql_select_query, row_limit_clause
+            );
+
+            // Row by row, otherwise libpq fetches the whole result before the first row is read
+            let rows = sql_query(&json_row_sql_query)
+                .load_iter::<JsonDataRow, PgRowByRowLoadingMode>(self)
+                .map_err(|error| map_query_error(error, limits))?
+                .map(|row| row.map(|row| row.data));
+            collect_json_rows(rows, limits)
+        }
+
+        fn latest_temperature(
+            &mut self,
+            sensor_id: &str,
+        ) -> Result<Option<LatestTemperatureRow>, DieselError> {
+            sql_query(latest_temperature_query(
+                "CONCAT(TO_CHAR(date,'YYYY-MM-DD'),' ', TO_CHAR(time,'HH24:MI:SS'))::timestamp",
+                "$1",
+            ))
+            .bind::<Text, _>(sensor_id)
+            .get_result(self)
+            .optional()
+        }
+
+        fn sensor_info(&mut self, sensor_id: &str) -> Result<Option<SensorInfoRow>, DieselError> {
+            sql_query(sensor_info_query("$1"))
+                .bind::<Text, _>(sensor_id)
+                .get_result(self)
+                .optional()
+        }
+    }
+}
+
+#[cfg(feature = "mysql")]
+mod mysql {
+    use super::*;
+    use crate::JsonObjectRow;
+    use diesel::connection::{DefaultLoadingMode, SimpleConnection};
+    use diesel::mysql::MysqlConnection;
+
+    impl DatasourceBackendConnection for MysqlConnection {
+        fn set_read_only(&mut self) -> Result<(), DieselError> {
+            self.batch_execute("SET SESSION TRANSACTION READ ONLY;")
+        }
+
+        fn set_statement_timeout(
+            &mut self,
+            timeout_seconds: Option<u32>,
+        ) -> Result<(), DieselError> {
+            // There is no transaction scoped timeout, so it's set (or cleared with 0) for every query
+            let timeout_seconds = u64::from(timeout_seconds.unwrap_or(0));
+            // MySQL and MariaDB use different variables for the timeout
+            self.batch_execute(&format!(
+                "SET SESSION max_execution_time = {};",
+                timeout_seconds * 1000
+            ))
+            .or_else(|_| {
+                self.batch_execute(&format!(
+                    "SET SESSION max_statement_time = {};",
+                    timeout_seconds
+                ))
+            })
+        }
+
+        fn load_json_rows(
+            &mut self,
+            sql_select_query: &str,
+            row_limit_clause: &str,
+            limits: &QueryLimits,
+        ) -> Result<Vec<serde_json::Value>, QueryError> {
+            let rows = sql_query(json_object_rows_query(sql_select_query, row_limit_clause))
+                .load_iter::<JsonObjectRow, DefaultLoadingMode>(self)
+                .map_err(|error| map_query_error(error, limits))?
+                .map(|row| row.map(|row| row.0));
+            collect_json_rows(rows, limits)
+        }
+
+        fn latest_temperature(
+            &mut self,
+            sensor_id: &str,
+        ) -> Result<Option<LatestTemperatureRow>, DieselError> {
+            sql_query(latest_temperature_query("TIMESTAMP(date, time)", "?"))
+                .bind::<Text, _>(sensor_id)
+                .get_result(self)
+                .optional()
+        }
+
+        fn sensor_info(&mut self, sensor_id: &str) -> Result<Option<SensorInfoRow>, DieselError> {
+            sql_query(sensor_info_query("?"))
+                .bind::<Text, _>(sensor_id)
+                .get_result(self)
+                .optional()
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use crate::JsonObjectRow;
+    use diesel::connection::{DefaultLoadingMode, SimpleConnection};
+    use diesel::sqlite::SqliteConnection;
+
+    impl DatasourceBackendConnection for SqliteConnection {
+        fn set_read_only(&mut self) -> Result<(), DieselError> {
+            self.batch_execute("PRAGMA query_only = ON;")
+        }
+
+        fn set_statement_timeout(
+            &mut self,
+            _timeout_seconds: Option<u32>,
+        ) -> Result<(), DieselError> {
+            // Sqlite has no statement timeout, `timeout_seconds` isn't applied to sqlite datasources
+            Ok(())
+        }
+
+        fn load_json_rows(
+            &mut self,
+            sql_select_query: &str,
+            row_limit_clause: &str,
+            limits: &QueryLimits,
+        ) -> Result<Vec<serde_json::Value>, QueryError> {
+            let rows = sql_query(json_object_rows_query(sql_select_query, row_limit_clause))
+                .load_iter::<JsonObjectRow, DefaultLoadingMode>(self)
+                .map_err(|error| map_query_error(error, limits))?
+                .map(|row| row.map(|row| row.0));
+            collect_json_rows(rows, limits)
+        }
+
+        fn latest_temperature(
+            &mut self,
+            sensor_id: &str,
+        ) -> Result<Option<LatestTemperatureRow>, DieselError> {
+            sql_query(latest_temperature_query("date || ' ' || time", "?"))
+                .bind::<Text, _>(sensor_id)
+                .get_result(self)
+                .optional()
+        }
+
+        fn sensor_info(&mut self, sensor_id: &str) -> Result<Option<SensorInfoRow>, DieselError> {
+            sql_query(sensor_info_query("?"))
+                .bind::<Text, _>(sensor_id)
+                .get_result(self)
+                .optional()
+        }
+    }
+}
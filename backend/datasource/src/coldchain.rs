@@ -0,0 +1,135 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Nullable, Text, Timestamp};
+
+// Cold chain monitoring reads the mSupply dashboard's sensor tables, each backend runs these queries through `DatasourceBackendConnection`
+
+#[derive(QueryableByName, Debug, PartialEq, Clone)]
+#[diesel(table_name = temperature_data)]
+pub struct LatestTemperatureRow {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Text)]
+    pub sensor_id: String,
+    #[diesel(sql_type = Timestamp)]
+    pub log_datetime: chrono::NaiveDateTime,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub temperature: Option<f64>,
+}
+
+#[derive(QueryableByName, Debug, PartialEq, Clone)]
+#[diesel(table_name = sensor_info)]
+pub struct SensorInfoRow {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Text)]
+    pub store_name: String,
+    #[diesel(sql_type = Text)]
+    pub store_id: String,
+    #[diesel(sql_type = Text)]
+    pub location_name: String,
+    #[diesel(sql_type = Text)]
+    pub sensor_name: String,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub batterylevel: Option<f64>,
+}
+
+/// The sensor's most recent temperature log.
+/// `log_datetime` is the backend's sql for a timestamp from the log's `date` and `time` columns, and `placeholder` binds the sensor id
+pub(crate) fn latest_temperature_query(log_datetime: &str, placeholder: &str) -> String {
+    format!(
+        "SELECT
+    id,
+    sensor_id,
+    {} AS log_datetime,
+    temperature
+    FROM temperature_log
+    WHERE sensor_id = {}
+    AND temperature < 55 -- ignore any obviously bad data see: https://github.com/msupply-foundation/notify/issues/283
+    ORDER BY date DESC, time DESC
+    LIMIT 1",
+        log_datetime, placeholder
+    )
+}
+
+/// The sensor's name, store and location, `placeholder` binds the sensor id
+pub(crate) fn sensor_info_query(placeholder: &str) -> String {
+    format!(
+        "SELECT sn.id as id,
+batterylevel,
+s.name as store_name,
+s.id as store_id,
+coalesce(l.description, '') as location_name,
+sn.name as sensor_name
+FROM SENSOR sn
+JOIN store s ON sn.storeid = s.id
+LEFT JOIN location l on sn.locationid = l.id
+WHERE sn.id = {}
+    LIMIT 1",
+        placeholder
+    )
+}
+
+#[cfg(test)]
+#[cfg(feature = "sqlite")]
+mod sqlite_tests {
+    use super::*;
+    use crate::backend::DatasourceBackendConnection;
+    use diesel::connection::SimpleConnection;
+    use diesel::sqlite::SqliteConnection;
+
+    fn connection() -> SqliteConnection {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        connection
+            .batch_execute(
+                r#"CREATE TABLE store (id TEXT, name TEXT);
+                   CREATE TABLE location (id TEXT, description TEXT);
+                   CREATE TABLE sensor (id TEXT, name TEXT, storeid TEXT, locationid TEXT, batterylevel REAL);
+                   CREATE TABLE temperature_log (id TEXT, sensor_id TEXT, date TEXT, time TEXT, temperature REAL);
+                   INSERT INTO store VALUES ('store1', 'Central Store');
+                   INSERT INTO sensor VALUES ('sensor1', 'Fridge 1', 'store1', NULL, 90);
+                   INSERT INTO temperature_log VALUES ('log1', 'sensor1', '2024-06-01', '10:00:00', 4.5);
+                   INSERT INTO temperature_log VALUES ('log2', 'sensor1', '2024-06-01', '10:15:00', 5.5);
+                   INSERT INTO temperature_log VALUES ('bad_data', 'sensor1', '2024-06-01', '10:30:00', 99);"#,
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_latest_temperature() {
+        let mut connection = connection();
+
+        // Obviously bad data is ignored
+        assert_eq!(
+            connection.latest_temperature("sensor1").unwrap(),
+            Some(LatestTemperatureRow {
+                id: "log2".to_string(),
+                sensor_id: "sensor1".to_string(),
+                log_datetime: chrono::NaiveDate::from_ymd_opt(2024, 6, 1)
+                    .unwrap()
+                    .and_hms_opt(10, 15, 0)
+                    .unwrap(),
+                temperature: Some(5.5),
+            })
+        );
+        assert_eq!(connection.latest_temperature("sensor2").unwrap(), None);
+    }
+
+    #[test]
+    fn test_sensor_info() {
+        let mut connection = connection();
+
+        assert_eq!(
+            connection.sensor_info("sensor1").unwrap(),
+            Some(SensorInfoRow {
+                id: "sensor1".to_string(),
+                store_name: "Central Store".to_string(),
+                store_id: "store1".to_string(),
+                location_name: "".to_string(),
+                sensor_name: "Fridge 1".to_string(),
+                batterylevel: Some(90.0),
+            })
+        );
+        assert_eq!(connection.sensor_info("sensor2").unwrap(), None);
+    }
+}
@@ -1,29 +1,155 @@
-use diesel::prelude::*;
 use diesel::r2d2::ConnectionManager;
 use diesel::r2d2::Pool;
+use diesel::r2d2::PoolError;
+use diesel::r2d2::PooledConnection;
 
-use crate::PostgresSettings;
+#[cfg(feature = "mysql")]
+use diesel::mysql::MysqlConnection;
+#[cfg(feature = "postgres")]
+use diesel::pg::PgConnection;
+#[cfg(feature = "sqlite")]
+use diesel::sqlite::SqliteConnection;
 
-pub struct DatasourcePool {
-    pub pool: Pool<ConnectionManager<PgConnection>>,
+use diesel::result::Error as DieselError;
+
+use crate::{
+    sql_query_as_json_rows, sql_query_as_recipients, BasicRecipientRow, DatasourceBackend,
+    DatasourceBackendConnection, DatasourceSettings, LatestTemperatureRow, QueryError, QueryLimits,
+    SensorInfoRow,
+};
+
+#[cfg(not(any(feature = "postgres", feature = "mysql", feature = "sqlite")))]
+compile_error!(
+    "At least one datasource backend feature (postgres, mysql or sqlite) must be enabled"
+);
+
+#[derive(Clone)]
+pub enum DatasourcePool {
+    #[cfg(feature = "postgres")]
+    Postgres(Pool<ConnectionManager<PgConnection>>),
+    #[cfg(feature = "mysql")]
+    Mysql(Pool<ConnectionManager<MysqlConnection>>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(Pool<ConnectionManager<SqliteConnection>>),
 }
 
-impl Clone for DatasourcePool {
-    fn clone(&self) -> Self {
-        DatasourcePool {
-            pool: self.pool.clone(),
-        }
-    }
+pub enum DatasourceConnection {
+    #[cfg(feature = "postgres")]
+    Postgres(PooledConnection<ConnectionManager<PgConnection>>),
+    #[cfg(feature = "mysql")]
+    Mysql(PooledConnection<ConnectionManager<MysqlConnection>>),
+    #[cfg(feature = "sqlite")]
+    Sqlite(PooledConnection<ConnectionManager<SqliteConnection>>),
 }
 
-pub fn get_datasource_pool(settings: &PostgresSettings) -> DatasourcePool {
-    let url = settings.connection_string();
-    let manager = ConnectionManager::<PgConnection>::new(url);
+fn build_pool<C>(settings: &DatasourceSettings) -> Pool<ConnectionManager<C>>
+where
+    C: diesel::r2d2::R2D2Connection + 'static,
+{
+    let manager = ConnectionManager::<C>::new(settings.connection_string());
 
-    let pool = Pool::builder()
+    Pool::builder()
         .min_idle(Some(1))
         .build(manager)
-        .expect("Could not create datasource connection pool");
+        .expect("Could not create datasource connection pool")
+}
+
+pub fn get_datasource_pool(settings: &DatasourceSettings) -> DatasourcePool {
+    match settings.backend {
+        #[cfg(feature = "postgres")]
+        DatasourceBackend::Postgres => DatasourcePool::Postgres(build_pool(settings)),
+        #[cfg(feature = "mysql")]
+        DatasourceBackend::Mysql => DatasourcePool::Mysql(build_pool(settings)),
+        #[cfg(feature = "sqlite")]
+        DatasourceBackend::Sqlite => DatasourcePool::Sqlite(build_pool(settings)),
+        #[allow(unreachable_patterns)]
+        ref backend => panic!(
+            "Datasource backend {:?} isn't available, the server needs to be built with it's cargo feature enabled",
+            backend
+        ),
+    }
+}
+
+impl DatasourcePool {
+    pub fn get(&self) -> Result<DatasourceConnection, PoolError> {
+        Ok(match self {
+            #[cfg(feature = "postgres")]
+            DatasourcePool::Postgres(pool) => DatasourceConnection::Postgres(pool.get()?),
+            #[cfg(feature = "mysql")]
+            DatasourcePool::Mysql(pool) => DatasourceConnection::Mysql(pool.get()?),
+            #[cfg(feature = "sqlite")]
+            DatasourcePool::Sqlite(pool) => DatasourceConnection::Sqlite(pool.get()?),
+        })
+    }
+}
 
-    DatasourcePool { pool }
+impl DatasourceConnection {
+    pub fn query_as_json_rows(
+        &mut self,
+        sql_select_query: String,
+        limits: &QueryLimits,
+    ) -> Result<Vec<serde_json::Value>, QueryError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DatasourceConnection::Postgres(connection) => {
+                sql_query_as_json_rows(&mut **connection, sql_select_query, limits)
+            }
+            #[cfg(feature = "mysql")]
+            DatasourceConnection::Mysql(connection) => {
+                sql_query_as_json_rows(&mut **connection, sql_select_query, limits)
+            }
+            #[cfg(feature = "sqlite")]
+            DatasourceConnection::Sqlite(connection) => {
+                sql_query_as_json_rows(&mut **connection, sql_select_query, limits)
+            }
+        }
+    }
+
+    pub fn query_as_recipients(
+        &mut self,
+        sql_select_query: String,
+        limits: &QueryLimits,
+    ) -> Result<Vec<BasicRecipientRow>, QueryError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DatasourceConnection::Postgres(connection) => {
+                sql_query_as_recipients(&mut **connection, sql_select_query, limits)
+            }
+            #[cfg(feature = "mysql")]
+            DatasourceConnection::Mysql(connection) => {
+                sql_query_as_recipients(&mut **connection, sql_select_query, limits)
+            }
+            #[cfg(feature = "sqlite")]
+            DatasourceConnection::Sqlite(connection) => {
+                sql_query_as_recipients(&mut **connection, sql_select_query, limits)
+            }
+        }
+    }
+
+    /// The sensor's most recent temperature log, for cold chain monitoring
+    pub fn latest_temperature(
+        &mut self,
+        sensor_id: &str,
+    ) -> Result<Option<LatestTemperatureRow>, DieselError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DatasourceConnection::Postgres(connection) => connection.latest_temperature(sensor_id),
+            #[cfg(feature = "mysql")]
+            DatasourceConnection::Mysql(connection) => connection.latest_temperature(sensor_id),
+            #[cfg(feature = "sqlite")]
+            DatasourceConnection::Sqlite(connection) => connection.latest_temperature(sensor_id),
+        }
+    }
+
+    /// The sensor's name, store and location, for cold chain monitoring
+    pub fn sensor_info(&mut self, sensor_id: &str) -> Result<Option<SensorInfoRow>, DieselError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            DatasourceConnection::Postgres(connection) => connection.sensor_info(sensor_id),
+            #[cfg(feature = "mysql")]
+            DatasourceConnection::Mysql(connection) => connection.sensor_info(sensor_id),
+            #[cfg(feature = "sqlite")]
+            DatasourceConnection::Sqlite(connection) => connection.sensor_info(sensor_id),
+        }
+    }
 }
@@ -1,25 +1,57 @@
 use serde;
 
+use crate::QueryLimits;
+
+/// Type of database a datasource connects to, each one is behind a cargo feature of the same name
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Default)]
+pub enum DatasourceBackend {
+    #[default]
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
 #[derive(serde::Deserialize, Clone)]
-pub struct PostgresSettings {
+pub struct DatasourceSettings {
+    /// Postgres (default) | Mysql | Sqlite
+    #[serde(default)]
+    pub backend: DatasourceBackend,
+    // Connection details aren't needed for Sqlite, so they're optional
+    #[serde(default)]
     pub username: String,
+    #[serde(default)]
     pub password: String,
+    #[serde(default)]
     pub port: u16,
+    #[serde(default)]
     pub host: String,
+    /// For Sqlite this is the path to the database file
     pub database_name: String,
+    /// Limits applied to every query run against this datasource
+    #[serde(default)]
+    pub query_limits: QueryLimits,
 }
 
-impl PostgresSettings {
+impl DatasourceSettings {
     pub fn connection_string(&self) -> String {
-        format!(
-            "postgres://{}:{}@{}:{}/{}",
-            self.username, self.password, self.host, self.port, self.database_name
-        )
+        match self.backend {
+            DatasourceBackend::Sqlite => self.database_name.clone(),
+            _ => format!(
+                "{}/{}",
+                self.connection_string_without_db(),
+                self.database_name
+            ),
+        }
     }
     pub fn connection_string_without_db(&self) -> String {
+        let scheme = match self.backend {
+            DatasourceBackend::Postgres => "postgres",
+            DatasourceBackend::Mysql => "mysql",
+            DatasourceBackend::Sqlite => return "".to_string(),
+        };
         format!(
-            "postgres://{}:{}@{}:{}",
-            self.username, self.password, self.host, self.port
+            "{}://{}:{}@{}:{}",
+            scheme, self.username, self.password, self.host, self.port
         )
     }
 }
@@ -1,39 +1,38 @@
-use diesel::connection::SimpleConnection;
-use diesel::pg::PgConnection;
 use diesel::prelude::*;
-use diesel::result::Error as DieselError;
 use diesel::sql_types::*;
-use diesel::{sql_query, RunQueryDsl};
+
+use crate::backend::DatasourceBackendConnection;
+use crate::query_limits::{check_row_limit, row_limit_clause, QueryError, QueryLimits};
 
 #[derive(QueryableByName, Debug, PartialEq)]
 #[diesel(table_name = json_data)]
 pub struct JsonDataRow {
     #[diesel(sql_type = Json)]
-    data: serde_json::Value,
+    pub(crate) data: serde_json::Value,
 }
 
-pub fn pg_sql_query_as_json_rows(
-    connection: &mut PgConnection,
+pub fn sql_query_as_json_rows<C: DatasourceBackendConnection>(
+    connection: &mut C,
     sql_select_query: String,
-) -> Result<Vec<serde_json::Value>, DieselError> {
-    let _ = connection.batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY;")?;
+    limits: &QueryLimits,
+) -> Result<Vec<serde_json::Value>, QueryError> {
+    connection.set_read_only()?;
 
-    let json_row_sql_query = format!(
-        "WITH provided_query AS(
-        {}
-        ) SELECT row_to_json(provided_query) as data FROM provided_query;",
-        sql_select_query
-    );
+    let row_limit_clause = row_limit_clause(limits);
+    let rows = connection.with_statement_timeout(limits, |connection| {
+        connection.load_json_rows(&sql_select_query, &row_limit_clause, limits)
+    })?;
 
-    let json_results: Vec<JsonDataRow> = sql_query(&json_row_sql_query).load(connection)?;
+    check_row_limit(rows.len(), limits)?;
 
-    Ok(json_results.into_iter().map(|r| r.data).collect())
+    Ok(rows)
 }
 
 #[cfg(test)]
-#[cfg(feature = "datasource-tests")]
+#[cfg(all(feature = "datasource-tests", feature = "postgres"))]
 mod tests {
     use super::*;
+    use diesel::pg::PgConnection;
     use serde_json::json;
     use std::env;
 
@@ -63,7 +62,12 @@ mod tests {
                             UNION 
                             SELECT 2 as row_id, 'Row Two' as description"#;
 
-        let result = pg_sql_query_as_json_rows(&mut connection, sql_query.to_string()).unwrap();
+        let result = sql_query_as_json_rows(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             result,
@@ -84,7 +88,12 @@ mod tests {
         let sql_query = r#"WITH s1 as (SELECT 1 as row_id, 'Row One' as description), s2 as (SELECT 2 as row_id, 'Row Two' as description)
                            SELECT * from s1 UNION SELECT * from s2"#;
 
-        let result = pg_sql_query_as_json_rows(&mut connection, sql_query.to_string()).unwrap();
+        let result = sql_query_as_json_rows(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             result,
@@ -105,8 +114,208 @@ mod tests {
         // We probably don't want anyone running a query like this but still...
         let sql_query = r#"DROP TABLE users;"#;
 
-        let result = pg_sql_query_as_json_rows(&mut connection, sql_query.to_string());
+        let result = sql_query_as_json_rows(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_row_limit() {
+        let database_url =
+            env::var("DATABASE_URL").expect("the DATABASE_URL environment variable must be set");
+        let mut connection = PgConnection::establish(&database_url)
+            .unwrap_or_else(|e| panic!("Error connecting to {} : {}", database_url, e));
+
+        let sql_query = r#"SELECT generate_series(1, 5) as row_id"#;
+
+        let limits = QueryLimits {
+            max_rows: Some(5),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection, sql_query.to_string(), &limits);
+        assert_eq!(result.unwrap().len(), 5);
+
+        let limits = QueryLimits {
+            max_rows: Some(4),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection, sql_query.to_string(), &limits);
+        assert_eq!(result, Err(QueryError::RowLimitExceeded { max_rows: 4 }));
+    }
+
+    #[test]
+    fn test_result_size_limit() {
+        let database_url =
+            env::var("DATABASE_URL").expect("the DATABASE_URL environment variable must be set");
+        let mut connection = PgConnection::establish(&database_url)
+            .unwrap_or_else(|e| panic!("Error connecting to {} : {}", database_url, e));
+
+        let sql_query = r#"SELECT repeat('x', 1000) as description"#;
+
+        let limits = QueryLimits {
+            max_result_bytes: Some(100),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection, sql_query.to_string(), &limits);
+        assert_eq!(
+            result,
+            Err(QueryError::ResultSizeExceeded {
+                max_result_bytes: 100
+            })
+        );
+
+        // Without a row limit, reading stops once the limit is passed rather than loading every row first
+        let sql_query = r#"SELECT generate_series(1, 1000000) as row_id"#;
+        let result = sql_query_as_json_rows(&mut connection, sql_query.to_string(), &limits);
+        assert_eq!(
+            result,
+            Err(QueryError::ResultSizeExceeded {
+                max_result_bytes: 100
+            })
+        );
+        // The connection can still be used afterwards
+        let result =
+            sql_query_as_json_rows(&mut connection, "SELECT 1 as row_id".to_string(), &limits);
+        assert_eq!(result, Ok(vec![json!({"row_id": 1})]));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let database_url =
+            env::var("DATABASE_URL").expect("the DATABASE_URL environment variable must be set");
+        let mut connection = PgConnection::establish(&database_url)
+            .unwrap_or_else(|e| panic!("Error connecting to {} : {}", database_url, e));
+
+        let sql_query = r#"SELECT pg_sleep(3)::text as slept"#;
+
+        let limits = QueryLimits {
+            timeout_seconds: Some(1),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection, sql_query.to_string(), &limits);
+        assert_eq!(result, Err(QueryError::Timeout { timeout_seconds: 1 }));
+
+        // The timeout shouldn't carry over to the next query on the same connection
+        let sql_query = r#"SELECT pg_sleep(1.5)::text as slept"#;
+        let result = sql_query_as_json_rows(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::unlimited(),
+        );
+        assert!(result.is_ok());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "sqlite")]
+mod sqlite_tests {
+    use super::*;
+    use diesel::sqlite::SqliteConnection;
+    use serde_json::json;
+
+    // These use an in memory sqlite database, so they don't need an external database
+
+    fn connection() -> SqliteConnection {
+        SqliteConnection::establish(":memory:").unwrap()
+    }
+
+    #[test]
+    fn test_simple_select() {
+        let sql_query = r#"SELECT 1 as row_id, 'Row One' as description
+                            UNION
+                            SELECT 2 as row_id, 'Row Two' as description"#;
+
+        let result = sql_query_as_json_rows(
+            &mut connection(),
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                json!({"row_id": 1, "description": "Row One"}),
+                json!({"row_id": 2, "description": "Row Two"})
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_types() {
+        let sql_query = r#"SELECT 1.5 as float_value, NULL as null_value, x'0aff' as blob_value, 'text' as text_value"#;
+
+        let result = sql_query_as_json_rows(
+            &mut connection(),
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![json!({
+                "float_value": 1.5,
+                "null_value": null,
+                "blob_value": "\\x0aff",
+                "text_value": "text"
+            })]
+        );
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        let mut connection = connection();
+        // We probably don't want anyone running a query like this but still...
+        let sql_query = r#"DROP TABLE users;"#;
+
+        let result = sql_query_as_json_rows(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        );
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_row_limit() {
+        let sql_query = r#"WITH RECURSIVE series(row_id) AS (SELECT 1 UNION ALL SELECT row_id + 1 FROM series WHERE row_id < 5)
+                           SELECT row_id FROM series"#;
+
+        let limits = QueryLimits {
+            max_rows: Some(5),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection(), sql_query.to_string(), &limits);
+        assert_eq!(result.unwrap().len(), 5);
+
+        let limits = QueryLimits {
+            max_rows: Some(4),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection(), sql_query.to_string(), &limits);
+        assert_eq!(result, Err(QueryError::RowLimitExceeded { max_rows: 4 }));
+    }
+
+    #[test]
+    fn test_result_size_limit() {
+        let sql_query = r#"SELECT printf('%.1000c', 'x') as description"#;
+
+        let limits = QueryLimits {
+            max_result_bytes: Some(100),
+            ..QueryLimits::unlimited()
+        };
+        let result = sql_query_as_json_rows(&mut connection(), sql_query.to_string(), &limits);
+        assert_eq!(
+            result,
+            Err(QueryError::ResultSizeExceeded {
+                max_result_bytes: 100
+            })
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use diesel::backend::Backend;
+use diesel::deserialize::{self, QueryableByName};
+use diesel::result::UnexpectedEndOfRow;
+use diesel::row::{Field, NamedRow, Row};
+use serde_json::{Map, Number, Value};
+
+/// A row with any columns, loaded as a json object keyed by column name.
+/// Used for the backends that can't build the json in the query itself, like Postgres does with `row_to_json`
+#[derive(Debug, PartialEq)]
+pub struct JsonObjectRow(pub serde_json::Value);
+
+/// Converts a single (non null) value to json, this depends on how each backend represents its values
+#[cfg(any(feature = "sqlite", feature = "mysql"))]
+trait JsonValueBackend: Backend {
+    fn value_to_json(value: Self::RawValue<'_>) -> deserialize::Result<Value>;
+}
+
+#[cfg(any(feature = "sqlite", feature = "mysql"))]
+fn build_json_object<'a, DB, R>(row: &R) -> deserialize::Result<JsonObjectRow>
+where
+    DB: JsonValueBackend,
+    R: Row<'a, DB>,
+{
+    let mut object = Map::new();
+    for index in 0..row.field_count() {
+        let field = Row::get(row, index).ok_or(UnexpectedEndOfRow)?;
+        let name = field.field_name().unwrap_or_default().to_string();
+        let value = match field.value() {
+            Some(value) => DB::value_to_json(value)?,
+            None => Value::Null,
+        };
+        object.insert(name, value);
+    }
+    Ok(JsonObjectRow(Value::Object(object)))
+}
+
+#[cfg(any(feature = "sqlite", feature = "mysql"))]
+fn float_to_json(value: f64) -> Value {
+    // NaN and infinity can't be represented in json
+    Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or(Value::Null)
+}
+
+#[cfg(any(feature = "sqlite", feature = "mysql"))]
+/// Matches the way Postgres' `row_to_json` formats `bytea` columns
+fn bytes_to_json(bytes: &[u8]) -> Value {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Value::String(format!("\\x{}", hex))
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use diesel::deserialize::FromSql;
+    use diesel::sql_types::{BigInt, Binary, Double, Text};
+    use diesel::sqlite::{Sqlite, SqliteType, SqliteValue};
+
+    impl JsonValueBackend for Sqlite {
+        fn value_to_json(value: SqliteValue<'_, '_, '_>) -> deserialize::Result<Value> {
+            // Sqlite values are dynamically typed, so we use the storage class of each value
+            Ok(match value.value_type() {
+                Some(SqliteType::Long) | Some(SqliteType::Integer) | Some(SqliteType::SmallInt) => {
+                    Value::from(<i64 as FromSql<BigInt, Sqlite>>::from_sql(value)?)
+                }
+                Some(SqliteType::Double) | Some(SqliteType::Float) => {
+                    float_to_json(<f64 as FromSql<Double, Sqlite>>::from_sql(value)?)
+                }
+                Some(SqliteType::Binary) => {
+                    bytes_to_json(&<Vec<u8> as FromSql<Binary, Sqlite>>::from_sql(value)?)
+                }
+                Some(SqliteType::Text) => {
+                    Value::String(<String as FromSql<Text, Sqlite>>::from_sql(value)?)
+                }
+                None => Value::Null,
+            })
+        }
+    }
+
+    impl QueryableByName<Sqlite> for JsonObjectRow {
+        fn build<'a>(row: &impl NamedRow<'a, Sqlite>) -> deserialize::Result<Self> {
+            build_json_object(row)
+        }
+    }
+}
+
+#[cfg(feature = "mysql")]
+mod mysql {
+    use super::*;
+    use diesel::deserialize::FromSql;
+    use diesel::mysql::sql_types::{Datetime, Unsigned};
+    use diesel::mysql::{Mysql, MysqlType, MysqlValue};
+    use diesel::sql_types::{BigInt, Date, Double, Time};
+
+    impl JsonValueBackend for Mysql {
+        fn value_to_json(value: MysqlValue<'_>) -> deserialize::Result<Value> {
+            Ok(match value.value_type() {
+                MysqlType::Tiny | MysqlType::Short | MysqlType::Long | MysqlType::LongLong => {
+                    Value::from(<i64 as FromSql<BigInt, Mysql>>::from_sql(value)?)
+                }
+                MysqlType::UnsignedTiny
+                | MysqlType::UnsignedShort
+                | MysqlType::UnsignedLong
+                | MysqlType::UnsignedLongLong => {
+                    Value::from(<u64 as FromSql<Unsigned<BigInt>, Mysql>>::from_sql(value)?)
+                }
+                MysqlType::Float | MysqlType::Double => {
+                    float_to_json(<f64 as FromSql<Double, Mysql>>::from_sql(value)?)
+                }
+                MysqlType::Numeric => {
+                    // Decimals are sent as text, parsing them keeps the full precision in the json
+                    let text = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    match serde_json::from_str::<Number>(&text) {
+                        Ok(number) => Value::Number(number),
+                        Err(_) => Value::String(text),
+                    }
+                }
+                MysqlType::Date => Value::String(
+                    <chrono::NaiveDate as FromSql<Date, Mysql>>::from_sql(value)?
+                        .format("%Y-%m-%d")
+                        .to_string(),
+                ),
+                MysqlType::Time => Value::String(
+                    <chrono::NaiveTime as FromSql<Time, Mysql>>::from_sql(value)?
+                        .format("%H:%M:%S%.f")
+                        .to_string(),
+                ),
+                MysqlType::DateTime | MysqlType::Timestamp => Value::String(
+                    <chrono::NaiveDateTime as FromSql<Datetime, Mysql>>::from_sql(value)?
+                        .format("%Y-%m-%dT%H:%M:%S%.f")
+                        .to_string(),
+                ),
+                MysqlType::Bit => Value::from(
+                    value
+                        .as_bytes()
+                        .iter()
+                        .fold(0u64, |bits, byte| (bits << 8) | u64::from(*byte)),
+                ),
+                // String, Enum, Set and Blob, text columns are reported as blobs so anything that is valid utf8 is treated as text
+                _ => match std::str::from_utf8(value.as_bytes()) {
+                    Ok(text) => Value::String(text.to_string()),
+                    Err(_) => bytes_to_json(value.as_bytes()),
+                },
+            })
+        }
+    }
+
+    impl QueryableByName<Mysql> for JsonObjectRow {
+        fn build<'a>(row: &impl NamedRow<'a, Mysql>) -> deserialize::Result<Self> {
+            build_json_object(row)
+        }
+    }
+}
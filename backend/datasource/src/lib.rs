@@ -2,7 +2,15 @@ pub mod database_settings;
 pub use database_settings::*;
 pub mod connection_pool;
 pub use connection_pool::*;
+pub mod backend;
+pub use backend::*;
+pub mod coldchain;
+pub use coldchain::*;
 pub mod json_query;
 pub use json_query::*;
+pub mod json_row;
+pub use json_row::*;
 pub mod recipient;
 pub use recipient::*;
+pub mod query_limits;
+pub use query_limits::*;
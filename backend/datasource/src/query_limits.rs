@@ -0,0 +1,229 @@
+use std::fmt::{Display, Formatter};
+
+use diesel::result::Error as DieselError;
+
+// Defaults are deliberately generous, they are there to stop a runaway query from hanging or exhausting the server
+const DEFAULT_TIMEOUT_SECONDS: u32 = 60;
+const DEFAULT_MAX_ROWS: u32 = 10_000;
+const DEFAULT_MAX_RESULT_BYTES: u32 = 10 * 1024 * 1024;
+
+/// Limits applied when running admin supplied sql against a datasource.
+/// A `None` value means the limit is not applied.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct QueryLimits {
+    /// Maximum time the query may run for before the database cancels it
+    pub timeout_seconds: Option<u32>,
+    /// Maximum number of rows the query may return
+    pub max_rows: Option<u32>,
+    /// Maximum size of the json serialized result
+    pub max_result_bytes: Option<u32>,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            timeout_seconds: Some(DEFAULT_TIMEOUT_SECONDS),
+            max_rows: Some(DEFAULT_MAX_ROWS),
+            max_result_bytes: Some(DEFAULT_MAX_RESULT_BYTES),
+        }
+    }
+}
+
+impl QueryLimits {
+    pub fn unlimited() -> Self {
+        QueryLimits {
+            timeout_seconds: None,
+            max_rows: None,
+            max_result_bytes: None,
+        }
+    }
+
+    /// Combines these (global) limits with per query overrides.
+    /// Overrides can only tighten a limit, the global limits act as a ceiling.
+    pub fn restricted_by(&self, overrides: &QueryLimits) -> QueryLimits {
+        fn min(global: Option<u32>, local: Option<u32>) -> Option<u32> {
+            match (global, local) {
+                (Some(global), Some(local)) => Some(global.min(local)),
+                (global, None) => global,
+                (None, local) => local,
+            }
+        }
+
+        QueryLimits {
+            timeout_seconds: min(self.timeout_seconds, overrides.timeout_seconds),
+            max_rows: min(self.max_rows, overrides.max_rows),
+            max_result_bytes: min(self.max_result_bytes, overrides.max_result_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    Timeout { timeout_seconds: u32 },
+    RowLimitExceeded { max_rows: u32 },
+    ResultSizeExceeded { max_result_bytes: u32 },
+    DatabaseError(String),
+}
+
+impl QueryError {
+    /// True if the query was stopped because it exceeded one of the configured `QueryLimits`
+    pub fn is_limit_exceeded(&self) -> bool {
+        !matches!(self, QueryError::DatabaseError(_))
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::Timeout { timeout_seconds } => write!(
+                f,
+                "Query cancelled as it took longer than the {} second timeout",
+                timeout_seconds
+            ),
+            QueryError::RowLimitExceeded { max_rows } => write!(
+                f,
+                "Query returned more than the maximum of {} rows",
+                max_rows
+            ),
+            QueryError::ResultSizeExceeded { max_result_bytes } => write!(
+                f,
+                "Query result is larger than the maximum of {} bytes",
+                max_result_bytes
+            ),
+            QueryError::DatabaseError(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<DieselError> for QueryError {
+    fn from(error: DieselError) -> Self {
+        QueryError::DatabaseError(error.to_string())
+    }
+}
+
+// Cancelled statements are reported as generic database errors, so we check the message to identify a timeout
+const STATEMENT_TIMEOUT_MESSAGES: [&str; 3] = [
+    // Postgres
+    "canceling statement due to statement timeout",
+    // MySQL
+    "maximum statement execution time exceeded",
+    // MariaDB
+    "max_statement_time exceeded",
+];
+
+fn is_statement_timeout(error: &DieselError) -> bool {
+    match error {
+        DieselError::DatabaseError(_, info) => STATEMENT_TIMEOUT_MESSAGES
+            .iter()
+            .any(|message| info.message().contains(message)),
+        _ => false,
+    }
+}
+
+pub(crate) fn map_query_error(error: DieselError, limits: &QueryLimits) -> QueryError {
+    match limits.timeout_seconds {
+        Some(timeout_seconds) if is_statement_timeout(&error) => {
+            QueryError::Timeout { timeout_seconds }
+        }
+        _ => error.into(),
+    }
+}
+
+/// `LIMIT` clause that fetches one row more than allowed, so we can tell if the limit was exceeded
+pub(crate) fn row_limit_clause(limits: &QueryLimits) -> String {
+    match limits.max_rows {
+        Some(max_rows) => format!("LIMIT {}", u64::from(max_rows) + 1),
+        None => "".to_string(),
+    }
+}
+
+pub(crate) fn check_row_limit(row_count: usize, limits: &QueryLimits) -> Result<(), QueryError> {
+    match limits.max_rows {
+        Some(max_rows) if row_count > max_rows as usize => {
+            Err(QueryError::RowLimitExceeded { max_rows })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Collects the rows as they're read, stopping as soon as the json serialized result is larger than allowed,
+/// so a large result is never loaded into memory in full
+pub(crate) fn collect_json_rows<I>(
+    rows: I,
+    limits: &QueryLimits,
+) -> Result<Vec<serde_json::Value>, QueryError>
+where
+    I: Iterator<Item = Result<serde_json::Value, DieselError>>,
+{
+    let mut result_bytes: usize = 0;
+    let mut collected = Vec::new();
+    for row in rows {
+        let row = row.map_err(|error| map_query_error(error, limits))?;
+        if let Some(max_result_bytes) = limits.max_result_bytes {
+            // Size of the rows once serialized as a json array (each row plus a separating comma)
+            result_bytes += row.to_string().len() + 1;
+            if result_bytes > max_result_bytes as usize {
+                return Err(QueryError::ResultSizeExceeded { max_result_bytes });
+            }
+        }
+        collected.push(row);
+    }
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restricted_by() {
+        let global = QueryLimits {
+            timeout_seconds: Some(60),
+            max_rows: Some(100),
+            max_result_bytes: None,
+        };
+
+        // Overrides can tighten a limit, but not loosen it
+        let overrides = QueryLimits {
+            timeout_seconds: Some(10),
+            max_rows: Some(1000),
+            max_result_bytes: Some(2048),
+        };
+        assert_eq!(
+            global.restricted_by(&overrides),
+            QueryLimits {
+                timeout_seconds: Some(10),
+                max_rows: Some(100),
+                max_result_bytes: Some(2048),
+            }
+        );
+
+        // No overrides leaves the global limits in place
+        assert_eq!(global.restricted_by(&QueryLimits::unlimited()), global);
+    }
+
+    #[test]
+    fn test_collect_json_rows() {
+        let limits = QueryLimits {
+            max_result_bytes: Some(20),
+            ..QueryLimits::unlimited()
+        };
+        let rows = || (0..).map(|id| Ok(serde_json::json!({ "id": id })));
+
+        // Reading stops at the first row over the limit, rows after it aren't read
+        assert_eq!(
+            collect_json_rows(rows(), &limits),
+            Err(QueryError::ResultSizeExceeded {
+                max_result_bytes: 20
+            })
+        );
+        assert_eq!(
+            collect_json_rows(rows().take(2), &limits),
+            Ok(vec![
+                serde_json::json!({"id": 0}),
+                serde_json::json!({"id": 1})
+            ])
+        );
+    }
+}
@@ -1,10 +1,14 @@
-use diesel::connection::SimpleConnection;
-use diesel::pg::PgConnection;
 use diesel::prelude::*;
-use diesel::result::Error as DieselError;
+use diesel::query_builder::SqlQuery;
+use diesel::query_dsl::LoadQuery;
 use diesel::sql_types::*;
 use diesel::{sql_query, RunQueryDsl};
 
+use crate::backend::DatasourceBackendConnection;
+use crate::query_limits::{
+    check_row_limit, map_query_error, row_limit_clause, QueryError, QueryLimits,
+};
+
 #[derive(QueryableByName, Debug, PartialEq)]
 #[diesel(table_name = basic_recipient)]
 pub struct BasicRecipientRow {
@@ -18,28 +22,42 @@ pub struct BasicRecipientRow {
     pub to_address: String,
 }
 
-pub fn pg_sql_query_as_recipients(
-    connection: &mut PgConnection,
+pub fn sql_query_as_recipients<C>(
+    connection: &mut C,
     sql_select_query: String,
-) -> Result<Vec<BasicRecipientRow>, DieselError> {
-    let _ = connection.batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY;")?;
+    limits: &QueryLimits,
+) -> Result<Vec<BasicRecipientRow>, QueryError>
+where
+    C: DatasourceBackendConnection,
+    for<'a> SqlQuery: LoadQuery<'a, C, BasicRecipientRow>,
+{
+    connection.set_read_only()?;
 
     let recipient_sql_query = format!(
         "WITH provided_query AS(
         {}
-        ) SELECT id, name, notification_type, to_address FROM provided_query;",
-        sql_select_query
+        ) SELECT id, name, notification_type, to_address FROM provided_query {};",
+        sql_select_query,
+        row_limit_clause(limits)
     );
 
-    let results: Vec<BasicRecipientRow> = sql_query(&recipient_sql_query).load(connection)?;
+    let results: Vec<BasicRecipientRow> =
+        connection.with_statement_timeout(limits, |connection| {
+            sql_query(&recipient_sql_query)
+                .load(connection)
+                .map_err(|error| map_query_error(error, limits))
+        })?;
+
+    check_row_limit(results.len(), limits)?;
 
     Ok(results)
 }
 
 #[cfg(test)]
-#[cfg(feature = "datasource-tests")]
+#[cfg(all(feature = "datasource-tests", feature = "postgres"))]
 mod tests {
     use super::*;
+    use diesel::pg::PgConnection;
     use std::env;
 
     /*
@@ -67,7 +85,51 @@ mod tests {
         let sql_query = r#"SELECT '1' as id, 'Name One' as name, 
                         'EMAIL' as notification_type, 'name1@example.com' as to_address"#;
 
-        let result = pg_sql_query_as_recipients(&mut connection, sql_query.to_string()).unwrap();
+        let result = sql_query_as_recipients(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            vec![BasicRecipientRow {
+                id: "1".to_string(),
+                name: "Name One".to_string(),
+                notification_type: "EMAIL".to_string(),
+                to_address: "name1@example.com".to_string(),
+            }]
+        );
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "sqlite")]
+mod sqlite_tests {
+    use super::*;
+    use diesel::connection::SimpleConnection;
+    use diesel::sqlite::SqliteConnection;
+
+    #[test]
+    fn test_select_from_table() {
+        let mut connection = SqliteConnection::establish(":memory:").unwrap();
+        connection
+            .batch_execute(
+                r#"CREATE TABLE contact (id TEXT, name TEXT, email TEXT);
+                   INSERT INTO contact VALUES ('1', 'Name One', 'name1@example.com');"#,
+            )
+            .unwrap();
+
+        let sql_query =
+            r#"SELECT id, name, 'EMAIL' as notification_type, email as to_address FROM contact"#;
+
+        let result = sql_query_as_recipients(
+            &mut connection,
+            sql_query.to_string(),
+            &QueryLimits::default(),
+        )
+        .unwrap();
 
         assert_eq!(
             result,
@@ -78,5 +140,9 @@ mod tests {
                 to_address: "name1@example.com".to_string(),
             }]
         );
+
+        // Queries are run read only, so the datasource can't be modified
+        let result = connection.batch_execute("DELETE FROM contact;");
+        assert!(result.is_err());
     }
 }
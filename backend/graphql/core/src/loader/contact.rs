@@ -0,0 +1,70 @@
+use repository::StorageConnectionManager;
+use repository::{
+    Contact, ContactFilter, ContactRepository, EqualFilter, Recipient,
+    RecipientListContactRowRepository, RecipientRowRepository,
+};
+
+use async_graphql::dataloader::*;
+use async_graphql::*;
+use std::collections::HashMap;
+
+/// Each contact's channels in the order they're tried in
+pub struct ContactChannelsLoader {
+    pub connection_manager: StorageConnectionManager,
+}
+
+#[async_trait::async_trait]
+impl Loader<String> for ContactChannelsLoader {
+    type Value = Vec<Recipient>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        contact_ids: &[String],
+    ) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let connection = self.connection_manager.connection()?;
+        let repo = RecipientRowRepository::new(&connection);
+
+        let mut result_map: HashMap<String, Vec<Recipient>> = HashMap::new();
+        for contact_id in contact_ids {
+            result_map.insert(
+                contact_id.to_string(),
+                repo.find_all_for_contact(contact_id)?,
+            );
+        }
+        Ok(result_map)
+    }
+}
+
+/// The contacts on each recipient list
+pub struct RecipientListContactsLoader {
+    pub connection_manager: StorageConnectionManager,
+}
+
+#[async_trait::async_trait]
+impl Loader<String> for RecipientListContactsLoader {
+    type Value = Vec<Contact>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        recipient_list_ids: &[String],
+    ) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let connection = self.connection_manager.connection()?;
+        let list_contact_repo = RecipientListContactRowRepository::new(&connection);
+        let contact_repo = ContactRepository::new(&connection);
+
+        let mut result_map: HashMap<String, Vec<Contact>> = HashMap::new();
+        for recipient_list_id in recipient_list_ids {
+            let contact_ids = list_contact_repo
+                .find_all_for_recipient_lists(&[recipient_list_id.to_string()])?
+                .into_iter()
+                .map(|row| row.contact_id)
+                .collect();
+            let contacts = contact_repo
+                .query_by_filter(ContactFilter::new().id(EqualFilter::equal_any(contact_ids)))?;
+            result_map.insert(recipient_list_id.to_string(), contacts);
+        }
+        Ok(result_map)
+    }
+}
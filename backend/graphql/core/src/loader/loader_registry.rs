@@ -6,8 +6,8 @@ use repository::StorageConnectionManager;
 use service::service_provider::ServiceProvider;
 
 use super::{
-    user_permission::UserPermissionLoader, AuditLogLoader, NotificationConfigLoader,
-    RecipientsLoader,
+    user_permission::UserPermissionLoader, AuditLogLoader, ContactChannelsLoader,
+    NotificationConfigLoader, RecipientListContactsLoader, RecipientOptOutLoader, RecipientsLoader,
 };
 
 pub type LoaderMap = Map<AnyLoader>;
@@ -56,6 +56,30 @@ pub async fn get_loaders(
     );
     loaders.insert(recipient_loader);
 
+    let recipient_opt_out_loader = DataLoader::new(
+        RecipientOptOutLoader {
+            connection_manager: connection_manager.clone(),
+        },
+        async_std::task::spawn,
+    );
+    loaders.insert(recipient_opt_out_loader);
+
+    let contact_channels_loader = DataLoader::new(
+        ContactChannelsLoader {
+            connection_manager: connection_manager.clone(),
+        },
+        async_std::task::spawn,
+    );
+    loaders.insert(contact_channels_loader);
+
+    let recipient_list_contacts_loader = DataLoader::new(
+        RecipientListContactsLoader {
+            connection_manager: connection_manager.clone(),
+        },
+        async_std::task::spawn,
+    );
+    loaders.insert(recipient_list_contacts_loader);
+
     let audit_log_loader = DataLoader::new(
         AuditLogLoader {
             connection_manager: connection_manager.clone(),
@@ -1,4 +1,5 @@
 mod audit_log;
+mod contact;
 mod loader_registry;
 mod notification_config;
 mod recipient;
@@ -6,6 +7,7 @@ mod user;
 mod user_permission;
 
 pub use audit_log::*;
+pub use contact::*;
 pub use loader_registry::{get_loaders, LoaderMap, LoaderRegistry};
 pub use notification_config::*;
 pub use recipient::*;
@@ -1,6 +1,6 @@
 use repository::{
     EqualFilter, Pagination, Recipient, RecipientFilter, RecipientListMemberRepository,
-    RecipientRepository,
+    RecipientOptOutRowRepository, RecipientRepository,
 };
 use repository::{RecipientListMemberFilter, StorageConnectionManager};
 
@@ -52,3 +52,33 @@ impl Loader<String> for RecipientsLoader {
         Ok(result_map)
     }
 }
+
+/// The notification config ids each recipient has opted out of
+pub struct RecipientOptOutLoader {
+    pub connection_manager: StorageConnectionManager,
+}
+
+#[async_trait::async_trait]
+impl Loader<String> for RecipientOptOutLoader {
+    type Value = Vec<String>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        recipient_ids: &[String],
+    ) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let connection = self.connection_manager.connection()?;
+        let repo = RecipientOptOutRowRepository::new(&connection);
+
+        let mut result_map: HashMap<String, Vec<String>> = HashMap::new();
+        for recipient_id in recipient_ids {
+            let notification_config_ids = repo
+                .find_all_for_recipient(recipient_id)?
+                .into_iter()
+                .map(|row| row.notification_config_id)
+                .collect();
+            result_map.insert(recipient_id.to_string(), notification_config_ids);
+        }
+        Ok(result_map)
+    }
+}
@@ -8,7 +8,6 @@ use service::{
 mod types;
 use self::types::*;
 
-
 pub fn map_error(error: DatasourceServiceError) -> Result<QueryResultResponse> {
     let graphql_error = match error {
         DatasourceServiceError::InternalError(e) => InternalError(e),
@@ -23,7 +22,12 @@ pub struct DatasourceQueries;
 
 #[Object]
 impl DatasourceQueries {
-    pub async fn run_sql_query(&self, ctx: &Context<'_>, sql_query: String) -> Result<QueryResultResponse> {
+    pub async fn run_sql_query(
+        &self,
+        ctx: &Context<'_>,
+        sql_query: String,
+        datasource_id: Option<String>,
+    ) -> Result<QueryResultResponse> {
         let user = validate_auth(
             ctx,
             &ResourceAccessRequest {
@@ -36,8 +40,10 @@ impl DatasourceQueries {
         // TODO some kind of query validation?
 
         // Query datasource service and return result
-        match datasource_service.run_sql_query(sql_query) {
-            Ok(result) => Ok(QueryResultResponse::Response(QueryResultNode::from_domain(result))),
+        match datasource_service.run_sql_query(datasource_id, sql_query, None) {
+            Ok(result) => Ok(QueryResultResponse::Response(QueryResultNode::from_domain(
+                result,
+            ))),
             Err(error) => map_error(error),
         }
     }
@@ -47,6 +53,7 @@ impl DatasourceQueries {
         ctx: &Context<'_>,
         sql_query: String,
         parameters: String,
+        datasource_id: Option<String>,
     ) -> Result<QueryResultResponse> {
         let user = validate_auth(
             ctx,
@@ -67,9 +74,32 @@ impl DatasourceQueries {
         })?;
 
         // Query datasource service and return result
-        match datasource_service.run_sql_query_with_parameters(sql_query, parameters) {
-            Ok(result) => Ok(QueryResultResponse::Response(QueryResultNode::from_domain(result))),
+        match datasource_service.run_sql_query_with_parameters(
+            datasource_id,
+            sql_query,
+            parameters,
+            None,
+        ) {
+            Ok(result) => Ok(QueryResultResponse::Response(QueryResultNode::from_domain(
+                result,
+            ))),
             Err(error) => map_error(error),
         }
     }
+
+    /// Ids of the named datasources that queries can be run against, the default datasource isn't listed
+    pub async fn datasources(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        let user = validate_auth(
+            ctx,
+            &ResourceAccessRequest {
+                resource: Resource::ServerAdmin,
+            },
+        )?;
+
+        let service_ctx = ctx.service_context(Some(&user))?;
+        Ok(service_ctx
+            .service_provider
+            .datasource_service
+            .datasource_ids())
+    }
 }
@@ -23,8 +23,8 @@ impl QueryResultNode {
         &self.row().results
     }
 
-    pub async fn query_error(&self) -> &Option<std::string::String> {
-        &self.row().query_error
+    pub async fn query_error(&self) -> Option<String> {
+        self.row().query_error.as_ref().map(|e| e.to_string())
     }
 }
 
@@ -13,6 +13,7 @@ use graphql_types::types::*;
 use repository::NotificationConfigFilter;
 use repository::PaginationOption;
 use service::auth::{Resource, ResourceAccessRequest};
+use service::email::layout::preview_email_layout;
 
 #[derive(Default, Clone)]
 pub struct NotificationConfigQueries;
@@ -53,6 +54,58 @@ impl NotificationConfigQueries {
             NotificationConfigConnector::from_domain(configs),
         ))
     }
+
+    /// Names of the configured mail sender profiles a notification config can send emails from
+    pub async fn sender_profiles(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        let user = validate_auth(
+            ctx,
+            &ResourceAccessRequest {
+                resource: Resource::ServerAdmin,
+            },
+        )?;
+
+        let service_context = ctx.service_context(Some(&user))?;
+        let mut sender_profiles: Vec<String> = service_context
+            .service_provider
+            .settings
+            .mail
+            .sender_profiles
+            .keys()
+            .cloned()
+            .collect();
+        sender_profiles.sort();
+        Ok(sender_profiles)
+    }
+
+    /// The html of an example notification email in a notification config's email layout.
+    /// Any emailLayout values are shown instead of the config's, so changes can be previewed before they're saved
+    pub async fn email_layout_preview(
+        &self,
+        ctx: &Context<'_>,
+        notification_config_id: Option<String>,
+        email_layout: Option<EmailLayoutInput>,
+    ) -> Result<String> {
+        let user = validate_auth(
+            ctx,
+            &ResourceAccessRequest {
+                resource: Resource::ServerAdmin,
+            },
+        )?;
+
+        let service_context = ctx.service_context(Some(&user))?;
+        preview_email_layout(
+            &service_context,
+            notification_config_id,
+            email_layout.map(EmailLayoutInput::to_domain),
+        )
+        .map_err(|e| {
+            StandardGraphqlError::InternalError(format!(
+                "Unable to preview the email layout : {:?}",
+                e
+            ))
+            .extend()
+        })
+    }
 }
 
 #[derive(Default, Clone)]
@@ -2,12 +2,14 @@ use async_graphql::*;
 
 use chrono::{DateTime, Utc};
 use graphql_core::{standard_graphql_error::validate_auth, ContextExt};
-use graphql_types::types::{ConfigStatus, NotificationConfigNode};
+use graphql_types::types::{ConfigStatus, NotificationConfigNode, NotificationPriorityNode};
 use service::{
     auth::{Resource, ResourceAccessRequest},
     notification_config::update::UpdateNotificationConfig,
 };
 
+use crate::types::EmailLayoutInput;
+
 use super::{map_error, ModifyNotificationConfigResponse};
 #[derive(InputObject, Clone)]
 pub struct UpdateNotificationConfigInput {
@@ -21,6 +23,14 @@ pub struct UpdateNotificationConfigInput {
     pub recipient_list_ids: Option<Vec<String>>,
     pub sql_recipient_list_ids: Option<Vec<String>>,
     pub next_due_datetime: Option<DateTime<Utc>>,
+    /// One of the senderProfiles, or an empty string to send from the default from address
+    pub sender_profile: Option<String>,
+    pub email_layout: Option<EmailLayoutInput>,
+    pub contact_ids: Option<Vec<String>>,
+    /// Recipient list that notifications are sent to when they fail permanently, an empty string removes it
+    pub fallback_recipient_list_id: Option<String>,
+    /// Overrides the priority the plugin gives the config's notifications, null goes back to the plugin's priority
+    pub priority: MaybeUndefined<NotificationPriorityNode>,
 }
 
 pub fn update_notification_config(
@@ -60,6 +70,11 @@ impl From<UpdateNotificationConfigInput> for UpdateNotificationConfig {
             recipient_list_ids,
             sql_recipient_list_ids,
             next_due_datetime,
+            sender_profile,
+            email_layout,
+            contact_ids,
+            fallback_recipient_list_id,
+            priority,
         }: UpdateNotificationConfigInput,
     ) -> Self {
         UpdateNotificationConfig {
@@ -73,6 +88,15 @@ impl From<UpdateNotificationConfigInput> for UpdateNotificationConfig {
             recipient_list_ids,
             sql_recipient_list_ids,
             next_due_datetime: next_due_datetime.map(|d| d.naive_utc()),
+            sender_profile,
+            email_layout: email_layout.map(EmailLayoutInput::to_domain),
+            contact_ids,
+            fallback_recipient_list_id,
+            priority: match priority {
+                MaybeUndefined::Undefined => None,
+                MaybeUndefined::Null => Some(None),
+                MaybeUndefined::Value(priority) => Some(Some(priority.to_domain())),
+            },
         }
     }
 }
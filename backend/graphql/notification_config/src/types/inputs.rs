@@ -8,6 +8,7 @@ use repository::{
     EqualFilter, NotificationConfigFilter, NotificationConfigSort, NotificationConfigSortField,
     StringFilter,
 };
+use service::email::layout::EmailBranding;
 
 #[derive(Enum, Copy, Clone, PartialEq, Eq)]
 #[graphql(rename_items = "camelCase")]
@@ -76,3 +77,32 @@ impl From<NotificationConfigFilterInput> for NotificationConfigFilter {
         }
     }
 }
+
+/// Changes to the email layout's logo, colours and footer, empty values use the mail layout settings
+#[derive(InputObject, Clone)]
+pub struct EmailLayoutInput {
+    pub logo_url: Option<String>,
+    /// A hex colour e.g. #2dc26b, used for links, headings and buttons
+    pub primary_colour: Option<String>,
+    /// A hex colour e.g. #e7e7e7, shown around the email
+    pub background_colour: Option<String>,
+    /// The name the email is signed off with
+    pub footer_text: Option<String>,
+}
+
+impl EmailLayoutInput {
+    pub fn to_domain(self) -> EmailBranding {
+        let EmailLayoutInput {
+            logo_url,
+            primary_colour,
+            background_colour,
+            footer_text,
+        } = self;
+        EmailBranding {
+            logo_url,
+            primary_colour,
+            background_colour,
+            footer_text,
+        }
+    }
+}
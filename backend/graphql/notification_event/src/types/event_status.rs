@@ -7,8 +7,11 @@ use serde::Serialize;
 pub enum EventStatus {
     Queued,
     Sent,
-    Errored, // Errored will be re-tried
-    Failed,  // Failed will not be re-tried
+    Errored,  // Errored will be re-tried
+    Failed,   // Failed will not be re-tried
+    Digest,   // Held until it's sent in the recipient's daily or weekly digest
+    Sending,  // Being sent, still sending after a restart means it was interrupted
+    Digested, // Added to a digest, it's sent or failed along with the digest
 }
 
 impl EventStatus {
@@ -18,6 +21,9 @@ impl EventStatus {
             EventStatus::Sent => NotificationEventStatus::Sent,
             EventStatus::Errored => NotificationEventStatus::Errored,
             EventStatus::Failed => NotificationEventStatus::Failed,
+            EventStatus::Digest => NotificationEventStatus::Digest,
+            EventStatus::Sending => NotificationEventStatus::Sending,
+            EventStatus::Digested => NotificationEventStatus::Digested,
         }
     }
 
@@ -27,6 +33,9 @@ impl EventStatus {
             NotificationEventStatus::Sent => EventStatus::Sent,
             NotificationEventStatus::Errored => EventStatus::Errored,
             NotificationEventStatus::Failed => EventStatus::Failed,
+            NotificationEventStatus::Digest => EventStatus::Digest,
+            NotificationEventStatus::Sending => EventStatus::Sending,
+            NotificationEventStatus::Digested => EventStatus::Digested,
         }
     }
 }
@@ -8,6 +8,8 @@ use repository::{
     NotificationEventSortField,
 };
 
+use graphql_types::types::NotificationPriorityNode;
+
 use super::EventStatus;
 
 #[derive(Enum, Copy, Clone, PartialEq, Eq)]
@@ -29,6 +31,13 @@ pub struct EqualFilterEventStatusInput {
     pub not_equal_to: Option<EventStatus>,
 }
 
+#[derive(InputObject, Clone)]
+pub struct EqualFilterNotificationPriorityInput {
+    pub equal_to: Option<NotificationPriorityNode>,
+    pub equal_any: Option<Vec<NotificationPriorityNode>>,
+    pub not_equal_to: Option<NotificationPriorityNode>,
+}
+
 #[derive(InputObject)]
 pub struct NotificationEventSortInput {
     /// Sort query result by `key`
@@ -65,6 +74,7 @@ pub struct NotificationEventFilterInput {
     pub search: Option<String>,
     pub status: Option<EqualFilterEventStatusInput>,
     pub created_at: Option<DatetimeFilterInput>,
+    pub priority: Option<EqualFilterNotificationPriorityInput>,
 }
 
 impl From<NotificationEventFilterInput> for NotificationEventFilter {
@@ -75,6 +85,9 @@ impl From<NotificationEventFilterInput> for NotificationEventFilter {
             search: f.search,
             status: f.status.map(|t| map_filter!(t, EventStatus::to_domain)),
             created_at: f.created_at.map(DatetimeFilter::from),
+            priority: f
+                .priority
+                .map(|t| map_filter!(t, NotificationPriorityNode::to_domain)),
         }
     }
 }
@@ -3,13 +3,17 @@ use chrono::{DateTime, Utc};
 use graphql_core::simple_generic_errors::NodeError;
 use graphql_core::{loader::NotificationConfigLoader, ContextExt};
 
-use graphql_types::types::{NotificationConfigNode, NotificationTypeNode};
+use graphql_types::types::{
+    NotificationConfigNode, NotificationPriorityNode, NotificationTypeNode,
+};
 use repository::NotificationEvent;
 use service::ListResult;
 use util::usize_to_u32;
 
 use super::EventStatus;
 
+const SYSTEM_EMAIL_MESSAGE: &str = "System email, the message isn't shown";
+
 #[derive(Union)]
 pub enum NotificationEventsResponse {
     Response(NotificationEventConnector),
@@ -36,10 +40,24 @@ impl NotificationEventNode {
         self.row().notification_config_id.to_owned()
     }
 
+    /// The failed notification that this notification was sent as a fallback for
+    pub async fn parent_event_id(&self) -> Option<String> {
+        self.row().parent_event_id.to_owned()
+    }
+
+    /// The digest this notification was sent in
+    pub async fn digest_event_id(&self) -> Option<String> {
+        self.row().digest_event_id.to_owned()
+    }
+
     pub async fn title(&self) -> String {
         self.row().title.to_owned().unwrap_or_default()
     }
+    /// System emails, such as password resets, aren't shown as they contain the user's reset or invite link
     pub async fn message(&self) -> &str {
+        if self.row().html_message.is_some() {
+            return SYSTEM_EMAIL_MESSAGE;
+        }
         &self.row().message
     }
     pub async fn to_address(&self) -> &str {
@@ -70,6 +88,10 @@ impl NotificationEventNode {
         EventStatus::from_domain(&self.row().status)
     }
 
+    pub async fn priority(&self) -> NotificationPriorityNode {
+        NotificationPriorityNode::from_domain(&self.row().priority)
+    }
+
     pub async fn send_attempts(&self) -> i32 {
         self.row().send_attempts
     }
@@ -14,6 +14,8 @@ pub struct CreateNotificationQueryInput {
     pub id: String,
     pub name: String,
     pub reference_name: String,
+    /// Named datasource to run the query against, if not set the default datasource is used
+    pub datasource_id: Option<String>,
 }
 
 pub fn create_notification_query(
@@ -47,12 +49,14 @@ impl From<CreateNotificationQueryInput> for CreateNotificationQuery {
             id,
             name,
             reference_name,
+            datasource_id,
         }: CreateNotificationQueryInput,
     ) -> Self {
         CreateNotificationQuery {
             id,
             name,
             reference_name,
+            datasource_id,
         }
     }
 }
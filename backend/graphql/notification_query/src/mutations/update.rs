@@ -6,7 +6,7 @@ use service::{
     notification_query::update::UpdateNotificationQuery,
 };
 
-use crate::types::NotificationQueryNode;
+use crate::types::{AttachmentFormatNode, NotificationQueryNode};
 
 use super::{map_error, ModifyNotificationQueryResponse};
 #[derive(InputObject, Clone)]
@@ -17,6 +17,14 @@ pub struct UpdateNotificationQueryInput {
     pub description: Option<String>,
     pub query: Option<String>,
     pub required_parameters: Option<Vec<String>>,
+    /// Query limits, null goes back to the datasource's default
+    pub timeout_seconds: MaybeUndefined<i32>,
+    pub max_rows: MaybeUndefined<i32>,
+    pub max_result_bytes: MaybeUndefined<i32>,
+    /// Named datasource to run the query against, an empty string selects the default datasource
+    pub datasource_id: Option<String>,
+    /// Attach the query results to notifications as a file, NONE stops attaching them
+    pub attachment_format: Option<AttachmentFormatNode>,
 }
 
 pub fn update_notification_query(
@@ -52,6 +60,11 @@ impl From<UpdateNotificationQueryInput> for UpdateNotificationQuery {
             description,
             query,
             required_parameters,
+            timeout_seconds,
+            max_rows,
+            max_result_bytes,
+            datasource_id,
+            attachment_format,
         }: UpdateNotificationQueryInput,
     ) -> Self {
         UpdateNotificationQuery {
@@ -61,6 +74,11 @@ impl From<UpdateNotificationQueryInput> for UpdateNotificationQuery {
             description,
             query,
             required_parameters,
+            timeout_seconds: timeout_seconds.into(),
+            max_rows: max_rows.into(),
+            max_result_bytes: max_result_bytes.into(),
+            datasource_id,
+            attachment_format: attachment_format.map(AttachmentFormatNode::to_domain),
         }
     }
 }
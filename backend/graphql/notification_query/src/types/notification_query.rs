@@ -1,7 +1,7 @@
-use async_graphql::{Object, SimpleObject, Union};
+use async_graphql::{Enum, Object, SimpleObject, Union};
 use graphql_core::simple_generic_errors::NodeError;
 
-use repository::NotificationQuery;
+use repository::{AttachmentFormat, NotificationQuery};
 use service::ListResult;
 use util::usize_to_u32;
 
@@ -45,6 +45,22 @@ impl NotificationQueryNode {
 
         Ok(parameters)
     }
+    pub async fn timeout_seconds(&self) -> Option<i32> {
+        self.row().timeout_seconds
+    }
+    pub async fn max_rows(&self) -> Option<i32> {
+        self.row().max_rows
+    }
+    pub async fn max_result_bytes(&self) -> Option<i32> {
+        self.row().max_result_bytes
+    }
+    pub async fn datasource_id(&self) -> Option<String> {
+        self.row().datasource_id.clone()
+    }
+    /// Whether the query results are also attached to the notification as a file
+    pub async fn attachment_format(&self) -> AttachmentFormatNode {
+        AttachmentFormatNode::from_domain(&self.row().attachment_format)
+    }
 }
 
 impl NotificationQueryNode {
@@ -57,6 +73,31 @@ impl NotificationQueryNode {
     }
 }
 
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AttachmentFormatNode {
+    None,
+    Csv,
+    Xlsx,
+}
+
+impl AttachmentFormatNode {
+    pub fn to_domain(self) -> AttachmentFormat {
+        match self {
+            AttachmentFormatNode::None => AttachmentFormat::None,
+            AttachmentFormatNode::Csv => AttachmentFormat::Csv,
+            AttachmentFormatNode::Xlsx => AttachmentFormat::Xlsx,
+        }
+    }
+
+    pub fn from_domain(attachment_format: &AttachmentFormat) -> AttachmentFormatNode {
+        match attachment_format {
+            AttachmentFormat::None => AttachmentFormatNode::None,
+            AttachmentFormat::Csv => AttachmentFormatNode::Csv,
+            AttachmentFormat::Xlsx => AttachmentFormatNode::Xlsx,
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 pub struct NotificationQueryConnector {
     total_count: u32,
@@ -10,6 +10,7 @@ use graphql_core::{
     ContextExt,
 };
 use graphql_types::types::*;
+use repository::ContactFilter;
 use repository::PaginationOption;
 use repository::RecipientFilter;
 use service::auth::{Resource, ResourceAccessRequest};
@@ -54,6 +55,42 @@ impl RecipientQueries {
             RecipientConnector::from_domain(recipients),
         ))
     }
+
+    /// Query "contact" entries, the people that can be reached on one or more recipients
+    pub async fn contacts(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Pagination option (first and offset)")] page: Option<PaginationInput>,
+        #[graphql(desc = "Filter option")] filter: Option<ContactFilterInput>,
+        #[graphql(desc = "Sort options (only first sort input is evaluated for this endpoint)")]
+        sort: Option<Vec<ContactSortInput>>,
+    ) -> Result<ContactsResponse> {
+        let user = validate_auth(
+            ctx,
+            &ResourceAccessRequest {
+                resource: Resource::ServerAdmin,
+            },
+        )?;
+
+        let service_context = ctx.service_context(Some(&user))?;
+
+        let contacts = service_context
+            .service_provider
+            .contact_service
+            .get_contacts(
+                &service_context,
+                page.map(PaginationOption::from),
+                filter.map(ContactFilter::from),
+                // Currently only one sort option is supported, use the first from the list.
+                sort.and_then(|mut sort_list| sort_list.pop())
+                    .map(|sort| sort.to_domain()),
+            )
+            .map_err(StandardGraphqlError::from_list_error)?;
+
+        Ok(ContactsResponse::Response(ContactConnector::from_domain(
+            contacts,
+        )))
+    }
 }
 
 #[derive(Default, Clone)]
@@ -84,6 +121,46 @@ impl RecipientMutations {
     ) -> Result<DeleteRecipientResponse> {
         delete_recipient(ctx, &recipient_id)
     }
+
+    async fn update_recipient_preferences(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateRecipientPreferencesInput,
+    ) -> Result<UpdateRecipientPreferencesResponse> {
+        update_recipient_preferences(ctx, input)
+    }
+
+    async fn create_contact(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateContactInput,
+    ) -> Result<ModifyContactResponse> {
+        create_contact(ctx, input)
+    }
+
+    async fn update_contact(
+        &self,
+        ctx: &Context<'_>,
+        input: UpdateContactInput,
+    ) -> Result<ModifyContactResponse> {
+        update_contact(ctx, input)
+    }
+
+    async fn delete_contact(
+        &self,
+        ctx: &Context<'_>,
+        contact_id: String,
+    ) -> Result<DeleteContactResponse> {
+        delete_contact(ctx, &contact_id)
+    }
+
+    async fn set_contact_channels(
+        &self,
+        ctx: &Context<'_>,
+        input: SetContactChannelsInput,
+    ) -> Result<SetContactChannelsResponse> {
+        set_contact_channels(ctx, input)
+    }
 }
 
 #[cfg(test)]
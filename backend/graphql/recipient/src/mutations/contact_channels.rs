@@ -0,0 +1,61 @@
+use async_graphql::*;
+
+use graphql_core::{standard_graphql_error::validate_auth, ContextExt};
+use graphql_types::types::RecipientConnector;
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    contact::channels::SetContactChannels,
+};
+
+use super::map_contact_error;
+
+pub fn set_contact_channels(
+    ctx: &Context<'_>,
+    input: SetContactChannelsInput,
+) -> Result<SetContactChannelsResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+    match service_context
+        .service_provider
+        .contact_service
+        .set_contact_channels(&service_context, input.into())
+    {
+        Ok(channels) => Ok(SetContactChannelsResponse::Response(
+            RecipientConnector::from_vec(channels),
+        )),
+        Err(error) => map_contact_error(error),
+    }
+}
+
+/// Replaces the contact's channels
+#[derive(InputObject, Clone)]
+pub struct SetContactChannelsInput {
+    pub contact_id: String,
+    /// The contact's recipients in the order they're tried in, the next is sent to when sending to one fails
+    pub recipient_ids: Vec<String>,
+}
+
+impl From<SetContactChannelsInput> for SetContactChannels {
+    fn from(
+        SetContactChannelsInput {
+            contact_id,
+            recipient_ids,
+        }: SetContactChannelsInput,
+    ) -> Self {
+        SetContactChannels {
+            contact_id,
+            recipient_ids,
+        }
+    }
+}
+
+#[derive(Union)]
+pub enum SetContactChannelsResponse {
+    Response(RecipientConnector),
+}
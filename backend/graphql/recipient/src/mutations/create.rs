@@ -75,6 +75,7 @@ fn map_error(error: ModifyRecipientError) -> Result<CreateRecipientResponse> {
         ModifyRecipientError::RecipientDoesNotExist => BadUserInput(formatted_error),
         ModifyRecipientError::DatabaseError(_) => InternalError(formatted_error),
         ModifyRecipientError::ModifiedRecordNotFound => InternalError(formatted_error),
+        ModifyRecipientError::InvalidToAddress(_) => BadUserInput(formatted_error),
         ModifyRecipientError::GenericError(s) => InternalError(s),
     };
 
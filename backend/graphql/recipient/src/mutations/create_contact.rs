@@ -0,0 +1,46 @@
+use async_graphql::*;
+
+use graphql_core::{standard_graphql_error::validate_auth, ContextExt};
+use graphql_types::types::ContactNode;
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    contact::create::CreateContact,
+};
+
+use super::{map_contact_error, ModifyContactResponse};
+
+pub fn create_contact(
+    ctx: &Context<'_>,
+    input: CreateContactInput,
+) -> Result<ModifyContactResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+    match service_context
+        .service_provider
+        .contact_service
+        .create_contact(&service_context, input.into())
+    {
+        Ok(contact_row) => Ok(ModifyContactResponse::Response(ContactNode::from_domain(
+            contact_row,
+        ))),
+        Err(error) => map_contact_error(error),
+    }
+}
+
+#[derive(InputObject, Clone)]
+pub struct CreateContactInput {
+    pub id: String,
+    pub name: String,
+}
+
+impl From<CreateContactInput> for CreateContact {
+    fn from(CreateContactInput { id, name }: CreateContactInput) -> Self {
+        CreateContact { id, name }
+    }
+}
@@ -0,0 +1,48 @@
+use async_graphql::*;
+use graphql_core::{
+    standard_graphql_error::{validate_auth, StandardGraphqlError},
+    ContextExt,
+};
+
+use graphql_types::types::DeleteResponse;
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    contact::delete::DeleteContactError,
+};
+
+pub fn delete_contact(ctx: &Context<'_>, contact_id: &str) -> Result<DeleteContactResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+
+    match service_context
+        .service_provider
+        .contact_service
+        .delete_contact(&service_context, contact_id)
+    {
+        Ok(contact_id) => Ok(DeleteContactResponse::Response(DeleteResponse(contact_id))),
+        Err(error) => map_error(error),
+    }
+}
+
+#[derive(Union)]
+pub enum DeleteContactResponse {
+    Response(DeleteResponse),
+}
+
+fn map_error(error: DeleteContactError) -> Result<DeleteContactResponse> {
+    use StandardGraphqlError::*;
+    let formatted_error = format!("{:#?}", error);
+
+    let graphql_error = match error {
+        DeleteContactError::ContactDoesNotExist => BadUserInput(formatted_error),
+        DeleteContactError::DatabaseError(_) => InternalError(formatted_error),
+    };
+
+    Err(graphql_error.extend())
+}
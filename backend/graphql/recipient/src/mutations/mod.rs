@@ -1,7 +1,43 @@
+use async_graphql::*;
+use graphql_core::standard_graphql_error::StandardGraphqlError::*;
+use graphql_types::types::ContactNode;
+use service::contact::ModifyContactError;
+
+mod contact_channels;
 mod create;
+mod create_contact;
 mod delete;
+mod delete_contact;
+mod preferences;
 mod update;
+mod update_contact;
 
+pub use contact_channels::*;
 pub use create::*;
+pub use create_contact::*;
 pub use delete::*;
+pub use delete_contact::*;
+pub use preferences::*;
 pub use update::*;
+pub use update_contact::*;
+
+#[derive(Union)]
+pub enum ModifyContactResponse {
+    Response(ContactNode),
+}
+
+pub fn map_contact_error<T>(error: ModifyContactError) -> Result<T> {
+    let formatted_error = format!("{:#?}", error);
+
+    let graphql_error = match error {
+        ModifyContactError::ContactDoesNotExist => BadUserInput(formatted_error),
+        ModifyContactError::ContactAlreadyExists => BadUserInput(formatted_error),
+        ModifyContactError::RecipientDoesNotExist => BadUserInput(formatted_error),
+        ModifyContactError::ModifiedRecordNotFound => InternalError(formatted_error),
+        ModifyContactError::DatabaseError(_) => InternalError(formatted_error),
+        // Invalid input, e.g. an empty name or a recipient listed twice
+        ModifyContactError::GenericError(s) => BadUserInput(s),
+    };
+
+    Err(graphql_error.extend())
+}
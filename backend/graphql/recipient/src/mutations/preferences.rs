@@ -0,0 +1,85 @@
+use async_graphql::*;
+
+use graphql_core::{
+    standard_graphql_error::{validate_auth, StandardGraphqlError},
+    ContextExt,
+};
+use graphql_types::types::{DigestFrequencyNode, RecipientNode};
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    recipient::preferences::RecipientPreferences,
+    recipient::ModifyRecipientError,
+};
+
+pub fn update_recipient_preferences(
+    ctx: &Context<'_>,
+    input: UpdateRecipientPreferencesInput,
+) -> Result<UpdateRecipientPreferencesResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+    match service_context
+        .service_provider
+        .recipient_service
+        .set_recipient_preferences(&service_context, input.into())
+    {
+        Ok(recipient_row) => Ok(UpdateRecipientPreferencesResponse::Response(
+            RecipientNode::from_domain(recipient_row),
+        )),
+        Err(error) => map_error(error),
+    }
+}
+
+/// Replaces the recipient's preferences
+#[derive(InputObject, Clone)]
+pub struct UpdateRecipientPreferencesInput {
+    pub recipient_id: String,
+    pub digest_frequency: DigestFrequencyNode,
+    pub preferred_recipient_id: Option<String>,
+    pub opted_out_notification_config_ids: Vec<String>,
+}
+
+impl From<UpdateRecipientPreferencesInput> for RecipientPreferences {
+    fn from(
+        UpdateRecipientPreferencesInput {
+            recipient_id,
+            digest_frequency,
+            preferred_recipient_id,
+            opted_out_notification_config_ids,
+        }: UpdateRecipientPreferencesInput,
+    ) -> Self {
+        RecipientPreferences {
+            recipient_id,
+            digest_frequency: digest_frequency.to_domain(),
+            preferred_recipient_id,
+            opted_out_notification_config_ids,
+        }
+    }
+}
+
+#[derive(Union)]
+pub enum UpdateRecipientPreferencesResponse {
+    Response(RecipientNode),
+}
+
+fn map_error(error: ModifyRecipientError) -> Result<UpdateRecipientPreferencesResponse> {
+    use StandardGraphqlError::*;
+    let formatted_error = format!("{:#?}", error);
+
+    let graphql_error = match error {
+        ModifyRecipientError::RecipientDoesNotExist => BadUserInput(formatted_error),
+        ModifyRecipientError::RecipientAlreadyExists => BadUserInput(formatted_error),
+        ModifyRecipientError::ModifiedRecordNotFound => InternalError(formatted_error),
+        ModifyRecipientError::DatabaseError(_) => InternalError(formatted_error),
+        ModifyRecipientError::InvalidToAddress(_) => BadUserInput(formatted_error),
+        // Invalid preferences, e.g. the preferred recipient sends to another recipient itself
+        ModifyRecipientError::GenericError(s) => BadUserInput(s),
+    };
+
+    Err(graphql_error.extend())
+}
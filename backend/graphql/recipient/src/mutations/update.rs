@@ -73,6 +73,7 @@ fn map_error(error: ModifyRecipientError) -> Result<UpdateRecipientResponse> {
         ModifyRecipientError::RecipientAlreadyExists => BadUserInput(formatted_error),
         ModifyRecipientError::ModifiedRecordNotFound => InternalError(formatted_error),
         ModifyRecipientError::DatabaseError(_) => InternalError(formatted_error),
+        ModifyRecipientError::InvalidToAddress(_) => BadUserInput(formatted_error),
         ModifyRecipientError::GenericError(s) => InternalError(s),
     };
 
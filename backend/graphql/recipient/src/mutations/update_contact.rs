@@ -0,0 +1,46 @@
+use async_graphql::*;
+
+use graphql_core::{standard_graphql_error::validate_auth, ContextExt};
+use graphql_types::types::ContactNode;
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    contact::update::UpdateContact,
+};
+
+use super::{map_contact_error, ModifyContactResponse};
+
+pub fn update_contact(
+    ctx: &Context<'_>,
+    input: UpdateContactInput,
+) -> Result<ModifyContactResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+    match service_context
+        .service_provider
+        .contact_service
+        .update_contact(&service_context, input.into())
+    {
+        Ok(contact_row) => Ok(ModifyContactResponse::Response(ContactNode::from_domain(
+            contact_row,
+        ))),
+        Err(error) => map_contact_error(error),
+    }
+}
+
+#[derive(InputObject, Clone)]
+pub struct UpdateContactInput {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+impl From<UpdateContactInput> for UpdateContact {
+    fn from(UpdateContactInput { id, name }: UpdateContactInput) -> Self {
+        UpdateContact { id, name }
+    }
+}
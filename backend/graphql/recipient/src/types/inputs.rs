@@ -4,7 +4,10 @@ use graphql_core::{
     map_filter,
 };
 use graphql_types::types::NotificationTypeNode;
-use repository::{EqualFilter, RecipientFilter, RecipientSort, RecipientSortField, StringFilter};
+use repository::{
+    ContactFilter, ContactSort, ContactSortField, EqualFilter, RecipientFilter, RecipientSort,
+    RecipientSortField, StringFilter,
+};
 
 #[derive(Enum, Copy, Clone, PartialEq, Eq)]
 #[graphql(rename_items = "camelCase")]
@@ -50,6 +53,8 @@ pub struct RecipientFilterInput {
     pub search: Option<String>,
     pub to_address: Option<StringFilterInput>,
     pub notification_type: Option<EqualFilterNotificationTypeInput>,
+    /// Only recipients that are undeliverable or deactivated (true), or neither (false)
+    pub has_delivery_problem: Option<bool>,
 }
 
 impl From<RecipientFilterInput> for RecipientFilter {
@@ -61,6 +66,51 @@ impl From<RecipientFilterInput> for RecipientFilter {
                 .notification_type
                 .map(|t| map_filter!(t, NotificationTypeNode::to_domain)),
             search: f.search,
+            has_delivery_problem: f.has_delivery_problem,
+        }
+    }
+}
+
+#[derive(Enum, Copy, Clone, PartialEq, Eq)]
+#[graphql(rename_items = "camelCase")]
+pub enum ContactSortFieldInput {
+    Name,
+}
+
+#[derive(InputObject)]
+pub struct ContactSortInput {
+    /// Sort query result by `key`
+    key: ContactSortFieldInput,
+    /// Sort query result is sorted descending or ascending (if not provided the default is
+    /// ascending)
+    desc: Option<bool>,
+}
+impl ContactSortInput {
+    pub fn to_domain(self) -> ContactSort {
+        let key = match self.key {
+            ContactSortFieldInput::Name => ContactSortField::Name,
+        };
+
+        ContactSort {
+            key,
+            desc: self.desc,
+        }
+    }
+}
+
+#[derive(Clone, InputObject)]
+pub struct ContactFilterInput {
+    pub id: Option<EqualFilterStringInput>,
+    pub name: Option<StringFilterInput>,
+    pub search: Option<String>,
+}
+
+impl From<ContactFilterInput> for ContactFilter {
+    fn from(f: ContactFilterInput) -> Self {
+        ContactFilter {
+            id: f.id.map(EqualFilter::from),
+            name: f.name.map(StringFilter::from),
+            search: f.search,
         }
     }
 }
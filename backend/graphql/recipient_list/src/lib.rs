@@ -98,6 +98,7 @@ impl RecipientListQueries {
         ctx: &Context<'_>,
         query: String,
         params: String,
+        datasource_id: Option<String>,
     ) -> Result<RecipientsResponse> {
         let user = validate_auth(
             ctx,
@@ -111,7 +112,7 @@ impl RecipientListQueries {
         let recipients = service_context
             .service_provider
             .sql_recipient_list_service
-            .get_recipients_by_sql_query(&service_context, query, params)
+            .get_recipients_by_sql_query(&service_context, datasource_id, query, params)
             .map_err(StandardGraphqlError::from_list_error)?;
 
         Ok(RecipientsResponse::Response(
@@ -165,6 +166,22 @@ impl RecipientListMutations {
         remove_recipient_from_list(ctx, input)
     }
 
+    async fn add_contact_to_list(
+        &self,
+        ctx: &Context<'_>,
+        input: AddContactToListInput,
+    ) -> Result<ModifyRecipientListMembersResponse> {
+        add_contact_to_list(ctx, input)
+    }
+
+    async fn remove_contact_from_list(
+        &self,
+        ctx: &Context<'_>,
+        input: RemoveContactFromListInput,
+    ) -> Result<ModifyRecipientListMembersResponse> {
+        remove_contact_from_list(ctx, input)
+    }
+
     async fn create_sql_recipient_list(
         &self,
         ctx: &Context<'_>,
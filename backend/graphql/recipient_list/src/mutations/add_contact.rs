@@ -0,0 +1,52 @@
+use async_graphql::*;
+use graphql_types::types::IdResponse;
+
+use super::{map_error, ModifyRecipientListMembersResponse};
+use graphql_core::{standard_graphql_error::validate_auth, ContextExt};
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    recipient_list::add_contact::AddContactToList,
+};
+
+pub fn add_contact_to_list(
+    ctx: &Context<'_>,
+    input: AddContactToListInput,
+) -> Result<ModifyRecipientListMembersResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+
+    let service = &service_context.service_provider.recipient_list_service;
+
+    match service.add_contact_to_list(&service_context, input.into()) {
+        Ok(list_contact) => Ok(ModifyRecipientListMembersResponse::Response(IdResponse(
+            list_contact.contact_id,
+        ))),
+        Err(error) => map_error(error),
+    }
+}
+
+#[derive(InputObject, Clone)]
+pub struct AddContactToListInput {
+    pub contact_id: String,
+    pub recipient_list_id: String,
+}
+
+impl From<AddContactToListInput> for AddContactToList {
+    fn from(
+        AddContactToListInput {
+            contact_id,
+            recipient_list_id,
+        }: AddContactToListInput,
+    ) -> Self {
+        AddContactToList {
+            contact_id,
+            recipient_list_id,
+        }
+    }
+}
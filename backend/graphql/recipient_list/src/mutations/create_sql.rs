@@ -16,6 +16,7 @@ pub struct CreateSqlRecipientListInput {
     pub description: String,
     pub query: String,
     pub parameters: Vec<String>, // This will be saved as a JSON array object containing parameter names ["param1", "param2"] all params are assumed to be strings
+    pub datasource_id: Option<String>,
 }
 
 pub fn create_sql_recipient_list(
@@ -51,6 +52,7 @@ impl From<CreateSqlRecipientListInput> for CreateSqlRecipientList {
             description,
             query,
             parameters,
+            datasource_id,
         }: CreateSqlRecipientListInput,
     ) -> Self {
         CreateSqlRecipientList {
@@ -59,6 +61,7 @@ impl From<CreateSqlRecipientListInput> for CreateSqlRecipientList {
             description,
             query,
             required_parameters: parameters,
+            datasource_id,
         }
     }
 }
@@ -5,20 +5,24 @@ use service::{
     recipient_list::ModifyRecipientListError, sql_recipient_list::ModifySqlRecipientListError,
 };
 
+mod add_contact;
 mod add_member;
 mod create;
 mod create_sql;
 mod delete;
 mod delete_sql;
+mod remove_contact;
 mod remove_member;
 mod update;
 mod update_sql;
 
+pub use add_contact::*;
 pub use add_member::*;
 pub use create::*;
 pub use create_sql::*;
 pub use delete::*;
 pub use delete_sql::*;
+pub use remove_contact::*;
 pub use remove_member::*;
 pub use update::*;
 pub use update_sql::*;
@@ -46,6 +50,11 @@ pub fn map_error<T>(error: ModifyRecipientListError) -> Result<T> {
         ModifyRecipientListError::RecipientDoesNotExist => BadUserInput(formatted_error),
         ModifyRecipientListError::RecipientListMemberAlreadyExists => BadUserInput(formatted_error),
         ModifyRecipientListError::RecipientListMemberDoesNotExist => BadUserInput(formatted_error),
+        ModifyRecipientListError::ContactDoesNotExist => BadUserInput(formatted_error),
+        ModifyRecipientListError::RecipientListContactAlreadyExists => {
+            BadUserInput(formatted_error)
+        }
+        ModifyRecipientListError::RecipientListContactDoesNotExist => BadUserInput(formatted_error),
         ModifyRecipientListError::ModifiedRecordNotFound => InternalError(formatted_error),
         ModifyRecipientListError::DatabaseError(_) => InternalError(formatted_error),
         ModifyRecipientListError::GenericError(s) => InternalError(s),
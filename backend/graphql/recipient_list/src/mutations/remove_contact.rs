@@ -0,0 +1,52 @@
+use async_graphql::*;
+use graphql_types::types::IdResponse;
+
+use super::{map_error, ModifyRecipientListMembersResponse};
+use graphql_core::{standard_graphql_error::validate_auth, ContextExt};
+use service::{
+    auth::{Resource, ResourceAccessRequest},
+    recipient_list::remove_contact::RemoveContactFromList,
+};
+
+pub fn remove_contact_from_list(
+    ctx: &Context<'_>,
+    input: RemoveContactFromListInput,
+) -> Result<ModifyRecipientListMembersResponse> {
+    let user = validate_auth(
+        ctx,
+        &ResourceAccessRequest {
+            resource: Resource::ServerAdmin,
+        },
+    )?;
+
+    let service_context = ctx.service_context(Some(&user))?;
+
+    let service = &service_context.service_provider.recipient_list_service;
+
+    match service.remove_contact_from_list(&service_context, input.into()) {
+        Ok(list_contact) => Ok(ModifyRecipientListMembersResponse::Response(IdResponse(
+            list_contact.contact_id,
+        ))),
+        Err(error) => map_error(error),
+    }
+}
+
+#[derive(InputObject, Clone)]
+pub struct RemoveContactFromListInput {
+    pub contact_id: String,
+    pub recipient_list_id: String,
+}
+
+impl From<RemoveContactFromListInput> for RemoveContactFromList {
+    fn from(
+        RemoveContactFromListInput {
+            contact_id,
+            recipient_list_id,
+        }: RemoveContactFromListInput,
+    ) -> Self {
+        RemoveContactFromList {
+            contact_id,
+            recipient_list_id,
+        }
+    }
+}
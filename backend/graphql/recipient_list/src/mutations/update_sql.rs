@@ -40,6 +40,8 @@ pub struct UpdateSqlRecipientListInput {
     pub description: Option<String>,
     pub query: Option<String>,
     pub parameters: Option<Vec<String>>,
+    /// An empty string selects the default datasource
+    pub datasource_id: Option<String>,
 }
 
 impl From<UpdateSqlRecipientListInput> for UpdateSqlRecipientList {
@@ -50,6 +52,7 @@ impl From<UpdateSqlRecipientListInput> for UpdateSqlRecipientList {
             description,
             query,
             parameters,
+            datasource_id,
         }: UpdateSqlRecipientListInput,
     ) -> Self {
         UpdateSqlRecipientList {
@@ -58,6 +61,7 @@ impl From<UpdateSqlRecipientListInput> for UpdateSqlRecipientList {
             description,
             query,
             required_parameters: parameters,
+            datasource_id,
         }
     }
 }
@@ -1,11 +1,11 @@
 use async_graphql::{dataloader::DataLoader, Context, Object, SimpleObject, Union};
 use graphql_core::{
-    loader::{AuditLogLoader, RecipientsLoader},
+    loader::{AuditLogLoader, RecipientListContactsLoader, RecipientsLoader},
     simple_generic_errors::NodeError,
     ContextExt,
 };
 
-use graphql_types::types::{LogNode, RecipientNode};
+use graphql_types::types::{ContactNode, LogNode, RecipientNode};
 use repository::RecipientList;
 use service::ListResult;
 use util::usize_to_u32;
@@ -63,6 +63,19 @@ impl RecipientListNode {
 
         Ok(result.into_iter().map(RecipientNode::from_domain).collect())
     }
+
+    pub async fn contacts(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<ContactNode>, async_graphql::Error> {
+        let loader = ctx.get_loader::<DataLoader<RecipientListContactsLoader>>();
+        let result = loader
+            .load_one(self.row().id.to_string())
+            .await?
+            .unwrap_or_default();
+
+        Ok(result.into_iter().map(ContactNode::from_domain).collect())
+    }
 }
 
 impl RecipientListNode {
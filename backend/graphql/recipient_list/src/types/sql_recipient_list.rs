@@ -36,6 +36,9 @@ impl SqlRecipientListNode {
     pub async fn query(&self) -> &str {
         &self.row().query
     }
+    pub async fn datasource_id(&self) -> Option<String> {
+        self.row().datasource_id.clone()
+    }
     pub async fn parameters(&self) -> Result<Vec<String>, async_graphql::Error> {
         // Convert the parameters from a JSON array to a Vec<String>
         let parameters = serde_json::from_str::<Vec<String>>(&self.row().required_parameters)
@@ -0,0 +1,92 @@
+use super::{dataloader::DataLoader, LogNode, RecipientNode};
+use async_graphql::{Context, Object, SimpleObject, Union};
+use graphql_core::{
+    loader::{AuditLogLoader, ContactChannelsLoader},
+    ContextExt,
+};
+use repository::Contact;
+use service::ListResult;
+use util::usize_to_u32;
+
+#[derive(Union)]
+pub enum ContactsResponse {
+    Response(ContactConnector),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ContactNode {
+    pub contact: Contact,
+}
+
+#[Object]
+impl ContactNode {
+    pub async fn id(&self) -> &str {
+        &self.row().id
+    }
+    pub async fn name(&self) -> &str {
+        &self.row().name
+    }
+    /// The recipients the contact can be reached on, notifications go to the first that can be sent to
+    /// and fall back to the next if sending fails
+    pub async fn channels(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<RecipientNode>, async_graphql::Error> {
+        let loader = ctx.get_loader::<DataLoader<ContactChannelsLoader>>();
+        let result = loader
+            .load_one(self.row().id.to_string())
+            .await?
+            .unwrap_or_default();
+
+        Ok(result.into_iter().map(RecipientNode::from_domain).collect())
+    }
+
+    pub async fn audit_logs(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<LogNode>, async_graphql::Error> {
+        let loader = ctx.get_loader::<DataLoader<AuditLogLoader>>();
+        let result = loader
+            .load_one(self.row().id.to_string())
+            .await?
+            .unwrap_or_default();
+
+        Ok(result.into_iter().map(LogNode::from_domain).collect())
+    }
+}
+
+impl ContactNode {
+    pub fn from_domain(contact: Contact) -> ContactNode {
+        ContactNode { contact }
+    }
+
+    pub fn row(&self) -> &Contact {
+        &self.contact
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ContactConnector {
+    total_count: u32,
+    nodes: Vec<ContactNode>,
+}
+
+impl ContactConnector {
+    pub fn from_domain(contacts: ListResult<Contact>) -> ContactConnector {
+        ContactConnector {
+            total_count: contacts.count,
+            nodes: contacts
+                .rows
+                .into_iter()
+                .map(ContactNode::from_domain)
+                .collect(),
+        }
+    }
+
+    pub fn from_vec(contacts: Vec<Contact>) -> ContactConnector {
+        ContactConnector {
+            total_count: usize_to_u32(contacts.len()),
+            nodes: contacts.into_iter().map(ContactNode::from_domain).collect(),
+        }
+    }
+}
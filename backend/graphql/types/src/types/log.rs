@@ -35,6 +35,16 @@ pub enum LogNodeType {
     SqlRecipientListUpdated,
     NotificationQueryCreated,
     NotificationQueryUpdated,
+    RecipientDeactivated,
+    RecipientReactivated,
+    RecipientChatMigrated,
+    RecipientUndeliverable,
+    RecipientPreferencesUpdated,
+    ContactCreated,
+    ContactUpdated,
+    ContactChannelsUpdated,
+    ContactAddedToList,
+    ContactRemovedFromList,
 }
 
 #[Object]
@@ -101,6 +111,16 @@ impl LogNodeType {
             }
             LogType::NotificationQueryCreated => LogNodeType::NotificationQueryCreated,
             LogType::NotificationQueryUpdated => LogNodeType::NotificationQueryUpdated,
+            LogType::RecipientDeactivated => LogNodeType::RecipientDeactivated,
+            LogType::RecipientReactivated => LogNodeType::RecipientReactivated,
+            LogType::RecipientChatMigrated => LogNodeType::RecipientChatMigrated,
+            LogType::RecipientUndeliverable => LogNodeType::RecipientUndeliverable,
+            LogType::RecipientPreferencesUpdated => LogNodeType::RecipientPreferencesUpdated,
+            LogType::ContactCreated => LogNodeType::ContactCreated,
+            LogType::ContactUpdated => LogNodeType::ContactUpdated,
+            LogType::ContactChannelsUpdated => LogNodeType::ContactChannelsUpdated,
+            LogType::ContactAddedToList => LogNodeType::ContactAddedToList,
+            LogType::ContactRemovedFromList => LogNodeType::ContactRemovedFromList,
         }
     }
 
@@ -124,6 +144,16 @@ impl LogNodeType {
             }
             LogNodeType::NotificationQueryCreated => LogType::NotificationQueryCreated,
             LogNodeType::NotificationQueryUpdated => LogType::NotificationQueryUpdated,
+            LogNodeType::RecipientDeactivated => LogType::RecipientDeactivated,
+            LogNodeType::RecipientReactivated => LogType::RecipientReactivated,
+            LogNodeType::RecipientChatMigrated => LogType::RecipientChatMigrated,
+            LogNodeType::RecipientUndeliverable => LogType::RecipientUndeliverable,
+            LogNodeType::RecipientPreferencesUpdated => LogType::RecipientPreferencesUpdated,
+            LogNodeType::ContactCreated => LogType::ContactCreated,
+            LogNodeType::ContactUpdated => LogType::ContactUpdated,
+            LogNodeType::ContactChannelsUpdated => LogType::ContactChannelsUpdated,
+            LogNodeType::ContactAddedToList => LogType::ContactAddedToList,
+            LogNodeType::ContactRemovedFromList => LogType::ContactRemovedFromList,
         }
     }
 }
@@ -1,3 +1,5 @@
+pub mod contact;
+pub use self::contact::*;
 pub mod notification_config;
 pub use self::notification_config::*;
 pub mod recipient;
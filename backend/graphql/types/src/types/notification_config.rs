@@ -1,9 +1,11 @@
 use super::{dataloader::DataLoader, LogNode};
 use async_graphql::{Context, Enum, Object, SimpleObject, Union};
 use graphql_core::{loader::AuditLogLoader, simple_generic_errors::NodeError, ContextExt};
-use repository::{NotificationConfigKind, NotificationConfigStatus};
+use repository::{NotificationConfigKind, NotificationConfigStatus, NotificationPriority};
 use serde::Serialize;
-use service::{notification_config::query::NotificationConfig, ListResult};
+use service::{
+    email::layout::EmailBranding, notification_config::query::NotificationConfig, ListResult,
+};
 use util::usize_to_u32;
 
 #[derive(Union)]
@@ -14,7 +16,7 @@ pub enum NotificationConfigsResponse {
 #[derive(Union)]
 pub enum NotificationConfigResponse {
     Error(NodeError),
-    Response(NotificationConfigNode),
+    Response(Box<NotificationConfigNode>),
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -60,6 +62,34 @@ impl NotificationConfigNode {
         &self.row().sql_recipient_list_ids
     }
 
+    pub async fn contact_ids(&self) -> &[String] {
+        &self.row().contact_ids
+    }
+
+    pub async fn fallback_recipient_list_id(&self) -> &Option<String> {
+        &self.row().fallback_recipient_list_id
+    }
+
+    /// Priority of the config's notifications, if not set the plugin that sends them decides
+    pub async fn priority(&self) -> Option<NotificationPriorityNode> {
+        self.row()
+            .priority
+            .as_ref()
+            .map(NotificationPriorityNode::from_domain)
+    }
+
+    pub async fn sender_profile(&self) -> &Option<String> {
+        &self.row().sender_profile
+    }
+
+    /// Changes to the email layout's logo, colours and footer for this config's emails
+    pub async fn email_layout(&self) -> Option<EmailLayoutNode> {
+        self.row()
+            .email_layout
+            .clone()
+            .map(EmailLayoutNode::from_domain)
+    }
+
     pub async fn audit_logs(
         &self,
         ctx: &Context<'_>,
@@ -86,6 +116,32 @@ impl NotificationConfigNode {
     }
 }
 
+#[derive(SimpleObject, PartialEq, Debug, Clone)]
+pub struct EmailLayoutNode {
+    pub logo_url: Option<String>,
+    pub primary_colour: Option<String>,
+    pub background_colour: Option<String>,
+    pub footer_text: Option<String>,
+}
+
+impl EmailLayoutNode {
+    pub fn from_domain(
+        EmailBranding {
+            logo_url,
+            primary_colour,
+            background_colour,
+            footer_text,
+        }: EmailBranding,
+    ) -> EmailLayoutNode {
+        EmailLayoutNode {
+            logo_url,
+            primary_colour,
+            background_colour,
+            footer_text,
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 pub struct NotificationConfigConnector {
     total_count: u32,
@@ -160,3 +216,29 @@ impl ConfigStatus {
         }
     }
 }
+
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationPriorityNode {
+    Critical,
+    Normal,
+    Low,
+}
+
+impl NotificationPriorityNode {
+    pub fn to_domain(self) -> NotificationPriority {
+        match self {
+            NotificationPriorityNode::Critical => NotificationPriority::Critical,
+            NotificationPriorityNode::Normal => NotificationPriority::Normal,
+            NotificationPriorityNode::Low => NotificationPriority::Low,
+        }
+    }
+
+    pub fn from_domain(priority: &NotificationPriority) -> NotificationPriorityNode {
+        match priority {
+            NotificationPriority::Critical => NotificationPriorityNode::Critical,
+            NotificationPriority::Normal => NotificationPriorityNode::Normal,
+            NotificationPriority::Low => NotificationPriorityNode::Low,
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use super::{dataloader::DataLoader, LogNode};
 use async_graphql::{Context, Enum, Object, SimpleObject, Union};
+use chrono::{DateTime, Utc};
 use datasource::BasicRecipientRow;
-use graphql_core::{loader::AuditLogLoader, simple_generic_errors::NodeError, ContextExt};
-use repository::{NotificationType, Recipient};
+use graphql_core::{
+    loader::{AuditLogLoader, RecipientOptOutLoader},
+    simple_generic_errors::NodeError,
+    ContextExt,
+};
+use repository::{DigestFrequency, NotificationType, Recipient};
 use serde::Serialize;
 use service::ListResult;
 use util::usize_to_u32;
@@ -39,6 +44,60 @@ impl RecipientNode {
     pub async fn notification_type(&self) -> NotificationTypeNode {
         NotificationTypeNode::from_domain(&self.row().notification_type)
     }
+    /// Set when a telegram chat mutes notifications with the /mute command, critical alerts are still sent
+    pub async fn muted_until(&self) -> Option<DateTime<Utc>> {
+        self.row()
+            .muted_until
+            .map(|muted_until| DateTime::<Utc>::from_utc(muted_until, Utc))
+    }
+    /// Set when the recipient can't be sent to any more, e.g. the bot was removed from the telegram chat.
+    /// Notifications aren't sent to deactivated recipients.
+    pub async fn deactivated_datetime(&self) -> Option<DateTime<Utc>> {
+        self.row()
+            .deactivated_datetime
+            .map(|deactivated_datetime| DateTime::<Utc>::from_utc(deactivated_datetime, Utc))
+    }
+    /// Set when the address can't be delivered to, e.g. an email address that hard bounced.
+    /// Notifications to undeliverable recipients fail without being sent.
+    pub async fn undeliverable_datetime(&self) -> Option<DateTime<Utc>> {
+        self.row()
+            .undeliverable_datetime
+            .map(|undeliverable_datetime| DateTime::<Utc>::from_utc(undeliverable_datetime, Utc))
+    }
+    /// Why the address can't be delivered to, e.g. the mail server's reply in the bounce
+    pub async fn undeliverable_reason(&self) -> Option<&str> {
+        self.row().undeliverable_reason.as_deref()
+    }
+
+    /// Whether notifications are sent as they happen, or collected into a daily or weekly digest
+    pub async fn digest_frequency(&self) -> DigestFrequencyNode {
+        DigestFrequencyNode::from_domain(&self.row().digest_frequency)
+    }
+    /// Another of the person's recipients that notifications are sent to instead of this one
+    pub async fn preferred_recipient_id(&self) -> Option<&str> {
+        self.row().preferred_recipient_id.as_deref()
+    }
+    /// The contact (person) the recipient is one of the channels of
+    pub async fn contact_id(&self) -> Option<&str> {
+        self.row().contact_id.as_deref()
+    }
+    /// The order the contact's channels are tried in, lowest first
+    pub async fn fallback_order(&self) -> i32 {
+        self.row().fallback_order
+    }
+    /// Notification configs the recipient has chosen not to receive
+    pub async fn opted_out_notification_config_ids(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Vec<String>, async_graphql::Error> {
+        let loader = ctx.get_loader::<DataLoader<RecipientOptOutLoader>>();
+        let result = loader
+            .load_one(self.row().id.to_string())
+            .await?
+            .unwrap_or_default();
+
+        Ok(result)
+    }
 
     pub async fn audit_logs(
         &self,
@@ -122,6 +181,11 @@ impl RecipientConnector {
 pub enum NotificationTypeNode {
     Email,
     Telegram,
+    Teams,
+    Slack,
+    Mattermost,
+    Sms,
+    NtfyPush,
     Unknown,
 }
 
@@ -130,6 +194,11 @@ impl NotificationTypeNode {
         match self {
             NotificationTypeNode::Email => NotificationType::Email,
             NotificationTypeNode::Telegram => NotificationType::Telegram,
+            NotificationTypeNode::Teams => NotificationType::Teams,
+            NotificationTypeNode::Slack => NotificationType::Slack,
+            NotificationTypeNode::Mattermost => NotificationType::Mattermost,
+            NotificationTypeNode::Sms => NotificationType::Sms,
+            NotificationTypeNode::NtfyPush => NotificationType::NtfyPush,
             NotificationTypeNode::Unknown => NotificationType::Unknown,
         }
     }
@@ -138,7 +207,38 @@ impl NotificationTypeNode {
         match notification_type {
             NotificationType::Email => NotificationTypeNode::Email,
             NotificationType::Telegram => NotificationTypeNode::Telegram,
+            NotificationType::Teams => NotificationTypeNode::Teams,
+            NotificationType::Slack => NotificationTypeNode::Slack,
+            NotificationType::Mattermost => NotificationTypeNode::Mattermost,
+            NotificationType::Sms => NotificationTypeNode::Sms,
+            NotificationType::NtfyPush => NotificationTypeNode::NtfyPush,
             NotificationType::Unknown => NotificationTypeNode::Unknown,
         }
     }
 }
+
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DigestFrequencyNode {
+    Immediate,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequencyNode {
+    pub fn to_domain(self) -> DigestFrequency {
+        match self {
+            DigestFrequencyNode::Immediate => DigestFrequency::Immediate,
+            DigestFrequencyNode::Daily => DigestFrequency::Daily,
+            DigestFrequencyNode::Weekly => DigestFrequency::Weekly,
+        }
+    }
+
+    pub fn from_domain(digest_frequency: &DigestFrequency) -> DigestFrequencyNode {
+        match digest_frequency {
+            DigestFrequency::Immediate => DigestFrequencyNode::Immediate,
+            DigestFrequency::Daily => DigestFrequencyNode::Daily,
+            DigestFrequency::Weekly => DigestFrequencyNode::Weekly,
+        }
+    }
+}
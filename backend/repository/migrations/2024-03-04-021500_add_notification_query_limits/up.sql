@@ -0,0 +1,4 @@
+-- Optional per query limits, NULL means the datasource's global limit applies
+ALTER TABLE notification_query ADD COLUMN timeout_seconds INTEGER;
+ALTER TABLE notification_query ADD COLUMN max_rows INTEGER;
+ALTER TABLE notification_query ADD COLUMN max_result_bytes INTEGER;
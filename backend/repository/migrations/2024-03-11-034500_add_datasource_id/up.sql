@@ -0,0 +1,3 @@
+-- Named datasource to run the query against, NULL means the default datasource
+ALTER TABLE notification_query ADD COLUMN datasource_id TEXT;
+ALTER TABLE sql_recipient_list ADD COLUMN datasource_id TEXT;
@@ -0,0 +1,4 @@
+-- NONE means the query results are only used in the notification template
+ALTER TABLE notification_query ADD COLUMN attachment_format TEXT NOT NULL DEFAULT 'NONE';
+-- JSON array of the files sent with the notification, file content is base64 encoded
+ALTER TABLE notification_event ADD COLUMN attachments TEXT;
@@ -0,0 +1,2 @@
+-- Notifications aren't sent to a recipient until this time, e.g. after a telegram chat sends /mute
+ALTER TABLE recipient ADD COLUMN muted_until TIMESTAMP;
@@ -0,0 +1,2 @@
+-- Set when a recipient can't be sent to any more, e.g. after the bot is removed from a telegram chat
+ALTER TABLE recipient ADD COLUMN deactivated_datetime TIMESTAMP;
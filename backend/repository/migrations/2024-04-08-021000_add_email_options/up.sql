@@ -0,0 +1,5 @@
+-- JSON array of the files sent with the email, file content is base64 encoded
+ALTER TABLE email_queue ADD COLUMN attachments TEXT;
+-- JSON object of the email's inline images, reply-to, cc, bcc and custom headers
+ALTER TABLE email_queue ADD COLUMN options TEXT;
+ALTER TABLE notification_event ADD COLUMN email_options TEXT;
@@ -0,0 +1,15 @@
+-- System emails (password resets, invites etc) are now sent as notification events, so there is one delivery log and retry policy
+-- The html body of emails that are written in html rather than rendered from the markdown message
+ALTER TABLE notification_event ADD COLUMN html_message TEXT;
+
+-- Errored emails are retried straight away, the same as they were from the email queue
+INSERT INTO notification_event (
+    id, notification_config_id, notification_type, to_address, title, message, html_message, status,
+    created_at, updated_at, sent_at, retry_at, send_attempts, error_message, context, attachments, email_options
+)
+SELECT
+    id, NULL, 'EMAIL', to_address, subject, text_body, html_body, status,
+    created_at, updated_at, sent_at, CASE WHEN status = 'ERRORED' THEN updated_at ELSE NULL END, retries, error, NULL, attachments, options
+FROM email_queue;
+
+DROP TABLE email_queue;
@@ -0,0 +1,2 @@
+-- Name of the mail sender_profiles entry that emails for this config are sent from
+ALTER TABLE notification_config ADD COLUMN sender_profile TEXT;
@@ -0,0 +1,3 @@
+-- Set when a recipient's address can't be delivered to, e.g. an email address that hard bounced
+ALTER TABLE recipient ADD COLUMN undeliverable_datetime TIMESTAMP;
+ALTER TABLE recipient ADD COLUMN undeliverable_reason TEXT;
@@ -0,0 +1,2 @@
+-- Changes to the email layout's logo, colours and footer for this config's emails, as a json object
+ALTER TABLE notification_config ADD COLUMN email_layout TEXT;
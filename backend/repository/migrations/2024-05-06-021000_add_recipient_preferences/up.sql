@@ -0,0 +1,13 @@
+-- How the recipient gets their notifications, IMMEDIATE or collected into a DAILY or WEEKLY digest
+ALTER TABLE recipient ADD COLUMN digest_frequency TEXT NOT NULL DEFAULT 'IMMEDIATE';
+-- Another of the person's recipients, e.g. their telegram chat, that notifications are sent to instead
+ALTER TABLE recipient ADD COLUMN preferred_recipient_id TEXT REFERENCES recipient(id);
+
+-- Notification configs the recipient has chosen not to receive
+CREATE TABLE recipient_opt_out (
+    id TEXT NOT NULL PRIMARY KEY,
+    recipient_id TEXT NOT NULL REFERENCES recipient(id),
+    notification_config_id TEXT NOT NULL REFERENCES notification_config(id),
+    created_datetime TIMESTAMP NOT NULL
+);
+CREATE UNIQUE INDEX ux_recipient_opt_out ON recipient_opt_out (recipient_id, notification_config_id);
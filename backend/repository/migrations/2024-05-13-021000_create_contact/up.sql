@@ -0,0 +1,19 @@
+-- A person, who can be reached through one or more recipients (their channels)
+CREATE TABLE contact (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    deleted_datetime TIMESTAMP
+);
+
+-- The contact the recipient is a channel of, and the order the contact's channels are tried in
+ALTER TABLE recipient ADD COLUMN contact_id TEXT REFERENCES contact(id);
+ALTER TABLE recipient ADD COLUMN fallback_order INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE recipient_list_contact (
+    id TEXT NOT NULL PRIMARY KEY,
+    recipient_list_id TEXT NOT NULL REFERENCES recipient_list(id),
+    contact_id TEXT NOT NULL REFERENCES contact(id)
+);
+CREATE UNIQUE INDEX ux_recipient_list_contact ON recipient_list_contact (recipient_list_id, contact_id);
+
+ALTER TABLE notification_config ADD COLUMN contact_ids TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,5 @@
+-- Recipient list that a notification is sent to when it fails permanently and the contact has no other channel to try
+ALTER TABLE notification_config ADD COLUMN fallback_recipient_list_id TEXT;
+
+-- The failed notification that this notification was queued as a fallback for
+ALTER TABLE notification_event ADD COLUMN parent_event_id TEXT REFERENCES notification_event(id);
@@ -0,0 +1,7 @@
+-- CRITICAL, NORMAL or LOW, when it's null the plugin chooses each notification's priority
+ALTER TABLE notification_config ADD COLUMN priority TEXT;
+
+ALTER TABLE notification_event ADD COLUMN priority TEXT NOT NULL DEFAULT 'NORMAL';
+-- Scheduled reports waiting to be sent are low priority
+UPDATE notification_event SET priority = 'LOW'
+WHERE notification_config_id IN (SELECT id FROM notification_config WHERE kind = 'SCHEDULED');
@@ -0,0 +1,2 @@
+-- How many of a notification's messages have been delivered, so a retry after a telegram message part or attachment fails doesn't send them again
+ALTER TABLE notification_event ADD COLUMN sent_parts INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- The Message-ID of the email a notification was sent as, so a bounce can be matched to the email it's for
+ALTER TABLE notification_event ADD COLUMN message_id TEXT;
+CREATE INDEX ix_notification_event_message_id ON notification_event (message_id);
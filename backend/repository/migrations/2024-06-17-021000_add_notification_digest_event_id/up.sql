@@ -0,0 +1,3 @@
+-- The digest a held notification was sent in, the notification is sent or failed along with its digest
+ALTER TABLE notification_event ADD COLUMN digest_event_id TEXT;
+CREATE INDEX ix_notification_event_digest_event_id ON notification_event (digest_event_id);
@@ -35,6 +35,16 @@ pub enum LogType {
     SqlRecipientListUpdated,
     NotificationQueryCreated,
     NotificationQueryUpdated,
+    RecipientDeactivated,
+    RecipientReactivated,
+    RecipientChatMigrated,
+    RecipientUndeliverable,
+    RecipientPreferencesUpdated,
+    ContactCreated,
+    ContactUpdated,
+    ContactChannelsUpdated,
+    ContactAddedToList,
+    ContactRemovedFromList,
 }
 
 #[derive(Clone, Queryable, Insertable, AsChangeset, Debug, PartialEq)]
@@ -0,0 +1,120 @@
+use super::{
+    contact_row::{contact, contact::dsl as contact_dsl},
+    ContactRow, DBType, StorageConnection,
+};
+use crate::{
+    diesel_macros::{apply_equal_filter, apply_sort_no_case, apply_string_filter},
+    repository_error::RepositoryError,
+    EqualFilter, Pagination, Sort, StringFilter,
+};
+
+use diesel::{dsl::IntoBoxed, prelude::*};
+
+pub type Contact = ContactRow;
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ContactFilter {
+    pub id: Option<EqualFilter<String>>,
+    pub name: Option<StringFilter>,
+    pub search: Option<String>,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ContactSortField {
+    Name,
+}
+
+pub type ContactSort = Sort<ContactSortField>;
+
+pub struct ContactRepository<'a> {
+    connection: &'a StorageConnection,
+}
+
+impl<'a> ContactRepository<'a> {
+    pub fn new(connection: &'a StorageConnection) -> Self {
+        ContactRepository { connection }
+    }
+
+    pub fn count(&self, filter: Option<ContactFilter>) -> Result<i64, RepositoryError> {
+        let query = create_filtered_query(filter);
+
+        Ok(query.count().get_result(&self.connection.connection)?)
+    }
+
+    pub fn query_by_filter(&self, filter: ContactFilter) -> Result<Vec<Contact>, RepositoryError> {
+        self.query(Pagination::new(), Some(filter), None)
+    }
+
+    pub fn query_one(&self, filter: ContactFilter) -> Result<Option<Contact>, RepositoryError> {
+        Ok(self.query_by_filter(filter)?.pop())
+    }
+
+    pub fn query(
+        &self,
+        pagination: Pagination,
+        filter: Option<ContactFilter>,
+        sort: Option<ContactSort>,
+    ) -> Result<Vec<Contact>, RepositoryError> {
+        let mut query = create_filtered_query(filter);
+
+        if let Some(sort) = sort {
+            match sort.key {
+                ContactSortField::Name => {
+                    apply_sort_no_case!(query, sort, contact_dsl::name);
+                }
+            }
+        } else {
+            query = query.order(contact_dsl::id.asc())
+        }
+
+        let final_query = query
+            .offset(pagination.offset as i64)
+            .limit(pagination.limit as i64);
+
+        let result = final_query.load::<Contact>(&self.connection.connection)?;
+        Ok(result)
+    }
+}
+
+type BoxedContactQuery = IntoBoxed<'static, contact::table, DBType>;
+
+fn create_filtered_query(filter: Option<ContactFilter>) -> BoxedContactQuery {
+    let mut query = contact_dsl::contact
+        .into_boxed()
+        .filter(contact_dsl::deleted_datetime.is_null());
+
+    if let Some(f) = filter {
+        let ContactFilter { id, name, search } = f;
+
+        apply_equal_filter!(query, id, contact_dsl::id);
+        apply_string_filter!(query, name, contact_dsl::name);
+
+        if let Some(search) = search {
+            let search_term = format!("%{}%", search);
+            query = query.filter(contact_dsl::name.like(search_term));
+        }
+    }
+
+    query
+}
+
+impl ContactFilter {
+    pub fn new() -> ContactFilter {
+        ContactFilter::default()
+    }
+
+    pub fn id(mut self, filter: EqualFilter<String>) -> Self {
+        self.id = Some(filter);
+        self
+    }
+
+    pub fn name(mut self, filter: StringFilter) -> Self {
+        self.name = Some(filter);
+        self
+    }
+
+    pub fn search(mut self, filter: String) -> Self {
+        self.search = Some(filter);
+        self
+    }
+}
@@ -0,0 +1,63 @@
+use super::{contact_row::contact::dsl as contact_dsl, StorageConnection};
+use crate::repository_error::RepositoryError;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+table! {
+    contact (id) {
+        id -> Text,
+        name -> Text,
+        deleted_datetime -> Nullable<Timestamp>,
+    }
+}
+
+/// A person, their recipients are the channels they can be reached on
+#[derive(
+    Clone, Queryable, Identifiable, Insertable, AsChangeset, Debug, PartialEq, Eq, Default,
+)]
+#[table_name = "contact"]
+pub struct ContactRow {
+    pub id: String,
+    pub name: String,
+    pub deleted_datetime: Option<NaiveDateTime>,
+}
+
+pub struct ContactRowRepository<'a> {
+    connection: &'a StorageConnection,
+}
+
+impl<'a> ContactRowRepository<'a> {
+    pub fn new(connection: &'a StorageConnection) -> Self {
+        ContactRowRepository { connection }
+    }
+
+    pub fn insert_one(&self, row: &ContactRow) -> Result<(), RepositoryError> {
+        diesel::insert_into(contact_dsl::contact)
+            .values(row)
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn update_one(&self, row: &ContactRow) -> Result<(), RepositoryError> {
+        let query = diesel::update(row).set(row);
+        query.execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn mark_deleted(&self, id: &str) -> Result<(), RepositoryError> {
+        let query = diesel::update(contact_dsl::contact)
+            .filter(contact_dsl::id.eq(id))
+            .filter(contact_dsl::deleted_datetime.is_null())
+            .set(contact_dsl::deleted_datetime.eq(chrono::Utc::now().naive_utc()));
+        query.execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn find_one_by_id(&self, id: &str) -> Result<Option<ContactRow>, RepositoryError> {
+        let result = contact_dsl::contact
+            .filter(contact_dsl::id.eq(id))
+            .first(&self.connection.connection)
+            .optional()?;
+        Ok(result)
+    }
+}
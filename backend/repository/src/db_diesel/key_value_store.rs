@@ -23,6 +23,7 @@ pub enum KeyValueType {
     LogLevel,
     LogDirectory,
     LogFileName,
+    TelegramLastUpdateId,
 }
 
 #[derive(Clone, Queryable, Insertable, AsChangeset, Debug, PartialEq)]
@@ -5,8 +5,9 @@ use crate::repository_error::RepositoryError;
 
 pub mod audit_log;
 mod audit_log_row;
+pub mod contact;
+mod contact_row;
 pub mod diesel_schema;
-mod email_queue_row;
 mod filter_sort_pagination;
 pub mod key_value_store;
 pub mod notification_config;
@@ -18,9 +19,11 @@ pub mod notification_query_row;
 pub mod plugin_store;
 pub mod recipient;
 pub mod recipient_list;
+mod recipient_list_contact_row;
 pub mod recipient_list_member;
 mod recipient_list_member_row;
 mod recipient_list_row;
+mod recipient_opt_out_row;
 mod recipient_row;
 pub mod sql_recipient_list;
 pub mod sql_recipient_list_row;
@@ -31,7 +34,8 @@ pub mod user_permission;
 mod user_permission_row;
 pub use audit_log::*;
 pub use audit_log_row::*;
-pub use email_queue_row::*;
+pub use contact::*;
+pub use contact_row::*;
 pub use filter_sort_pagination::*;
 pub use key_value_store::*;
 pub use notification_config::*;
@@ -43,9 +47,11 @@ pub use notification_query_row::*;
 pub use plugin_store::*;
 pub use recipient::*;
 pub use recipient_list::*;
+pub use recipient_list_contact_row::*;
 pub use recipient_list_member::*;
 pub use recipient_list_member_row::*;
 pub use recipient_list_row::*;
+pub use recipient_opt_out_row::*;
 pub use recipient_row::*;
 pub use sql_recipient_list::*;
 pub use sql_recipient_list_row::*;
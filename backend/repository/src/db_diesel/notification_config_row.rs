@@ -1,7 +1,7 @@
 use super::{
     notification_config_row::notification_config::dsl as notification_config_dsl, StorageConnection,
 };
-use crate::{repository_error::RepositoryError, EqualFilter};
+use crate::{repository_error::RepositoryError, EqualFilter, NotificationPriority};
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel_derive_enum::DbEnum;
@@ -20,6 +20,11 @@ table! {
         sql_recipient_list_ids -> Text,
         last_run_datetime -> Nullable<Timestamp>,
         next_due_datetime -> Nullable<Timestamp>,
+        sender_profile -> Nullable<Text>,
+        email_layout -> Nullable<Text>,
+        contact_ids -> Text,
+        fallback_recipient_list_id -> Nullable<Text>,
+        priority -> Nullable<crate::db_diesel::notification_event_row::NotificationPriorityMapping>,
     }
 }
 
@@ -86,13 +91,18 @@ pub struct NotificationConfigRow {
     // it would appear the diesel JSON types are only available if the postgres feature is enabled...
     pub configuration_data: String,
     pub status: NotificationConfigStatus,
-    pub parameters: String,             // JSON object {key: "value"}
+    pub parameters: String, // JSON object {key: "value"}
     pub parameter_query_id: Option<String>,
     pub recipient_ids: String,          // JSON array of strings (ids)
     pub recipient_list_ids: String,     // JSON array of strings (ids)
     pub sql_recipient_list_ids: String, // JSON array of strings (ids)
     pub last_run_datetime: Option<NaiveDateTime>,
     pub next_due_datetime: Option<NaiveDateTime>,
+    pub sender_profile: Option<String>, // Name of the mail sender profile to send emails from
+    pub email_layout: Option<String>, // JSON object of changes to the email layout's logo, colours and footer
+    pub contact_ids: String,          // JSON array of strings (ids)
+    pub fallback_recipient_list_id: Option<String>, // Recipient list that permanently failed notifications are sent to instead
+    pub priority: Option<NotificationPriority>, // Overrides the priority the plugin gives the config's notifications
 }
 
 pub struct NotificationConfigRowRepository<'a> {
@@ -7,7 +7,7 @@ use super::{
 use crate::{
     diesel_macros::{apply_date_time_filter, apply_equal_filter, apply_sort_no_case},
     repository_error::RepositoryError,
-    DatetimeFilter, EqualFilter, NotificationEventStatus, Pagination, Sort,
+    DatetimeFilter, EqualFilter, NotificationEventStatus, NotificationPriority, Pagination, Sort,
 };
 
 use diesel::{dsl::IntoBoxed, prelude::*};
@@ -21,6 +21,7 @@ pub struct NotificationEventFilter {
     pub notification_config_id: Option<EqualFilter<String>>,
     pub status: Option<EqualFilter<NotificationEventStatus>>,
     pub created_at: Option<DatetimeFilter>,
+    pub priority: Option<EqualFilter<NotificationPriority>>,
 }
 
 impl NotificationEventFilter {
@@ -42,6 +43,11 @@ impl NotificationEventFilter {
         self.search = Some(filter);
         self
     }
+
+    pub fn priority(mut self, filter: EqualFilter<NotificationPriority>) -> Self {
+        self.priority = Some(filter);
+        self
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -153,10 +159,12 @@ fn create_filtered_query(filter: Option<NotificationEventFilter>) -> BoxedQuery
             status,
             created_at,
             notification_config_id,
+            priority,
         } = f;
 
         apply_equal_filter!(query, id, notification_event_dsl::id);
         apply_equal_filter!(query, status, notification_event_dsl::status);
+        apply_equal_filter!(query, priority, notification_event_dsl::priority);
         apply_equal_filter!(
             query,
             notification_config_id,
@@ -169,7 +177,10 @@ fn create_filtered_query(filter: Option<NotificationEventFilter>) -> BoxedQuery
             query = query.filter(
                 notification_event_dsl::title
                     .like(search_term.clone())
-                    .or(notification_event_dsl::message.like(search_term.clone()))
+                    // System emails can contain password reset links, so their message isn't searched
+                    .or(notification_event_dsl::message
+                        .like(search_term.clone())
+                        .and(notification_event_dsl::html_message.is_null()))
                     .or(notification_event_dsl::to_address.like(search_term.clone()))
                     .or(notification_event_dsl::error_message.like(search_term.clone())),
             );
@@ -39,6 +39,14 @@ table! {
         send_attempts -> Integer,
         error_message -> Nullable<Text>,
         context -> Nullable<Text>,
+        attachments -> Nullable<Text>,
+        email_options -> Nullable<Text>,
+        html_message -> Nullable<Text>,
+        parent_event_id -> Nullable<Text>,
+        priority -> crate::db_diesel::notification_event_row::NotificationPriorityMapping,
+        sent_parts -> Integer,
+        message_id -> Nullable<Text>,
+        digest_event_id -> Nullable<Text>,
     }
 }
 
@@ -48,8 +56,21 @@ pub enum NotificationEventStatus {
     #[default]
     Queued,
     Sent,
-    Errored, // Errored will be re-tried
-    Failed,  // Failed will not be re-tried
+    Errored,  // Errored will be re-tried
+    Failed,   // Failed will not be re-tried
+    Digest,   // Held until it's sent in the recipient's daily or weekly digest
+    Sending,  // Being sent, still sending after a restart means it was interrupted
+    Digested, // Added to a digest, it's sent or failed along with the digest
+}
+
+/// How urgent a notification is, it decides the order notifications are sent in and how hard they're retried
+#[derive(DbEnum, Debug, Clone, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+#[DbValueStyle = "SCREAMING_SNAKE_CASE"]
+pub enum NotificationPriority {
+    Critical, // e.g. a fridge that's too hot or cold
+    #[default]
+    Normal,
+    Low, // e.g. scheduled reports
 }
 
 #[derive(
@@ -71,6 +92,14 @@ pub struct NotificationEventRow {
     pub send_attempts: i32,
     pub error_message: Option<String>,
     pub context: Option<String>, // JSON object, the tera context for the event
+    pub attachments: Option<String>, // JSON array of files to send with the message
+    pub email_options: Option<String>, // JSON object of inline images, reply-to, cc, bcc and headers for emails
+    pub html_message: Option<String>, // Emails written in html, e.g. password resets, rather than rendered from the markdown message
+    pub parent_event_id: Option<String>, // The failed notification this one was queued as a fallback for
+    pub priority: NotificationPriority,
+    pub sent_parts: i32, // Messages already delivered when a notification is sent as several, e.g. a telegram message and its attachments
+    pub message_id: Option<String>, // The Message-ID header of the email, bounces are matched to the notification by it
+    pub digest_event_id: Option<String>, // The digest a held notification was added to
 }
 
 pub struct NotificationEventRowRepository<'a> {
@@ -104,15 +133,92 @@ impl<'a> NotificationEventRowRepository<'a> {
         Ok(())
     }
 
+    pub fn find_one_by_id(
+        &self,
+        notification_event_id: &str,
+    ) -> Result<Option<NotificationEventRow>, RepositoryError> {
+        let result = notification_event_dsl::notification_event
+            .filter(notification_event_dsl::id.eq(notification_event_id))
+            .first(&self.connection.connection)
+            .optional()?;
+        Ok(result)
+    }
+
     pub fn un_sent(&self) -> Result<Vec<NotificationEventRow>, RepositoryError> {
         let result = notification_event_dsl::notification_event
             .filter(
                 notification_event_dsl::status
                     .eq(NotificationEventStatus::Queued)
-                    .or(notification_event_dsl::status.eq(NotificationEventStatus::Errored)
+                    .or(notification_event_dsl::status
+                        .eq(NotificationEventStatus::Errored)
                         .and(notification_event_dsl::retry_at.le(diesel::dsl::now))),
             )
             .load::<NotificationEventRow>(&self.connection.connection)?;
         Ok(result)
     }
+
+    /// Marks the notification as sending, if it hasn't changed since it was loaded.
+    /// Returns false when it's already been claimed or sent
+    pub fn claim_for_sending(
+        &self,
+        notification: &NotificationEventRow,
+    ) -> Result<bool, RepositoryError> {
+        let claimed = diesel::update(
+            notification_event_dsl::notification_event
+                .filter(notification_event_dsl::id.eq(&notification.id))
+                .filter(notification_event_dsl::status.eq(&notification.status)),
+        )
+        .set((
+            notification_event_dsl::status.eq(NotificationEventStatus::Sending),
+            notification_event_dsl::updated_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(&self.connection.connection)?;
+        Ok(claimed == 1)
+    }
+
+    /// Notifications that were being sent when the server stopped
+    pub fn find_all_sending(&self) -> Result<Vec<NotificationEventRow>, RepositoryError> {
+        let result = notification_event_dsl::notification_event
+            .filter(notification_event_dsl::status.eq(NotificationEventStatus::Sending))
+            .load::<NotificationEventRow>(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    /// The email with the Message-ID, e.g. the original email returned with a bounce
+    pub fn find_one_by_message_id(
+        &self,
+        message_id: &str,
+    ) -> Result<Option<NotificationEventRow>, RepositoryError> {
+        let result = notification_event_dsl::notification_event
+            .filter(notification_event_dsl::message_id.eq(message_id))
+            .first(&self.connection.connection)
+            .optional()?;
+        Ok(result)
+    }
+
+    /// Gives the notifications added to the digest the digest's status, once it's been sent or has failed
+    pub fn update_digested(&self, digest: &NotificationEventRow) -> Result<(), RepositoryError> {
+        diesel::update(
+            notification_event_dsl::notification_event
+                .filter(notification_event_dsl::digest_event_id.eq(&digest.id))
+                .filter(notification_event_dsl::status.eq(NotificationEventStatus::Digested)),
+        )
+        .set((
+            notification_event_dsl::status.eq(&digest.status),
+            notification_event_dsl::sent_at.eq(digest.sent_at),
+            notification_event_dsl::error_message.eq(&digest.error_message),
+            notification_event_dsl::updated_at.eq(digest.updated_at),
+        ))
+        .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    /// Notifications held for recipients' digests
+    pub fn find_all_held_for_digest(&self) -> Result<Vec<NotificationEventRow>, RepositoryError> {
+        let result = notification_event_dsl::notification_event
+            .filter(notification_event_dsl::status.eq(NotificationEventStatus::Digest))
+            .order(notification_event_dsl::created_at.asc())
+            .load::<NotificationEventRow>(&self.connection.connection)?;
+        Ok(result)
+    }
 }
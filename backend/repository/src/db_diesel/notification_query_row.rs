@@ -4,6 +4,7 @@ use super::{
 use crate::repository_error::RepositoryError;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use diesel_derive_enum::DbEnum;
 
 table! {
     notification_query (id) {
@@ -15,13 +16,29 @@ table! {
         required_parameters -> Text,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        timeout_seconds -> Nullable<Integer>,
+        max_rows -> Nullable<Integer>,
+        max_result_bytes -> Nullable<Integer>,
+        datasource_id -> Nullable<Text>,
+        attachment_format -> crate::db_diesel::notification_query_row::AttachmentFormatMapping,
     }
 }
 
+/// File format the query results are attached to a notification in
+#[derive(DbEnum, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[DbValueStyle = "SCREAMING_SNAKE_CASE"]
+pub enum AttachmentFormat {
+    #[default]
+    None,
+    Csv,
+    Xlsx,
+}
+
 #[derive(
     Clone, Queryable, Identifiable, Insertable, AsChangeset, Debug, PartialEq, Eq, Default,
 )]
 #[table_name = "notification_query"]
+#[changeset_options(treat_none_as_null = "true")]
 pub struct NotificationQueryRow {
     pub id: String,
     pub name: String,
@@ -31,6 +48,13 @@ pub struct NotificationQueryRow {
     pub required_parameters: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    // Per query overrides for the datasource query limits, None uses the datasource's limit
+    pub timeout_seconds: Option<i32>,
+    pub max_rows: Option<i32>,
+    pub max_result_bytes: Option<i32>,
+    // Named datasource to run the query against, None uses the default datasource
+    pub datasource_id: Option<String>,
+    pub attachment_format: AttachmentFormat,
 }
 
 pub struct NotificationQueryRowRepository<'a> {
@@ -18,6 +18,8 @@ pub struct RecipientFilter {
     pub notification_type: Option<EqualFilter<NotificationType>>,
     pub to_address: Option<StringFilter>,
     pub search: Option<String>,
+    /// Recipients that are undeliverable or deactivated, so notifications to them aren't being received
+    pub has_delivery_problem: Option<bool>,
 }
 
 #[derive(PartialEq, Debug)]
@@ -103,6 +105,7 @@ fn create_filtered_query(filter: Option<RecipientFilter>) -> BoxedRecipientQuery
             notification_type,
             to_address,
             search,
+            has_delivery_problem,
         } = f;
 
         apply_equal_filter!(query, id, recipient_dsl::id);
@@ -117,6 +120,22 @@ fn create_filtered_query(filter: Option<RecipientFilter>) -> BoxedRecipientQuery
                     .or(recipient_dsl::to_address.like(search_term)),
             );
         }
+
+        match has_delivery_problem {
+            Some(true) => {
+                query = query.filter(
+                    recipient_dsl::undeliverable_datetime
+                        .is_not_null()
+                        .or(recipient_dsl::deactivated_datetime.is_not_null()),
+                )
+            }
+            Some(false) => {
+                query = query
+                    .filter(recipient_dsl::undeliverable_datetime.is_null())
+                    .filter(recipient_dsl::deactivated_datetime.is_null())
+            }
+            None => {}
+        }
     }
 
     query
@@ -143,4 +162,8 @@ impl RecipientFilter {
         self.search = Some(filter);
         self
     }
+    pub fn has_delivery_problem(mut self, filter: bool) -> Self {
+        self.has_delivery_problem = Some(filter);
+        self
+    }
 }
@@ -0,0 +1,87 @@
+use super::{
+    recipient_list_contact_row::recipient_list_contact::dsl as recipient_list_contact_dsl,
+    StorageConnection,
+};
+use crate::repository_error::RepositoryError;
+use diesel::prelude::*;
+
+table! {
+    recipient_list_contact (id) {
+        id -> Text,
+        recipient_list_id -> Text,
+        contact_id -> Text,
+    }
+}
+
+/// A contact on a recipient list, notifications to the list go to one of the contact's channels
+#[derive(
+    Clone, Queryable, Identifiable, Insertable, AsChangeset, Debug, PartialEq, Eq, Default,
+)]
+#[table_name = "recipient_list_contact"]
+pub struct RecipientListContactRow {
+    pub id: String,
+    pub recipient_list_id: String,
+    pub contact_id: String,
+}
+
+pub struct RecipientListContactRowRepository<'a> {
+    connection: &'a StorageConnection,
+}
+
+impl<'a> RecipientListContactRowRepository<'a> {
+    pub fn new(connection: &'a StorageConnection) -> Self {
+        RecipientListContactRowRepository { connection }
+    }
+
+    pub fn insert_one(&self, row: &RecipientListContactRow) -> Result<(), RepositoryError> {
+        diesel::insert_into(recipient_list_contact_dsl::recipient_list_contact)
+            .values(row)
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), RepositoryError> {
+        diesel::delete(
+            recipient_list_contact_dsl::recipient_list_contact
+                .filter(recipient_list_contact_dsl::id.eq(id)),
+        )
+        .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn find_one(
+        &self,
+        recipient_list_id: &str,
+        contact_id: &str,
+    ) -> Result<Option<RecipientListContactRow>, RepositoryError> {
+        let result = recipient_list_contact_dsl::recipient_list_contact
+            .filter(recipient_list_contact_dsl::recipient_list_id.eq(recipient_list_id))
+            .filter(recipient_list_contact_dsl::contact_id.eq(contact_id))
+            .first(&self.connection.connection)
+            .optional()?;
+        Ok(result)
+    }
+
+    pub fn find_all_for_recipient_lists(
+        &self,
+        recipient_list_ids: &[String],
+    ) -> Result<Vec<RecipientListContactRow>, RepositoryError> {
+        let result = recipient_list_contact_dsl::recipient_list_contact
+            .filter(recipient_list_contact_dsl::recipient_list_id.eq_any(recipient_list_ids))
+            .order(recipient_list_contact_dsl::id.asc())
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    pub fn delete_all_for_recipient_list_id(
+        &self,
+        recipient_list_id: &str,
+    ) -> Result<(), RepositoryError> {
+        diesel::delete(
+            recipient_list_contact_dsl::recipient_list_contact
+                .filter(recipient_list_contact_dsl::recipient_list_id.eq(recipient_list_id)),
+        )
+        .execute(&self.connection.connection)?;
+        Ok(())
+    }
+}
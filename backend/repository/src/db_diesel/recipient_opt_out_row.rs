@@ -0,0 +1,90 @@
+use super::{
+    recipient_opt_out_row::recipient_opt_out::dsl as recipient_opt_out_dsl, StorageConnection,
+};
+use crate::repository_error::RepositoryError;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+table! {
+    recipient_opt_out (id) {
+        id -> Text,
+        recipient_id -> Text,
+        notification_config_id -> Text,
+        created_datetime -> Timestamp,
+    }
+}
+
+/// The recipient has chosen not to receive the notification config's notifications
+#[derive(
+    Clone, Queryable, Identifiable, Insertable, AsChangeset, Debug, PartialEq, Eq, Default,
+)]
+#[table_name = "recipient_opt_out"]
+pub struct RecipientOptOutRow {
+    pub id: String,
+    pub recipient_id: String,
+    pub notification_config_id: String,
+    pub created_datetime: NaiveDateTime,
+}
+
+pub struct RecipientOptOutRowRepository<'a> {
+    connection: &'a StorageConnection,
+}
+
+impl<'a> RecipientOptOutRowRepository<'a> {
+    pub fn new(connection: &'a StorageConnection) -> Self {
+        RecipientOptOutRowRepository { connection }
+    }
+
+    pub fn insert_one(&self, row: &RecipientOptOutRow) -> Result<(), RepositoryError> {
+        diesel::insert_into(recipient_opt_out_dsl::recipient_opt_out)
+            .values(row)
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn delete(
+        &self,
+        recipient_id: &str,
+        notification_config_id: &str,
+    ) -> Result<(), RepositoryError> {
+        diesel::delete(
+            recipient_opt_out_dsl::recipient_opt_out
+                .filter(recipient_opt_out_dsl::recipient_id.eq(recipient_id))
+                .filter(recipient_opt_out_dsl::notification_config_id.eq(notification_config_id)),
+        )
+        .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn delete_all_for_notification_config(
+        &self,
+        notification_config_id: &str,
+    ) -> Result<(), RepositoryError> {
+        diesel::delete(
+            recipient_opt_out_dsl::recipient_opt_out
+                .filter(recipient_opt_out_dsl::notification_config_id.eq(notification_config_id)),
+        )
+        .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn find_all_for_recipient(
+        &self,
+        recipient_id: &str,
+    ) -> Result<Vec<RecipientOptOutRow>, RepositoryError> {
+        let result = recipient_opt_out_dsl::recipient_opt_out
+            .filter(recipient_opt_out_dsl::recipient_id.eq(recipient_id))
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    pub fn find_all_for_notification_config(
+        &self,
+        notification_config_id: &str,
+    ) -> Result<Vec<RecipientOptOutRow>, RepositoryError> {
+        let result = recipient_opt_out_dsl::recipient_opt_out
+            .filter(recipient_opt_out_dsl::notification_config_id.eq(notification_config_id))
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+}
@@ -14,6 +14,14 @@ table! {
         notification_type -> crate::db_diesel::recipient_row::NotificationTypeMapping,
         to_address -> Text,
         deleted_datetime -> Nullable<Timestamp>,
+        muted_until -> Nullable<Timestamp>,
+        deactivated_datetime -> Nullable<Timestamp>,
+        undeliverable_datetime -> Nullable<Timestamp>,
+        undeliverable_reason -> Nullable<Text>,
+        digest_frequency -> crate::db_diesel::recipient_row::DigestFrequencyMapping,
+        preferred_recipient_id -> Nullable<Text>,
+        contact_id -> Nullable<Text>,
+        fallback_order -> Integer,
     }
 }
 
@@ -23,9 +31,24 @@ pub enum NotificationType {
     #[default]
     Email,
     Telegram,
+    Teams,
+    Slack,
+    Mattermost,
+    Sms,
+    NtfyPush,
     Unknown,
 }
 
+/// How often the recipient gets their notifications
+#[derive(DbEnum, Debug, Clone, PartialEq, Eq, Hash, Default, Serialize)]
+#[DbValueStyle = "SCREAMING_SNAKE_CASE"]
+pub enum DigestFrequency {
+    #[default]
+    Immediate,
+    Daily,
+    Weekly,
+}
+
 impl FromStr for NotificationType {
     type Err = String;
 
@@ -33,6 +56,11 @@ impl FromStr for NotificationType {
         match s {
             "TELEGRAM" => Ok(NotificationType::Telegram),
             "EMAIL" => Ok(NotificationType::Email),
+            "TEAMS" => Ok(NotificationType::Teams),
+            "SLACK" => Ok(NotificationType::Slack),
+            "MATTERMOST" => Ok(NotificationType::Mattermost),
+            "SMS" => Ok(NotificationType::Sms),
+            "NTFY_PUSH" => Ok(NotificationType::NtfyPush),
             _ => Ok(NotificationType::Unknown),
         }
     }
@@ -48,6 +76,14 @@ impl NotificationType {
             is_null: None,
         }
     }
+
+    /// Teams, Slack and Mattermost recipients are the url of an incoming webhook
+    pub fn is_chat_webhook(&self) -> bool {
+        matches!(
+            self,
+            NotificationType::Teams | NotificationType::Slack | NotificationType::Mattermost
+        )
+    }
 }
 
 #[derive(
@@ -60,6 +96,17 @@ pub struct RecipientRow {
     pub notification_type: NotificationType,
     pub to_address: String,
     pub deleted_datetime: Option<NaiveDateTime>,
+    pub muted_until: Option<NaiveDateTime>,
+    pub deactivated_datetime: Option<NaiveDateTime>,
+    pub undeliverable_datetime: Option<NaiveDateTime>,
+    pub undeliverable_reason: Option<String>,
+    pub digest_frequency: DigestFrequency,
+    /// Another of the person's recipients that notifications are sent to instead of this one
+    pub preferred_recipient_id: Option<String>,
+    /// The contact (person) the recipient is one of the channels of
+    pub contact_id: Option<String>,
+    /// The order the contact's channels are tried in, lowest first
+    pub fallback_order: i32,
 }
 
 pub struct RecipientRowRepository<'a> {
@@ -120,4 +167,142 @@ impl<'a> RecipientRowRepository<'a> {
             .optional()?;
         Ok(result)
     }
+
+    /// Recipients with the name, a person's recipients are usually given their name
+    pub fn find_all_by_name(&self, name: &str) -> Result<Vec<RecipientRow>, RepositoryError> {
+        let result = recipient_dsl::recipient
+            .filter(recipient_dsl::name.eq(name))
+            .filter(recipient_dsl::deleted_datetime.is_null())
+            .order(recipient_dsl::id.asc())
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    /// Sets or (with None) clears the time notifications to the recipient are muted until
+    pub fn set_muted_until(
+        &self,
+        id: &str,
+        muted_until: Option<NaiveDateTime>,
+    ) -> Result<(), RepositoryError> {
+        diesel::update(recipient_dsl::recipient)
+            .filter(recipient_dsl::id.eq(id))
+            .set(recipient_dsl::muted_until.eq(muted_until))
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    /// Recipients that are still muted at `now`
+    pub fn find_all_muted(&self, now: NaiveDateTime) -> Result<Vec<RecipientRow>, RepositoryError> {
+        let result = recipient_dsl::recipient
+            .filter(recipient_dsl::muted_until.gt(now))
+            .filter(recipient_dsl::deleted_datetime.is_null())
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    /// Sets or (with None) clears the time the recipient was deactivated
+    pub fn set_deactivated_datetime(
+        &self,
+        id: &str,
+        deactivated_datetime: Option<NaiveDateTime>,
+    ) -> Result<(), RepositoryError> {
+        diesel::update(recipient_dsl::recipient)
+            .filter(recipient_dsl::id.eq(id))
+            .set(recipient_dsl::deactivated_datetime.eq(deactivated_datetime))
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    pub fn find_all_deactivated(&self) -> Result<Vec<RecipientRow>, RepositoryError> {
+        let result = recipient_dsl::recipient
+            .filter(recipient_dsl::deactivated_datetime.is_not_null())
+            .filter(recipient_dsl::deleted_datetime.is_null())
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    /// Marks the recipient as undeliverable with the reason, e.g. the bounce's diagnostic, or (with None) clears it
+    pub fn set_undeliverable(
+        &self,
+        id: &str,
+        undeliverable: Option<(NaiveDateTime, String)>,
+    ) -> Result<(), RepositoryError> {
+        let (undeliverable_datetime, undeliverable_reason) = match undeliverable {
+            Some((datetime, reason)) => (Some(datetime), Some(reason)),
+            None => (None, None),
+        };
+        diesel::update(recipient_dsl::recipient)
+            .filter(recipient_dsl::id.eq(id))
+            .set((
+                recipient_dsl::undeliverable_datetime.eq(undeliverable_datetime),
+                recipient_dsl::undeliverable_reason.eq(undeliverable_reason),
+            ))
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    /// Sets how often the recipient gets their notifications, and which of the person's recipients gets them
+    pub fn set_preferences(
+        &self,
+        id: &str,
+        digest_frequency: DigestFrequency,
+        preferred_recipient_id: Option<String>,
+    ) -> Result<(), RepositoryError> {
+        diesel::update(recipient_dsl::recipient)
+            .filter(recipient_dsl::id.eq(id))
+            .set((
+                recipient_dsl::digest_frequency.eq(digest_frequency),
+                recipient_dsl::preferred_recipient_id.eq(preferred_recipient_id),
+            ))
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    /// Recipients whose notifications are sent to another of the person's recipients.
+    /// A contact's channels are sent to in the contact's channel order instead, so they're not included
+    pub fn find_all_with_preferred_recipient(&self) -> Result<Vec<RecipientRow>, RepositoryError> {
+        let result = recipient_dsl::recipient
+            .filter(recipient_dsl::preferred_recipient_id.is_not_null())
+            .filter(recipient_dsl::contact_id.is_null())
+            .filter(recipient_dsl::deleted_datetime.is_null())
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
+
+    /// Adds the recipient to the contact's channels, or (with None) removes it from its contact.
+    /// The contact's channel order replaces the recipient's preferred recipient
+    pub fn set_contact(
+        &self,
+        id: &str,
+        contact_id: Option<String>,
+        fallback_order: i32,
+    ) -> Result<(), RepositoryError> {
+        if contact_id.is_some() {
+            diesel::update(recipient_dsl::recipient)
+                .filter(recipient_dsl::id.eq(id))
+                .set(recipient_dsl::preferred_recipient_id.eq(None::<String>))
+                .execute(&self.connection.connection)?;
+        }
+        diesel::update(recipient_dsl::recipient)
+            .filter(recipient_dsl::id.eq(id))
+            .set((
+                recipient_dsl::contact_id.eq(contact_id),
+                recipient_dsl::fallback_order.eq(fallback_order),
+            ))
+            .execute(&self.connection.connection)?;
+        Ok(())
+    }
+
+    /// The contact's channels in the order they're tried in
+    pub fn find_all_for_contact(
+        &self,
+        contact_id: &str,
+    ) -> Result<Vec<RecipientRow>, RepositoryError> {
+        let result = recipient_dsl::recipient
+            .filter(recipient_dsl::contact_id.eq(contact_id))
+            .filter(recipient_dsl::deleted_datetime.is_null())
+            .order((recipient_dsl::fallback_order.asc(), recipient_dsl::id.asc()))
+            .load(&self.connection.connection)?;
+        Ok(result)
+    }
 }
@@ -14,7 +14,8 @@ CREATE TABLE
         query TEXT NOT NULL,
         required_parameters TEXT NOT NULL, -- JSON e.g. {"region","tags","limit"}
         created_at TIMESTAMP NOT NULL,
-        updated_at TIMESTAMP NOT NULL
+        updated_at TIMESTAMP NOT NULL,
+        datasource_id TEXT
     );
 
 */
@@ -28,6 +29,7 @@ table! {
         required_parameters -> Text,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        datasource_id -> Nullable<Text>,
     }
 }
 
@@ -35,6 +37,7 @@ table! {
     Clone, Queryable, Identifiable, Insertable, AsChangeset, Debug, PartialEq, Eq, Default,
 )]
 #[table_name = "sql_recipient_list"]
+#[changeset_options(treat_none_as_null = "true")]
 pub struct SqlRecipientListRow {
     pub id: String,
     pub name: String,
@@ -43,6 +46,8 @@ pub struct SqlRecipientListRow {
     pub required_parameters: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    // Named datasource to run the query against, None uses the default datasource
+    pub datasource_id: Option<String>,
 }
 
 pub struct SqlRecipientListRowRepository<'a> {
@@ -1,6 +1,6 @@
 use chrono::NaiveDateTime;
 
-use crate::{NotificationType, RecipientRow};
+use crate::{DigestFrequency, NotificationType, RecipientRow};
 
 pub fn mock_recipients() -> Vec<RecipientRow> {
     vec![
@@ -19,6 +19,14 @@ pub fn mock_recipient_a() -> RecipientRow {
         notification_type: NotificationType::Email,
         to_address: String::from("a@openmsupply.foundation"),
         deleted_datetime: None,
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     }
 }
 
@@ -29,6 +37,14 @@ pub fn mock_recipient_aa() -> RecipientRow {
         notification_type: NotificationType::Email,
         to_address: String::from("aa@openmsupply.foundation"),
         deleted_datetime: None,
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     }
 }
 
@@ -39,6 +55,14 @@ pub fn mock_recipient_b() -> RecipientRow {
         notification_type: NotificationType::Email,
         to_address: String::from("b@openmsupply.foundation"),
         deleted_datetime: None,
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     }
 }
 
@@ -49,6 +73,14 @@ pub fn mock_recipient_c() -> RecipientRow {
         notification_type: NotificationType::Telegram,
         to_address: String::from("chat_id_c"),
         deleted_datetime: None,
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     }
 }
 
@@ -61,5 +93,13 @@ pub fn mock_recipient_d_deleted() -> RecipientRow {
         deleted_datetime: Some(
             NaiveDateTime::parse_from_str("2023-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
         ),
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     }
 }
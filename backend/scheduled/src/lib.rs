@@ -1,3 +1,6 @@
+use std::{sync::OnceLock, time::Duration};
+
+use query_cache::SharedQueryCache;
 use service::{
     plugin::{PluginError, PluginTrait},
     service_provider::ServiceContext,
@@ -6,6 +9,7 @@ use service::{
 pub mod parse;
 pub mod process;
 pub mod query;
+pub mod query_cache;
 
 #[derive(Debug)]
 pub enum NotificationError {
@@ -14,16 +18,22 @@ pub enum NotificationError {
     UnableToParseConfig(String),
     InternalError(String),
     InvalidNextDueDate,
+    QueryLimitExceeded(String),
 }
 
-pub struct ScheduledNotificationPlugin {}
+pub struct ScheduledNotificationPlugin {
+    /// Created on the first tick, if `query_cache.shared_ttl_seconds` is configured
+    shared_query_cache: OnceLock<SharedQueryCache>,
+}
 
 impl PluginTrait for ScheduledNotificationPlugin {
     fn new() -> Self
     where
         Self: Sized,
     {
-        ScheduledNotificationPlugin {}
+        ScheduledNotificationPlugin {
+            shared_query_cache: OnceLock::new(),
+        }
     }
 
     fn name(&self) -> String {
@@ -35,7 +45,17 @@ impl PluginTrait for ScheduledNotificationPlugin {
 
         // Process any scheduled notifications that are due
         let current_time = chrono::Utc::now().naive_utc();
-        let result = process::process_scheduled_notifications(ctx, current_time);
+        let shared_query_cache = ctx
+            .service_provider
+            .settings
+            .query_cache
+            .shared_ttl_seconds
+            .map(|ttl_seconds| {
+                self.shared_query_cache
+                    .get_or_init(|| SharedQueryCache::new(Duration::from_secs(ttl_seconds)))
+            });
+        let result =
+            process::process_scheduled_notifications(ctx, current_time, shared_query_cache);
         match result {
             Ok(count) => {
                 if count > 0 {
@@ -1,24 +1,42 @@
 use std::collections::HashMap;
 
 use chrono::{DateTime, NaiveDateTime, Utc};
-use repository::{NotificationConfigKind, NotificationConfigRowRepository};
+use repository::{NotificationConfigKind, NotificationConfigRowRepository, NotificationPriority};
 use service::{
-    notification::enqueue::{create_notification_events, NotificationContext, TemplateDefinition},
-    notification_config::{query::NotificationConfig, recipients::get_notification_targets, parameters::get_notification_parameters},
+    notification::enqueue::{
+        create_failed_notification_event, create_notification_events, NotificationContext,
+        TemplateDefinition,
+    },
+    notification_config::{
+        parameters::get_notification_parameters, query::NotificationConfig,
+        recipients::get_notification_targets,
+    },
     service_provider::ServiceContext,
 };
 
 use crate::{
-    parse::ScheduledNotificationPluginConfig, query::get_notification_query_results,
+    parse::ScheduledNotificationPluginConfig,
+    query::get_notification_query_results,
+    query_cache::{QueryCache, SharedQueryCache},
     NotificationError,
 };
 
+// Reports are sent after alerts and other notifications
+const REPORT_PRIORITY: NotificationPriority = NotificationPriority::Low;
+
 pub fn process_scheduled_notifications(
     ctx: &ServiceContext,
     current_time: NaiveDateTime,
+    shared_query_cache: Option<&SharedQueryCache>,
 ) -> Result<usize, NotificationError> {
     log::info!("Processing scheduled notifications due at {}", current_time);
 
+    // Query results are reused by every notification processed in this run
+    if let Some(shared_query_cache) = shared_query_cache {
+        shared_query_cache.remove_expired();
+    }
+    let mut query_cache = QueryCache::new(shared_query_cache);
+
     // Check if any scheduled notifications are due according to the database
     let scheduled_notifications = ctx
         .service_provider
@@ -38,7 +56,12 @@ pub fn process_scheduled_notifications(
             scheduled_notification.id,
         );
 
-        match try_process_scheduled_notifications(ctx, scheduled_notification, current_time) {
+        match try_process_scheduled_notifications(
+            ctx,
+            scheduled_notification,
+            current_time,
+            &mut query_cache,
+        ) {
             Err(e) => {
                 log::error!("{:?}", e);
                 errored_notifications += 1;
@@ -67,6 +90,16 @@ pub fn process_scheduled_notifications(
         skipped_notifications,
         errored_notifications
     );
+    let query_cache_stats = query_cache.stats();
+    if query_cache_stats.hits + query_cache_stats.shared_hits + query_cache_stats.misses > 0 {
+        log::info!(
+            "Ran {} notification queries in {}ms, {} cache hits and {} shared cache hits",
+            query_cache_stats.misses,
+            query_cache_stats.query_time.as_millis(),
+            query_cache_stats.hits,
+            query_cache_stats.shared_hits
+        );
+    }
     Ok(notifications_processed)
 }
 
@@ -80,6 +113,7 @@ fn try_process_scheduled_notifications(
     ctx: &ServiceContext,
     scheduled_notification: NotificationConfig,
     now: NaiveDateTime,
+    query_cache: &mut QueryCache,
 ) -> Result<ProcessingResult, NotificationError> {
     // Load the notification config
     let config =
@@ -121,7 +155,12 @@ fn try_process_scheduled_notifications(
     let param_results = get_notification_parameters(ctx, &scheduled_notification);
     let mut all_params = match param_results {
         Ok(val) => val,
-        Err(e) => return Err(NotificationError::InternalError(format!("Failed to fetch parameters: {:?}", e)))
+        Err(e) => {
+            return Err(NotificationError::InternalError(format!(
+                "Failed to fetch parameters: {:?}",
+                e
+            )))
+        }
     };
 
     if all_params.len() == 0 {
@@ -142,6 +181,7 @@ fn try_process_scheduled_notifications(
             ctx,
             &scheduled_notification,
             sql_params.clone(),
+            REPORT_PRIORITY,
         )
         .map_err(|e| {
             NotificationError::InternalError(format!("Failed to get notification targets: {:?}", e))
@@ -153,10 +193,24 @@ fn try_process_scheduled_notifications(
             continue;
         }
 
-        let sql_query_parameters = get_notification_query_results(ctx, sql_params, &config)?;
+        let query_results =
+            match get_notification_query_results(ctx, sql_params, &config, query_cache) {
+                Ok(query_results) => query_results,
+                Err(NotificationError::QueryLimitExceeded(message)) => {
+                    // Record the failure as a notification event so it's visible to admins, then carry on with the next parameter set
+                    create_failed_notification_event(
+                        ctx,
+                        Some(scheduled_notification.id.clone()),
+                        message,
+                    )
+                    .map_err(|e| NotificationError::InternalError(format!("{:?}", e)))?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
 
         // Template data should include the notification config parameters, plus the results of any queries
-        template_params.extend(sql_query_parameters);
+        template_params.extend(query_results.data);
 
         let template_data = serde_json::to_value(template_params).map_err(|e| {
             NotificationError::InternalError(format!("Failed to parse template data: {:?}", e))
@@ -170,6 +224,9 @@ fn try_process_scheduled_notifications(
             body_template: TemplateDefinition::Template(config.body_template.clone()),
             template_data: template_data,
             recipients: notification_targets,
+            attachments: query_results.attachments,
+            email_options: Default::default(),
+            priority: REPORT_PRIORITY,
         };
 
         create_notification_events(ctx, Some(scheduled_notification.id.clone()), notification)
@@ -192,7 +249,6 @@ mod test {
     };
     use repository::NotificationEventRowRepository;
     use repository::{mock::MockDataInserts, test_db::setup_all};
-    use service::test_utils::email_test::send_test_emails;
     use service::test_utils::get_test_settings;
 
     use service::service_provider::ServiceProvider;
@@ -214,6 +270,7 @@ mod test {
         let result = process_scheduled_notifications(
             &ServiceContext::new(service_provider).unwrap(),
             chrono::Utc::now().naive_utc(),
+            None,
         )
         .unwrap();
 
@@ -271,6 +328,7 @@ mod test {
             &service_context,
             notification_config,
             chrono::Utc::now().naive_utc(),
+            &mut QueryCache::new(None),
         )
         .unwrap();
 
@@ -327,6 +385,7 @@ mod test {
             &service_context,
             notification_config,
             chrono::Utc::now().naive_utc(),
+            &mut QueryCache::new(None),
         )
         .unwrap();
 
@@ -385,6 +444,7 @@ mod test {
             &service_context,
             notification_config,
             chrono::Utc::now().naive_utc(),
+            &mut QueryCache::new(None),
         )
         .unwrap();
 
@@ -446,6 +506,7 @@ mod test {
             &service_context,
             notification_config,
             chrono::Utc::now().naive_utc(),
+            &mut QueryCache::new(None),
         )
         .unwrap();
 
@@ -463,7 +524,6 @@ mod test {
         );
 
         send_test_notifications(&service_context).await;
-        send_test_emails(&service_context);
     }
 
     // Test that we don't send notifications if template fails to render
@@ -515,6 +575,7 @@ mod test {
             &service_context,
             notification_config,
             chrono::Utc::now().naive_utc(),
+            &mut QueryCache::new(None),
         )
         .unwrap();
 
@@ -1,18 +1,35 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use log::info;
 use repository::{EqualFilter, NotificationQueryFilter, NotificationQueryRepository};
 use serde_json::json;
-use service::service_provider::ServiceContext;
-
-use crate::{parse::ScheduledNotificationPluginConfig, NotificationError};
+use service::{
+    datasource::{notification_query_limits, render_sql_query, QueryResult},
+    notification::attachment::{query_results_attachment, NotificationAttachment},
+    service_provider::ServiceContext,
+};
+
+use crate::{
+    parse::ScheduledNotificationPluginConfig,
+    query_cache::{QueryCache, QueryCacheKey},
+    NotificationError,
+};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct NotificationQueryResults {
+    /// Results of each query keyed by its reference_name, for use in the templates
+    pub data: HashMap<String, serde_json::Value>,
+    /// Results of the queries that are configured to be attached to the notification
+    pub attachments: Vec<NotificationAttachment>,
+}
 
 pub fn get_notification_query_results(
     ctx: &ServiceContext,
     parameters: serde_json::Value,
     config: &ScheduledNotificationPluginConfig,
-) -> Result<HashMap<String, serde_json::Value>, NotificationError> {
-    let mut query_results = HashMap::new();
+    query_cache: &mut QueryCache,
+) -> Result<NotificationQueryResults, NotificationError> {
+    let mut query_results = NotificationQueryResults::default();
 
     // get all the configured queries
 
@@ -26,16 +43,76 @@ pub fn get_notification_query_results(
             NotificationError::InternalError(format!("Unable to get notification queries: {:?}", e))
         })?;
 
-    // loop through all the notification query ids, run them, and store the results
+    // loop through all the notification query ids, run them (unless they're cached), and store the results
     for query in queries {
-        let now = chrono::Utc::now();
-        let result = ctx
-            .service_provider
-            .datasource_service
-            .run_sql_query_with_parameters(query.query.clone(), parameters.clone());
+        let start_time = Instant::now();
+        let datasource_service = &ctx.service_provider.datasource_service;
+        let query_limits = notification_query_limits(&query);
+        let result = render_sql_query(&query.query, parameters.clone()).and_then(|rendered_sql| {
+            let key = QueryCacheKey {
+                datasource_id: query.datasource_id.clone(),
+                rendered_sql: rendered_sql.clone(),
+                query_limits: query_limits.clone(),
+            };
+            query_cache.get_or_run(key, || {
+                datasource_service.run_sql_query(
+                    query.datasource_id.clone(),
+                    rendered_sql,
+                    Some(query_limits),
+                )
+            })
+        });
         let query_json = match result {
-            Ok(result) => serde_json::from_str(&result.results)
-                .unwrap_or_else(|_| json!([{"error": "Unable to parse query result"}])),
+            // A query that hit a limit would only give partial (or no) data, so don't send anything for it
+            Ok((
+                QueryResult {
+                    query_error: Some(query_error),
+                    ..
+                },
+                _,
+            )) if query_error.is_limit_exceeded() => {
+                log::error!(
+                    "Query {} for {}({}) exceeded a limit: {}",
+                    query.reference_name,
+                    config.title,
+                    config.id,
+                    query_error
+                );
+                return Err(NotificationError::QueryLimitExceeded(format!(
+                    "Query {} : {}",
+                    query.reference_name, query_error
+                )));
+            }
+            Ok((result, cache_status)) => {
+                info!(
+                    "Query {} for {}({}) took {}ms ({})",
+                    query.reference_name,
+                    config.title,
+                    config.id,
+                    start_time.elapsed().as_millis(),
+                    cache_status
+                );
+                let query_json = serde_json::from_str(&result.results)
+                    .unwrap_or_else(|_| json!([{"error": "Unable to parse query result"}]));
+                if result.query_error.is_none() {
+                    match query_results_attachment(
+                        &query.reference_name,
+                        query.attachment_format,
+                        &query_json,
+                    ) {
+                        Ok(Some(attachment)) => query_results.attachments.push(attachment),
+                        Ok(None) => {}
+                        Err(e) => log::error!(
+                            "Unable to attach query {} for {}({}) : {:?}",
+                            query.reference_name,
+                            config.title,
+                            config.id,
+                            e
+                        ),
+                    }
+                }
+                query_json
+            }
             Err(e) => {
                 log::error!(
                     "Error running query {} for {}({}) : {:?}",
@@ -47,14 +124,8 @@ pub fn get_notification_query_results(
                 json!([{"error": "error running query", "query": query.query, "parameters": parameters}])
             }
         };
-        let end_time = chrono::Utc::now();
-        info!(
-            "Query {} took {}ms",
-            query.reference_name,
-            (end_time - now).num_milliseconds()
-        );
 
-        query_results.insert(query.reference_name, query_json);
+        query_results.data.insert(query.reference_name, query_json);
     }
 
     Ok(query_results)
@@ -70,7 +141,8 @@ mod tests {
             MockDataInserts,
         },
         test_db::setup_all,
-        NotificationConfigStatus,
+        AttachmentFormat, NotificationConfigStatus, NotificationQueryRow,
+        NotificationQueryRowRepository,
     };
     use util::uuid::uuid;
 
@@ -116,14 +188,19 @@ mod tests {
         };
 
         // Call the function being tested
-        let query_results =
-            get_notification_query_results(&context, all_params[0].clone(), &config).unwrap();
+        let query_results = get_notification_query_results(
+            &context,
+            all_params[0].clone(),
+            &config,
+            &mut QueryCache::new(None),
+        )
+        .unwrap();
 
         let result_key = mock_notification_query_with_no_param_2_rows().reference_name;
 
         // Check we got the result we expected
         assert_eq!(
-            query_results.get(&result_key).unwrap(),
+            query_results.data.get(&result_key).unwrap(),
             &json!([
                 {
                     "latest_temperature": 1.25,
@@ -181,14 +258,19 @@ mod tests {
         };
 
         // Call the function being tested
-        let query_results =
-            get_notification_query_results(&context, all_params[0].clone(), &config).unwrap();
+        let query_results = get_notification_query_results(
+            &context,
+            all_params[0].clone(),
+            &config,
+            &mut QueryCache::new(None),
+        )
+        .unwrap();
 
         let result_key = mock_notification_query_with_params().reference_name;
 
         // Check we got the result we expected
         assert_eq!(
-            query_results.get(&result_key).unwrap(),
+            query_results.data.get(&result_key).unwrap(),
             &json!([
                 {
                     "latest_temperature": 8.5,
@@ -244,17 +326,22 @@ mod tests {
         };
 
         // Call the function being tested
-        let query_results =
-            get_notification_query_results(&context, all_params[0].clone(), &config).unwrap();
+        let query_results = get_notification_query_results(
+            &context,
+            all_params[0].clone(),
+            &config,
+            &mut QueryCache::new(None),
+        )
+        .unwrap();
 
         // Check we got the 2 results we expected
-        assert_eq!(query_results.len(), 2);
+        assert_eq!(query_results.data.len(), 2);
 
         let result_key = mock_notification_query_with_params().reference_name;
 
         // Check we got the result we expected for the first query
         assert_eq!(
-            query_results.get(&result_key).unwrap(),
+            query_results.data.get(&result_key).unwrap(),
             &json!([
                 {
                     "latest_temperature": 8.5,
@@ -268,7 +355,7 @@ mod tests {
 
         // Check we got the result we expected for the second query
         assert_eq!(
-            query_results.get(&result_key).unwrap(),
+            query_results.data.get(&result_key).unwrap(),
             &json!([
                 {
                     "latest_temperature": 1.25,
@@ -326,13 +413,172 @@ mod tests {
         };
 
         // Call the function being tested
-        let result =
-            get_notification_query_results(&context, all_params[0].clone(), &config).unwrap();
+        let result = get_notification_query_results(
+            &context,
+            all_params[0].clone(),
+            &config,
+            &mut QueryCache::new(None),
+        )
+        .unwrap();
 
         // Check we got the error we expected
         assert_eq!(
-            result.get("query1").unwrap()[0]["error"].as_str().unwrap(),
+            result.data.get("query1").unwrap()[0]["error"]
+                .as_str()
+                .unwrap(),
             "error running query"
         );
     }
+
+    // Test we get a query limit error back if a query returns more rows than it's allowed to
+    #[tokio::test]
+    async fn test_get_notification_query_results_row_limit() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_get_notification_query_results_row_limit",
+            MockDataInserts::none(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        // The query returns 2 rows, but is only allowed 1
+        let query = NotificationQueryRow {
+            max_rows: Some(1),
+            ..mock_notification_query_with_no_param_2_rows()
+        };
+        NotificationQueryRowRepository::new(&context.connection)
+            .insert_one(&query)
+            .unwrap();
+
+        let config = ScheduledNotificationPluginConfig {
+            notification_query_ids: vec![query.id],
+            ..Default::default()
+        };
+
+        let result = get_notification_query_results(
+            &context,
+            json!({}),
+            &config,
+            &mut QueryCache::new(None),
+        );
+
+        assert!(matches!(
+            result,
+            Err(NotificationError::QueryLimitExceeded(_))
+        ));
+    }
+
+    // Test that parameter sets that render to the same sql only run the query once
+    #[tokio::test]
+    async fn test_get_notification_query_results_cached() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_get_notification_query_results_cached",
+            MockDataInserts::none().notification_queries(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let config = ScheduledNotificationPluginConfig {
+            notification_query_ids: vec![
+                mock_notification_query_with_params().id,
+                mock_notification_query_with_no_param_2_rows().id,
+            ],
+            ..Default::default()
+        };
+        let mut query_cache = QueryCache::new(None);
+
+        let params = json!({"sensor_limit": "8", "latest_temperature": "8.5"});
+        let first_results =
+            get_notification_query_results(&context, params.clone(), &config, &mut query_cache)
+                .unwrap();
+        assert_eq!(query_cache.stats().misses, 2);
+
+        // The same parameters (and the query without parameters) come from the cache
+        let second_results =
+            get_notification_query_results(&context, params, &config, &mut query_cache).unwrap();
+        assert_eq!(second_results, first_results);
+        assert_eq!(query_cache.stats().hits, 2);
+        assert_eq!(query_cache.stats().misses, 2);
+
+        // Different parameters only re-run the query that uses them
+        let other_params = json!({"sensor_limit": "9", "latest_temperature": "8.5"});
+        let other_results =
+            get_notification_query_results(&context, other_params, &config, &mut query_cache)
+                .unwrap();
+        assert_eq!(
+            other_results
+                .data
+                .get(&mock_notification_query_with_params().reference_name)
+                .unwrap(),
+            &json!([
+                {
+                    "latest_temperature": 8.5,
+                    "sensor_limit": 9,
+                    "is_above_limit": false,
+                }
+            ])
+        );
+        assert_eq!(query_cache.stats().hits, 3);
+        assert_eq!(query_cache.stats().misses, 3);
+    }
+
+    // Test that queries configured as attachments have their results attached
+    #[tokio::test]
+    async fn test_get_notification_query_results_attachment() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_get_notification_query_results_attachment",
+            MockDataInserts::none().notification_queries(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        NotificationQueryRowRepository::new(&context.connection)
+            .update_one(&NotificationQueryRow {
+                attachment_format: AttachmentFormat::Csv,
+                ..mock_notification_query_with_no_param_2_rows()
+            })
+            .unwrap();
+
+        let config = ScheduledNotificationPluginConfig {
+            notification_query_ids: vec![
+                mock_notification_query_with_params().id,
+                mock_notification_query_with_no_param_2_rows().id,
+            ],
+            ..Default::default()
+        };
+
+        let query_results = get_notification_query_results(
+            &context,
+            json!({"sensor_limit": "8", "latest_temperature": "8.5"}),
+            &config,
+            &mut QueryCache::new(None),
+        )
+        .unwrap();
+
+        // Only the query configured with an attachment format is attached
+        assert_eq!(query_results.attachments.len(), 1);
+        let attachment = &query_results.attachments[0];
+        assert_eq!(
+            attachment.filename,
+            format!(
+                "{}.csv",
+                mock_notification_query_with_no_param_2_rows().reference_name
+            )
+        );
+        assert_eq!(
+            String::from_utf8(attachment.content.clone()).unwrap(),
+            "latest_temperature,sensor_limit,sensor_name\n1.25,-10,sensor1\n1.51,-10,sensor2\n"
+        );
+    }
 }
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use datasource::QueryLimits;
+use service::datasource::{DatasourceServiceError, QueryResult};
+
+/// Identifies a query by the sql that is actually run, so queries from different configs,
+/// or parameter sets that render to the same sql, share a result
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QueryCacheKey {
+    pub datasource_id: Option<String>,
+    pub rendered_sql: String,
+    pub query_limits: QueryLimits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryCacheStatus {
+    /// The query was run against the datasource
+    Miss,
+    /// The result was already fetched earlier in this run
+    Hit,
+    /// The result was fetched by a previous run, within the shared cache's ttl
+    SharedHit,
+}
+
+impl Display for QueryCacheStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            QueryCacheStatus::Miss => "cache miss",
+            QueryCacheStatus::Hit => "cache hit",
+            QueryCacheStatus::SharedHit => "shared cache hit",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QueryCacheStats {
+    pub hits: usize,
+    pub shared_hits: usize,
+    pub misses: usize,
+    /// Total time spent running the queries that missed the cache
+    pub query_time: Duration,
+}
+
+struct SharedQueryCacheEntry {
+    result: QueryResult,
+    cached_at: Instant,
+}
+
+/// Query results shared between scheduled notification runs, entries expire after `ttl`
+pub struct SharedQueryCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<QueryCacheKey, SharedQueryCacheEntry>>,
+}
+
+impl SharedQueryCache {
+    pub fn new(ttl: Duration) -> Self {
+        SharedQueryCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &QueryCacheKey) -> Option<QueryResult> {
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(key)
+            .filter(|entry| entry.cached_at.elapsed() < self.ttl)
+            .map(|entry| entry.result.clone())
+    }
+
+    fn insert(&self, key: QueryCacheKey, result: QueryResult) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(
+                key,
+                SharedQueryCacheEntry {
+                    result,
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Drops expired entries, so results for parameters that are no longer used don't build up
+    pub fn remove_expired(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|_, entry| entry.cached_at.elapsed() < self.ttl);
+        }
+    }
+}
+
+/// Query results for a single scheduled notification run
+pub struct QueryCache<'a> {
+    entries: HashMap<QueryCacheKey, QueryResult>,
+    shared: Option<&'a SharedQueryCache>,
+    stats: QueryCacheStats,
+}
+
+impl<'a> QueryCache<'a> {
+    pub fn new(shared: Option<&'a SharedQueryCache>) -> Self {
+        QueryCache {
+            entries: HashMap::new(),
+            shared,
+            stats: QueryCacheStats::default(),
+        }
+    }
+
+    /// Returns the cached result for `key`, or calls `run_query` and caches its result
+    pub fn get_or_run<F>(
+        &mut self,
+        key: QueryCacheKey,
+        run_query: F,
+    ) -> Result<(QueryResult, QueryCacheStatus), DatasourceServiceError>
+    where
+        F: FnOnce() -> Result<QueryResult, DatasourceServiceError>,
+    {
+        if let Some(result) = self.entries.get(&key) {
+            self.stats.hits += 1;
+            return Ok((result.clone(), QueryCacheStatus::Hit));
+        }
+
+        if let Some(result) = self.shared.and_then(|shared| shared.get(&key)) {
+            self.stats.shared_hits += 1;
+            self.entries.insert(key, result.clone());
+            return Ok((result, QueryCacheStatus::SharedHit));
+        }
+
+        let start_time = Instant::now();
+        let result = run_query()?;
+        self.stats.misses += 1;
+        self.stats.query_time += start_time.elapsed();
+
+        // A failed query is only remembered for this run, later runs should try it again
+        if result.query_error.is_none() {
+            if let Some(shared) = self.shared {
+                shared.insert(key.clone(), result.clone());
+            }
+        }
+        self.entries.insert(key, result.clone());
+
+        Ok((result, QueryCacheStatus::Miss))
+    }
+
+    pub fn stats(&self) -> &QueryCacheStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datasource::QueryError;
+
+    use super::*;
+
+    fn key(rendered_sql: &str) -> QueryCacheKey {
+        QueryCacheKey {
+            datasource_id: None,
+            rendered_sql: rendered_sql.to_string(),
+            query_limits: QueryLimits::default(),
+        }
+    }
+
+    fn result(results: &str) -> Result<QueryResult, DatasourceServiceError> {
+        Ok(QueryResult {
+            results: results.to_string(),
+            query: "".to_string(),
+            query_error: None,
+        })
+    }
+
+    #[test]
+    fn test_query_cache_reuses_results_within_a_run() {
+        let mut cache = QueryCache::new(None);
+
+        let (first, status) = cache.get_or_run(key("SELECT 1"), || result("[1]")).unwrap();
+        assert_eq!(status, QueryCacheStatus::Miss);
+        assert_eq!(first.results, "[1]");
+
+        let (second, status) = cache
+            .get_or_run(key("SELECT 1"), || panic!("query should be cached"))
+            .unwrap();
+        assert_eq!(status, QueryCacheStatus::Hit);
+        assert_eq!(second, first);
+
+        // Different sql, or the same sql against another datasource, is a different query
+        let (_, status) = cache.get_or_run(key("SELECT 2"), || result("[2]")).unwrap();
+        assert_eq!(status, QueryCacheStatus::Miss);
+        let other_datasource = QueryCacheKey {
+            datasource_id: Some("country_a".to_string()),
+            ..key("SELECT 1")
+        };
+        let (_, status) = cache
+            .get_or_run(other_datasource, || result("[1]"))
+            .unwrap();
+        assert_eq!(status, QueryCacheStatus::Miss);
+
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().shared_hits, 0);
+        assert_eq!(cache.stats().misses, 3);
+    }
+
+    #[test]
+    fn test_query_cache_shares_results_between_runs() {
+        let shared = SharedQueryCache::new(Duration::from_secs(60));
+
+        let mut first_run = QueryCache::new(Some(&shared));
+        first_run
+            .get_or_run(key("SELECT 1"), || result("[1]"))
+            .unwrap();
+        first_run
+            .get_or_run(key("SELECT 2"), || {
+                Ok(QueryResult {
+                    query_error: Some(QueryError::RowLimitExceeded { max_rows: 10 }),
+                    ..result("[]").unwrap()
+                })
+            })
+            .unwrap();
+
+        let mut second_run = QueryCache::new(Some(&shared));
+        let (cached, status) = second_run
+            .get_or_run(key("SELECT 1"), || panic!("query should be cached"))
+            .unwrap();
+        assert_eq!(status, QueryCacheStatus::SharedHit);
+        assert_eq!(cached.results, "[1]");
+
+        // Results with a query error aren't shared, so the query is tried again
+        let (_, status) = second_run
+            .get_or_run(key("SELECT 2"), || result("[2]"))
+            .unwrap();
+        assert_eq!(status, QueryCacheStatus::Miss);
+
+        assert_eq!(second_run.stats().shared_hits, 1);
+        assert_eq!(second_run.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_shared_query_cache_expires_results() {
+        let shared = SharedQueryCache::new(Duration::from_secs(0));
+
+        QueryCache::new(Some(&shared))
+            .get_or_run(key("SELECT 1"), || result("[1]"))
+            .unwrap();
+
+        let (_, status) = QueryCache::new(Some(&shared))
+            .get_or_run(key("SELECT 1"), || result("[1]"))
+            .unwrap();
+        assert_eq!(status, QueryCacheStatus::Miss);
+
+        shared.remove_expired();
+        assert!(shared.entries.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::web::{self, Bytes, Data};
+use actix_web::{HttpRequest, HttpResponse};
+use service::email::bounce::process_bounce_message;
+use service::service_provider::{ServiceContext, ServiceProvider};
+
+pub const BOUNCE_SECRET_HEADER: &str = "X-Notify-Secret";
+// Bounces usually include the original email, which can have attachments
+const MAX_BOUNCE_SIZE: usize = 10 * 1024 * 1024;
+
+pub fn config_bounce_webhook(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/email/bounce")
+            .app_data(web::PayloadConfig::new(MAX_BOUNCE_SIZE))
+            .route(web::post().to(bounce_webhook)),
+    );
+}
+
+/// Takes a bounce email, as the raw message, e.g. piped from the mail server or forwarded by a mail provider
+async fn bounce_webhook(
+    req: HttpRequest,
+    message: Bytes,
+    service_provider: Data<ServiceProvider>,
+) -> HttpResponse {
+    // Only available when a webhook secret is configured
+    let webhook_secret = match service_provider
+        .settings
+        .mail
+        .bounces
+        .as_ref()
+        .and_then(|bounces| bounces.webhook_secret.clone())
+    {
+        Some(webhook_secret) => webhook_secret,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    let secret = req
+        .headers()
+        .get(BOUNCE_SECRET_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if secret != Some(webhook_secret.as_str()) {
+        log::warn!("Bounce webhook request with an invalid secret");
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    let ctx = match ServiceContext::new(service_provider.into_inner()) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            log::error!("Unable to process bounce - {:?}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match process_bounce_message(&ctx, &String::from_utf8_lossy(&message)) {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => {
+            log::error!("Unable to process bounce - {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
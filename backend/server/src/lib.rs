@@ -1,7 +1,9 @@
 use crate::{
-    auto_backup::auto_backup, configuration::get_or_create_token_secret, cors::cors_policy,
+    auto_backup::auto_backup, bounce_webhook::config_bounce_webhook,
+    configuration::get_or_create_token_secret, cors::cors_policy,
     scheduled_tasks::scheduled_task_runner, serve_frontend::config_server_frontend,
-    static_files::config_static_files,
+    static_files::config_static_files, telegram_webhook::config_telegram_webhook,
+    unsubscribe::config_unsubscribe,
 };
 
 use self::middleware::{compress as compress_middleware, logger as logger_middleware};
@@ -27,12 +29,15 @@ use service::{
     recipient::telegram::update_telegram_recipients,
     service_provider::{ServiceContext, ServiceProvider},
     settings::{is_develop, ServerSettings, Settings},
+    telegram_commands::handle_telegram_commands,
+    telegram_updates::KeyValueTelegramUpdateIdStore,
     token_bucket::TokenBucket,
 };
 
-use telegram::{service::TelegramService, TelegramClient};
+use telegram::service::{TelegramService, TelegramWebhook};
 
 mod auto_backup;
+mod bounce_webhook;
 pub mod configuration;
 pub mod cors;
 pub mod environment;
@@ -41,6 +46,15 @@ pub mod middleware;
 mod scheduled_tasks;
 mod serve_frontend;
 pub mod static_files;
+mod telegram_webhook;
+mod unsubscribe;
+
+fn plugins() -> Vec<Box<dyn PluginTrait>> {
+    vec![
+        Box::new(ScheduledNotificationPlugin::new()),
+        Box::new(ColdChainPlugin::new()),
+    ]
+}
 
 fn auth_data(
     _server_settings: &ServerSettings,
@@ -108,26 +122,39 @@ async fn run_server(
         }
     };
 
-    // Setup plugins
-    let plugins: Vec<Box<dyn service::plugin::PluginTrait>> = vec![
-        Box::new(ScheduledNotificationPlugin::new()),
-        Box::new(ColdChainPlugin::new()),
-    ];
-
     let mut scheduled_task_handle = actix_web::rt::spawn(async move {
-        scheduled_task_runner(scheduled_task_context, plugins).await;
+        scheduled_task_runner(scheduled_task_context, plugins()).await;
     });
 
     // Setup a channel to receive telegram messages, which we want to handle in recipient service
-    let telegram_token = config_settings.clone().telegram.token;
-    let telegram_update_handler_option = match telegram_token {
+    // The client is shared with notification sending, so they use the same rate limits
+    let mut telegram_webhook: Option<TelegramWebhook> = None;
+    let telegram_update_handler_option = match service_provider_data.telegram.clone() {
         None => None,
-        Some(telegram_token) => {
+        Some(telegram_client) => {
             let telegram_service = TelegramService::new(
-                TelegramClient::new(telegram_token),
+                telegram_client.clone(),
                 config_settings.server.app_url.clone(),
+                Arc::new(KeyValueTelegramUpdateIdStore::new(
+                    connection_manager.clone(),
+                )),
             );
-            let telegram_update_channel = telegram_service.init().await;
+            let telegram_update_channel = match &config_settings.telegram.webhook {
+                None => telegram_service.init().await,
+                Some(webhook_settings) => {
+                    let (telegram_update_channel, webhook) = telegram_service
+                        .init_webhook(&webhook_settings.url, &webhook_settings.secret_token)
+                        .await
+                        .map_err(|error| {
+                            std::io::Error::new(
+                                std::io::ErrorKind::Other,
+                                format!("Error unable to set telegram webhook: {:?}", error),
+                            )
+                        })?;
+                    telegram_webhook = Some(webhook);
+                    telegram_update_channel
+                }
+            };
 
             // Handle telegram updates in recipient service
             let telegram_update_context =
@@ -144,8 +171,31 @@ async fn run_server(
                     ));
                 }
             };
+            // Reply to commands sent to the bot
+            let telegram_command_context =
+                ServiceContext::new(service_provider_data.clone().into_inner());
+            let telegram_command_context = match telegram_command_context {
+                Ok(telegram_command_context) => telegram_command_context,
+                Err(error) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!(
+                            "Error unable to create telegram command task context: {:?}",
+                            error
+                        ),
+                    ));
+                }
+            };
             let telegram_update_handler = actix_web::rt::spawn(async move {
-                update_telegram_recipients(telegram_update_context, &telegram_update_channel).await
+                tokio::join!(
+                    update_telegram_recipients(telegram_update_context, &telegram_update_channel),
+                    handle_telegram_commands(
+                        telegram_command_context,
+                        telegram_client,
+                        &telegram_update_channel,
+                        plugins(),
+                    )
+                );
             });
             Some(telegram_update_handler)
         }
@@ -187,6 +237,7 @@ async fn run_server(
     }
 
     let http_server_config_settings = config_settings.clone();
+    let telegram_webhook_data = Data::new(telegram_webhook);
     let mut http_server = HttpServer::new(move || {
         let cors = cors_policy(&http_server_config_settings);
         App::new()
@@ -205,6 +256,10 @@ async fn run_server(
                 restart_switch.clone(),
             ))
             .configure(config_static_files)
+            .app_data(telegram_webhook_data.clone())
+            .configure(config_telegram_webhook)
+            .configure(config_unsubscribe)
+            .configure(config_bounce_webhook)
             .wrap(limit_content_length())
             .configure(config_server_frontend)
     })
@@ -1,30 +1,88 @@
-use service::{plugin::PluginTrait, service_provider::ServiceContext};
-use std::time::Duration;
+use service::{
+    email::bounce::check_bounce_mailbox,
+    notification::{digest::send_digests, dispatch::fail_interrupted_sends},
+    plugin::PluginTrait,
+    service_provider::{ServiceContext, ServiceProvider},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
 
 static TASK_INTERVAL: Duration = Duration::from_secs(10);
+static DEFAULT_BOUNCE_POLL_INTERVAL: u64 = 300;
 
 pub async fn scheduled_task_runner(
     service_context: ServiceContext,
     plugins: Vec<Box<dyn PluginTrait>>,
 ) {
-    let mut interval = actix_web::rt::time::interval(TASK_INTERVAL);
+    // Notifications that were still sending when the server stopped are failed once, before anything is sent again
+    match fail_interrupted_sends(&service_context.connection) {
+        Ok(num) => {
+            if num > 0 {
+                log::info!("Failed {} notifications interrupted while sending", num);
+            }
+        }
+        Err(error) => log::error!("Error failing interrupted notifications: {:?}", error),
+    };
+
+    let sender_context = match ServiceContext::new(service_context.service_provider.clone()) {
+        Ok(sender_context) => sender_context,
+        Err(error) => {
+            log::error!(
+                "Error creating the notification sender context: {:?}",
+                error
+            );
+            return;
+        }
+    };
+    // Sending a backlog of notifications can take a while, so it runs alongside the other tasks rather than holding them up
+    tokio::join!(
+        notification_sender(sender_context),
+        run_scheduled_tasks(service_context, plugins)
+    );
+}
 
+async fn notification_sender(service_context: ServiceContext) {
+    let mut interval = actix_web::rt::time::interval(TASK_INTERVAL);
     loop {
         interval.tick().await;
-        log::debug!("Processing Scheduled Tasks");
-        let send_emails = service_context
+        // Send Notifications, including system emails such as password resets
+        let send_notifications = service_context
             .service_provider
-            .email_service
-            .send_queued_emails(&service_context);
-        match send_emails {
+            .notification_service
+            .send_queued_notifications(&service_context)
+            .await;
+        match send_notifications {
             Ok(num) => {
                 if num > 0 {
-                    log::info!("Sent {} queued emails", num);
+                    log::info!("Sent {} queued notifications", num);
                 }
             }
-            Err(error) => log::error!("Error sending queued emails: {:?}", error),
+            Err(error) => log::error!("Error sending queued notifications: {:?}", error),
         };
+    }
+}
+
+async fn run_scheduled_tasks(service_context: ServiceContext, plugins: Vec<Box<dyn PluginTrait>>) {
+    let mut interval = actix_web::rt::time::interval(TASK_INTERVAL);
+    let bounce_poll_interval = Duration::from_secs(
+        service_context
+            .service_provider
+            .settings
+            .mail
+            .bounces
+            .as_ref()
+            .and_then(|bounces| bounces.poll_interval_seconds)
+            .unwrap_or(DEFAULT_BOUNCE_POLL_INTERVAL),
+    );
+    let mut last_bounce_check: Option<Instant> = None;
+    let mut bounce_check: Option<JoinHandle<()>> = None;
 
+    loop {
+        interval.tick().await;
+        log::debug!("Processing Scheduled Tasks");
         // Process plugins
         // Note: If a plugin starts an infinite loop here, we're a bit stuffed as no more scheduled tasks will be processed.
         // Hopefully people will be smart enough not to do that?
@@ -36,19 +94,47 @@ pub async fn scheduled_task_runner(
             }
         }
 
-        // Send Notifications
-        let send_notifications = service_context
-            .service_provider
-            .notification_service
-            .send_queued_notifications(&service_context)
-            .await;
-        match send_notifications {
+        // Queue the digests that are due, so they're sent with the other notifications
+        match send_digests(&service_context) {
             Ok(num) => {
                 if num > 0 {
-                    log::info!("Sent {} queued notifications", num);
+                    log::info!("Queued {} notification digests", num);
                 }
             }
-            Err(error) => log::error!("Error sending queued notifications: {:?}", error),
+            Err(error) => log::error!("Error queuing notification digests: {:?}", error),
         };
+
+        // Mark recipients that have bounced as undeliverable, before more is sent to them.
+        // Reading the mailbox blocks, so it's done on a blocking thread, and not started again until the last check has finished
+        let checking_bounces = bounce_check
+            .as_ref()
+            .is_some_and(|bounce_check| !bounce_check.is_finished());
+        if !checking_bounces
+            && last_bounce_check.is_none_or(|checked| checked.elapsed() >= bounce_poll_interval)
+        {
+            last_bounce_check = Some(Instant::now());
+            let service_provider = service_context.service_provider.clone();
+            bounce_check = Some(tokio::task::spawn_blocking(move || {
+                check_bounces(service_provider)
+            }));
+        }
+    }
+}
+
+fn check_bounces(service_provider: Arc<ServiceProvider>) {
+    let service_context = match ServiceContext::new(service_provider) {
+        Ok(service_context) => service_context,
+        Err(error) => {
+            log::error!("Error creating the bounce check context: {:?}", error);
+            return;
+        }
+    };
+    match check_bounce_mailbox(&service_context) {
+        Ok(num) => {
+            if num > 0 {
+                log::info!("Marked {} bounced recipients as undeliverable", num);
+            }
+        }
+        Err(error) => log::error!("Error checking the bounce mailbox: {:?}", error),
     }
 }
@@ -0,0 +1,39 @@
+use actix_web::web::{self, Data};
+use actix_web::{HttpRequest, HttpResponse};
+use telegram::service::{TelegramWebhook, TelegramWebhookError, SECRET_TOKEN_HEADER};
+
+pub fn config_telegram_webhook(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/telegram/webhook").route(web::post().to(telegram_webhook)));
+}
+
+async fn telegram_webhook(
+    req: HttpRequest,
+    update: web::Json<serde_json::Value>,
+    webhook: Data<Option<TelegramWebhook>>,
+) -> HttpResponse {
+    // Only available when telegram is configured to use a webhook
+    let webhook = match webhook.as_ref() {
+        Some(webhook) => webhook,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    let secret_token = req
+        .headers()
+        .get(SECRET_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    match webhook
+        .handle_update(secret_token, update.into_inner())
+        .await
+    {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(TelegramWebhookError::InvalidSecretToken) => {
+            log::warn!("Telegram webhook request with an invalid secret token");
+            HttpResponse::Unauthorized().finish()
+        }
+        Err(TelegramWebhookError::InvalidUpdate(e)) => {
+            log::error!("Invalid telegram webhook update: {}", e);
+            HttpResponse::BadRequest().body(e)
+        }
+    }
+}
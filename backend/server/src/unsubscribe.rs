@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::ContentType;
+use actix_web::web::{self, Data};
+use actix_web::{HttpResponse, HttpResponseBuilder};
+use repository::DigestFrequency;
+use serde::{Deserialize, Serialize};
+use service::email::unsubscribe::{
+    get_managed_preferences, unsubscribe, update_managed_preferences, ManagedPreferences,
+    UnsubscribeError, UNSUBSCRIBE_PATH,
+};
+use service::notification::renderer::render_template;
+use service::service_provider::{ServiceContext, ServiceProvider};
+
+#[derive(Deserialize)]
+struct UnsubscribeQuery {
+    token: String,
+}
+
+#[derive(Serialize)]
+struct MessageParams {
+    message: String,
+}
+
+#[derive(Serialize)]
+struct PreferencesParams {
+    action: String,
+    preferences: ManagedPreferences,
+}
+
+pub fn config_unsubscribe(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource(UNSUBSCRIBE_PATH)
+            .route(web::get().to(manage_preferences_page))
+            .route(web::post().to(unsubscribe_post)),
+    );
+}
+
+/// Renders one of the pages in `templates/unsubscribe`
+fn page(
+    ctx: &ServiceContext,
+    mut response: HttpResponseBuilder,
+    template_name: &str,
+    params: impl Serialize,
+) -> HttpResponse {
+    let tera = ctx.service_provider.notification_service.tera();
+    match render_template(tera, template_name, params) {
+        Ok(html) => response.content_type(ContentType::html()).body(html),
+        Err(e) => {
+            log::error!("Unable to render {} - {:?}", template_name, e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+fn message_page(
+    ctx: &ServiceContext,
+    response: HttpResponseBuilder,
+    message: &str,
+) -> HttpResponse {
+    page(
+        ctx,
+        response,
+        "unsubscribe/message.html",
+        MessageParams {
+            message: message.to_string(),
+        },
+    )
+}
+
+fn service_context(
+    service_provider: Data<ServiceProvider>,
+) -> Result<ServiceContext, HttpResponse> {
+    ServiceContext::new(service_provider.into_inner()).map_err(|e| {
+        log::error!("Unable to open notification preferences - {:?}", e);
+        HttpResponse::InternalServerError().finish()
+    })
+}
+
+fn error_response(ctx: &ServiceContext, error: UnsubscribeError) -> HttpResponse {
+    let (response, message) = match error {
+        UnsubscribeError::InvalidToken => (
+            HttpResponse::BadRequest(),
+            "This unsubscribe link isn't valid.",
+        ),
+        UnsubscribeError::RecipientNotFound => (
+            HttpResponse::NotFound(),
+            "This email address isn't a notification recipient, please contact the sender to be removed.",
+        ),
+        e => {
+            log::error!("Unable to update notification preferences - {:?}", e);
+            (
+                HttpResponse::InternalServerError(),
+                "Something went wrong, please try again later.",
+            )
+        }
+    };
+    message_page(ctx, response, message)
+}
+
+fn digest_frequency_from_form(value: &str) -> DigestFrequency {
+    match value {
+        "DAILY" => DigestFrequency::Daily,
+        "WEEKLY" => DigestFrequency::Weekly,
+        _ => DigestFrequency::Immediate,
+    }
+}
+
+// Mail scanners open links in emails, so opening the link only shows the recipient's preferences
+async fn manage_preferences_page(
+    query: web::Query<UnsubscribeQuery>,
+    service_provider: Data<ServiceProvider>,
+) -> HttpResponse {
+    let ctx = match service_context(service_provider) {
+        Ok(ctx) => ctx,
+        Err(response) => return response,
+    };
+
+    match get_managed_preferences(&ctx, &query.token) {
+        Ok(preferences) => page(
+            &ctx,
+            HttpResponse::Ok(),
+            "unsubscribe/preferences.html",
+            PreferencesParams {
+                action: format!("{}?token={}", UNSUBSCRIBE_PATH, query.token),
+                preferences,
+            },
+        ),
+        Err(e) => error_response(&ctx, e),
+    }
+}
+
+// Posted by the preferences page, or directly by mail clients supporting one-click unsubscribe
+async fn unsubscribe_post(
+    query: web::Query<UnsubscribeQuery>,
+    form: Option<web::Form<HashMap<String, String>>>,
+    service_provider: Data<ServiceProvider>,
+) -> HttpResponse {
+    let ctx = match service_context(service_provider) {
+        Ok(ctx) => ctx,
+        Err(response) => return response,
+    };
+    let form = form.map(|form| form.into_inner()).unwrap_or_default();
+
+    let result = match form.get("action").map(String::as_str) {
+        Some("save") => save_preferences(&ctx, &query.token, &form).map(|to_address| {
+            format!(
+                "Notification preferences for {} have been saved.",
+                to_address
+            )
+        }),
+        _ => unsubscribe(&ctx, &query.token).map(|to_address| {
+            format!("{} won't receive any more notification emails.", to_address)
+        }),
+    };
+
+    match result {
+        Ok(message) => message_page(&ctx, HttpResponse::Ok(), &message),
+        Err(e) => error_response(&ctx, e),
+    }
+}
+
+// Unchecked boxes aren't posted, so the configs on the page that weren't posted are opted out of
+fn save_preferences(
+    ctx: &ServiceContext,
+    token: &str,
+    form: &HashMap<String, String>,
+) -> Result<String, UnsubscribeError> {
+    let preferences = get_managed_preferences(ctx, token)?;
+    let opted_out_notification_config_ids = preferences
+        .notification_configs
+        .into_iter()
+        .filter(|config| !form.contains_key(&format!("receive_{}", config.id)))
+        .map(|config| config.id)
+        .collect();
+    let digest_frequency = form
+        .get("digest_frequency")
+        .map(|value| digest_frequency_from_form(value))
+        .unwrap_or_default();
+    // The recipient's own address is posted as an empty value
+    let preferred_recipient_id = form
+        .get("preferred_recipient_id")
+        .filter(|id| !id.is_empty())
+        .cloned();
+
+    update_managed_preferences(
+        ctx,
+        token,
+        digest_frequency,
+        opted_out_notification_config_ids,
+        preferred_recipient_id,
+    )
+}
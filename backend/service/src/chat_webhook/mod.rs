@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use repository::NotificationType;
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use serde_json::{json, Value};
+
+use self::{slack::slack_message, teams::teams_message};
+
+pub mod slack;
+#[cfg(test)]
+pub(crate) mod stub;
+pub mod teams;
+
+const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+// Used if a webhook says we've sent too many messages without saying how long to wait
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Sends messages to Teams, Slack and Mattermost incoming webhooks, the recipient's to_address is the webhook url
+#[derive(Clone)]
+pub struct ChatWebhookClient {
+    http_client: reqwest::Client,
+}
+
+#[derive(Debug)]
+pub enum ChatWebhookError {
+    /// The webhook won't accept the message, e.g. the webhook has been removed, retrying won't help
+    Permanent(String),
+    Temporary(String),
+    /// Too many messages have been sent to the webhook, try again after retry_after
+    RateLimited {
+        description: String,
+        retry_after: Duration,
+    },
+}
+
+impl ChatWebhookError {
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, ChatWebhookError::Permanent(_))
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ChatWebhookError::RateLimited { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ChatWebhookError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_builder() {
+            // e.g. the url isn't valid
+            return ChatWebhookError::Permanent(error.to_string());
+        }
+        ChatWebhookError::Temporary(error.to_string())
+    }
+}
+
+impl Default for ChatWebhookClient {
+    fn default() -> Self {
+        ChatWebhookClient::new()
+    }
+}
+
+impl ChatWebhookClient {
+    pub fn new() -> ChatWebhookClient {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DEFAULT_REQUEST_TIMEOUT))
+            .build()
+            .expect("Something went unexpectedly wrong building the webhook reqwest client");
+        ChatWebhookClient { http_client }
+    }
+
+    /// Converts the common markdown to the platform's message format and posts it to the webhook url
+    pub async fn send_message(
+        &self,
+        notification_type: &NotificationType,
+        url: &str,
+        title: Option<&str>,
+        common_markdown: &str,
+    ) -> Result<(), ChatWebhookError> {
+        let payload = match notification_type {
+            NotificationType::Teams => teams_message(common_markdown),
+            NotificationType::Slack => slack_message(title, common_markdown),
+            NotificationType::Mattermost => mattermost_message(common_markdown),
+            _ => {
+                return Err(ChatWebhookError::Permanent(format!(
+                    "{:?} notifications aren't sent to a webhook",
+                    notification_type
+                )))
+            }
+        };
+
+        let response = self.http_client.post(url).json(&payload).send().await?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let response_text = response.text().await.unwrap_or_default();
+        let description = format!("{} - {}", status, response_text);
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ChatWebhookError::RateLimited {
+                description,
+                retry_after: retry_after.unwrap_or(DEFAULT_RETRY_AFTER),
+            });
+        }
+        if status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT {
+            return Err(ChatWebhookError::Temporary(description));
+        }
+        if !status.is_success() {
+            return Err(ChatWebhookError::Permanent(description));
+        }
+        // Teams connectors reply 200 even when Teams itself rate limited the message
+        if response_text.contains("returned HTTP error 429") {
+            return Err(ChatWebhookError::RateLimited {
+                description,
+                retry_after: DEFAULT_RETRY_AFTER,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Mattermost understands common markdown, so the message is sent as it is
+/// https://developers.mattermost.com/integrate/webhooks/incoming/
+pub fn mattermost_message(common_markdown: &str) -> Value {
+    json!({ "text": common_markdown })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{stub::WebhookStub, *};
+
+    #[actix_rt::test]
+    async fn test_send_chat_webhook_message() {
+        let stub = WebhookStub::start();
+        let client = ChatWebhookClient::new();
+
+        client
+            .send_message(
+                &NotificationType::Slack,
+                &stub.url("/services/T000/B000/XXXX"),
+                Some("Sensor alert"),
+                "**Sensor** is too hot",
+            )
+            .await
+            .unwrap();
+        client
+            .send_message(
+                &NotificationType::Mattermost,
+                &stub.url("/hooks/abc"),
+                None,
+                "**Sensor** is too hot",
+            )
+            .await
+            .unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/services/T000/B000/XXXX");
+        assert_eq!(requests[0].body["text"], "Sensor alert");
+        assert_eq!(
+            requests[0].body["blocks"][0]["text"]["text"],
+            "*Sensor* is too hot"
+        );
+        assert_eq!(requests[1].body, json!({ "text": "**Sensor** is too hot" }));
+    }
+
+    #[actix_rt::test]
+    async fn test_send_chat_webhook_errors() {
+        let stub = WebhookStub::start();
+        let client = ChatWebhookClient::new();
+        let url = stub.url("/webhook");
+        let send =
+            || client.send_message(&NotificationType::Teams, &url, None, "Sensor is too hot");
+
+        stub.push_response(
+            "/webhook",
+            429,
+            &[("Retry-After", "30")],
+            "Too Many Requests",
+        );
+        let result = send().await;
+        assert_eq!(
+            result.unwrap_err().retry_after(),
+            Some(Duration::from_secs(30))
+        );
+
+        stub.push_response(
+            "/webhook",
+            200,
+            &[],
+            "Webhook message delivery failed with error: Microsoft Teams endpoint returned HTTP error 429",
+        );
+        let result = send().await;
+        assert_eq!(result.unwrap_err().retry_after(), Some(DEFAULT_RETRY_AFTER));
+
+        stub.push_response("/webhook", 502, &[], "Bad Gateway");
+        let result = send().await;
+        assert!(matches!(result, Err(ChatWebhookError::Temporary(_))));
+
+        // The webhook has been removed
+        stub.push_response("/webhook", 404, &[], "no_service");
+        let result = send().await;
+        assert!(result.unwrap_err().is_permanent());
+
+        send().await.unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+/*
+   Converts common markdown to a Slack Block Kit message
+   https://api.slack.com/reference/block-kit/blocks
+   https://api.slack.com/reference/surfaces/formatting
+*/
+
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+use serde_json::{json, Value};
+
+// Slack rejects messages with more blocks than this
+const MAX_BLOCKS: usize = 50;
+const MAX_SECTION_LENGTH: usize = 3000;
+const MAX_HEADER_LENGTH: usize = 150;
+
+/// The title is used as the text of the notification, the message is shown as blocks.
+/// Messages with too many blocks are sent as text only.
+pub fn slack_message(title: Option<&str>, common_markdown: &str) -> Value {
+    let blocks = slack_blocks(common_markdown);
+
+    let mrkdwn = blocks
+        .iter()
+        .map(|block| match block["type"].as_str() {
+            Some("header") => format!("*{}*", block["text"]["text"].as_str().unwrap_or_default()),
+            Some("divider") => "-----".to_string(),
+            _ => block["text"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    if blocks.len() > MAX_BLOCKS {
+        return json!({ "text": mrkdwn });
+    }
+
+    let text = match title {
+        Some(title) if !title.trim().is_empty() => escape_slack_text(title.trim()),
+        _ => mrkdwn,
+    };
+    json!({
+        "text": text,
+        "blocks": blocks,
+    })
+}
+
+fn slack_blocks(common_markdown: &str) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    let mut section = String::new();
+    let mut heading: Option<String> = None;
+    let mut list_number: Option<u64> = None;
+    let mut quote_depth = 0;
+
+    for event in Parser::new(common_markdown) {
+        // Headers are plain text, so any formatting inside them is dropped
+        if let Some(heading_text) = &mut heading {
+            match event {
+                Event::Text(text) | Event::Code(text) => heading_text.push_str(&text),
+                Event::SoftBreak | Event::HardBreak => heading_text.push(' '),
+                Event::End(Tag::Heading(..)) => {
+                    push_section(&mut blocks, &mut section);
+                    blocks.push(json!({
+                        "type": "header",
+                        "text": {
+                            "type": "plain_text",
+                            "text": heading_text.chars().take(MAX_HEADER_LENGTH).collect::<String>(),
+                            "emoji": true,
+                        }
+                    }));
+                    heading = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Text(text) | Event::Html(text) | Event::FootnoteReference(text) => {
+                section.push_str(&escape_slack_text(&text))
+            }
+            Event::Code(text) => {
+                section.push('`');
+                section.push_str(&escape_slack_text(&text));
+                section.push('`');
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                section.push('\n');
+                if quote_depth > 0 {
+                    section.push_str("> ");
+                }
+            }
+            Event::Rule => {
+                push_section(&mut blocks, &mut section);
+                blocks.push(json!({ "type": "divider" }));
+            }
+            Event::Start(Tag::Heading(..)) => heading = Some(String::new()),
+            Event::End(Tag::Paragraph) => section.push_str("\n\n"),
+            Event::Start(Tag::Emphasis) | Event::End(Tag::Emphasis) => section.push('_'),
+            Event::Start(Tag::Strong) | Event::End(Tag::Strong) => section.push('*'),
+            Event::Start(Tag::Strikethrough) | Event::End(Tag::Strikethrough) => section.push('~'),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                section.push_str("```");
+                if let CodeBlockKind::Indented = kind {
+                    section.push('\n');
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => section.push_str("```\n\n"),
+            Event::Start(Tag::Link(_link_type, url, _title)) => {
+                section.push('<');
+                section.push_str(&url);
+                section.push('|');
+            }
+            Event::End(Tag::Link(..)) => section.push('>'),
+            Event::Start(Tag::BlockQuote) => {
+                quote_depth += 1;
+                section.push_str("> ");
+            }
+            Event::End(Tag::BlockQuote) => {
+                quote_depth -= 1;
+                section.push('\n');
+            }
+            Event::Start(Tag::List(number)) => list_number = number,
+            Event::End(Tag::List(_)) => {
+                list_number = None;
+                section.push('\n');
+            }
+            Event::Start(Tag::Item) => match list_number {
+                Some(number) => {
+                    section.push_str(&format!("{}. ", number));
+                    list_number = Some(number + 1);
+                }
+                None => section.push_str("• "),
+            },
+            Event::End(Tag::Item) => section.push('\n'),
+            _ => {}
+        }
+    }
+    push_section(&mut blocks, &mut section);
+
+    blocks
+}
+
+// Adds the text as sections of mrkdwn, split between lines if it's longer than a section allows
+fn push_section(blocks: &mut Vec<Value>, section: &mut String) {
+    let text = section.trim().to_string();
+    section.clear();
+
+    let mut current = String::new();
+    let mut current_length = 0;
+    for line in text.lines() {
+        let line_length = line.chars().count();
+        if current_length + line_length + 1 > MAX_SECTION_LENGTH && current_length > 0 {
+            blocks.push(section_block(&current));
+            current.clear();
+            current_length = 0;
+        }
+        for character in line.chars() {
+            if current_length >= MAX_SECTION_LENGTH {
+                blocks.push(section_block(&current));
+                current.clear();
+                current_length = 0;
+            }
+            current.push(character);
+            current_length += 1;
+        }
+        current.push('\n');
+        current_length += 1;
+    }
+    if !current.trim().is_empty() {
+        blocks.push(section_block(&current));
+    }
+}
+
+fn section_block(text: &str) -> Value {
+    json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": text.trim_end(),
+        }
+    })
+}
+
+// Only &, < and > need to be escaped in Slack text
+fn escape_slack_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slack_message() {
+        let cmarkdown = r#"# Sensor alert
+
+**Sensor** is _too hot_ at [store](http://example.com/), temperature > 8°C
+- `Freezer 1`
+- Fridge & co
+
+---
+Last data received 2 hours ago
+"#; // Don't indent this!
+        let message = slack_message(Some("Sensor is too hot"), cmarkdown);
+        assert_eq!(
+            message,
+            json!({
+                "text": "Sensor is too hot",
+                "blocks": [
+                    {
+                        "type": "header",
+                        "text": {"type": "plain_text", "text": "Sensor alert", "emoji": true}
+                    },
+                    {
+                        "type": "section",
+                        "text": {
+                            "type": "mrkdwn",
+                            "text": "*Sensor* is _too hot_ at <http://example.com/|store>, temperature &gt; 8°C\n\n• `Freezer 1`\n• Fridge &amp; co"
+                        }
+                    },
+                    {"type": "divider"},
+                    {
+                        "type": "section",
+                        "text": {"type": "mrkdwn", "text": "Last data received 2 hours ago"}
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_slack_message_long() {
+        // A section can only be 3000 characters
+        let rows = (1..=300)
+            .map(|i| format!("*Sensor {}*: 5.1°C", i))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let message = slack_message(None, &rows);
+        let blocks = message["blocks"].as_array().unwrap();
+        assert!(blocks.len() > 1);
+        assert!(blocks.iter().all(|block| {
+            block["text"]["text"].as_str().unwrap().chars().count() <= MAX_SECTION_LENGTH
+        }));
+
+        // Too many blocks are sent as text
+        let headings = (1..=60)
+            .map(|i| format!("# Heading {}\n\nParagraph {}", i, i))
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        let message = slack_message(Some("Title"), &headings);
+        assert!(message.get("blocks").is_none());
+        assert!(message["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("*Heading 1*\nParagraph 1"));
+    }
+}
@@ -0,0 +1,166 @@
+/*
+   A local http server standing in for Teams, Slack and Mattermost incoming webhooks in tests.
+   Each request's path and json body are recorded, and replied to with the next response queued for the path, or 200 "ok".
+*/
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub path: String,
+    pub body: Value,
+}
+
+struct StubResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+#[derive(Default)]
+struct StubState {
+    requests: Vec<StubRequest>,
+    responses: Vec<(String, StubResponse)>,
+}
+
+pub struct WebhookStub {
+    url: String,
+    state: Arc<Mutex<StubState>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl WebhookStub {
+    pub fn start() -> WebhookStub {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to start webhook stub");
+        listener
+            .set_nonblocking(true)
+            .expect("Unable to start webhook stub");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_state = state.clone();
+        let thread_stopped = stopped.clone();
+        thread::spawn(move || {
+            while !thread_stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => handle_connection(stream, &thread_state),
+                    Err(_) => thread::sleep(Duration::from_millis(5)),
+                }
+            }
+        });
+
+        WebhookStub {
+            url,
+            state,
+            stopped,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.url, path)
+    }
+
+    /// The next request to the path is replied to with this response
+    pub fn push_response(&self, path: &str, status: u16, headers: &[(&str, &str)], body: &str) {
+        self.state.lock().unwrap().responses.push((
+            path.to_string(),
+            StubResponse {
+                status,
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                body: body.to_string(),
+            },
+        ));
+    }
+
+    pub fn requests(&self) -> Vec<StubRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for WebhookStub {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<StubState>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+
+    let mut data = Vec::new();
+    let mut buffer = [0; 4096];
+    let header_end = loop {
+        if let Some(position) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => data.extend_from_slice(&buffer[..read]),
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let path = head
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or_default();
+    while data.len() < header_end + content_length {
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => data.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let body = serde_json::from_slice(&data[header_end..]).unwrap_or(Value::Null);
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        let response = match state
+            .responses
+            .iter()
+            .position(|(response_path, _)| *response_path == path)
+        {
+            Some(index) => state.responses.remove(index).1,
+            None => StubResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: "ok".to_string(),
+            },
+        };
+        state.requests.push(StubRequest { path, body });
+        response
+    };
+
+    let mut reply = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in response.headers {
+        reply.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    reply.push_str("\r\n");
+    reply.push_str(&response.body);
+    let _ = stream.write_all(reply.as_bytes());
+}
@@ -0,0 +1,180 @@
+/*
+   Converts common markdown to a Microsoft Teams message containing an Adaptive Card
+   https://learn.microsoft.com/en-us/microsoftteams/platform/webhooks-and-connectors/how-to/connectors-using
+   https://learn.microsoft.com/en-us/adaptive-cards/authoring-cards/text-features
+*/
+
+use pulldown_cmark::{Event, Parser, Tag};
+use serde_json::{json, Map, Value};
+
+const ADAPTIVE_CARD_SCHEMA: &str = "http://adaptivecards.io/schemas/adaptive-card.json";
+const ADAPTIVE_CARD_VERSION: &str = "1.4";
+
+pub fn teams_message(common_markdown: &str) -> Value {
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "contentUrl": null,
+            "content": {
+                "$schema": ADAPTIVE_CARD_SCHEMA,
+                "type": "AdaptiveCard",
+                "version": ADAPTIVE_CARD_VERSION,
+                "msteams": { "width": "Full" },
+                "body": adaptive_card_body(common_markdown),
+            }
+        }]
+    })
+}
+
+/*
+   TextBlocks only support bold, italic, links and lists, so headings and code blocks get their own TextBlock,
+   and rules are shown as a separator above the next TextBlock.
+*/
+fn adaptive_card_body(common_markdown: &str) -> Vec<Value> {
+    let mut body = Vec::new();
+    let mut text = String::new();
+    let mut separator = false;
+    let mut list_number: Option<u64> = None;
+
+    for event in Parser::new(common_markdown) {
+        match event {
+            Event::Text(value)
+            | Event::Code(value)
+            | Event::Html(value)
+            | Event::FootnoteReference(value) => text.push_str(&value),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => {
+                push_text_block(&mut body, &mut text, &mut separator, &[]);
+                separator = true;
+            }
+            Event::Start(Tag::Heading(..)) | Event::Start(Tag::CodeBlock(_)) => {
+                push_text_block(&mut body, &mut text, &mut separator, &[])
+            }
+            Event::End(Tag::Heading(..)) => {
+                push_text_block(
+                    &mut body,
+                    &mut text,
+                    &mut separator,
+                    &[("size", "Medium"), ("weight", "Bolder")],
+                );
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                push_text_block(
+                    &mut body,
+                    &mut text,
+                    &mut separator,
+                    &[("fontType", "Monospace")],
+                );
+            }
+            Event::End(Tag::Paragraph) => text.push_str("\n\n"),
+            Event::Start(Tag::Emphasis) | Event::End(Tag::Emphasis) => text.push('_'),
+            Event::Start(Tag::Strong) | Event::End(Tag::Strong) => text.push_str("**"),
+            Event::Start(Tag::Link(..)) => text.push('['),
+            Event::End(Tag::Link(_link_type, url, _title)) => {
+                text.push_str("](");
+                text.push_str(&url);
+                text.push(')');
+            }
+            Event::Start(Tag::List(number)) => list_number = number,
+            Event::End(Tag::List(_)) => {
+                list_number = None;
+                text.push('\n');
+            }
+            Event::Start(Tag::Item) => match list_number {
+                Some(number) => {
+                    text.push_str(&format!("{}. ", number));
+                    list_number = Some(number + 1);
+                }
+                None => text.push_str("- "),
+            },
+            Event::End(Tag::Item) => text.push('\n'),
+            _ => {}
+        }
+    }
+    push_text_block(&mut body, &mut text, &mut separator, &[]);
+
+    body
+}
+
+fn push_text_block(
+    body: &mut Vec<Value>,
+    text: &mut String,
+    separator: &mut bool,
+    style: &[(&str, &str)],
+) {
+    let block_text = text.trim_start_matches('\n').trim_end().to_string();
+    text.clear();
+    if block_text.is_empty() {
+        return;
+    }
+    let mut block = Map::new();
+    block.insert("type".to_string(), json!("TextBlock"));
+    block.insert("text".to_string(), json!(block_text));
+    block.insert("wrap".to_string(), json!(true));
+    for (key, value) in style {
+        block.insert(key.to_string(), json!(value));
+    }
+    if *separator {
+        block.insert("separator".to_string(), json!(true));
+        *separator = false;
+    }
+    body.push(Value::Object(block));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_teams_message() {
+        let cmarkdown = r#"# Sensor alert
+
+**Sensor** is _too hot_ at [store](http://example.com/)
+- Freezer 1
+- Fridge 2
+
+```
+SELECT * FROM sensor
+```
+---
+Last data received 2 hours ago
+"#; // Don't indent this!
+        let message = teams_message(cmarkdown);
+        let card = &message["attachments"][0];
+        assert_eq!(
+            card["contentType"],
+            "application/vnd.microsoft.card.adaptive"
+        );
+        assert_eq!(card["content"]["type"], "AdaptiveCard");
+        assert_eq!(
+            card["content"]["body"],
+            json!([
+                {
+                    "type": "TextBlock",
+                    "text": "Sensor alert",
+                    "wrap": true,
+                    "size": "Medium",
+                    "weight": "Bolder"
+                },
+                {
+                    "type": "TextBlock",
+                    "text": "**Sensor** is _too hot_ at [store](http://example.com/)\n\n- Freezer 1\n- Fridge 2",
+                    "wrap": true
+                },
+                {
+                    "type": "TextBlock",
+                    "text": "SELECT * FROM sensor",
+                    "wrap": true,
+                    "fontType": "Monospace"
+                },
+                {
+                    "type": "TextBlock",
+                    "text": "Last data received 2 hours ago",
+                    "wrap": true,
+                    "separator": true
+                }
+            ])
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use super::{validate::check_contact_exists, ModifyContactError};
+use crate::{
+    audit_log::audit_log_entry, recipient::validate::check_recipient_exists,
+    service_provider::ServiceContext,
+};
+use chrono::Utc;
+use repository::{
+    LogType, NotificationPriority, NotificationType, Recipient, RecipientRow,
+    RecipientRowRepository, RepositoryError, StorageConnection,
+};
+
+#[derive(Clone)]
+pub struct SetContactChannels {
+    pub contact_id: String,
+    /// The contact's recipients in the order they're tried in, e.g. their telegram chat then their email
+    pub recipient_ids: Vec<String>,
+}
+
+pub fn get_contact_channels(
+    connection: &StorageConnection,
+    contact_id: &str,
+) -> Result<Vec<Recipient>, RepositoryError> {
+    RecipientRowRepository::new(connection).find_all_for_contact(contact_id)
+}
+
+/// Replaces the contact's channels, a recipient that's a channel of another contact is moved to this one
+pub fn set_contact_channels(
+    ctx: &ServiceContext,
+    input: SetContactChannels,
+) -> Result<Vec<Recipient>, ModifyContactError> {
+    let channels = ctx
+        .connection
+        .transaction_sync(|connection| {
+            validate(connection, &input)?;
+
+            let repo = RecipientRowRepository::new(connection);
+            for channel in repo.find_all_for_contact(&input.contact_id)? {
+                if !input.recipient_ids.contains(&channel.id) {
+                    repo.set_contact(&channel.id, None, 0)?;
+                }
+            }
+            for (fallback_order, recipient_id) in input.recipient_ids.iter().enumerate() {
+                repo.set_contact(
+                    recipient_id,
+                    Some(input.contact_id.clone()),
+                    fallback_order as i32,
+                )?;
+            }
+
+            get_contact_channels(connection, &input.contact_id).map_err(ModifyContactError::from)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    audit_log_entry(
+        ctx,
+        LogType::ContactChannelsUpdated,
+        Some(input.contact_id),
+        Utc::now().naive_utc(),
+    )?;
+
+    Ok(channels)
+}
+
+pub fn validate(
+    connection: &StorageConnection,
+    input: &SetContactChannels,
+) -> Result<(), ModifyContactError> {
+    if check_contact_exists(&input.contact_id, connection)?.is_none() {
+        return Err(ModifyContactError::ContactDoesNotExist);
+    }
+
+    for (index, recipient_id) in input.recipient_ids.iter().enumerate() {
+        match check_recipient_exists(recipient_id, connection)? {
+            Some(recipient) if recipient.deleted_datetime.is_none() => {}
+            _ => return Err(ModifyContactError::RecipientDoesNotExist),
+        }
+        if input.recipient_ids[..index].contains(recipient_id) {
+            return Err(ModifyContactError::GenericError(format!(
+                "Recipient {} is listed more than once",
+                recipient_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether notifications can be sent to the channel, it isn't deleted, deactivated or undeliverable.
+/// A muted channel is only used for critical notifications
+pub fn is_usable_channel(channel: &RecipientRow, priority: &NotificationPriority) -> bool {
+    let is_muted = channel
+        .muted_until
+        .is_some_and(|muted_until| muted_until > Utc::now().naive_utc());
+    channel.deleted_datetime.is_none()
+        && channel.deactivated_datetime.is_none()
+        && channel.undeliverable_datetime.is_none()
+        && (!is_muted || *priority == NotificationPriority::Critical)
+}
+
+/// The first of the contact's channels that notifications of this priority can be sent to
+pub fn first_usable_channel(
+    connection: &StorageConnection,
+    contact_id: &str,
+    priority: &NotificationPriority,
+) -> Result<Option<RecipientRow>, RepositoryError> {
+    Ok(get_contact_channels(connection, contact_id)?
+        .into_iter()
+        .find(|channel| is_usable_channel(channel, priority)))
+}
+
+/// The contact's next usable channel after the one a notification failed to send to
+pub fn next_fallback_channel(
+    connection: &StorageConnection,
+    notification_type: &NotificationType,
+    to_address: &str,
+    priority: &NotificationPriority,
+) -> Result<Option<RecipientRow>, RepositoryError> {
+    let repo = RecipientRowRepository::new(connection);
+    let failed_channel =
+        match repo.find_one_by_to_address_and_type(to_address, notification_type.clone())? {
+            Some(recipient) => recipient,
+            None => return Ok(None),
+        };
+    let contact_id = match &failed_channel.contact_id {
+        Some(contact_id) => contact_id,
+        None => return Ok(None),
+    };
+
+    Ok(get_contact_channels(connection, contact_id)?
+        .into_iter()
+        .skip_while(|channel| channel.id != failed_channel.id)
+        .skip(1)
+        .find(|channel| is_usable_channel(channel, priority)))
+}
@@ -0,0 +1,63 @@
+use super::{query::get_contact, validate::check_contact_does_not_exist, ModifyContactError};
+use crate::audit_log::audit_log_entry;
+use crate::service_provider::ServiceContext;
+
+use chrono::Utc;
+use repository::{Contact, ContactRow, ContactRowRepository, LogType, StorageConnection};
+
+#[derive(Clone)]
+pub struct CreateContact {
+    pub id: String,
+    pub name: String,
+}
+
+pub fn create_contact(
+    ctx: &ServiceContext,
+    new_contact: CreateContact,
+) -> Result<Contact, ModifyContactError> {
+    let contact = ctx
+        .connection
+        .transaction_sync(|connection| {
+            validate(&new_contact, connection)?;
+            let new_contact_row = generate(new_contact.clone());
+            ContactRowRepository::new(connection).insert_one(&new_contact_row)?;
+
+            get_contact(ctx, new_contact_row.id).map_err(ModifyContactError::from)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    // Audit logging
+    audit_log_entry(
+        ctx,
+        LogType::ContactCreated,
+        Some(new_contact.id),
+        Utc::now().naive_utc(),
+    )?;
+
+    Ok(contact)
+}
+
+pub fn validate(
+    new_contact: &CreateContact,
+    connection: &StorageConnection,
+) -> Result<(), ModifyContactError> {
+    if new_contact.name.trim().is_empty() {
+        return Err(ModifyContactError::GenericError(
+            "Contact name can't be empty".to_string(),
+        ));
+    }
+
+    if !check_contact_does_not_exist(&new_contact.id, connection)? {
+        return Err(ModifyContactError::ContactAlreadyExists);
+    }
+
+    Ok(())
+}
+
+pub fn generate(CreateContact { id, name }: CreateContact) -> ContactRow {
+    ContactRow {
+        id,
+        name: name.trim().to_string(),
+        deleted_datetime: None,
+    }
+}
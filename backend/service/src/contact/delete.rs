@@ -0,0 +1,53 @@
+use super::validate::check_contact_exists;
+use crate::service_provider::ServiceContext;
+use repository::{
+    ContactRow, ContactRowRepository, RecipientRowRepository, RepositoryError, StorageConnection,
+    TransactionError,
+};
+
+#[derive(PartialEq, Debug)]
+pub enum DeleteContactError {
+    ContactDoesNotExist,
+    DatabaseError(RepositoryError),
+}
+
+/// Marks the contact deleted, its channels are kept as recipients without a contact
+pub fn delete_contact(
+    ctx: &ServiceContext,
+    contact_id: &str,
+) -> Result<String, DeleteContactError> {
+    let contact = ctx
+        .connection
+        .transaction_sync(|connection| {
+            let contact_row = validate(connection, contact_id)?;
+
+            let recipient_repo = RecipientRowRepository::new(connection);
+            for channel in recipient_repo.find_all_for_contact(contact_id)? {
+                recipient_repo.set_contact(&channel.id, None, 0)?;
+            }
+            ContactRowRepository::new(connection).mark_deleted(contact_id)?;
+
+            Ok(contact_row)
+        })
+        .map_err(|error: TransactionError<DeleteContactError>| error.to_inner_error())?;
+
+    Ok(contact.id)
+}
+
+pub fn validate(
+    connection: &StorageConnection,
+    contact_id: &str,
+) -> Result<ContactRow, DeleteContactError> {
+    let contact_row = match check_contact_exists(contact_id, connection)? {
+        Some(contact_row) => contact_row,
+        None => return Err(DeleteContactError::ContactDoesNotExist),
+    };
+
+    Ok(contact_row)
+}
+
+impl From<RepositoryError> for DeleteContactError {
+    fn from(error: RepositoryError) -> Self {
+        DeleteContactError::DatabaseError(error)
+    }
+}
@@ -0,0 +1,111 @@
+use self::{
+    channels::{get_contact_channels, set_contact_channels, SetContactChannels},
+    create::{create_contact, CreateContact},
+    delete::{delete_contact, DeleteContactError},
+    query::{get_contact, get_contacts},
+    update::{update_contact, UpdateContact},
+};
+
+use super::{ListError, ListResult};
+use crate::{service_provider::ServiceContext, SingleRecordError};
+use repository::{
+    Contact, ContactFilter, ContactSort, PaginationOption, Recipient, RepositoryError,
+};
+
+mod tests;
+
+pub mod channels;
+pub mod create;
+pub mod delete;
+pub mod query;
+pub mod update;
+pub mod validate;
+
+pub trait ContactServiceTrait: Sync + Send {
+    fn get_contacts(
+        &self,
+        ctx: &ServiceContext,
+        pagination: Option<PaginationOption>,
+        filter: Option<ContactFilter>,
+        sort: Option<ContactSort>,
+    ) -> Result<ListResult<Contact>, ListError> {
+        get_contacts(ctx, pagination, filter, sort)
+    }
+
+    fn get_contact(
+        &self,
+        ctx: &ServiceContext,
+        contact_id: String,
+    ) -> Result<Contact, SingleRecordError> {
+        get_contact(ctx, contact_id)
+    }
+
+    fn create_contact(
+        &self,
+        ctx: &ServiceContext,
+        input: CreateContact,
+    ) -> Result<Contact, ModifyContactError> {
+        create_contact(ctx, input)
+    }
+
+    fn update_contact(
+        &self,
+        ctx: &ServiceContext,
+        input: UpdateContact,
+    ) -> Result<Contact, ModifyContactError> {
+        update_contact(ctx, input)
+    }
+
+    fn delete_contact(
+        &self,
+        ctx: &ServiceContext,
+        contact_id: &str,
+    ) -> Result<String, DeleteContactError> {
+        delete_contact(ctx, contact_id)
+    }
+
+    fn get_contact_channels(
+        &self,
+        ctx: &ServiceContext,
+        contact_id: &str,
+    ) -> Result<Vec<Recipient>, RepositoryError> {
+        get_contact_channels(&ctx.connection, contact_id)
+    }
+
+    fn set_contact_channels(
+        &self,
+        ctx: &ServiceContext,
+        input: SetContactChannels,
+    ) -> Result<Vec<Recipient>, ModifyContactError> {
+        set_contact_channels(ctx, input)
+    }
+}
+
+pub struct ContactService {}
+impl ContactServiceTrait for ContactService {}
+
+#[derive(Debug, PartialEq)]
+pub enum ModifyContactError {
+    ContactAlreadyExists,
+    ModifiedRecordNotFound,
+    DatabaseError(RepositoryError),
+    ContactDoesNotExist,
+    RecipientDoesNotExist,
+    GenericError(String),
+}
+
+impl From<RepositoryError> for ModifyContactError {
+    fn from(err: RepositoryError) -> Self {
+        ModifyContactError::DatabaseError(err)
+    }
+}
+
+impl From<SingleRecordError> for ModifyContactError {
+    fn from(error: SingleRecordError) -> Self {
+        use ModifyContactError::*;
+        match error {
+            SingleRecordError::DatabaseError(error) => DatabaseError(error),
+            SingleRecordError::NotFound(_) => ModifiedRecordNotFound,
+        }
+    }
+}
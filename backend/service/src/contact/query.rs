@@ -0,0 +1,40 @@
+use repository::{ContactFilter, ContactRepository, ContactSort, EqualFilter, PaginationOption};
+use util::i64_to_u32;
+
+use crate::{
+    get_default_pagination, service_provider::ServiceContext, ListError, ListResult,
+    SingleRecordError,
+};
+
+use super::Contact;
+
+pub const MAX_LIMIT: u32 = 1000;
+pub const MIN_LIMIT: u32 = 1;
+
+pub fn get_contacts(
+    ctx: &ServiceContext,
+    pagination: Option<PaginationOption>,
+    filter: Option<ContactFilter>,
+    sort: Option<ContactSort>,
+) -> Result<ListResult<Contact>, ListError> {
+    let pagination = get_default_pagination(pagination, MAX_LIMIT, MIN_LIMIT)?;
+    let repository = ContactRepository::new(&ctx.connection);
+
+    Ok(ListResult {
+        rows: repository.query(pagination, filter.clone(), sort)?,
+        count: i64_to_u32(repository.count(filter)?),
+    })
+}
+
+pub fn get_contact(ctx: &ServiceContext, id: String) -> Result<Contact, SingleRecordError> {
+    let repository = ContactRepository::new(&ctx.connection);
+
+    let mut result =
+        repository.query_by_filter(ContactFilter::new().id(EqualFilter::equal_to(&id)))?;
+
+    if let Some(record) = result.pop() {
+        Ok(record)
+    } else {
+        Err(SingleRecordError::NotFound(id))
+    }
+}
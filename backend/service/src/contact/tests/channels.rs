@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod contact_channels_test {
+    use std::sync::Arc;
+
+    use repository::mock::{mock_recipient_a, mock_recipient_b, mock_recipient_d_deleted};
+    use repository::{mock::MockDataInserts, test_db::setup_all, RecipientRowRepository};
+
+    use crate::contact::channels::SetContactChannels;
+    use crate::contact::create::CreateContact;
+    use crate::contact::ModifyContactError;
+    use crate::service_provider::ServiceContext;
+    use crate::service_provider::ServiceProvider;
+    use crate::test_utils::get_test_settings;
+
+    #[actix_rt::test]
+    async fn contact_service_channels() {
+        let (_, _, connection_manager, _) = setup_all(
+            "contact_service_channels",
+            MockDataInserts::none().recipients().permissions(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.contact_service;
+
+        let create = |id: &str| {
+            service.create_contact(
+                &context,
+                CreateContact {
+                    id: id.to_string(),
+                    name: format!("Contact {}", id),
+                },
+            )
+        };
+        create("contact_1").unwrap();
+        create("contact_2").unwrap();
+        assert_eq!(
+            create("contact_1"),
+            Err(ModifyContactError::ContactAlreadyExists)
+        );
+
+        // Contact does not exist
+        assert_eq!(
+            service.set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "new_id".to_string(),
+                    recipient_ids: vec![],
+                },
+            ),
+            Err(ModifyContactError::ContactDoesNotExist)
+        );
+
+        // Deleted recipients can't be channels
+        assert_eq!(
+            service.set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "contact_1".to_string(),
+                    recipient_ids: vec![mock_recipient_d_deleted().id],
+                },
+            ),
+            Err(ModifyContactError::RecipientDoesNotExist)
+        );
+
+        // Channels are in their fallback order
+        let channels = service
+            .set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "contact_1".to_string(),
+                    recipient_ids: vec![mock_recipient_b().id, mock_recipient_a().id],
+                },
+            )
+            .unwrap();
+        let channel_ids: Vec<String> = channels.into_iter().map(|channel| channel.id).collect();
+        assert_eq!(
+            channel_ids,
+            vec![mock_recipient_b().id, mock_recipient_a().id]
+        );
+
+        // A channel of another contact is moved
+        service
+            .set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "contact_2".to_string(),
+                    recipient_ids: vec![mock_recipient_a().id],
+                },
+            )
+            .unwrap();
+        let channels = service.get_contact_channels(&context, "contact_1").unwrap();
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].id, mock_recipient_b().id);
+
+        // Deleting the contact keeps its channels as recipients
+        service.delete_contact(&context, "contact_2").unwrap();
+        let recipient = RecipientRowRepository::new(&context.connection)
+            .find_one_by_id(&mock_recipient_a().id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(recipient.contact_id, None);
+        assert_eq!(recipient.deleted_datetime, None);
+    }
+}
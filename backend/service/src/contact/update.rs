@@ -0,0 +1,69 @@
+use super::{query::get_contact, validate::check_contact_exists, ModifyContactError};
+use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use chrono::Utc;
+use repository::{Contact, ContactRow, ContactRowRepository, LogType, StorageConnection};
+
+#[derive(Clone)]
+pub struct UpdateContact {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+pub fn update_contact(
+    ctx: &ServiceContext,
+    updated_contact: UpdateContact,
+) -> Result<Contact, ModifyContactError> {
+    let contact = ctx
+        .connection
+        .transaction_sync(|connection| {
+            let contact_row = validate(connection, &updated_contact)?;
+            let updated_contact_row = generate(updated_contact.clone(), contact_row);
+            ContactRowRepository::new(connection).update_one(&updated_contact_row)?;
+
+            get_contact(ctx, updated_contact_row.id).map_err(ModifyContactError::from)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    // Audit logging
+    audit_log_entry(
+        ctx,
+        LogType::ContactUpdated,
+        Some(updated_contact.id),
+        Utc::now().naive_utc(),
+    )?;
+    Ok(contact)
+}
+
+pub fn validate(
+    connection: &StorageConnection,
+    updated_contact: &UpdateContact,
+) -> Result<ContactRow, ModifyContactError> {
+    let contact_row = match check_contact_exists(&updated_contact.id, connection)? {
+        Some(contact_row) => contact_row,
+        None => return Err(ModifyContactError::ContactDoesNotExist),
+    };
+
+    if let Some(name) = &updated_contact.name {
+        if name.trim().is_empty() {
+            return Err(ModifyContactError::GenericError(
+                "Contact name can't be empty".to_string(),
+            ));
+        }
+    }
+
+    Ok(contact_row)
+}
+
+pub fn generate(
+    UpdateContact {
+        id: _id, //ID is already used for look up so we can assume it's the same
+        name,
+    }: UpdateContact,
+    current_contact_row: ContactRow,
+) -> ContactRow {
+    let mut new_contact_row = current_contact_row;
+    if let Some(name) = name {
+        new_contact_row.name = name.trim().to_string();
+    }
+    new_contact_row
+}
@@ -0,0 +1,18 @@
+use repository::{ContactRow, ContactRowRepository, RepositoryError, StorageConnection};
+
+pub fn check_contact_exists(
+    id: &str,
+    connection: &StorageConnection,
+) -> Result<Option<ContactRow>, RepositoryError> {
+    let contact = ContactRowRepository::new(connection).find_one_by_id(id)?;
+    Ok(contact.filter(|contact| contact.deleted_datetime.is_none()))
+}
+
+pub fn check_contact_does_not_exist(
+    id: &str,
+    connection: &StorageConnection,
+) -> Result<bool, RepositoryError> {
+    // Deleted contacts keep their id
+    let contact = ContactRowRepository::new(connection).find_one_by_id(id)?;
+    Ok(contact.is_none())
+}
@@ -1,34 +1,86 @@
-use crate::settings::Settings;
+use crate::{service_provider::ServiceContext, settings::Settings};
 use datasource::{
-    get_datasource_pool, pg_sql_query_as_json_rows, pg_sql_query_as_recipients, BasicRecipientRow,
-    DatasourcePool,
+    get_datasource_pool, BasicRecipientRow, DatasourceConnection, DatasourcePool,
+    DatasourceSettings, QueryError, QueryLimits,
 };
+use repository::NotificationQueryRow;
+use std::{collections::HashMap, convert::TryFrom};
 use tera::{Context, Tera};
 
 // We use a trait for DatasourceService to allow mocking in tests
+// `datasource_id` selects one of the named datasources, `None` uses the default datasource
+// `query_limits` are per query overrides, they can only tighten the limits configured for the datasource
 pub trait DatasourceServiceTrait: Send + Sync {
-    fn run_sql_query(&self, sql_query: String) -> Result<QueryResult, DatasourceServiceError>;
+    fn run_sql_query(
+        &self,
+        datasource_id: Option<String>,
+        sql_query: String,
+        query_limits: Option<QueryLimits>,
+    ) -> Result<QueryResult, DatasourceServiceError>;
     fn run_sql_query_with_parameters(
         &self,
+        datasource_id: Option<String>,
         sql_query: String,
         parameters: serde_json::Value,
+        query_limits: Option<QueryLimits>,
     ) -> Result<QueryResult, DatasourceServiceError>;
     fn run_recipient_query(
         &self,
+        datasource_id: Option<String>,
         sql_query: String,
     ) -> Result<Vec<BasicRecipientRow>, DatasourceServiceError>;
-    fn get_connection_pool(&self) -> DatasourcePool;
+    fn get_connection_pool(
+        &self,
+        datasource_id: Option<String>,
+    ) -> Result<DatasourcePool, DatasourceServiceError>;
+    /// Ids of the named datasources, the default datasource isn't included
+    fn datasource_ids(&self) -> Vec<String>;
+    fn datasource_exists(&self, datasource_id: &str) -> bool {
+        self.datasource_ids().iter().any(|id| id == datasource_id)
+    }
 }
 
-pub struct DatasourceService {
+#[derive(Clone)]
+struct Datasource {
     connection_pool: DatasourcePool,
+    query_limits: QueryLimits,
+}
+
+impl Datasource {
+    fn new(settings: &DatasourceSettings) -> Self {
+        Datasource {
+            connection_pool: get_datasource_pool(settings),
+            query_limits: settings.query_limits.clone(),
+        }
+    }
+
+    fn connection(&self) -> Result<DatasourceConnection, DatasourceServiceError> {
+        self.connection_pool.get().map_err(|error| {
+            DatasourceServiceError::InternalError(format!(
+                "Could not get connection from pool: {}",
+                error
+            ))
+        })
+    }
+
+    fn limits(&self, query_limits: Option<QueryLimits>) -> QueryLimits {
+        match query_limits {
+            Some(query_limits) => self.query_limits.restricted_by(&query_limits),
+            None => self.query_limits.clone(),
+        }
+    }
+}
+
+pub struct DatasourceService {
+    default_datasource: Datasource,
+    named_datasources: HashMap<String, Datasource>,
 }
 
 #[derive(Clone, Default, Debug, PartialEq)]
 pub struct QueryResult {
     pub results: String,
     pub query: String,
-    pub query_error: Option<String>,
+    pub query_error: Option<QueryError>,
 }
 
 #[derive(Debug)]
@@ -39,28 +91,101 @@ pub enum DatasourceServiceError {
 
 impl DatasourceService {
     pub fn new(settings: Settings) -> Self {
-        let connection_pool = get_datasource_pool(&settings.datasource);
+        let named_datasources = settings
+            .datasources
+            .iter()
+            .map(|(id, datasource_settings)| (id.clone(), Datasource::new(datasource_settings)))
+            .collect();
+
+        DatasourceService {
+            default_datasource: Datasource::new(&settings.datasource),
+            named_datasources,
+        }
+    }
+
+    fn datasource(
+        &self,
+        datasource_id: Option<String>,
+    ) -> Result<&Datasource, DatasourceServiceError> {
+        match datasource_id {
+            None => Ok(&self.default_datasource),
+            Some(datasource_id) => self.named_datasources.get(&datasource_id).ok_or(
+                DatasourceServiceError::BadUserInput(format!(
+                    "No datasource configured with id: {}",
+                    datasource_id
+                )),
+            ),
+        }
+    }
+}
+
+/// Checks a user supplied datasource_id, it's trimmed the same way as when it's stored and an empty id selects the default datasource
+pub fn check_datasource_id_is_valid(ctx: &ServiceContext, datasource_id: &str) -> bool {
+    match datasource_id_from_input(datasource_id.to_string()) {
+        None => true,
+        Some(datasource_id) => ctx
+            .service_provider
+            .datasource_service
+            .datasource_exists(&datasource_id),
+    }
+}
 
-        DatasourceService { connection_pool }
+/// Converts a datasource_id from user input to the stored value, an empty id is stored as None
+pub fn datasource_id_from_input(datasource_id: String) -> Option<String> {
+    match datasource_id.trim() {
+        "" => None,
+        datasource_id => Some(datasource_id.to_string()),
+    }
+}
+
+/// Renders the parameters into a sql query template, giving the sql that would be run for them
+pub fn render_sql_query(
+    sql_query: &str,
+    parameters: serde_json::Value,
+) -> Result<String, DatasourceServiceError> {
+    let tera_context = Context::from_value(parameters).map_err(|e| {
+        DatasourceServiceError::InternalError(format!(
+            "Failed to convert params to tera context: {}",
+            e
+        ))
+    })?;
+
+    Tera::one_off(sql_query, &tera_context, false).map_err(|e| {
+        DatasourceServiceError::InternalError(format!(
+            "Failed to parse query as tera template: {}",
+            e
+        ))
+    })
+}
+
+/// Per query limit overrides configured on a notification query
+pub fn notification_query_limits(query: &NotificationQueryRow) -> QueryLimits {
+    // Negative values are rejected when the query is saved, so these conversions shouldn't fail
+    QueryLimits {
+        timeout_seconds: query.timeout_seconds.and_then(|v| u32::try_from(v).ok()),
+        max_rows: query.max_rows.and_then(|v| u32::try_from(v).ok()),
+        max_result_bytes: query.max_result_bytes.and_then(|v| u32::try_from(v).ok()),
     }
 }
 
 impl DatasourceServiceTrait for DatasourceService {
-    fn run_sql_query(&self, sql_query: String) -> Result<QueryResult, DatasourceServiceError> {
-        let connection = &mut self.connection_pool.pool.get().map_err(|error| {
-            DatasourceServiceError::InternalError(format!(
-                "Could not get connection from pool: {}",
-                error
-            ))
-        })?;
+    fn run_sql_query(
+        &self,
+        datasource_id: Option<String>,
+        sql_query: String,
+        query_limits: Option<QueryLimits>,
+    ) -> Result<QueryResult, DatasourceServiceError> {
+        let datasource = self.datasource(datasource_id)?;
+        let connection = &mut datasource.connection()?;
         // Run query
-        let result = pg_sql_query_as_json_rows(connection, sql_query.clone());
+        let result =
+            connection.query_as_json_rows(sql_query.clone(), &datasource.limits(query_limits));
         let mut query_error = None;
-        let result = match result{
+        let result = match result {
             Ok(rows) => rows,
             Err(e) => {
-                query_error = Some(format!("{:?}", e));
-                vec![]// return empty array of results if there's an error
+                query_error = Some(e);
+                vec![] // return empty array of results if there's an error
             }
         };
 
@@ -80,57 +205,42 @@ impl DatasourceServiceTrait for DatasourceService {
     }
     fn run_recipient_query(
         &self,
+        datasource_id: Option<String>,
         sql_query: String,
     ) -> Result<Vec<BasicRecipientRow>, DatasourceServiceError> {
-        let connection = &mut self.connection_pool.pool.get().map_err(|error| {
-            DatasourceServiceError::InternalError(format!(
-                "Could not get connection from pool: {}",
-                error
-            ))
-        })?;
+        let datasource = self.datasource(datasource_id)?;
+        let connection = &mut datasource.connection()?;
         // Run query
-        let result = pg_sql_query_as_recipients(connection, sql_query).map_err(|error| {
-            DatasourceServiceError::BadUserInput(format!("Could not run query: {}", error))
-        })?;
+        let result = connection
+            .query_as_recipients(sql_query, &datasource.query_limits)
+            .map_err(|error| {
+                DatasourceServiceError::BadUserInput(format!("Could not run query: {}", error))
+            })?;
 
         Ok(result)
     }
 
     fn run_sql_query_with_parameters(
         &self,
+        datasource_id: Option<String>,
         sql_query: String,
         parameters: serde_json::Value,
+        query_limits: Option<QueryLimits>,
     ) -> Result<QueryResult, DatasourceServiceError> {
-        let connection = &mut self.connection_pool.pool.get().map_err(|error| {
-            DatasourceServiceError::InternalError(format!(
-                "Could not get connection from pool: {}",
-                error
-            ))
-        })?;
-
-        // Pass params to template to get the full query
-        let tera_context = Context::from_value(parameters).map_err(|e| {
-            DatasourceServiceError::InternalError(format!(
-                "Failed to convert params to tera context: {}",
-                e.to_string()
-            ))
-        })?;
+        let datasource = self.datasource(datasource_id)?;
+        let connection = &mut datasource.connection()?;
 
-        let full_query = Tera::one_off(&sql_query, &tera_context, false).map_err(|e| {
-            DatasourceServiceError::InternalError(format!(
-                "Failed to parse query as tera template: {}",
-                e.to_string()
-            ))
-        })?;
+        let full_query = render_sql_query(&sql_query, parameters)?;
 
         // Run query
-        let result = pg_sql_query_as_json_rows(connection, full_query.clone());
+        let result =
+            connection.query_as_json_rows(full_query.clone(), &datasource.limits(query_limits));
         let mut query_error = None;
-        let result = match result{
+        let result = match result {
             Ok(rows) => rows,
             Err(e) => {
-                query_error = Some(format!("{:?}", e));
-                vec![]// return empty array of results if there's an error
+                query_error = Some(e);
+                vec![] // return empty array of results if there's an error
             }
         };
 
@@ -149,8 +259,17 @@ impl DatasourceServiceTrait for DatasourceService {
         })
     }
 
-    fn get_connection_pool(&self) -> DatasourcePool {
-        self.connection_pool.clone()
+    fn get_connection_pool(
+        &self,
+        datasource_id: Option<String>,
+    ) -> Result<DatasourcePool, DatasourceServiceError> {
+        Ok(self.datasource(datasource_id)?.connection_pool.clone())
+    }
+
+    fn datasource_ids(&self) -> Vec<String> {
+        let mut datasource_ids: Vec<String> = self.named_datasources.keys().cloned().collect();
+        datasource_ids.sort();
+        datasource_ids
     }
 }
 
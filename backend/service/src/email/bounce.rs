@@ -0,0 +1,490 @@
+/*
+   Bounce handling, reading the delivery status notifications (DSNs) mail servers send back when an email can't be delivered.
+   https://www.rfc-editor.org/rfc/rfc3464
+   A DSN is a multipart/report email with a message/delivery-status part, that has a group of fields for each recipient, e.g.
+     Final-Recipient: rfc822; user@example.com
+     Action: failed
+     Status: 5.1.1
+     Diagnostic-Code: smtp; 550 5.1.1 User unknown
+   A failed action with a 5.x.x status is a hard bounce, the address won't ever work so the recipient is marked undeliverable.
+   Delays and 4.x.x statuses are temporary and ignored, the email is retried as usual.
+   A bounce is only accepted if the original email returned with it has the Message-ID of an email we sent to the recipient,
+   so anyone who can email the mailbox can't mark recipients undeliverable.
+*/
+
+use chrono::Utc;
+use repository::{
+    LogType, NotificationEventRowRepository, NotificationType, RecipientRowRepository,
+    RepositoryError,
+};
+
+use crate::{
+    audit_log::audit_log_entry, recipient::validate::normalise_to_address,
+    service_provider::ServiceContext,
+};
+
+use super::pop3::{read_mailbox, Pop3Error};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounce {
+    pub recipient: String,
+    pub status: String,
+    pub diagnostic: Option<String>,
+}
+
+impl Bounce {
+    pub fn reason(&self) -> String {
+        match &self.diagnostic {
+            Some(diagnostic) => format!("Bounced with status {} - {}", self.status, diagnostic),
+            None => format!("Bounced with status {}", self.status),
+        }
+    }
+}
+
+/// Joins folded lines back onto the line they continue
+fn unfold(lines: &[&str]) -> Vec<String> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in lines {
+        match (line.starts_with([' ', '\t']), unfolded.last_mut()) {
+            (true, Some(previous)) => {
+                previous.push(' ');
+                previous.push_str(line.trim());
+            }
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+    unfolded
+}
+
+/// The value after the address type, e.g. user@example.com from "rfc822; <user@example.com>"
+fn typed_value(value: &str) -> String {
+    let value = value.split_once(';').map_or(value, |(_, value)| value);
+    value
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+}
+
+/// The field groups of the message/delivery-status parts, with the field names lowercased
+fn delivery_status_groups(message: &str) -> Vec<Vec<(String, String)>> {
+    let lines: Vec<&str> = message.lines().collect();
+    let mut groups = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].to_ascii_lowercase();
+        i += 1;
+        // message/delivery-status, or message/global-delivery-status for utf-8 addresses
+        let is_delivery_status = line
+            .strip_prefix("content-type:")
+            .and_then(|content_type| content_type.split(';').next())
+            .is_some_and(|content_type| {
+                let content_type = content_type.trim();
+                content_type.starts_with("message/") && content_type.ends_with("delivery-status")
+            });
+        if !is_delivery_status {
+            continue;
+        }
+        // Skip the rest of the part's headers
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            i += 1;
+        }
+
+        let mut group: Vec<&str> = Vec::new();
+        while i < lines.len() && !lines[i].starts_with("--") {
+            if lines[i].trim().is_empty() {
+                if !group.is_empty() {
+                    groups.push(group);
+                    group = Vec::new();
+                }
+            } else {
+                group.push(lines[i]);
+            }
+            i += 1;
+        }
+        if !group.is_empty() {
+            groups.push(group);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|group| {
+            unfold(&group)
+                .into_iter()
+                .filter_map(|field| {
+                    field.split_once(':').map(|(name, value)| {
+                        (name.trim().to_ascii_lowercase(), value.trim().to_string())
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The Message-ID of the original email, from the message/rfc822 or text/rfc822-headers part returned with the DSN
+fn original_message_id(message: &str) -> Option<String> {
+    let lines: Vec<&str> = message.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].to_ascii_lowercase();
+        i += 1;
+        // message/global and message/global-headers for emails with utf-8 headers
+        let is_original_message = line
+            .strip_prefix("content-type:")
+            .and_then(|content_type| content_type.split(';').next())
+            .is_some_and(|content_type| {
+                matches!(
+                    content_type.trim(),
+                    "message/rfc822"
+                        | "text/rfc822-headers"
+                        | "message/global"
+                        | "message/global-headers"
+                )
+            });
+        if !is_original_message {
+            continue;
+        }
+        // Skip the rest of the part's headers, the original email's headers follow
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            i += 1;
+        }
+        let start = (i + 1).min(lines.len());
+        let mut end = start;
+        while end < lines.len() && !lines[end].trim().is_empty() {
+            end += 1;
+        }
+        return unfold(&lines[start..end]).into_iter().find_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("message-id")
+                .then(|| value.trim().to_string())
+        });
+    }
+    None
+}
+
+pub fn is_delivery_status_notification(message: &str) -> bool {
+    !delivery_status_groups(message).is_empty()
+}
+
+/// The recipients that permanently failed in a DSN, an email that isn't a DSN has none
+pub fn parse_hard_bounces(message: &str) -> Vec<Bounce> {
+    let mut bounces = Vec::new();
+    for fields in delivery_status_groups(message) {
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value.clone())
+        };
+        // Only the per recipient groups have a Final-Recipient
+        let Some(recipient) = field("final-recipient").or_else(|| field("original-recipient"))
+        else {
+            continue;
+        };
+        let action = field("action").unwrap_or_default().to_ascii_lowercase();
+        let status = field("status").unwrap_or_default();
+        if action != "failed" || !status.starts_with('5') {
+            continue;
+        }
+        bounces.push(Bounce {
+            recipient: typed_value(&recipient),
+            status,
+            diagnostic: field("diagnostic-code").map(|diagnostic| typed_value(&diagnostic)),
+        });
+    }
+    bounces
+}
+
+/// Marks the email recipients that hard bounced in the message as undeliverable, returning their ids.
+/// Bounces that don't match an email we sent to the bounced address are ignored.
+/// Bounced addresses that aren't recipients, e.g. from a sql recipient list, are only logged.
+pub fn process_bounce_message(
+    ctx: &ServiceContext,
+    message: &str,
+) -> Result<Vec<String>, RepositoryError> {
+    let repo = RecipientRowRepository::new(&ctx.connection);
+    let mut undeliverable_ids = Vec::new();
+
+    let Some(message_id) = original_message_id(message) else {
+        log::warn!("Ignoring a bounce that doesn't include the original email's Message-ID");
+        return Ok(undeliverable_ids);
+    };
+    let Some(sent_email) =
+        NotificationEventRowRepository::new(&ctx.connection).find_one_by_message_id(&message_id)?
+    else {
+        log::warn!(
+            "Ignoring a bounce for {}, it isn't an email we sent",
+            message_id
+        );
+        return Ok(undeliverable_ids);
+    };
+    let sent_to = normalise_to_address(&sent_email.to_address, &NotificationType::Email);
+
+    for bounce in parse_hard_bounces(message) {
+        let to_address = normalise_to_address(&bounce.recipient, &NotificationType::Email);
+        if to_address != sent_to {
+            log::warn!(
+                "Ignoring a bounce for {}, email {} was sent to {}",
+                to_address,
+                message_id,
+                sent_to
+            );
+            continue;
+        }
+        let Some(recipient) =
+            repo.find_one_by_to_address_and_type(&to_address, NotificationType::Email)?
+        else {
+            log::warn!(
+                "Email to {} bounced, but it isn't a recipient - {}",
+                to_address,
+                bounce.reason()
+            );
+            continue;
+        };
+
+        log::warn!(
+            "Email recipient {} is undeliverable - {}",
+            to_address,
+            bounce.reason()
+        );
+        let now = Utc::now().naive_utc();
+        repo.set_undeliverable(&recipient.id, Some((now, bounce.reason())))?;
+        audit_log_entry(
+            ctx,
+            LogType::RecipientUndeliverable,
+            Some(recipient.id.clone()),
+            now,
+        )?;
+        undeliverable_ids.push(recipient.id);
+    }
+
+    Ok(undeliverable_ids)
+}
+
+/// Processes the bounces in the pop3 mailbox from the mail bounces settings, returning the number of recipients marked undeliverable
+pub fn check_bounce_mailbox(ctx: &ServiceContext) -> Result<usize, Pop3Error> {
+    let Some(pop3) = ctx
+        .service_provider
+        .settings
+        .mail
+        .bounces
+        .as_ref()
+        .and_then(|bounces| bounces.pop3.as_ref())
+    else {
+        return Ok(0);
+    };
+
+    let mut undeliverable_count = 0;
+    read_mailbox(pop3, |message| {
+        if !is_delivery_status_notification(message) {
+            return false;
+        }
+        match process_bounce_message(ctx, message) {
+            Ok(undeliverable_ids) => {
+                undeliverable_count += undeliverable_ids.len();
+                true
+            }
+            // Left in the mailbox to try again next time
+            Err(e) => {
+                log::error!("Unable to process bounce - {:?}", e);
+                false
+            }
+        }
+    })?;
+    Ok(undeliverable_count)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use repository::{
+        mock::{mock_recipient_a, MockDataInserts},
+        test_db::setup_all,
+        NotificationEventRow, NotificationEventStatus,
+    };
+
+    use crate::{
+        email::pop3::stub::Pop3Stub,
+        service_provider::ServiceProvider,
+        settings::{BounceSettings, Pop3Settings},
+        test_utils::get_test_settings,
+    };
+
+    use super::*;
+
+    const BOUNCED_MESSAGE_ID: &str = "<sensor-alert@msupply.foundation>";
+
+    fn insert_sent_email(ctx: &ServiceContext, to_address: &str, message_id: &str) {
+        NotificationEventRowRepository::new(&ctx.connection)
+            .insert_one(&NotificationEventRow {
+                id: message_id.to_string(),
+                notification_type: NotificationType::Email,
+                to_address: to_address.to_string(),
+                status: NotificationEventStatus::Sent,
+                message_id: Some(message_id.to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+    }
+
+    const BOUNCE: &str = "From: Mail Delivery System <MAILER-DAEMON@mail.example.com>\r
+To: no-reply@msupply.foundation\r
+Subject: Undelivered Mail Returned to Sender\r
+MIME-Version: 1.0\r
+Content-Type: multipart/report; report-type=delivery-status;\r
+\tboundary=\"B0UNDARY\"\r
+\r
+--B0UNDARY\r
+Content-Type: text/plain; charset=us-ascii\r
+\r
+This is the mail system at host mail.example.com.\r
+I'm sorry to have to inform you that your message could not be delivered.\r
+\r
+--B0UNDARY\r
+Content-Type: message/delivery-status\r
+\r
+Reporting-MTA: dns; mail.example.com\r
+Arrival-Date: Mon, 29 Apr 2024 10:00:00 +1200 (NZST)\r
+\r
+Final-Recipient: rfc822; A@openmsupply.foundation\r
+Original-Recipient: rfc822;a@openmsupply.foundation\r
+Action: failed\r
+Status: 5.1.1\r
+Diagnostic-Code: smtp; 550 5.1.1 <a@openmsupply.foundation>: Recipient address\r
+    rejected: User unknown\r
+\r
+Final-Recipient: rfc822; busy@openmsupply.foundation\r
+Action: delayed\r
+Status: 4.2.2\r
+Diagnostic-Code: smtp; 452 4.2.2 Mailbox full\r
+\r
+Final-Recipient: rfc822; gone@example.com\r
+Action: failed\r
+Status: 5.1.1\r
+\r
+--B0UNDARY\r
+Content-Type: message/rfc822\r
+\r
+Message-ID: <sensor-alert@msupply.foundation>\r
+Subject: Sensor alert\r
+\r
+Sensor is too hot\r
+--B0UNDARY--\r
+";
+
+    #[test]
+    fn test_parse_hard_bounces() {
+        let bounces = parse_hard_bounces(BOUNCE);
+        assert_eq!(
+            bounces,
+            vec![
+                Bounce {
+                    recipient: "A@openmsupply.foundation".to_string(),
+                    status: "5.1.1".to_string(),
+                    diagnostic: Some("550 5.1.1 <a@openmsupply.foundation>: Recipient address rejected: User unknown".to_string()),
+                },
+                Bounce {
+                    recipient: "gone@example.com".to_string(),
+                    status: "5.1.1".to_string(),
+                    diagnostic: None,
+                },
+            ]
+        );
+
+        // Out of office replies and other emails aren't bounces
+        assert_eq!(
+            parse_hard_bounces("Subject: Out of office\r\n\r\nBack on Monday\r\n"),
+            vec![]
+        );
+
+        assert_eq!(
+            original_message_id(BOUNCE),
+            Some(BOUNCED_MESSAGE_ID.to_string())
+        );
+        assert_eq!(
+            original_message_id("Subject: Out of office\r\n\r\nBack on Monday\r\n"),
+            None
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_process_bounce_message() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_process_bounce_message",
+            MockDataInserts::none().recipients(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::new(service_provider).unwrap();
+
+        // A bounce for an email we didn't send is ignored
+        assert_eq!(
+            process_bounce_message(&context, BOUNCE).unwrap(),
+            Vec::<String>::new()
+        );
+        // As is one for an email that was sent to someone else
+        insert_sent_email(&context, "b@openmsupply.foundation", BOUNCED_MESSAGE_ID);
+        assert_eq!(
+            process_bounce_message(&context, BOUNCE).unwrap(),
+            Vec::<String>::new()
+        );
+
+        insert_sent_email(
+            &context,
+            "a@openmsupply.foundation",
+            "<other@msupply.foundation>",
+        );
+        let bounce = BOUNCE.replace(BOUNCED_MESSAGE_ID, "<other@msupply.foundation>");
+        let undeliverable_ids = process_bounce_message(&context, &bounce).unwrap();
+        // gone@example.com bounced too, but the email wasn't sent to them
+        assert_eq!(undeliverable_ids, vec![mock_recipient_a().id]);
+
+        let recipient = RecipientRowRepository::new(&context.connection)
+            .find_one_by_id(&mock_recipient_a().id)
+            .unwrap()
+            .unwrap();
+        assert!(recipient.undeliverable_datetime.is_some());
+        assert!(recipient
+            .undeliverable_reason
+            .unwrap()
+            .contains("User unknown"));
+    }
+
+    #[actix_rt::test]
+    async fn test_check_bounce_mailbox() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_check_bounce_mailbox",
+            MockDataInserts::none().recipients(),
+        )
+        .await;
+        let stub = Pop3Stub::start(vec![
+            BOUNCE.to_string(),
+            "Subject: Out of office\r\n\r\nBack on Monday\r\n".to_string(),
+        ]);
+        let mut settings = get_test_settings("");
+        settings.mail.bounces = Some(BounceSettings {
+            pop3: Some(Pop3Settings {
+                host: "127.0.0.1".to_string(),
+                port: stub.port(),
+                tls: false,
+                username: "bounces".to_string(),
+                password: "password".to_string(),
+            }),
+            ..Default::default()
+        });
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::new(service_provider).unwrap();
+        insert_sent_email(&context, "a@openmsupply.foundation", BOUNCED_MESSAGE_ID);
+
+        assert_eq!(check_bounce_mailbox(&context).unwrap(), 1);
+        // The bounce is deleted, other emails are left in the mailbox
+        assert_eq!(stub.messages().len(), 1);
+        assert!(stub.messages()[0].contains("Out of office"));
+    }
+}
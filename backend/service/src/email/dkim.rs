@@ -0,0 +1,230 @@
+/*
+   DKIM signing of outgoing emails, https://www.rfc-editor.org/rfc/rfc6376
+   Receiving servers check the signature against the public key published at <selector>._domainkey.<domain>,
+   unsigned emails from self hosted servers are much more likely to be treated as spam.
+*/
+
+use lettre::message::{
+    dkim::{
+        DkimCanonicalization, DkimCanonicalizationType, DkimConfig, DkimSigningAlgorithm,
+        DkimSigningKey,
+    },
+    header::HeaderName,
+};
+
+use crate::settings::{DkimAlgorithm, DkimSettings};
+
+/// Headers covered by the signature, a header the email doesn't have is signed as being absent.
+/// Content-Type isn't signed as lettre only adds it for a multipart body when the email is formatted, after signing.
+const SIGNED_HEADERS: [&str; 11] = [
+    "From",
+    "To",
+    "Cc",
+    "Reply-To",
+    "Subject",
+    "Date",
+    "Message-ID",
+    "MIME-Version",
+    "List-Unsubscribe",
+    "List-Unsubscribe-Post",
+    "Auto-Submitted",
+];
+
+pub fn dkim_config(settings: &DkimSettings) -> Result<DkimConfig, String> {
+    let private_key = match (&settings.private_key, &settings.private_key_file) {
+        (Some(private_key), _) => private_key.clone(),
+        (None, Some(private_key_file)) => {
+            std::fs::read_to_string(private_key_file).map_err(|e| {
+                format!(
+                    "Unable to read the DKIM private key file {} - {}",
+                    private_key_file, e
+                )
+            })?
+        }
+        (None, None) => return Err("No DKIM private_key or private_key_file is set".to_string()),
+    };
+    let algorithm = match settings.algorithm {
+        DkimAlgorithm::Rsa => DkimSigningAlgorithm::Rsa,
+        DkimAlgorithm::Ed25519 => DkimSigningAlgorithm::Ed25519,
+    };
+    let signing_key = DkimSigningKey::new(private_key.trim(), algorithm)
+        .map_err(|e| format!("Invalid DKIM private key - {}", e))?;
+
+    // Relaxed canonicalization allows for relays re-folding long headers and changing whitespace
+    Ok(DkimConfig::new(
+        settings.selector.clone(),
+        settings.domain.clone(),
+        signing_key,
+        SIGNED_HEADERS
+            .iter()
+            .map(|name| HeaderName::new_from_ascii_str(name))
+            .collect(),
+        DkimCanonicalization {
+            header: DkimCanonicalizationType::Relaxed,
+            body: DkimCanonicalizationType::Relaxed,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use ed25519_dalek::{Signature, SigningKey, Verifier, VerifyingKey};
+    use sha2::{Digest, Sha256};
+
+    use crate::email::{
+        options::{EmailHeader, EmailOptions},
+        send::build_message,
+    };
+
+    use super::*;
+
+    const PRIVATE_KEY: [u8; 32] = [7; 32];
+
+    fn settings() -> DkimSettings {
+        DkimSettings {
+            domain: "example.com".to_string(),
+            selector: "notify".to_string(),
+            algorithm: DkimAlgorithm::Ed25519,
+            private_key: Some(STANDARD.encode(PRIVATE_KEY)),
+            private_key_file: None,
+        }
+    }
+
+    fn collapse_whitespace(value: &str) -> String {
+        value.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc6376#section-3.4.2
+    fn relaxed_header(name: &str, value: &str) -> String {
+        format!("{}:{}\r\n", name.to_lowercase(), collapse_whitespace(value))
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc6376#section-3.4.4
+    fn relaxed_body(body: &str) -> String {
+        let mut lines: Vec<String> = body
+            .split("\r\n")
+            .map(|line| {
+                let mut relaxed = String::new();
+                for c in line.chars() {
+                    let c = if c == '\t' { ' ' } else { c };
+                    if !(c == ' ' && relaxed.ends_with(' ')) {
+                        relaxed.push(c);
+                    }
+                }
+                relaxed.trim_end_matches(' ').to_string()
+            })
+            .collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines.iter().map(|line| format!("{}\r\n", line)).collect()
+    }
+
+    /// Checks the DKIM-Signature the way a receiving server would
+    fn verify(email: &str, public_key: &VerifyingKey) -> bool {
+        let (head, body) = email.split_once("\r\n\r\n").unwrap();
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in head.split("\r\n") {
+            match (line.starts_with([' ', '\t']), headers.last_mut()) {
+                (true, Some((_, value))) => value.push_str(line),
+                _ => {
+                    let (name, value) = line.split_once(':').unwrap();
+                    headers.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        };
+
+        let (_, signature_header) = header("DKIM-Signature").unwrap();
+        let tags: HashMap<String, String> = signature_header
+            .split(';')
+            .filter_map(|tag| tag.split_once('='))
+            .map(|(name, value)| (name.trim().to_string(), value.split_whitespace().collect()))
+            .collect();
+        let body_hash = STANDARD.encode(Sha256::digest(relaxed_body(body)));
+        if tags["bh"] != body_hash {
+            return false;
+        }
+
+        let mut signed = String::new();
+        for name in tags["h"].split(':') {
+            if let Some((name, value)) = header(name) {
+                signed.push_str(&relaxed_header(name, value));
+            }
+        }
+        // The signature header is signed with an empty b= tag
+        let unsigned_header: Vec<String> = signature_header
+            .split(';')
+            .map(|tag| match tag.trim_start().starts_with("b=") {
+                true => " b=".to_string(),
+                false => tag.to_string(),
+            })
+            .collect();
+        signed.push_str(relaxed_header("DKIM-Signature", &unsigned_header.join(";")).trim_end());
+
+        let signature = Signature::from_slice(&STANDARD.decode(&tags["b"]).unwrap()).unwrap();
+        public_key
+            .verify(&Sha256::digest(signed.as_bytes()), &signature)
+            .is_ok()
+    }
+
+    #[test]
+    fn test_dkim_signature() {
+        let mut message = build_message(
+            "Cold Chain Alerts <alerts@example.com>".parse().unwrap(),
+            "user@example.com".to_string(),
+            "Sensor alert".to_string(),
+            "<p>Sensor is too hot</p>".to_string(),
+            "Sensor is too hot".to_string(),
+            vec![],
+            EmailOptions {
+                headers: vec![EmailHeader::new(
+                    "List-Unsubscribe",
+                    "<https://notify.example.com/unsubscribe?token=abc>",
+                )],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        message.sign(&dkim_config(&settings()).unwrap());
+        let email = String::from_utf8(message.formatted()).unwrap();
+        assert!(email.contains("a=ed25519-sha256; d=example.com; s=notify;"));
+
+        let public_key = SigningKey::from_bytes(&PRIVATE_KEY).verifying_key();
+        assert!(verify(&email, &public_key));
+
+        // Changing the email after it's signed breaks the signature
+        assert!(!verify(
+            &email.replace("Sensor is too hot", "Sensor is fine"),
+            &public_key
+        ));
+        assert!(!verify(
+            &email.replace("Subject: Sensor alert", "Subject: Sensor ok"),
+            &public_key
+        ));
+        let other_key = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert!(!verify(&email, &other_key));
+    }
+
+    #[test]
+    fn test_dkim_config_errors() {
+        let result = dkim_config(&DkimSettings {
+            private_key: None,
+            ..settings()
+        });
+        assert!(result.is_err());
+
+        let result = dkim_config(&DkimSettings {
+            algorithm: DkimAlgorithm::Rsa,
+            ..settings()
+        });
+        assert!(result.unwrap_err().starts_with("Invalid DKIM private key"));
+    }
+}
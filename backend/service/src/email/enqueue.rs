@@ -1,10 +1,18 @@
 use chrono::Utc;
-use repository::{EmailQueueRow, EmailQueueRowRepository, EmailQueueStatus};
+use repository::{
+    NotificationEventRow, NotificationEventRowRepository, NotificationEventStatus, NotificationType,
+};
 use util::uuid::uuid;
 
-use crate::service_provider::ServiceContext;
+use crate::{
+    notification::attachment::{attachments_to_json, NotificationAttachment},
+    service_provider::ServiceContext,
+};
 
-use super::EmailServiceError;
+use super::{
+    options::{email_options_to_json, EmailOptions},
+    EmailServiceError,
+};
 
 #[derive(Debug)]
 pub struct EnqueueEmailData {
@@ -12,30 +20,110 @@ pub struct EnqueueEmailData {
     pub subject: String,
     pub html_body: String,
     pub text_body: String,
+    pub attachments: Vec<NotificationAttachment>,
+    pub options: EmailOptions,
 }
 
+/// Queues a system email, such as a password reset, as a notification event.
+/// It's sent, retried and logged the same way as any other notification.
 pub fn enqueue_email(
     ctx: &ServiceContext,
     email: EnqueueEmailData,
 ) -> Result<(), EmailServiceError> {
-    let repo = EmailQueueRowRepository::new(&ctx.connection);
+    let repo = NotificationEventRowRepository::new(&ctx.connection);
+    let attachments = attachments_to_json(&email.attachments)
+        .map_err(|e| EmailServiceError::GenericError(format!("{:?}", e)))?;
+    let email_options = email_options_to_json(&email.options)?;
 
-    let email_queue_row = EmailQueueRow {
+    let notification_event_row = NotificationEventRow {
         id: uuid(),
+        notification_type: NotificationType::Email,
         to_address: email.to_address,
-        subject: email.subject,
-        html_body: email.html_body,
-        text_body: email.text_body,
+        title: Some(email.subject),
+        message: email.text_body,
+        html_message: Some(email.html_body),
+        status: NotificationEventStatus::Queued,
         created_at: Utc::now().naive_utc(),
-        sent_at: None,
-        error: None,
-        retries: 0,
         updated_at: Utc::now().naive_utc(),
-        status: EmailQueueStatus::Queued,
+        attachments,
+        email_options,
+        ..Default::default()
     };
 
-    repo.insert_one(&email_queue_row)
+    repo.insert_one(&notification_event_row)
         .map_err(|e| EmailServiceError::DatabaseError(e))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use repository::{mock::MockDataInserts, test_db::setup_all};
+
+    use crate::{
+        email::options::{email_options_from_json, EmailHeader},
+        notification::attachment::attachments_from_json,
+        service_provider::ServiceProvider,
+        test_utils::get_test_settings,
+    };
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_enqueue_email_with_attachments() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_enqueue_email_with_attachments",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let options = EmailOptions {
+            bcc: vec!["audit@example.com".to_string()],
+            headers: vec![EmailHeader::new(
+                "List-Unsubscribe",
+                "<https://example.com/unsubscribe>",
+            )],
+            ..Default::default()
+        };
+        enqueue_email(
+            &context,
+            EnqueueEmailData {
+                to_address: "user@example.com".to_string(),
+                subject: "Weekly report".to_string(),
+                html_body: "<p>Attached</p>".to_string(),
+                text_body: "Attached".to_string(),
+                attachments: vec![NotificationAttachment {
+                    filename: "report.csv".to_string(),
+                    content_type: "text/csv".to_string(),
+                    content: b"a,b\n1,2\n".to_vec(),
+                }],
+                options: options.clone(),
+            },
+        )
+        .unwrap();
+
+        // The queued row keeps everything needed to send the email again if it has to be retried
+        let queued = NotificationEventRowRepository::new(&context.connection)
+            .un_sent()
+            .unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].notification_type, NotificationType::Email);
+        assert_eq!(queued[0].title, Some("Weekly report".to_string()));
+        assert_eq!(queued[0].message, "Attached");
+        assert_eq!(queued[0].html_message, Some("<p>Attached</p>".to_string()));
+        let attachments = attachments_from_json(&queued[0].attachments).unwrap();
+        assert_eq!(attachments[0].content, b"a,b\n1,2\n".to_vec());
+        assert_eq!(
+            email_options_from_json(&queued[0].email_options).unwrap(),
+            options
+        );
+    }
+}
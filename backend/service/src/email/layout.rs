@@ -0,0 +1,392 @@
+/*
+   The html layout notification emails are sent in, base.html with the notification's rendered markdown as its content.
+   The logo, colours and footer come from the notification config's email layout, then the mail layout settings, then the defaults.
+   Many mail clients ignore <style> rules, so the css is inlined into style attributes before the email is sent.
+*/
+
+use repository::NotificationConfigRowRepository;
+use serde::{Deserialize, Serialize};
+use tera::{Context, Tera};
+
+use crate::{service_provider::ServiceContext, settings::MailSettings};
+
+use super::{
+    options::{EmailHeader, EmailOptions},
+    unsubscribe::UNSUBSCRIBE_PATH,
+    EmailServiceError,
+};
+
+const BASE_TEMPLATE: &str = include_str!("base.html");
+const NOTIFICATION_TEMPLATE: &str = include_str!("notification.html");
+
+pub const DEFAULT_LOGO_URL: &str = "https://msupply.foundation/images/13.png";
+pub const DEFAULT_PRIMARY_COLOUR: &str = "#2dc26b";
+pub const DEFAULT_BACKGROUND_COLOUR: &str = "#e7e7e7";
+pub const DEFAULT_FOOTER_TEXT: &str = "mSupply - Notify";
+
+const PREVIEW_TITLE: &str = "Example notification";
+const PREVIEW_BODY: &str = "Sensor **Fridge 1** in *Main Store* is too hot.
+
+| Sensor | Temperature |
+| --- | --- |
+| Fridge 1 | 9.2°C |
+
+See the [dashboard](https://msupply.foundation) for more details.";
+
+/// Changes to the layout's logo, colours and footer, anything not set is left as it is
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmailBranding {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_url: Option<String>,
+    /// Used for links, headings and buttons, as a hex colour e.g. #2dc26b
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_colour: Option<String>,
+    /// Shown around the email, as a hex colour e.g. #e7e7e7
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_colour: Option<String>,
+    /// The name the email is signed off with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer_text: Option<String>,
+}
+
+impl EmailBranding {
+    /// Drops any empty values, so the layout's value is used for them
+    pub fn non_empty(self) -> EmailBranding {
+        let non_empty = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        EmailBranding {
+            logo_url: non_empty(self.logo_url),
+            primary_colour: non_empty(self.primary_colour),
+            background_colour: non_empty(self.background_colour),
+            footer_text: non_empty(self.footer_text),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == EmailBranding::default()
+    }
+
+    /// Colours are put into the layout's css, so only hex colours are allowed
+    pub fn validate(&self) -> Result<(), String> {
+        for colour in [
+            self.primary_colour.as_ref(),
+            self.background_colour.as_ref(),
+        ]
+        .iter()
+        .flatten()
+        {
+            if !is_hex_colour(colour) {
+                return Err(format!("{} is not a hex colour, e.g. #2dc26b", colour));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_hex_colour(colour: &str) -> bool {
+    match colour.strip_prefix('#') {
+        Some(hex) => {
+            (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+/// Reads the `email_layout` column of a notification config, branding that can't be read is ignored
+pub fn email_branding_from_json(email_layout: &Option<String>) -> Option<EmailBranding> {
+    let email_layout = email_layout.as_ref()?;
+    match serde_json::from_str(email_layout) {
+        Ok(branding) => Some(branding),
+        Err(e) => {
+            log::error!("Unable to read email layout {} - {:?}", email_layout, e);
+            None
+        }
+    }
+}
+
+/// The branding a layout is rendered with, `layout` in the templates
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmailLayout {
+    pub logo_url: String,
+    pub primary_colour: String,
+    pub background_colour: String,
+    pub footer_text: String,
+}
+
+impl Default for EmailLayout {
+    fn default() -> Self {
+        EmailLayout {
+            logo_url: DEFAULT_LOGO_URL.to_string(),
+            primary_colour: DEFAULT_PRIMARY_COLOUR.to_string(),
+            background_colour: DEFAULT_BACKGROUND_COLOUR.to_string(),
+            footer_text: DEFAULT_FOOTER_TEXT.to_string(),
+        }
+    }
+}
+
+impl EmailLayout {
+    /// The defaults with the mail layout settings' branding, used for system emails
+    pub fn from_settings(mail_settings: &MailSettings) -> EmailLayout {
+        match &mail_settings.layout {
+            Some(layout_settings) => {
+                EmailLayout::default().with_branding(&layout_settings.branding)
+            }
+            None => EmailLayout::default(),
+        }
+    }
+
+    pub fn with_branding(self, branding: &EmailBranding) -> EmailLayout {
+        let EmailBranding {
+            logo_url,
+            primary_colour,
+            background_colour,
+            footer_text,
+        } = branding.clone().non_empty();
+        EmailLayout {
+            logo_url: logo_url.unwrap_or(self.logo_url),
+            primary_colour: primary_colour.unwrap_or(self.primary_colour),
+            background_colour: background_colour.unwrap_or(self.background_colour),
+            footer_text: footer_text.unwrap_or(self.footer_text),
+        }
+    }
+}
+
+pub fn markdown_to_html(markdown: &str) -> String {
+    let parser = pulldown_cmark::Parser::new_ext(markdown, pulldown_cmark::Options::ENABLE_TABLES);
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    html
+}
+
+/// The url from the List-Unsubscribe header, linked to in the email's footer
+fn unsubscribe_url(options: &EmailOptions) -> Option<String> {
+    options
+        .headers
+        .iter()
+        .find(|EmailHeader { name, .. }| name.eq_ignore_ascii_case("List-Unsubscribe"))
+        .and_then(|header| {
+            header
+                .value
+                .split(',')
+                .map(|url| url.trim().trim_start_matches('<').trim_end_matches('>'))
+                .find(|url| url.starts_with("http"))
+                .map(str::to_string)
+        })
+}
+
+fn render_layout(notification_template: &str, context: &Context) -> Result<String, tera::Error> {
+    let mut tera = Tera::default();
+    tera.add_raw_templates(vec![
+        ("base.html", BASE_TEMPLATE),
+        ("notification.html", notification_template),
+    ])?;
+    tera.render("notification.html", context)
+}
+
+/// Renders the notification's markdown inside the email layout, with the css inlined.
+/// A custom layout template that can't be read or rendered is logged and the built in layout used instead.
+pub fn render_notification_email(
+    mail_settings: &MailSettings,
+    title: &str,
+    markdown: &str,
+    options: &EmailOptions,
+) -> Result<String, tera::Error> {
+    let layout = EmailLayout::from_settings(mail_settings)
+        .with_branding(&options.layout.clone().unwrap_or_default());
+    let mut context = Context::new();
+    context.insert("layout", &layout);
+    context.insert("title_text", title);
+    context.insert("body", &markdown_to_html(markdown));
+    context.insert("unsubscribe_url", &unsubscribe_url(options));
+
+    let template_file = mail_settings
+        .layout
+        .as_ref()
+        .and_then(|layout_settings| layout_settings.template_file.as_ref());
+    if let Some(template_file) = template_file {
+        let custom_html = std::fs::read_to_string(template_file)
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|template| {
+                render_layout(&template, &context).map_err(|e| format!("{:?}", e))
+            });
+        match custom_html {
+            Ok(html) => return Ok(inline_css(&html)),
+            Err(e) => log::error!(
+                "Unable to render email layout {}, using the built in layout - {}",
+                template_file,
+                e
+            ),
+        }
+    }
+
+    let html = render_layout(NOTIFICATION_TEMPLATE, &context)?;
+    Ok(inline_css(&html))
+}
+
+/// An example notification in the layout for a notification config, with any unsaved branding changes applied
+pub fn preview_email_layout(
+    ctx: &ServiceContext,
+    notification_config_id: Option<String>,
+    branding: Option<EmailBranding>,
+) -> Result<String, EmailServiceError> {
+    let config_branding = match notification_config_id {
+        Some(id) => NotificationConfigRowRepository::new(&ctx.connection)
+            .find_one_by_id(&id)?
+            .and_then(|config| email_branding_from_json(&config.email_layout)),
+        None => None,
+    };
+    // Unsaved changes are shown over the config's saved branding
+    let saved = config_branding.unwrap_or_default().non_empty();
+    let unsaved = branding.unwrap_or_default().non_empty();
+    let branding = EmailBranding {
+        logo_url: unsaved.logo_url.or(saved.logo_url),
+        primary_colour: unsaved.primary_colour.or(saved.primary_colour),
+        background_colour: unsaved.background_colour.or(saved.background_colour),
+        footer_text: unsaved.footer_text.or(saved.footer_text),
+    };
+
+    let settings = &ctx.service_provider.settings;
+    let unsubscribe_url = format!(
+        "{}{}",
+        settings.server.app_url.trim_end_matches('/'),
+        UNSUBSCRIBE_PATH
+    );
+    let options = EmailOptions {
+        layout: Some(branding),
+        headers: vec![EmailHeader::new(
+            "List-Unsubscribe",
+            &format!("<{}>", unsubscribe_url),
+        )],
+        ..Default::default()
+    };
+
+    render_notification_email(&settings.mail, PREVIEW_TITLE, PREVIEW_BODY, &options)
+        .map_err(|e| EmailServiceError::GenericError(format!("{:?}", e)))
+}
+
+/// Copies the <style> rules onto the style attribute of the elements they match, the element's own style still wins.
+/// The <style> is kept for the clients that support it, e.g. for @media rules and :hover, which can't be inlined.
+/// Html that can't be inlined is sent as it is.
+pub fn inline_css(html: &str) -> String {
+    let inliner = css_inline::CSSInliner::options()
+        .keep_style_tags(true)
+        .load_remote_stylesheets(false)
+        .build();
+    match inliner.inline(html) {
+        Ok(inlined) => inlined,
+        Err(e) => {
+            log::error!("Unable to inline the email's css - {:?}", e);
+            html.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::settings::EmailLayoutSettings;
+    use crate::test_utils::get_test_settings;
+
+    use super::*;
+
+    #[test]
+    fn test_inline_css() {
+        let html = r#"<html><head><style>
+            p { color: red; margin: 0 }
+            /* A comment */
+            .note { color: blue }
+            a:hover { color: green }
+            @media (max-width: 480px) { p { margin: 4px } }
+        </style></head><body><p class="note" style="font-size: 12px">Hello</p><p>World</p></body></html>"#;
+
+        let inlined = inline_css(html);
+        // The class rule is more specific, and the element's own style still wins
+        assert!(inlined.contains(
+            r#"<p class="note" style="margin: 0;color: blue;font-size: 12px">Hello</p>"#
+        ));
+        assert!(inlined.contains(r#"<p style="color: red;margin: 0;">World</p>"#));
+        // Media queries are left in the stylesheet
+        assert!(inlined.contains("@media (max-width: 480px)"));
+    }
+
+    #[test]
+    fn test_render_notification_email() {
+        let mut settings = get_test_settings("").mail;
+        settings.layout = Some(EmailLayoutSettings {
+            template_file: None,
+            branding: EmailBranding {
+                logo_url: Some("https://example.com/logo.png".to_string()),
+                primary_colour: Some("#123456".to_string()),
+                ..Default::default()
+            },
+        });
+        // The notification config's branding is used over the settings
+        let options = EmailOptions {
+            layout: Some(EmailBranding {
+                primary_colour: Some("#abcdef".to_string()),
+                footer_text: Some(" ".to_string()),
+                ..Default::default()
+            }),
+            headers: vec![EmailHeader::new(
+                "List-Unsubscribe",
+                "<https://example.com/unsubscribe?token=abc>",
+            )],
+            ..Default::default()
+        };
+
+        let html =
+            render_notification_email(&settings, "Sensor alert", "It's **hot**", &options).unwrap();
+        assert!(html.contains("https://example.com/logo.png"));
+        assert!(html.contains("#abcdef"));
+        assert!(!html.contains("#123456"));
+        // An empty footer falls back to the default
+        assert!(html.contains(DEFAULT_FOOTER_TEXT));
+        assert!(html.contains("Sensor alert"));
+        assert!(html.contains("It's <strong style=\"line-height: inherit;\">hot</strong>"));
+        assert!(html.contains("https://example.com/unsubscribe?token=abc"));
+        // There's no button without a url
+        assert!(!html.contains("class=\"v-button"));
+    }
+
+    #[test]
+    fn test_custom_layout_template() {
+        let template_file = std::env::temp_dir().join("test_custom_layout_template.html");
+        std::fs::write(
+            &template_file,
+            "<html><body><h1>{{layout.footer_text}}</h1>{{body | safe}}</body></html>",
+        )
+        .unwrap();
+        let mut settings = get_test_settings("").mail;
+        settings.layout = Some(EmailLayoutSettings {
+            template_file: Some(template_file.to_string_lossy().to_string()),
+            ..Default::default()
+        });
+
+        let html = render_notification_email(&settings, "Title", "Body", &EmailOptions::default())
+            .unwrap();
+        assert!(html.contains("<h1>mSupply - Notify</h1><p>Body</p>"));
+
+        // The built in layout is used if the template can't be read
+        settings.layout = Some(EmailLayoutSettings {
+            template_file: Some("missing_layout.html".to_string()),
+            ..Default::default()
+        });
+        let html = render_notification_email(&settings, "Title", "Body", &EmailOptions::default())
+            .unwrap();
+        assert!(html.contains("notification-body"));
+    }
+
+    #[test]
+    fn test_email_branding_validate() {
+        let branding = |colour: &str| EmailBranding {
+            primary_colour: Some(colour.to_string()),
+            ..Default::default()
+        };
+        assert!(branding("#2dc26b").validate().is_ok());
+        assert!(branding("#FFF").validate().is_ok());
+        assert!(branding("red").validate().is_err());
+        assert!(branding("#2dc26b; display: none").validate().is_err());
+    }
+}
@@ -1,46 +1,68 @@
-use chrono::Utc;
 use lettre::address::AddressError;
-use lettre::message::Mailbox;
+use lettre::message::{dkim::DkimConfig, Mailbox};
 use lettre::{
     transport::smtp::{
         authentication::Credentials,
         client::{Tls, TlsParameters},
+        PoolConfig,
     },
     SmtpTransport,
 };
+use std::collections::HashMap;
 use std::time::Duration;
 
-use repository::{EmailQueueRowRepository, EmailQueueStatus, RepositoryError};
+use repository::RepositoryError;
 
-use crate::email::send::send_email;
-use crate::service_provider::ServiceContext;
-use crate::settings::Settings;
+use crate::email::{
+    dkim::dkim_config,
+    send::{build_message, send_email},
+};
+use crate::notification::attachment::NotificationAttachment;
+use crate::settings::{Settings, SmtpRelaySettings};
 
+use self::options::EmailOptions;
 use self::send::EmailSendError;
 
+pub mod bounce;
+pub mod dkim;
 pub mod enqueue;
+pub mod layout;
+pub mod options;
+pub mod pop3;
 pub mod send;
+#[cfg(test)]
+pub(crate) mod stub;
+pub mod unsubscribe;
 
-pub static MAX_RETRIES: i32 = 3;
 pub static TIMEOUT_MS: u64 = 30_000; // 30 seconds
 
 // We use a trait for EmailService to allow mocking in tests
 pub trait EmailServiceTrait: Send + Sync {
     fn test_connection(&self) -> Result<bool, EmailServiceError>;
 
-    fn send_queued_emails(&self, ctx: &ServiceContext) -> Result<usize, EmailServiceError>;
+    /// Returns the sent email's Message-ID, which bounces are matched to the notification by
     fn send_email(
         &self,
         to: String,
         subject: String,
         html_body: String,
         text_body: String,
-    ) -> Result<(), EmailSendError>;
+        attachments: Vec<NotificationAttachment>,
+        options: EmailOptions,
+    ) -> Result<String, EmailSendError>;
 }
 
-pub struct EmailService {
+pub struct SmtpRelay {
+    pub host: String,
     pub mailer: SmtpTransport,
+}
+
+pub struct EmailService {
+    /// The configured relay first, then the fallback relays in the order they're tried
+    pub relays: Vec<SmtpRelay>,
     pub from: Mailbox,
+    pub sender_profiles: HashMap<String, Mailbox>,
+    pub dkim: Option<DkimConfig>,
     pub url: String,
 }
 
@@ -61,153 +83,254 @@ impl From<RepositoryError> for EmailServiceError {
 
 impl EmailService {
     pub fn new(settings: Settings) -> Self {
-        let mut transport_builder =
-            SmtpTransport::builder_dangerous(settings.mail.host.clone()).port(settings.mail.port);
-
-        if settings.mail.starttls {
-            let tls_parameters = TlsParameters::new(settings.mail.host);
-            match tls_parameters {
-                Ok(tls_parameters) => {
-                    transport_builder = transport_builder.tls(Tls::Required(tls_parameters));
-                }
-                Err(error) => {
-                    panic!("EmailService error creating tls parameters {}", error);
-                }
-            }
-        }
+        let mut relay_settings = vec![settings.mail.primary_relay()];
+        relay_settings.extend(settings.mail.fallback_relays.clone());
+        let relays = relay_settings
+            .iter()
+            .map(|relay| SmtpRelay {
+                host: relay.host.clone(),
+                mailer: smtp_transport(relay, settings.mail.max_connections),
+            })
+            .collect();
 
-        if !settings.mail.username.is_empty() && !settings.mail.password.is_empty() {
-            let credentials = Credentials::new(
-                settings.mail.username.clone(),
-                settings.mail.password.clone(),
-            );
-            transport_builder = transport_builder.credentials(credentials);
+        let sender_profiles = settings
+            .mail
+            .sender_profiles
+            .iter()
+            .map(|(name, from)| {
+                let mailbox = from.parse().unwrap_or_else(|_| {
+                    panic!("The mail:sender_profiles {} address is not valid", name)
+                });
+                (name.clone(), mailbox)
+            })
+            .collect();
+
+        // The layout's colours are put into the email's css, the same as a notification config's
+        if let Some(layout) = &settings.mail.layout {
+            if let Err(e) = layout.branding.validate() {
+                panic!("The mail:layout settings are not valid - {}", e);
+            }
         }
 
-        let mailer = transport_builder
-            .timeout(Some(Duration::from_millis(TIMEOUT_MS)))
-            .build();
+        let dkim = settings.mail.dkim.as_ref().map(|dkim| {
+            dkim_config(dkim)
+                .unwrap_or_else(|e| panic!("The mail:dkim settings are not valid - {}", e))
+        });
 
         EmailService {
-            mailer,
+            relays,
             from: settings
                 .mail
                 .from
                 .parse()
                 .expect("The configured mail:from address is not valid"), // This could panic on startup, but only if an invalid from address is configured
+            sender_profiles,
+            dkim,
             url: settings.server.app_url,
         }
     }
+
+    fn sender(&self, options: &EmailOptions) -> Mailbox {
+        let Some(sender_profile) = &options.sender_profile else {
+            return self.from.clone();
+        };
+        match self.sender_profiles.get(sender_profile) {
+            Some(mailbox) => mailbox.clone(),
+            None => {
+                // The profile has been removed from the settings since the email was queued,
+                // sending from the default address is better than not sending an alert at all
+                log::warn!(
+                    "Unknown sender profile {}, sending from {}",
+                    sender_profile,
+                    self.from
+                );
+                self.from.clone()
+            }
+        }
+    }
 }
 
-impl EmailServiceTrait for EmailService {
-    fn test_connection(&self) -> Result<bool, EmailServiceError> {
-        self.mailer
-            .test_connection()
-            .map_err(|e| EmailServiceError::SmtpError(e))
+// Connections to the relay are pooled and reused between emails, rather than connecting for every email
+fn smtp_transport(relay: &SmtpRelaySettings, max_connections: Option<u32>) -> SmtpTransport {
+    let mut transport_builder =
+        SmtpTransport::builder_dangerous(relay.host.clone()).port(relay.port);
+
+    if relay.starttls {
+        let tls_parameters = TlsParameters::new(relay.host.clone());
+        match tls_parameters {
+            Ok(tls_parameters) => {
+                transport_builder = transport_builder.tls(Tls::Required(tls_parameters));
+            }
+            Err(error) => {
+                panic!("EmailService error creating tls parameters {}", error);
+            }
+        }
+    }
+
+    if !relay.username.is_empty() && !relay.password.is_empty() {
+        let credentials = Credentials::new(relay.username.clone(), relay.password.clone());
+        transport_builder = transport_builder.credentials(credentials);
+    }
+
+    let mut pool_config = PoolConfig::new();
+    if let Some(max_connections) = max_connections {
+        pool_config = pool_config.max_size(max_connections);
     }
 
-    fn send_queued_emails(&self, ctx: &ServiceContext) -> Result<usize, EmailServiceError> {
-        log::debug!("Sending queued emails");
-
-        let repo = EmailQueueRowRepository::new(&ctx.connection);
-        let queued_emails = repo.un_sent()?;
-        let mut error_count = 0;
-        let mut sent_count = 0;
-
-        for mut email in queued_emails {
-            let email_clone = email.clone();
-            let result = send_email(
-                &self.mailer,
-                self.from.clone(),
-                email_clone.to_address,
-                email_clone.subject,
-                email_clone.html_body,
-                email_clone.text_body,
-            );
-
-            match result {
-                Ok(_) => {
-                    // Successfully Sent
-                    email.error = None;
-                    email.status = EmailQueueStatus::Sent;
-                    email.sent_at = Some(Utc::now().naive_utc());
-                    email.updated_at = Utc::now().naive_utc();
-                    repo.update_one(&email)?;
-                    sent_count += 1;
+    transport_builder
+        .timeout(Some(Duration::from_millis(TIMEOUT_MS)))
+        .pool_config(pool_config)
+        .build()
+}
+
+impl EmailServiceTrait for EmailService {
+    /// Tests every relay, so a fallback relay that can't be reached is found before it's needed.
+    /// The first relay's error is returned once they've all been tried.
+    fn test_connection(&self) -> Result<bool, EmailServiceError> {
+        let mut connected = true;
+        let mut first_error = None;
+        for relay in &self.relays {
+            match relay.mailer.test_connection() {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::error!("Unable to connect to SMTP relay {}", relay.host);
+                    connected = false;
                 }
-                Err(send_error) => {
-                    // Failed to send
-                    email.updated_at = Utc::now().naive_utc();
-
-                    if email.retries >= MAX_RETRIES {
-                        log::error!(
-                            "Failed to send email {} to {} after {} retries - {:?}",
-                            email.id,
-                            email.to_address,
-                            MAX_RETRIES,
-                            send_error
-                        );
-                        email.error = Some(format!(
-                            "Failed to send email after {} retries - {:?}",
-                            MAX_RETRIES, send_error
-                        ));
-                        email.status = EmailQueueStatus::Failed;
-                    } else if send_error.is_permanent() {
-                        log::error!(
-                            "Permanently failed to send email {} to {}",
-                            email.id,
-                            email.to_address,
-                        );
-                        email.error = Some(format!("{:?}", send_error));
-                        email.status = EmailQueueStatus::Failed;
-                    } else {
-                        log::error!(
-                            "Temporarily failed to send email {} to {} - {:?}",
-                            email.id,
-                            email.to_address,
-                            send_error
-                        );
-                        email.error = Some(format!("{:?}", send_error));
-                        email.status = EmailQueueStatus::Errored;
-                        email.retries += 1;
-                    }
-
-                    error_count += 1;
-                    repo.update_one(&email)?;
-
-                    continue;
+                Err(e) => {
+                    log::error!("Error connecting to SMTP relay {} - {:?}", relay.host, e);
+                    first_error.get_or_insert(EmailServiceError::SmtpError(e));
                 }
             }
         }
-
-        if error_count > 0 {
-            return Err(EmailServiceError::GenericError(format!(
-                "Failed to send {} emails",
-                error_count
-            )));
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(connected),
         }
-
-        log::debug!("Sent {} emails", sent_count);
-
-        Ok(sent_count)
     }
 
+    /// Sends through the first relay that accepts the email. A temporary failure, such as the relay being
+    /// unreachable, moves on to the next relay, a permanent failure is returned straight away.
     fn send_email(
         &self,
         to: String,
         subject: String,
         html_body: String,
         text_body: String,
-    ) -> Result<(), EmailSendError> {
-        send_email(
-            &self.mailer,
-            self.from.clone(),
+        attachments: Vec<NotificationAttachment>,
+        options: EmailOptions,
+    ) -> Result<String, EmailSendError> {
+        let mut message = build_message(
+            self.sender(&options),
             to,
             subject,
             html_body,
             text_body,
-        )
+            attachments,
+            options,
+        )?;
+        if let Some(dkim) = &self.dkim {
+            message.sign(dkim);
+        }
+        let message_id = message
+            .headers()
+            .get_raw("Message-ID")
+            .unwrap_or_default()
+            .to_string();
+
+        let mut result = Ok(());
+        for relay in &self.relays {
+            result = send_email(&relay.mailer, &message);
+            match &result {
+                Ok(_) => return Ok(message_id),
+                Err(e) if e.is_permanent() => break,
+                Err(e) => log::warn!("Unable to send email via {} - {:?}", relay.host, e),
+            }
+        }
+        result.map(|_| message_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        email::layout::EmailBranding, settings::EmailLayoutSettings, test_utils::get_test_settings,
+    };
+
+    use super::{stub::SmtpSink, *};
+
+    fn from_line(message: &str) -> String {
+        message
+            .lines()
+            .find(|line| line.starts_with("From:"))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn test_send_email_with_fallback_relays() {
+        let primary = SmtpSink::start();
+        let fallback = SmtpSink::start();
+        let mut settings = get_test_settings("");
+        settings.mail.host = "127.0.0.1".to_string();
+        settings.mail.port = primary.port();
+        settings.mail.fallback_relays = vec![SmtpRelaySettings {
+            host: "127.0.0.1".to_string(),
+            port: fallback.port(),
+            starttls: false,
+            username: "".to_string(),
+            password: "".to_string(),
+        }];
+        settings.mail.sender_profiles = HashMap::from([(
+            "alerts".to_string(),
+            "Cold Chain Alerts <alerts@example.com>".to_string(),
+        )]);
+        let email_service = EmailService::new(settings);
+        let send = |sender_profile: &str| {
+            email_service.send_email(
+                "user@example.com".to_string(),
+                "Sensor alert".to_string(),
+                "<p>Sensor is too hot</p>".to_string(),
+                "Sensor is too hot".to_string(),
+                vec![],
+                EmailOptions {
+                    sender_profile: Some(sender_profile.to_string()),
+                    ..Default::default()
+                },
+            )
+        };
+
+        let message_id = send("alerts").unwrap();
+        let messages = primary.messages();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains(&format!("Message-ID: {}", message_id)));
+        assert!(from_line(&messages[0]).contains("alerts@example.com"));
+
+        // A temporary failure on the primary relay sends via the fallback relay, an unknown profile uses the from address
+        primary.set_mail_reply("451 Try again later");
+        send("reports").unwrap();
+        let messages = fallback.messages();
+        assert_eq!(messages.len(), 1);
+        assert!(from_line(&messages[0]).contains("no-reply@msupply.foundation"));
+
+        // A permanent failure isn't tried on the fallback relay
+        primary.set_mail_reply("550 Sender rejected");
+        let result = send("alerts");
+        assert!(result.unwrap_err().is_permanent());
+        assert_eq!(fallback.messages().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The mail:layout settings are not valid")]
+    fn test_invalid_layout_colour() {
+        let mut settings = get_test_settings("");
+        settings.mail.layout = Some(EmailLayoutSettings {
+            branding: EmailBranding {
+                primary_colour: Some("red; display: none".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        EmailService::new(settings);
     }
 }
 
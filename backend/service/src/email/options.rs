@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::notification::attachment::{from_base64, to_base64};
+
+use super::{layout::EmailBranding, EmailServiceError};
+
+/// Headers that are set from the email itself, so can't be set as custom headers
+pub const RESERVED_HEADERS: [&str; 13] = [
+    "from",
+    "to",
+    "cc",
+    "bcc",
+    "reply-to",
+    "subject",
+    "date",
+    "message-id",
+    "mime-version",
+    "content-type",
+    "content-transfer-encoding",
+    "auto-submitted",
+    "dkim-signature",
+];
+
+/// Everything about an email other than its addressee, subject, body and attachments.
+/// Stored as json with the queued email, or notification event, so retries send the same email.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmailOptions {
+    /// Images shown in the html body with <img src="cid:content_id">, or ![chart](cid:content_id) in markdown
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inline_images: Vec<InlineImage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cc: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bcc: Vec<String>,
+    /// Extra headers such as List-Unsubscribe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<EmailHeader>,
+    /// Name of the mail sender_profiles entry to send from, otherwise the mail from address is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_profile: Option<String>,
+    /// The notification config's changes to the email layout's logo, colours and footer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<EmailBranding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InlineImage {
+    pub content_id: String,
+    pub content_type: String,
+    #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: &str, value: &str) -> EmailHeader {
+        EmailHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/// Serializes the options for the `options` and `email_options` columns, the default options are stored as NULL
+pub fn email_options_to_json(options: &EmailOptions) -> Result<Option<String>, EmailServiceError> {
+    if *options == EmailOptions::default() {
+        return Ok(None);
+    }
+    serde_json::to_string(options).map(Some).map_err(|e| {
+        EmailServiceError::GenericError(format!("Failed to serialize email options: {:?}", e))
+    })
+}
+
+pub fn email_options_from_json(
+    options: &Option<String>,
+) -> Result<EmailOptions, EmailServiceError> {
+    match options {
+        None => Ok(EmailOptions::default()),
+        Some(options) => serde_json::from_str(options).map_err(|e| {
+            EmailServiceError::GenericError(format!("Failed to read email options: {:?}", e))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_email_options_json() {
+        assert_eq!(
+            email_options_to_json(&EmailOptions::default()).unwrap(),
+            None
+        );
+        assert_eq!(
+            email_options_from_json(&None).unwrap(),
+            EmailOptions::default()
+        );
+
+        let options = EmailOptions {
+            inline_images: vec![InlineImage {
+                content_id: "chart".to_string(),
+                content_type: "image/png".to_string(),
+                content: vec![0x89, 0x50, 0x4E, 0x47],
+            }],
+            reply_to: Some("support@example.com".to_string()),
+            cc: vec!["manager@example.com".to_string()],
+            bcc: vec![],
+            headers: vec![EmailHeader::new(
+                "List-Unsubscribe",
+                "<https://example.com/unsubscribe>",
+            )],
+            sender_profile: Some("reports".to_string()),
+            layout: Some(EmailBranding {
+                primary_colour: Some("#123456".to_string()),
+                ..Default::default()
+            }),
+        };
+        let json = email_options_to_json(&options).unwrap();
+        assert!(json.as_ref().unwrap().contains(r#""content":"iVBORw==""#));
+        assert!(!json.as_ref().unwrap().contains("bcc"));
+        assert_eq!(email_options_from_json(&json).unwrap(), options);
+    }
+}
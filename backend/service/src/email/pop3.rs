@@ -0,0 +1,269 @@
+/*
+   A minimal POP3 client for reading bounces from a mailbox, https://www.rfc-editor.org/rfc/rfc1939
+   Each message is retrieved in turn and deleted once it's been handled, the deletes only happen when the session ends with QUIT.
+*/
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::settings::Pop3Settings;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Pop3Error {
+    Connection(String),
+    /// The server replied -ERR, e.g. to a wrong password
+    Server(String),
+}
+
+impl From<std::io::Error> for Pop3Error {
+    fn from(error: std::io::Error) -> Self {
+        Pop3Error::Connection(error.to_string())
+    }
+}
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+struct Session {
+    reader: BufReader<Box<dyn Stream>>,
+}
+
+impl Session {
+    fn connect(settings: &Pop3Settings) -> Result<Session, Pop3Error> {
+        let address = (settings.host.as_str(), settings.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Pop3Error::Connection(format!("Unknown host {}", settings.host)))?;
+        let tcp_stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        tcp_stream.set_read_timeout(Some(TIMEOUT))?;
+        tcp_stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let stream: Box<dyn Stream> = match settings.tls {
+            true => {
+                let connector = native_tls::TlsConnector::new()
+                    .map_err(|e| Pop3Error::Connection(e.to_string()))?;
+                Box::new(
+                    connector
+                        .connect(&settings.host, tcp_stream)
+                        .map_err(|e| Pop3Error::Connection(e.to_string()))?,
+                )
+            }
+            false => Box::new(tcp_stream),
+        };
+        let mut session = Session {
+            reader: BufReader::new(stream),
+        };
+        session.read_reply()?;
+        Ok(session)
+    }
+
+    // Bounces can include the original message in any charset, so lines that aren't UTF-8 are read lossily
+    fn read_line(&mut self) -> Result<String, Pop3Error> {
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(Pop3Error::Connection(
+                "The server closed the connection".to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    }
+
+    fn read_reply(&mut self) -> Result<String, Pop3Error> {
+        let line = self.read_line()?;
+        match line.strip_prefix("+OK") {
+            Some(reply) => Ok(reply.trim().to_string()),
+            None => Err(Pop3Error::Server(line.trim().to_string())),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<String, Pop3Error> {
+        let stream = self.reader.get_mut();
+        stream.write_all(format!("{}\r\n", command).as_bytes())?;
+        stream.flush()?;
+        self.read_reply()
+    }
+
+    /// The lines of a multi-line reply up to the terminating ".", with any leading dot stuffing removed
+    fn read_multi_line(&mut self) -> Result<String, Pop3Error> {
+        let mut message = String::new();
+        loop {
+            let line = self.read_line()?;
+            if line == ".\r\n" || line == ".\n" {
+                return Ok(message);
+            }
+            message.push_str(line.strip_prefix('.').unwrap_or(&line));
+        }
+    }
+}
+
+/// Reads every message in the mailbox, deleting the ones handle returns true for. Returns the number of messages read.
+pub fn read_mailbox(
+    settings: &Pop3Settings,
+    mut handle: impl FnMut(&str) -> bool,
+) -> Result<usize, Pop3Error> {
+    let mut session = Session::connect(settings)?;
+    session.command(&format!("USER {}", settings.username))?;
+    session.command(&format!("PASS {}", settings.password))?;
+
+    // STAT replies with the message count and the mailbox size
+    let stat = session.command("STAT")?;
+    let message_count: usize = stat
+        .split_whitespace()
+        .next()
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| Pop3Error::Server(format!("Unexpected STAT reply {}", stat)))?;
+
+    for message_number in 1..=message_count {
+        session.command(&format!("RETR {}", message_number))?;
+        let message = session.read_multi_line()?;
+        if handle(&message) {
+            session.command(&format!("DELE {}", message_number))?;
+        }
+    }
+
+    session.command("QUIT")?;
+    Ok(message_count)
+}
+
+#[cfg(test)]
+pub(crate) mod stub {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    /// A local POP3 server with a single mailbox, any username and password are accepted
+    pub struct Pop3Stub {
+        port: u16,
+        messages: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Pop3Stub {
+        pub fn start(messages: Vec<String>) -> Pop3Stub {
+            let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to start POP3 stub");
+            let port = listener.local_addr().unwrap().port();
+            let messages = Arc::new(Mutex::new(messages));
+
+            let thread_messages = messages.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else {
+                        return;
+                    };
+                    let Ok(mut writer) = stream.try_clone() else {
+                        continue;
+                    };
+                    let mut reader = BufReader::new(stream);
+                    let mut deleted: Vec<usize> = Vec::new();
+                    let _ = writer.write_all(b"+OK POP3 stub ready\r\n");
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            break;
+                        }
+                        let mut parts = line.split_whitespace();
+                        let command = parts.next().unwrap_or_default().to_uppercase();
+                        let number = parts.next().and_then(|n| n.parse::<usize>().ok());
+                        let mut messages = thread_messages.lock().unwrap();
+                        let reply = match (command.as_str(), number) {
+                            ("STAT", _) => format!("+OK {} 0\r\n", messages.len()),
+                            ("RETR", Some(n)) if n >= 1 && n <= messages.len() => {
+                                let mut reply = "+OK\r\n".to_string();
+                                for message_line in messages[n - 1].split_inclusive("\r\n") {
+                                    if message_line.starts_with('.') {
+                                        reply.push('.');
+                                    }
+                                    reply.push_str(message_line);
+                                }
+                                reply.push_str(".\r\n");
+                                reply
+                            }
+                            ("DELE", Some(n)) => {
+                                deleted.push(n);
+                                "+OK\r\n".to_string()
+                            }
+                            ("QUIT", _) => {
+                                // Deletes happen when the session ends
+                                deleted.sort();
+                                for n in deleted.iter().rev() {
+                                    messages.remove(n - 1);
+                                }
+                                let _ = writer.write_all(b"+OK bye\r\n");
+                                break;
+                            }
+                            ("RETR", _) => "-ERR no such message\r\n".to_string(),
+                            _ => "+OK\r\n".to_string(),
+                        };
+                        if writer.write_all(reply.as_bytes()).is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+
+            Pop3Stub { port, messages }
+        }
+
+        pub fn port(&self) -> u16 {
+            self.port
+        }
+
+        /// The messages that haven't been deleted
+        pub fn messages(&self) -> Vec<String> {
+            self.messages.lock().unwrap().clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{stub::Pop3Stub, *};
+
+    #[test]
+    fn test_read_mailbox() {
+        let stub = Pop3Stub::start(vec![
+            "Subject: Bounce\r\n\r\nUser unknown\r\n".to_string(),
+            "Subject: Out of office\r\n\r\n.Back on Monday\r\n".to_string(),
+        ]);
+        let settings = Pop3Settings {
+            host: "127.0.0.1".to_string(),
+            port: stub.port(),
+            tls: false,
+            username: "bounces".to_string(),
+            password: "password".to_string(),
+        };
+
+        let mut read = Vec::new();
+        let count = read_mailbox(&settings, |message| {
+            read.push(message.to_string());
+            message.contains("User unknown")
+        })
+        .unwrap();
+        assert_eq!(count, 2);
+        // Dot stuffing is removed
+        assert_eq!(read[1], "Subject: Out of office\r\n\r\n.Back on Monday\r\n");
+        // Only the handled message is deleted
+        assert_eq!(
+            stub.messages(),
+            vec!["Subject: Out of office\r\n\r\n.Back on Monday\r\n".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_read_line_that_isnt_utf8() {
+        // A Latin-1 é in the original message doesn't stop the rest of the mailbox being read
+        let stream: Box<dyn Stream> =
+            Box::new(std::io::Cursor::new(b"Subject: Caf\xe9\r\n".to_vec()));
+        let mut session = Session {
+            reader: BufReader::new(stream),
+        };
+        assert_eq!(session.read_line().unwrap(), "Subject: Caf\u{fffd}\r\n");
+    }
+}
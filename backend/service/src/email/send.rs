@@ -1,8 +1,16 @@
 use lettre::{
     address::AddressError,
-    message::{Mailbox, MultiPart},
+    message::{
+        header::{ContentType, HeaderName, HeaderValue},
+        Attachment, Mailbox, MultiPart,
+    },
     Message, SmtpTransport, Transport,
 };
+use util::uuid::uuid;
+
+use crate::notification::attachment::NotificationAttachment;
+
+use super::options::{EmailOptions, RESERVED_HEADERS};
 
 // This enum defines the errors that can occur when sending an email.
 // It provides a is_permanent method to check if the error is permanent or temporary.
@@ -11,6 +19,8 @@ pub enum EmailSendError {
     AddressError(String),
     MessageBuildError(lettre::error::Error),
     SmtpError(lettre::transport::smtp::Error),
+    AttachmentError(String),
+    OptionsError(String),
 }
 
 impl EmailSendError {
@@ -19,38 +29,211 @@ impl EmailSendError {
             EmailSendError::AddressError(_) => true,
             EmailSendError::MessageBuildError(_) => true,
             EmailSendError::SmtpError(e) => e.is_permanent(),
+            EmailSendError::AttachmentError(_) => true,
+            EmailSendError::OptionsError(_) => true,
         }
     }
 }
 
 /**
-    send_email takes a mailer (provided as a SmtpTransport), a from address (provided as a Mailbox),
-    with a subject (provided as a string) and a body (provided as a string).
+    send_email sends a message built by build_message using the mailer (provided as a SmtpTransport).
     It returns an error format with either a permanent error (which should be logged and not retried)
     or a temporary error (which should be logged and retried).
 */
-pub fn send_email(
-    mailer: &SmtpTransport,
+pub fn send_email(mailer: &SmtpTransport, message: &Message) -> Result<(), EmailSendError> {
+    mailer.send(message).map_err(EmailSendError::SmtpError)?;
+
+    Ok(())
+}
+
+/**
+    build_message creates the email from a from address (provided as a Mailbox), to address, subject
+    and html and text bodies, plus any files to attach and the email's other options such as cc addresses
+    and inline images. Invalid addresses, content types or headers are permanent errors.
+    Every email gets a Message-ID in the from address's domain and is marked Auto-Submitted, which
+    spam filters look for and which stops out of office replies.
+*/
+pub fn build_message(
     from: Mailbox,
     to: String,
     subject: String,
     html_body: String,
     text_body: String,
-) -> Result<(), EmailSendError> {
-    let to: Mailbox = to
-        .parse()
-        .map_err(|e: AddressError| EmailSendError::AddressError(e.to_string()))?;
-
-    let message = Message::builder()
-        .to(to)
+    attachments: Vec<NotificationAttachment>,
+    options: EmailOptions,
+) -> Result<Message, EmailSendError> {
+    let message_id = format!("<{}@{}>", uuid(), from.email.domain());
+    let mut builder = Message::builder()
+        .to(parse_mailbox(&to)?)
         .from(from)
         .subject(subject)
-        .multipart(MultiPart::alternative_plain_html(text_body, html_body))
+        .message_id(Some(message_id));
+    if let Some(reply_to) = &options.reply_to {
+        builder = builder.reply_to(parse_mailbox(reply_to)?);
+    }
+    for cc in &options.cc {
+        builder = builder.cc(parse_mailbox(cc)?);
+    }
+    for bcc in &options.bcc {
+        builder = builder.bcc(parse_mailbox(bcc)?);
+    }
+
+    let mut body = MultiPart::alternative_plain_html(text_body, html_body);
+    if !options.inline_images.is_empty() {
+        // Inline images go with the html in a related multipart, so they can be referenced by their content id
+        body = MultiPart::related().multipart(body);
+        for image in options.inline_images {
+            let content_type = parse_content_type(&image.content_id, &image.content_type)?;
+            body = body.singlepart(
+                Attachment::new_inline(image.content_id).body(image.content, content_type),
+            );
+        }
+    }
+    if !attachments.is_empty() {
+        // The message and its attachments go in a mixed multipart, with the message first
+        body = MultiPart::mixed().multipart(body);
+        for attachment in attachments {
+            let content_type = parse_content_type(&attachment.filename, &attachment.content_type)?;
+            body = body.singlepart(
+                Attachment::new(attachment.filename).body(attachment.content, content_type),
+            );
+        }
+    }
+
+    let mut message = builder
+        .multipart(body)
         .map_err(|e| EmailSendError::MessageBuildError(e))?;
 
-    mailer
-        .send(&message)
-        .map_err(|e| EmailSendError::SmtpError(e))?;
+    for header in options.headers {
+        if RESERVED_HEADERS.contains(&header.name.to_ascii_lowercase().as_str()) {
+            return Err(EmailSendError::OptionsError(format!(
+                "The {} header can't be set",
+                header.name
+            )));
+        }
+        let name = HeaderName::new_from_ascii(header.name.clone())
+            .map_err(|e| EmailSendError::OptionsError(format!("{}: {}", header.name, e)))?;
+        message
+            .headers_mut()
+            .insert_raw(HeaderValue::new(name, header.value));
+    }
+    // https://www.rfc-editor.org/rfc/rfc3834#section-5
+    message.headers_mut().insert_raw(HeaderValue::new(
+        HeaderName::new_from_ascii_str("Auto-Submitted"),
+        "auto-generated".to_string(),
+    ));
+
+    Ok(message)
+}
 
-    Ok(())
+fn parse_mailbox(address: &str) -> Result<Mailbox, EmailSendError> {
+    address
+        .parse()
+        .map_err(|e: AddressError| EmailSendError::AddressError(format!("{}: {}", address, e)))
+}
+
+fn parse_content_type(name: &str, content_type: &str) -> Result<ContentType, EmailSendError> {
+    ContentType::parse(content_type)
+        .map_err(|e| EmailSendError::AttachmentError(format!("{}: {}", name, e)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::email::options::{EmailHeader, InlineImage};
+
+    use super::*;
+
+    fn message(attachments: Vec<NotificationAttachment>, options: EmailOptions) -> String {
+        let message = build_message(
+            "Notify <notify@example.com>".parse().unwrap(),
+            "user@example.com".to_string(),
+            "Sensor alert".to_string(),
+            "<p>Sensor is too hot</p><img src=\"cid:chart\">".to_string(),
+            "Sensor is too hot".to_string(),
+            attachments,
+            options,
+        )
+        .unwrap();
+        String::from_utf8(message.formatted()).unwrap()
+    }
+
+    #[test]
+    fn test_build_message() {
+        let email = message(vec![], EmailOptions::default());
+        assert!(email.contains("Content-Type: multipart/alternative"));
+        assert!(!email.contains("multipart/related"));
+        assert!(email.contains("Auto-Submitted: auto-generated"));
+        assert!(email.contains("Date: "));
+        let message_id = email
+            .lines()
+            .find(|line| line.starts_with("Message-ID: "))
+            .unwrap();
+        assert!(message_id.ends_with("@example.com>"));
+
+        let email = message(
+            vec![NotificationAttachment {
+                filename: "report.csv".to_string(),
+                content_type: "text/csv".to_string(),
+                content: b"sensor,temperature".to_vec(),
+            }],
+            EmailOptions {
+                inline_images: vec![InlineImage {
+                    content_id: "chart".to_string(),
+                    content_type: "image/png".to_string(),
+                    content: vec![0x89, 0x50, 0x4E, 0x47],
+                }],
+                reply_to: Some("support@example.com".to_string()),
+                cc: vec!["manager@example.com".to_string()],
+                bcc: vec!["audit@example.com".to_string()],
+                headers: vec![EmailHeader::new(
+                    "List-Unsubscribe",
+                    "<https://example.com/unsubscribe>",
+                )],
+                ..Default::default()
+            },
+        );
+        assert!(email.contains("Reply-To: support@example.com"));
+        assert!(email.contains("Cc: manager@example.com"));
+        // Bcc addresses are only in the envelope
+        assert!(!email.contains("audit@example.com"));
+        assert!(email.contains("List-Unsubscribe: <https://example.com/unsubscribe>"));
+        assert!(email.contains("Content-Type: multipart/mixed"));
+        assert!(email.contains("Content-Type: multipart/related"));
+        assert!(email.contains("Content-ID: <chart>"));
+        assert!(email.contains("Content-Disposition: inline"));
+        assert!(email.contains("Content-Disposition: attachment; filename=\"report.csv\""));
+    }
+
+    #[test]
+    fn test_build_message_errors() {
+        let build = |options: EmailOptions| {
+            build_message(
+                "notify@example.com".parse().unwrap(),
+                "user@example.com".to_string(),
+                "Sensor alert".to_string(),
+                String::new(),
+                String::new(),
+                vec![],
+                options,
+            )
+        };
+
+        let result = build(EmailOptions {
+            cc: vec!["not an address".to_string()],
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(EmailSendError::AddressError(_))));
+
+        let result = build(EmailOptions {
+            headers: vec![EmailHeader::new("Subject", "Something else")],
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(EmailSendError::OptionsError(_))));
+
+        let result = build(EmailOptions {
+            headers: vec![EmailHeader::new("Bad Header", "value")],
+            ..Default::default()
+        });
+        assert!(result.unwrap_err().is_permanent());
+    }
 }
@@ -0,0 +1,134 @@
+/*
+   A local SMTP sink standing in for a mail relay in tests.
+   It accepts any sender and recipient, records each message and replies to MAIL FROM with the reply set by set_mail_reply, "250 OK" by default.
+*/
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+struct SinkState {
+    messages: Vec<String>,
+    mail_reply: String,
+}
+
+pub struct SmtpSink {
+    port: u16,
+    state: Arc<Mutex<SinkState>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl SmtpSink {
+    pub fn start() -> SmtpSink {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to start SMTP sink");
+        listener
+            .set_nonblocking(true)
+            .expect("Unable to start SMTP sink");
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(SinkState {
+            messages: Vec::new(),
+            mail_reply: "250 OK".to_string(),
+        }));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_state = state.clone();
+        let thread_stopped = stopped.clone();
+        thread::spawn(move || {
+            while !thread_stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let connection_state = thread_state.clone();
+                        thread::spawn(move || handle_connection(stream, &connection_state));
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(5)),
+                }
+            }
+        });
+
+        SmtpSink {
+            port,
+            state,
+            stopped,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// e.g. "451 Try again later" for a temporary failure or "550 Rejected" for a permanent one
+    pub fn set_mail_reply(&self, reply: &str) {
+        self.state.lock().unwrap().mail_reply = reply.to_string();
+    }
+
+    /// The headers and body of each message received
+    pub fn messages(&self) -> Vec<String> {
+        self.state.lock().unwrap().messages.clone()
+    }
+}
+
+impl Drop for SmtpSink {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &Mutex<SinkState>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    let mut reply = |line: &str| writer.write_all(format!("{}\r\n", line).as_bytes());
+
+    if reply("220 localhost SMTP sink").is_err() {
+        return;
+    }
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let command = line.trim_end().to_uppercase();
+        let result = if command.starts_with("EHLO") || command.starts_with("HELO") {
+            reply("250 localhost")
+        } else if command.starts_with("MAIL FROM") {
+            let mail_reply = state.lock().unwrap().mail_reply.clone();
+            reply(&mail_reply)
+        } else if command.starts_with("DATA") {
+            if reply("354 End data with <CR><LF>.<CR><LF>").is_err() {
+                return;
+            }
+            let mut message = String::new();
+            loop {
+                let mut data_line = String::new();
+                if reader.read_line(&mut data_line).unwrap_or(0) == 0 {
+                    return;
+                }
+                if data_line == ".\r\n" {
+                    break;
+                }
+                message.push_str(&data_line);
+            }
+            state.lock().unwrap().messages.push(message);
+            reply("250 OK")
+        } else if command.starts_with("QUIT") {
+            let _ = reply("221 Bye");
+            return;
+        } else {
+            // RCPT TO, RSET and NOOP
+            reply("250 OK")
+        };
+        if result.is_err() {
+            return;
+        }
+    }
+}
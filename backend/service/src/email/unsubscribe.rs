@@ -0,0 +1,607 @@
+/*
+   List-Unsubscribe support, https://www.rfc-editor.org/rfc/rfc8058
+   Notification emails link to {app_url}/unsubscribe with a token signed with the server's token secret,
+   unsubscribing deactivates the email recipient so no more notifications are sent to the address.
+   The same link opens a page where the recipient can opt out of some notifications, get them in a digest,
+   or have them sent to another of their recipients.
+*/
+
+use std::collections::HashSet;
+
+use chrono::Utc;
+use repository::{
+    DigestFrequency, EqualFilter, KeyValueStoreRepository, KeyValueType, NotificationConfigFilter,
+    NotificationConfigRepository, NotificationConfigStatus, NotificationType,
+    RecipientListMemberFilter, RecipientListMemberRepository, RecipientRow, RecipientRowRepository,
+    RepositoryError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    notification_config::query::NotificationConfig,
+    recipient::{
+        deactivate::deactivate_recipient,
+        preferences::{get_recipient_preferences, set_recipient_preferences, RecipientPreferences},
+        ModifyRecipientError,
+    },
+    service_provider::ServiceContext,
+};
+
+use super::options::{EmailHeader, EmailOptions};
+
+pub const UNSUBSCRIBE_PATH: &str = "/unsubscribe";
+const AUDIENCE: &str = "unsubscribe";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UnsubscribeClaim {
+    /// The email address to unsubscribe
+    sub: String,
+    aud: String,
+    /// Issued at (as UTC timestamp)
+    iat: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UnsubscribeError {
+    InvalidToken,
+    RecipientNotFound,
+    NoTokenSecret,
+    InternalError(String),
+}
+
+impl From<RepositoryError> for UnsubscribeError {
+    fn from(error: RepositoryError) -> Self {
+        UnsubscribeError::InternalError(format!("{:?}", error))
+    }
+}
+
+impl From<ModifyRecipientError> for UnsubscribeError {
+    fn from(error: ModifyRecipientError) -> Self {
+        UnsubscribeError::InternalError(format!("{:?}", error))
+    }
+}
+
+fn token_secret(ctx: &ServiceContext) -> Result<String, UnsubscribeError> {
+    KeyValueStoreRepository::new(&ctx.connection)
+        .get_string(KeyValueType::SettingsTokenSecret)?
+        .ok_or(UnsubscribeError::NoTokenSecret)
+}
+
+/// The token doesn't expire, links in old emails keep working
+pub fn create_unsubscribe_token(
+    ctx: &ServiceContext,
+    to_address: &str,
+) -> Result<String, UnsubscribeError> {
+    let claim = UnsubscribeClaim {
+        sub: to_address.to_string(),
+        aud: AUDIENCE.to_string(),
+        iat: Utc::now().timestamp() as usize,
+    };
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claim,
+        &jsonwebtoken::EncodingKey::from_secret(token_secret(ctx)?.as_bytes()),
+    )
+    .map_err(|e| UnsubscribeError::InternalError(e.to_string()))
+}
+
+/// Returns the email address the token was created for
+pub fn validate_unsubscribe_token(
+    ctx: &ServiceContext,
+    token: &str,
+) -> Result<String, UnsubscribeError> {
+    let mut validation = jsonwebtoken::Validation::default();
+    validation.validate_exp = false;
+    validation.required_spec_claims = HashSet::new();
+    validation.set_audience(&[AUDIENCE]);
+    let decoded = jsonwebtoken::decode::<UnsubscribeClaim>(
+        token,
+        &jsonwebtoken::DecodingKey::from_secret(token_secret(ctx)?.as_bytes()),
+        &validation,
+    )
+    .map_err(|_| UnsubscribeError::InvalidToken)?;
+    Ok(decoded.claims.sub)
+}
+
+/// Adds the List-Unsubscribe headers, mail clients show an unsubscribe button for them.
+/// The email is still sent without them if the token can't be created.
+pub fn add_unsubscribe_headers(ctx: &ServiceContext, to_address: &str, options: &mut EmailOptions) {
+    let has_header = |options: &EmailOptions, name: &str| {
+        options
+            .headers
+            .iter()
+            .any(|header| header.name.eq_ignore_ascii_case(name))
+    };
+    if has_header(options, "List-Unsubscribe") {
+        return;
+    }
+    let token = match create_unsubscribe_token(ctx, to_address) {
+        Ok(token) => token,
+        Err(e) => {
+            log::error!(
+                "Unable to create an unsubscribe token for {} - {:?}",
+                to_address,
+                e
+            );
+            return;
+        }
+    };
+    let url = format!(
+        "{}{}?token={}",
+        ctx.service_provider
+            .settings
+            .server
+            .app_url
+            .trim_end_matches('/'),
+        UNSUBSCRIBE_PATH,
+        token
+    );
+    options
+        .headers
+        .push(EmailHeader::new("List-Unsubscribe", &format!("<{}>", url)));
+    if !has_header(options, "List-Unsubscribe-Post") {
+        options.headers.push(EmailHeader::new(
+            "List-Unsubscribe-Post",
+            "List-Unsubscribe=One-Click",
+        ));
+    }
+}
+
+fn recipient_for_token(
+    ctx: &ServiceContext,
+    token: &str,
+) -> Result<RecipientRow, UnsubscribeError> {
+    let to_address = validate_unsubscribe_token(ctx, token)?;
+    RecipientRowRepository::new(&ctx.connection)
+        .find_one_by_to_address_and_type(&to_address, NotificationType::Email)?
+        .ok_or(UnsubscribeError::RecipientNotFound)
+}
+
+/// Deactivates the email recipient the token was created for, returning the email address
+pub fn unsubscribe(ctx: &ServiceContext, token: &str) -> Result<String, UnsubscribeError> {
+    let recipient = recipient_for_token(ctx, token)?;
+    let to_address = recipient.to_address.clone();
+
+    // Already unsubscribed, e.g. the link was opened twice
+    if recipient.deactivated_datetime.is_some() {
+        return Ok(to_address);
+    }
+    deactivate_recipient(ctx, &recipient.id, Some(Utc::now().naive_utc()))?;
+    log::info!("{} unsubscribed from notification emails", to_address);
+    Ok(to_address)
+}
+
+/// A notification config that's sent to the recipient, or that they've opted out of
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecipientNotificationConfig {
+    pub id: String,
+    pub title: String,
+    pub opted_out: bool,
+}
+
+/// Another of the person's recipients that the recipient's notifications can be sent to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PreferredRecipientOption {
+    pub id: String,
+    pub notification_type: NotificationType,
+    pub to_address: String,
+}
+
+/// What the recipient can change on their manage preferences page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManagedPreferences {
+    pub to_address: String,
+    /// Unsubscribed from everything
+    pub unsubscribed: bool,
+    pub digest_frequency: DigestFrequency,
+    pub notification_configs: Vec<RecipientNotificationConfig>,
+    pub preferred_recipient_id: Option<String>,
+    /// Empty for a contact's channels, they're sent to in the contact's channel order instead
+    pub preferred_recipient_options: Vec<PreferredRecipientOption>,
+}
+
+/// Enabled configs sending to the recipient directly or through a recipient list, and any they've opted out of.
+/// Sql recipient lists are only known when the config runs, so those configs aren't included.
+fn notification_configs_for_recipient(
+    ctx: &ServiceContext,
+    recipient_id: &str,
+    opted_out_ids: &[String],
+) -> Result<Vec<RecipientNotificationConfig>, RepositoryError> {
+    let recipient_list_ids: Vec<String> = RecipientListMemberRepository::new(&ctx.connection)
+        .query_by_filter(
+            RecipientListMemberFilter::new().recipient_id(EqualFilter::equal_to(recipient_id)),
+        )?
+        .into_iter()
+        .map(|member| member.recipient_list_id)
+        .collect();
+    let configs = NotificationConfigRepository::new(&ctx.connection).query_by_filter(
+        NotificationConfigFilter::new().status(NotificationConfigStatus::equal_to(
+            NotificationConfigStatus::Enabled,
+        )),
+    )?;
+
+    Ok(configs
+        .into_iter()
+        .map(NotificationConfig::from)
+        .filter(|config| {
+            opted_out_ids.contains(&config.id)
+                || config.recipient_ids.iter().any(|id| id == recipient_id)
+                || config
+                    .recipient_list_ids
+                    .iter()
+                    .any(|id| recipient_list_ids.contains(id))
+        })
+        .map(|config| RecipientNotificationConfig {
+            opted_out: opted_out_ids.contains(&config.id),
+            id: config.id,
+            title: config.title,
+        })
+        .collect())
+}
+
+/// The person's other recipients, found by the recipient's name, and the current preferred recipient.
+/// Recipients that send their notifications on can't be chosen, so notifications only go one hop.
+fn preferred_recipient_options(
+    ctx: &ServiceContext,
+    recipient: &RecipientRow,
+) -> Result<Vec<PreferredRecipientOption>, RepositoryError> {
+    if recipient.contact_id.is_some() {
+        return Ok(vec![]);
+    }
+    let repo = RecipientRowRepository::new(&ctx.connection);
+    let mut options = repo.find_all_by_name(&recipient.name)?;
+    if let Some(preferred_recipient_id) = &recipient.preferred_recipient_id {
+        if !options
+            .iter()
+            .any(|other| other.id == *preferred_recipient_id)
+        {
+            options.extend(repo.find_one_by_id(preferred_recipient_id)?);
+        }
+    }
+
+    Ok(options
+        .into_iter()
+        .filter(|other| {
+            other.id != recipient.id
+                && other.deleted_datetime.is_none()
+                && other.preferred_recipient_id.is_none()
+        })
+        .map(|other| PreferredRecipientOption {
+            id: other.id,
+            notification_type: other.notification_type,
+            to_address: other.to_address,
+        })
+        .collect())
+}
+
+pub fn get_managed_preferences(
+    ctx: &ServiceContext,
+    token: &str,
+) -> Result<ManagedPreferences, UnsubscribeError> {
+    let recipient = recipient_for_token(ctx, token)?;
+    let preferences = get_recipient_preferences(&ctx.connection, &recipient.id)?;
+
+    Ok(ManagedPreferences {
+        notification_configs: notification_configs_for_recipient(
+            ctx,
+            &recipient.id,
+            &preferences.opted_out_notification_config_ids,
+        )?,
+        preferred_recipient_options: preferred_recipient_options(ctx, &recipient)?,
+        to_address: recipient.to_address,
+        unsubscribed: recipient.deactivated_datetime.is_some(),
+        digest_frequency: preferences.digest_frequency,
+        preferred_recipient_id: preferences.preferred_recipient_id,
+    })
+}
+
+/// Saves the recipient's choices from their manage preferences page, resubscribing them if they'd unsubscribed.
+/// Returns the email address.
+pub fn update_managed_preferences(
+    ctx: &ServiceContext,
+    token: &str,
+    digest_frequency: DigestFrequency,
+    opted_out_notification_config_ids: Vec<String>,
+    preferred_recipient_id: Option<String>,
+) -> Result<String, UnsubscribeError> {
+    let recipient = recipient_for_token(ctx, token)?;
+    let preferences = get_recipient_preferences(&ctx.connection, &recipient.id)?;
+    // Only the configs shown on the page can be opted out of
+    let shown = notification_configs_for_recipient(
+        ctx,
+        &recipient.id,
+        &preferences.opted_out_notification_config_ids,
+    )?;
+    let opted_out_notification_config_ids = opted_out_notification_config_ids
+        .into_iter()
+        .filter(|id| shown.iter().any(|config| config.id == *id))
+        .collect();
+    // Likewise only one of the recipients shown on the page can be chosen
+    let options = preferred_recipient_options(ctx, &recipient)?;
+    let preferred_recipient_id =
+        preferred_recipient_id.filter(|id| options.iter().any(|option| option.id == *id));
+
+    set_recipient_preferences(
+        ctx,
+        RecipientPreferences {
+            digest_frequency,
+            opted_out_notification_config_ids,
+            preferred_recipient_id,
+            ..preferences
+        },
+    )?;
+    if recipient.deactivated_datetime.is_some() {
+        deactivate_recipient(ctx, &recipient.id, None)?;
+        log::info!(
+            "{} resubscribed to notification emails",
+            recipient.to_address
+        );
+    }
+    Ok(recipient.to_address)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use repository::{
+        mock::{
+            mock_recipient_a, mock_recipient_b, mock_recipient_c,
+            mock_recipient_list_with_recipient_members_a_and_b, MockDataInserts,
+        },
+        test_db::setup_all,
+        NotificationConfigRow, NotificationConfigRowRepository,
+    };
+
+    use crate::{
+        notification::renderer::render_template,
+        service_provider::ServiceProvider,
+        test_utils::{find_base_dir, get_test_settings},
+    };
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_unsubscribe() {
+        let (_, _, connection_manager, _) =
+            setup_all("test_unsubscribe", MockDataInserts::none().recipients()).await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::new(service_provider).unwrap();
+        let to_address = mock_recipient_a().to_address;
+
+        assert_eq!(
+            create_unsubscribe_token(&context, &to_address),
+            Err(UnsubscribeError::NoTokenSecret)
+        );
+        KeyValueStoreRepository::new(&context.connection)
+            .set_string(
+                KeyValueType::SettingsTokenSecret,
+                Some("secret".to_string()),
+            )
+            .unwrap();
+
+        let mut options = EmailOptions::default();
+        add_unsubscribe_headers(&context, &to_address, &mut options);
+        assert_eq!(options.headers.len(), 2);
+        assert_eq!(options.headers[1].value, "List-Unsubscribe=One-Click");
+        let url = options.headers[0].value.clone();
+        assert!(url.starts_with("<http://localhost:8007/unsubscribe?token="));
+        let token = url
+            .trim_end_matches('>')
+            .split_once("token=")
+            .unwrap()
+            .1
+            .to_string();
+
+        // Headers set for the email aren't replaced
+        add_unsubscribe_headers(&context, &to_address, &mut options);
+        assert_eq!(options.headers.len(), 2);
+
+        assert_eq!(
+            unsubscribe(&context, "not a token"),
+            Err(UnsubscribeError::InvalidToken)
+        );
+        assert_eq!(unsubscribe(&context, &token), Ok(to_address.clone()));
+        let recipient = RecipientRowRepository::new(&context.connection)
+            .find_one_by_id(&mock_recipient_a().id)
+            .unwrap()
+            .unwrap();
+        assert!(recipient.deactivated_datetime.is_some());
+
+        // Opening the link again is fine
+        assert_eq!(unsubscribe(&context, &token), Ok(to_address));
+
+        // Only email recipients can be unsubscribed
+        let token = create_unsubscribe_token(&context, &mock_recipient_c().to_address).unwrap();
+        assert_eq!(
+            unsubscribe(&context, &token),
+            Err(UnsubscribeError::RecipientNotFound)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_manage_preferences() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_manage_preferences",
+            MockDataInserts::none()
+                .recipients()
+                .recipient_lists()
+                .recipient_list_members(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::new(service_provider).unwrap();
+        KeyValueStoreRepository::new(&context.connection)
+            .set_string(
+                KeyValueType::SettingsTokenSecret,
+                Some("secret".to_string()),
+            )
+            .unwrap();
+        let recipient = mock_recipient_a();
+        let token = create_unsubscribe_token(&context, &recipient.to_address).unwrap();
+
+        // Sent to the recipient through a recipient list, the other config isn't sent to them
+        let config_repo = NotificationConfigRowRepository::new(&context.connection);
+        config_repo
+            .insert_one(&NotificationConfigRow {
+                id: "stock_report".to_string(),
+                title: "Stock report".to_string(),
+                status: NotificationConfigStatus::Enabled,
+                recipient_list_ids: format!(
+                    "[\"{}\"]",
+                    mock_recipient_list_with_recipient_members_a_and_b().id
+                ),
+                ..Default::default()
+            })
+            .unwrap();
+        config_repo
+            .insert_one(&NotificationConfigRow {
+                id: "other_report".to_string(),
+                title: "Other report".to_string(),
+                status: NotificationConfigStatus::Enabled,
+                recipient_ids: format!("[\"{}\"]", mock_recipient_c().id),
+                ..Default::default()
+            })
+            .unwrap();
+        // The same person's telegram chat
+        let recipient_telegram = RecipientRow {
+            id: "recipient_a_telegram".to_string(),
+            name: recipient.name.clone(),
+            notification_type: NotificationType::Telegram,
+            to_address: "chat_id_a".to_string(),
+            ..Default::default()
+        };
+        RecipientRowRepository::new(&context.connection)
+            .insert_one(&recipient_telegram)
+            .unwrap();
+
+        let preferences = get_managed_preferences(&context, &token).unwrap();
+        assert_eq!(preferences.to_address, recipient.to_address);
+        assert!(!preferences.unsubscribed);
+        assert_eq!(preferences.digest_frequency, DigestFrequency::Immediate);
+        assert_eq!(
+            preferences.notification_configs,
+            vec![RecipientNotificationConfig {
+                id: "stock_report".to_string(),
+                title: "Stock report".to_string(),
+                opted_out: false,
+            }]
+        );
+        assert_eq!(preferences.preferred_recipient_id, None);
+        assert_eq!(
+            preferences.preferred_recipient_options,
+            vec![PreferredRecipientOption {
+                id: recipient_telegram.id.clone(),
+                notification_type: NotificationType::Telegram,
+                to_address: recipient_telegram.to_address.clone(),
+            }]
+        );
+
+        // Configs that aren't shown to the recipient are ignored
+        update_managed_preferences(
+            &context,
+            &token,
+            DigestFrequency::Weekly,
+            vec!["stock_report".to_string(), "other_report".to_string()],
+            Some(recipient_telegram.id.clone()),
+        )
+        .unwrap();
+        let preferences = get_managed_preferences(&context, &token).unwrap();
+        assert_eq!(preferences.digest_frequency, DigestFrequency::Weekly);
+        assert!(preferences.notification_configs[0].opted_out);
+        assert_eq!(
+            preferences.preferred_recipient_id,
+            Some(recipient_telegram.id.clone())
+        );
+
+        // Only the person's own recipients can be preferred
+        update_managed_preferences(
+            &context,
+            &token,
+            DigestFrequency::Weekly,
+            vec!["stock_report".to_string()],
+            Some(mock_recipient_b().id),
+        )
+        .unwrap();
+        let preferences = get_managed_preferences(&context, &token).unwrap();
+        assert_eq!(preferences.preferred_recipient_id, None);
+        assert_eq!(
+            get_recipient_preferences(&context.connection, &recipient.id)
+                .unwrap()
+                .opted_out_notification_config_ids,
+            vec!["stock_report".to_string()]
+        );
+
+        // Saving preferences after unsubscribing resubscribes the recipient
+        unsubscribe(&context, &token).unwrap();
+        assert!(
+            get_managed_preferences(&context, &token)
+                .unwrap()
+                .unsubscribed
+        );
+        update_managed_preferences(&context, &token, DigestFrequency::Immediate, vec![], None)
+            .unwrap();
+        let preferences = get_managed_preferences(&context, &token).unwrap();
+        assert!(!preferences.unsubscribed);
+        assert!(!preferences.notification_configs[0].opted_out);
+    }
+
+    #[test]
+    fn test_preferences_page() {
+        let templates_path = format!("{}/templates/**/*", find_base_dir().to_str().unwrap());
+        let tera = tera::Tera::new(&templates_path).unwrap();
+        let preferences = ManagedPreferences {
+            to_address: "a&b@openmsupply.foundation".to_string(),
+            unsubscribed: false,
+            digest_frequency: DigestFrequency::Daily,
+            notification_configs: vec![RecipientNotificationConfig {
+                id: "stock_report".to_string(),
+                title: "<b>Stock report</b>".to_string(),
+                opted_out: false,
+            }],
+            preferred_recipient_id: Some("recipient_a_telegram".to_string()),
+            preferred_recipient_options: vec![PreferredRecipientOption {
+                id: "recipient_a_telegram".to_string(),
+                notification_type: NotificationType::Telegram,
+                to_address: "chat_id_a".to_string(),
+            }],
+        };
+
+        let html = render_template(
+            &tera,
+            "unsubscribe/preferences.html",
+            serde_json::json!({ "action": "/unsubscribe?token=token", "preferences": preferences }),
+        )
+        .unwrap();
+        assert!(html.contains("Notification preferences for a&amp;b@openmsupply.foundation"));
+        assert!(html.contains(
+            "name=\"receive_stock_report\" checked> &lt;b&gt;Stock report&lt;&#x2F;b&gt;"
+        ));
+        assert!(html.contains("<option value=\"DAILY\" selected>"));
+        assert!(html.contains(
+            "<option value=\"recipient_a_telegram\" selected>chat_id_a (Telegram)</option>"
+        ));
+
+        // A contact's channels don't get the preferred recipient choice
+        let html = render_template(
+            &tera,
+            "unsubscribe/preferences.html",
+            serde_json::json!({
+                "action": "/unsubscribe?token=token",
+                "preferences": ManagedPreferences {
+                    preferred_recipient_id: None,
+                    preferred_recipient_options: vec![],
+                    ..preferences
+                },
+            }),
+        )
+        .unwrap();
+        assert!(!html.contains("preferred_recipient_id"));
+    }
+}
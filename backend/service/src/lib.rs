@@ -4,6 +4,8 @@ use repository::{Pagination, PaginationOption, DEFAULT_PAGINATION_LIMIT};
 pub mod audit_log;
 pub mod auth;
 pub mod auth_data;
+pub mod chat_webhook;
+pub mod contact;
 pub mod datasource;
 pub mod email;
 pub mod log_service;
@@ -14,12 +16,16 @@ pub mod notification_event;
 pub mod notification_query;
 pub mod plugin;
 pub mod plugin_store;
+pub mod push;
 pub mod recipient;
 pub mod recipient_list;
 pub mod service_provider;
 pub mod settings;
+pub mod sms;
 pub mod sql_recipient_list;
 pub mod static_files;
+pub mod telegram_commands;
+pub mod telegram_updates;
 pub mod test_utils;
 pub mod token;
 pub mod token_bucket;
@@ -0,0 +1,249 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use repository::AttachmentFormat;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use super::NotificationServiceError;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+pub const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// A file sent with a notification, e.g. the results of a query as a spreadsheet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationAttachment {
+    pub filename: String,
+    pub content_type: String,
+    // Stored as base64 so the attachments can be saved as json on the notification event
+    #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+    pub content: Vec<u8>,
+}
+
+pub(crate) fn to_base64<S: Serializer>(content: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&BASE64.encode(content))
+}
+
+pub(crate) fn from_base64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+    BASE64
+        .decode(encoded)
+        .map_err(|e| serde::de::Error::custom(e.to_string()))
+}
+
+/// Serializes attachments for the notification_event `attachments` column, no attachments are stored as NULL
+pub fn attachments_to_json(
+    attachments: &[NotificationAttachment],
+) -> Result<Option<String>, NotificationServiceError> {
+    if attachments.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(attachments).map(Some).map_err(|e| {
+        NotificationServiceError::InternalError(format!("Failed to serialize attachments: {:?}", e))
+    })
+}
+
+pub fn attachments_from_json(
+    attachments: &Option<String>,
+) -> Result<Vec<NotificationAttachment>, NotificationServiceError> {
+    match attachments {
+        None => Ok(vec![]),
+        Some(attachments) => serde_json::from_str(attachments).map_err(|e| {
+            NotificationServiceError::InternalError(format!("Failed to read attachments: {:?}", e))
+        }),
+    }
+}
+
+/// Creates an attachment from the json rows returned by a notification query,
+/// returns None if the query isn't configured to be attached
+pub fn query_results_attachment(
+    name: &str,
+    format: AttachmentFormat,
+    rows: &Value,
+) -> Result<Option<NotificationAttachment>, NotificationServiceError> {
+    let table = ResultTable::from_rows(rows);
+    let attachment = match format {
+        AttachmentFormat::None => return Ok(None),
+        AttachmentFormat::Csv => NotificationAttachment {
+            filename: format!("{}.csv", name),
+            content_type: CSV_CONTENT_TYPE.to_string(),
+            content: table.to_csv().map_err(|e| {
+                NotificationServiceError::InternalError(format!(
+                    "Failed to create csv for {}: {:?}",
+                    name, e
+                ))
+            })?,
+        },
+        AttachmentFormat::Xlsx => NotificationAttachment {
+            filename: format!("{}.xlsx", name),
+            content_type: XLSX_CONTENT_TYPE.to_string(),
+            content: table.to_xlsx(name).map_err(|e| {
+                NotificationServiceError::InternalError(format!(
+                    "Failed to create xlsx for {}: {:?}",
+                    name, e
+                ))
+            })?,
+        },
+    };
+    Ok(Some(attachment))
+}
+
+/// Query results as columns and rows. The columns are the keys of the row objects, serde_json keeps these in alphabetical order
+/// rather than the query's column order, keys that only appear in later rows are added after the first row's
+struct ResultTable<'a> {
+    columns: Vec<&'a str>,
+    rows: Vec<Vec<Option<&'a Value>>>,
+}
+
+impl<'a> ResultTable<'a> {
+    fn from_rows(rows: &'a Value) -> Self {
+        let objects: Vec<&serde_json::Map<String, Value>> = match rows {
+            Value::Array(rows) => rows.iter().filter_map(Value::as_object).collect(),
+            Value::Object(row) => vec![row],
+            _ => vec![],
+        };
+
+        let mut columns: Vec<&str> = vec![];
+        for object in &objects {
+            for key in object.keys() {
+                if !columns.contains(&key.as_str()) {
+                    columns.push(key);
+                }
+            }
+        }
+
+        let rows = objects
+            .iter()
+            .map(|object| columns.iter().map(|column| object.get(*column)).collect())
+            .collect();
+
+        ResultTable { columns, rows }
+    }
+
+    fn to_csv(&self) -> Result<Vec<u8>, csv::Error> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record(&self.columns)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|value| value_to_text(*value)))?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()))
+    }
+
+    fn to_xlsx(&self, sheet_name: &str) -> Result<Vec<u8>, XlsxError> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        // Sheet names can't be over 31 characters or contain some symbols, in that case the default name is kept
+        let _ = worksheet.set_name(sheet_name);
+
+        let header_format = Format::new().set_bold();
+        for (column, name) in self.columns.iter().enumerate() {
+            worksheet.write_string_with_format(0, column as u16, *name, &header_format)?;
+        }
+        for (row_index, row) in self.rows.iter().enumerate() {
+            for (column, value) in row.iter().enumerate() {
+                write_xlsx_value(worksheet, row_index as u32 + 1, column as u16, *value)?;
+            }
+        }
+
+        workbook.save_to_buffer()
+    }
+}
+
+fn value_to_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "".to_string(),
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn write_xlsx_value(
+    worksheet: &mut Worksheet,
+    row: u32,
+    column: u16,
+    value: Option<&Value>,
+) -> Result<(), XlsxError> {
+    match value {
+        None | Some(Value::Null) => {}
+        Some(Value::Bool(value)) => {
+            worksheet.write_boolean(row, column, *value)?;
+        }
+        Some(Value::Number(number)) => match number.as_f64() {
+            Some(number) => {
+                worksheet.write_number(row, column, number)?;
+            }
+            None => {
+                worksheet.write_string(row, column, number.to_string())?;
+            }
+        },
+        Some(value) => {
+            worksheet.write_string(row, column, value_to_text(Some(value)))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use repository::AttachmentFormat;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_query_results_csv_attachment() {
+        let rows = json!([
+            {"sensor_name": "sensor1", "latest_temperature": 1.25, "is_above_limit": false},
+            {"sensor_name": "sensor, 2", "latest_temperature": null, "extra": [1, 2]}
+        ]);
+
+        let attachment = query_results_attachment("sensors", AttachmentFormat::Csv, &rows)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(attachment.filename, "sensors.csv");
+        assert_eq!(attachment.content_type, CSV_CONTENT_TYPE);
+        assert_eq!(
+            String::from_utf8(attachment.content).unwrap(),
+            "is_above_limit,latest_temperature,sensor_name,extra\n\
+             false,1.25,sensor1,\n\
+             ,,\"sensor, 2\",\"[1,2]\"\n"
+        );
+    }
+
+    #[test]
+    fn test_query_results_xlsx_attachment() {
+        let rows = json!([{"sensor_name": "sensor1", "latest_temperature": 1.25}]);
+
+        let attachment = query_results_attachment("sensors", AttachmentFormat::Xlsx, &rows)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(attachment.filename, "sensors.xlsx");
+        assert_eq!(attachment.content_type, XLSX_CONTENT_TYPE);
+        // xlsx files are zip archives
+        assert!(attachment.content.starts_with(b"PK"));
+
+        assert_eq!(
+            query_results_attachment("sensors", AttachmentFormat::None, &rows).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_attachments_json() {
+        let attachments = vec![NotificationAttachment {
+            filename: "test.csv".to_string(),
+            content_type: CSV_CONTENT_TYPE.to_string(),
+            content: vec![0, 1, 2, 255],
+        }];
+
+        let json = attachments_to_json(&attachments).unwrap();
+        assert_eq!(attachments_from_json(&json).unwrap(), attachments);
+
+        assert_eq!(attachments_to_json(&[]).unwrap(), None);
+        assert_eq!(attachments_from_json(&None).unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use repository::{
+    DigestFrequency, NotificationEventRow, NotificationEventRowRepository, NotificationEventStatus,
+    NotificationType, RecipientRowRepository, RepositoryError, TransactionError,
+};
+use util::uuid::uuid;
+
+use crate::{
+    email::options::{email_options_from_json, email_options_to_json, EmailOptions},
+    service_provider::ServiceContext,
+};
+
+use super::{
+    attachment::{attachments_from_json, attachments_to_json},
+    NotificationServiceError,
+};
+
+fn digest_period(digest_frequency: &DigestFrequency) -> Duration {
+    match digest_frequency {
+        DigestFrequency::Immediate => Duration::zero(),
+        DigestFrequency::Daily => Duration::days(1),
+        DigestFrequency::Weekly => Duration::weeks(1),
+    }
+}
+
+/// Queues one notification for each recipient whose digest is due, combining the notifications held for them.
+/// A digest is due once the oldest held notification has waited a day, or a week, and straight away if
+/// the recipient has gone back to immediate notifications. Returns the number of digests queued.
+pub fn send_digests(ctx: &ServiceContext) -> Result<usize, NotificationServiceError> {
+    let now = Utc::now().naive_utc();
+    let held = NotificationEventRowRepository::new(&ctx.connection).find_all_held_for_digest()?;
+
+    let mut held_by_recipient: HashMap<(NotificationType, String), Vec<NotificationEventRow>> =
+        HashMap::new();
+    for event in held {
+        held_by_recipient
+            .entry((event.notification_type.clone(), event.to_address.clone()))
+            .or_default()
+            .push(event);
+    }
+
+    let recipient_repo = RecipientRowRepository::new(&ctx.connection);
+    let mut digest_count = 0;
+    for ((notification_type, to_address), events) in held_by_recipient {
+        let digest_frequency = recipient_repo
+            .find_one_by_to_address_and_type(&to_address, notification_type.clone())?
+            .map(|recipient| recipient.digest_frequency)
+            .unwrap_or_default();
+        // Events are in the order they were created
+        let oldest = events[0].created_at;
+        if oldest + digest_period(&digest_frequency) > now {
+            continue;
+        }
+
+        let digest = create_digest(notification_type, to_address, &events, now)?;
+        ctx.connection
+            .transaction_sync(|connection| {
+                let repo = NotificationEventRowRepository::new(connection);
+                repo.insert_one(&digest)?;
+                // They're sent or failed once the digest is
+                for event in &events {
+                    repo.update_one(&NotificationEventRow {
+                        status: NotificationEventStatus::Digested,
+                        digest_event_id: Some(digest.id.clone()),
+                        updated_at: now,
+                        ..event.clone()
+                    })?;
+                }
+                Ok(())
+            })
+            .map_err(|error: TransactionError<RepositoryError>| error.to_inner_error())?;
+        digest_count += 1;
+    }
+
+    Ok(digest_count)
+}
+
+/// A queued notification with a section for each of the held notifications, and all their attachments.
+/// It's sent with the most urgent priority, and the notification config if they're all from the same one, so it falls back the same way
+fn create_digest(
+    notification_type: NotificationType,
+    to_address: String,
+    events: &[NotificationEventRow],
+    now: NaiveDateTime,
+) -> Result<NotificationEventRow, NotificationServiceError> {
+    let mut attachments = Vec::new();
+    let mut sections = Vec::new();
+    for event in events {
+        attachments.extend(attachments_from_json(&event.attachments)?);
+        sections.push(format!(
+            "## {}\n\n_{}_\n\n{}",
+            event.title.as_deref().unwrap_or("Notification"),
+            event.created_at.format("%Y-%m-%d %H:%M UTC"),
+            event.message
+        ));
+    }
+
+    let notification_config_id = match events
+        .iter()
+        .all(|event| event.notification_config_id == events[0].notification_config_id)
+    {
+        true => events[0].notification_config_id.clone(),
+        false => None,
+    };
+    let priority = events
+        .iter()
+        .map(|event| event.priority.clone())
+        .min()
+        .unwrap_or_default();
+
+    Ok(NotificationEventRow {
+        id: uuid(),
+        notification_config_id,
+        notification_type,
+        to_address,
+        title: Some(format!(
+            "Notification digest: {} notifications",
+            events.len()
+        )),
+        message: sections.join("\n\n---\n\n"),
+        status: NotificationEventStatus::Queued,
+        created_at: now,
+        updated_at: now,
+        attachments: attachments_to_json(&attachments)?,
+        email_options: digest_email_options(events)?,
+        priority,
+        ..Default::default()
+    })
+}
+
+/// The oldest notification's sender, layout and reply-to, with the cc and bcc addresses and inline images of all of them
+fn digest_email_options(
+    events: &[NotificationEventRow],
+) -> Result<Option<String>, NotificationServiceError> {
+    let to_error = |e| NotificationServiceError::InternalError(format!("{:?}", e));
+    let mut options = EmailOptions::default();
+    for (index, event) in events.iter().enumerate() {
+        let event_options = email_options_from_json(&event.email_options).map_err(to_error)?;
+        if index == 0 {
+            options.sender_profile = event_options.sender_profile;
+            options.layout = event_options.layout;
+            options.reply_to = event_options.reply_to;
+        }
+        for cc in event_options.cc {
+            if !options.cc.contains(&cc) {
+                options.cc.push(cc);
+            }
+        }
+        for bcc in event_options.bcc {
+            if !options.bcc.contains(&bcc) {
+                options.bcc.push(bcc);
+            }
+        }
+        for image in event_options.inline_images {
+            if !options
+                .inline_images
+                .iter()
+                .any(|existing| existing.content_id == image.content_id)
+            {
+                options.inline_images.push(image);
+            }
+        }
+    }
+    email_options_to_json(&options).map_err(to_error)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use repository::{
+        mock::{mock_recipient_a, MockDataInserts},
+        test_db::setup_all,
+        NotificationConfigRow, NotificationConfigRowRepository, NotificationPriority,
+    };
+
+    use crate::{
+        notification::enqueue::{
+            create_notification_events, NotificationContext, NotificationTarget, TemplateDefinition,
+        },
+        notification::fallback::update_notification_event,
+        recipient::preferences::RecipientPreferences,
+        service_provider::ServiceProvider,
+        test_utils::get_test_settings,
+    };
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_send_digests() {
+        let (_, _, connection_manager, _) =
+            setup_all("test_send_digests", MockDataInserts::none().recipients()).await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let recipient = mock_recipient_a();
+        context
+            .service_provider
+            .recipient_service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient.id.clone(),
+                    digest_frequency: DigestFrequency::Daily,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let repo = NotificationEventRowRepository::new(&context.connection);
+        let held = |id: &str, title: &str, created_at: NaiveDateTime| NotificationEventRow {
+            id: id.to_string(),
+            notification_type: NotificationType::Email,
+            to_address: recipient.to_address.clone(),
+            title: Some(title.to_string()),
+            message: format!("{} message", title),
+            status: NotificationEventStatus::Digest,
+            created_at,
+            updated_at: created_at,
+            ..Default::default()
+        };
+        let now = Utc::now().naive_utc();
+
+        // Config notifications to the recipient are held for their digest
+        NotificationConfigRowRepository::new(&context.connection)
+            .insert_one(&NotificationConfigRow {
+                id: "alarm_config".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        create_notification_events(
+            &context,
+            Some("alarm_config".to_string()),
+            NotificationContext {
+                title_template: Some(TemplateDefinition::Template("Fridge alarm".to_string())),
+                body_template: TemplateDefinition::Template("Fridge alarm message".to_string()),
+                recipients: vec![NotificationTarget::from(recipient.clone())],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: EmailOptions {
+                    cc: vec!["store@example.com".to_string()],
+                    ..Default::default()
+                },
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+        assert!(repo.un_sent().unwrap().is_empty());
+        assert_eq!(repo.find_all_held_for_digest().unwrap().len(), 1);
+
+        // The digest isn't due until the oldest notification has waited a day
+        assert_eq!(send_digests(&context).unwrap(), 0);
+        assert!(repo.un_sent().unwrap().is_empty());
+
+        repo.insert_one(&NotificationEventRow {
+            priority: NotificationPriority::Critical,
+            email_options: Some(
+                r#"{"sender_profile":"reports","cc":["manager@example.com","store@example.com"]}"#
+                    .to_string(),
+            ),
+            ..held("held_2", "Stock report", now - Duration::hours(25))
+        })
+        .unwrap();
+        assert_eq!(send_digests(&context).unwrap(), 1);
+
+        let queued = repo.un_sent().unwrap();
+        assert_eq!(queued.len(), 1);
+        let digest = &queued[0];
+        assert_eq!(digest.to_address, recipient.to_address);
+        assert_eq!(
+            digest.title,
+            Some("Notification digest: 2 notifications".to_string())
+        );
+        // Oldest first
+        let report = digest.message.find("## Stock report").unwrap();
+        let alarm = digest.message.find("## Fridge alarm").unwrap();
+        assert!(report < alarm);
+        assert!(digest.message.contains("Fridge alarm message"));
+
+        // They're from different configs so the digest has none, it's as urgent as the most urgent,
+        // sent from the oldest's sender and cc'd to everyone they were
+        assert_eq!(digest.notification_config_id, None);
+        assert_eq!(digest.priority, NotificationPriority::Critical);
+        let options = email_options_from_json(&digest.email_options).unwrap();
+        assert_eq!(options.sender_profile, Some("reports".to_string()));
+        assert_eq!(
+            options.cc,
+            vec![
+                "manager@example.com".to_string(),
+                "store@example.com".to_string()
+            ]
+        );
+
+        // The held notifications are added to the digest, they're sent when it is
+        assert!(repo.find_all_held_for_digest().unwrap().is_empty());
+        assert_eq!(send_digests(&context).unwrap(), 0);
+        let held_2 = repo.find_one_by_id("held_2").unwrap().unwrap();
+        assert_eq!(held_2.status, NotificationEventStatus::Digested);
+        assert_eq!(held_2.digest_event_id, Some(digest.id.clone()));
+
+        update_notification_event(
+            &context.connection,
+            &NotificationEventRow {
+                status: NotificationEventStatus::Sent,
+                sent_at: Some(now),
+                ..digest.clone()
+            },
+        )
+        .unwrap();
+        let held_2 = repo.find_one_by_id("held_2").unwrap().unwrap();
+        assert_eq!(held_2.status, NotificationEventStatus::Sent);
+        assert_eq!(held_2.sent_at, Some(now));
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use repository::{
+    NotificationEventRow, NotificationEventRowRepository, NotificationEventStatus,
+    NotificationType, RepositoryError, StorageConnection,
+};
+
+use crate::settings::SendingSettings;
+
+use super::fallback::update_notification_event;
+
+pub static DEFAULT_WORKERS: usize = 4;
+pub static DEFAULT_SMS_WORKERS: usize = 1;
+
+/// Each channel has its own pool of workers, so a backlog of emails doesn't hold up telegram messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendChannel {
+    Email,
+    Telegram,
+    ChatWebhook,
+    Push,
+    Sms,
+    Unknown,
+}
+
+impl SendChannel {
+    pub fn from_notification_type(notification_type: &NotificationType) -> Self {
+        match notification_type {
+            NotificationType::Email => SendChannel::Email,
+            NotificationType::Telegram => SendChannel::Telegram,
+            NotificationType::Teams | NotificationType::Slack | NotificationType::Mattermost => {
+                SendChannel::ChatWebhook
+            }
+            NotificationType::NtfyPush => SendChannel::Push,
+            NotificationType::Sms => SendChannel::Sms,
+            NotificationType::Unknown => SendChannel::Unknown,
+        }
+    }
+
+    /// How many of the channel's notifications are sent at the same time
+    pub fn workers(&self, settings: &SendingSettings) -> usize {
+        let workers = match self {
+            SendChannel::Email => settings.email_workers,
+            SendChannel::Telegram => settings.telegram_workers,
+            SendChannel::ChatWebhook => settings.chat_webhook_workers,
+            SendChannel::Push => settings.push_workers,
+            SendChannel::Sms => Some(settings.sms_workers.unwrap_or(DEFAULT_SMS_WORKERS)),
+            // Unknown notification types fail without being sent
+            SendChannel::Unknown => Some(1),
+        };
+        workers.unwrap_or(DEFAULT_WORKERS).max(1)
+    }
+}
+
+/// Splits the notifications into queues for each channel, with a queue per address so messages to the same chat or inbox stay in order.
+/// Notifications are sorted by priority then age, so the queues with critical alerts in them are sent first.
+pub fn send_queues(
+    mut notifications: Vec<NotificationEventRow>,
+) -> HashMap<SendChannel, Vec<Vec<NotificationEventRow>>> {
+    notifications.sort_by(|a, b| {
+        a.priority
+            .cmp(&b.priority)
+            .then(a.created_at.cmp(&b.created_at))
+    });
+
+    let mut queues: HashMap<SendChannel, Vec<Vec<NotificationEventRow>>> = HashMap::new();
+    let mut queue_index: HashMap<(SendChannel, String), usize> = HashMap::new();
+    for notification in notifications {
+        let channel = SendChannel::from_notification_type(&notification.notification_type);
+        let channel_queues = queues.entry(channel).or_default();
+        match queue_index.get(&(channel, notification.to_address.clone())) {
+            Some(index) => channel_queues[*index].push(notification),
+            None => {
+                queue_index.insert(
+                    (channel, notification.to_address.clone()),
+                    channel_queues.len(),
+                );
+                channel_queues.push(vec![notification]);
+            }
+        }
+    }
+
+    queues
+}
+
+/// Fails the notifications that were still sending when the server stopped.
+/// They may have been delivered, so they aren't sent again, but they are sent to their fallback if there is one
+pub fn fail_interrupted_sends(connection: &StorageConnection) -> Result<usize, RepositoryError> {
+    let interrupted = NotificationEventRowRepository::new(connection).find_all_sending()?;
+    for mut notification in interrupted.clone() {
+        log::error!(
+            "Notification {} to {} was interrupted while sending, it won't be sent again",
+            notification.id,
+            notification.to_address
+        );
+        notification.error_message = Some(
+            "Sending was interrupted, it may have been delivered so it isn't sent again"
+                .to_string(),
+        );
+        notification.status = NotificationEventStatus::Failed;
+        notification.updated_at = Utc::now().naive_utc();
+        update_notification_event(connection, &notification)?;
+    }
+    Ok(interrupted.len())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use repository::{mock::MockDataInserts, test_db::setup_all, NotificationPriority};
+
+    use super::*;
+
+    #[test]
+    fn test_send_queues_order() {
+        let now = Utc::now().naive_utc();
+        let event =
+            |id: &str, priority: NotificationPriority, to_address: &str, age_minutes: i64| {
+                NotificationEventRow {
+                    id: id.to_string(),
+                    notification_type: NotificationType::Telegram,
+                    to_address: to_address.to_string(),
+                    created_at: now - Duration::minutes(age_minutes),
+                    priority,
+                    ..Default::default()
+                }
+            };
+        let queues = send_queues(vec![
+            event("old_report", NotificationPriority::Low, "-1", 10),
+            event("report", NotificationPriority::Low, "-2", 5),
+            event("alert", NotificationPriority::Critical, "-2", 1),
+            event("old_alert", NotificationPriority::Critical, "-3", 2),
+            event("password_reset", NotificationPriority::Normal, "-4", 20),
+            NotificationEventRow {
+                notification_type: NotificationType::Email,
+                ..event(
+                    "email_report",
+                    NotificationPriority::Low,
+                    "a@example.com",
+                    1,
+                )
+            },
+        ]);
+
+        let ids = |channel: SendChannel| -> Vec<Vec<String>> {
+            queues[&channel]
+                .iter()
+                .map(|queue| queue.iter().map(|event| event.id.clone()).collect())
+                .collect()
+        };
+        // The critical alerts jump ahead of the reports, even in the same chat
+        assert_eq!(
+            ids(SendChannel::Telegram),
+            vec![
+                vec!["old_alert".to_string()],
+                vec!["alert".to_string(), "report".to_string()],
+                vec!["password_reset".to_string()],
+                vec!["old_report".to_string()],
+            ]
+        );
+        assert_eq!(
+            ids(SendChannel::Email),
+            vec![vec!["email_report".to_string()]]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_fail_interrupted_sends() {
+        let (_, connection, _, _) =
+            setup_all("test_fail_interrupted_sends", MockDataInserts::none()).await;
+
+        let repo = NotificationEventRowRepository::new(&connection);
+        let notification = NotificationEventRow {
+            id: "interrupted".to_string(),
+            notification_type: NotificationType::Telegram,
+            to_address: "-1".to_string(),
+            status: NotificationEventStatus::Queued,
+            ..Default::default()
+        };
+        repo.insert_one(&notification).unwrap();
+
+        assert!(repo.claim_for_sending(&notification).unwrap());
+        // Only one sender can claim it
+        assert!(!repo.claim_for_sending(&notification).unwrap());
+        assert!(repo.un_sent().unwrap().is_empty());
+
+        assert_eq!(fail_interrupted_sends(&connection).unwrap(), 1);
+        let interrupted = repo.find_one_by_id(&notification.id).unwrap().unwrap();
+        assert_eq!(interrupted.status, NotificationEventStatus::Failed);
+        assert!(repo.un_sent().unwrap().is_empty());
+    }
+}
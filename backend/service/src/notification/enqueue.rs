@@ -1,15 +1,27 @@
+use std::collections::HashSet;
+
 use chrono::Utc;
 use repository::{
-    NotificationEventRow, NotificationEventRowRepository, NotificationEventStatus,
-    NotificationType, RecipientRow,
+    DigestFrequency, NotificationConfigRowRepository, NotificationEventRow,
+    NotificationEventRowRepository, NotificationEventStatus, NotificationPriority,
+    NotificationType, RecipientRow, RecipientRowRepository,
 };
 use serde::Serialize;
 use tera::{Context, Error, Tera};
 use util::uuid::uuid;
 
-use crate::service_provider::ServiceContext;
+use crate::{
+    email::{
+        layout::email_branding_from_json,
+        options::{email_options_to_json, EmailOptions},
+    },
+    service_provider::ServiceContext,
+};
 
-use super::NotificationServiceError;
+use super::{
+    attachment::{attachments_to_json, NotificationAttachment},
+    NotificationServiceError,
+};
 
 // This struct is intended to be able to be created by a plugin from a datasource, and defines what a template can expect from a recipient
 // Often it will be derived RecipientRow which is why we implement From<RecipientRow> for NotificationRecipient
@@ -42,6 +54,12 @@ pub struct NotificationContext {
     pub body_template: TemplateDefinition,
     pub recipients: Vec<NotificationTarget>,
     pub template_data: serde_json::Value,
+    /// Files sent to every recipient, along with the message
+    pub attachments: Vec<NotificationAttachment>,
+    /// Inline images, reply-to, cc, bcc and headers for email recipients
+    pub email_options: EmailOptions,
+    /// Chosen by the plugin, unless the notification config sets its own priority
+    pub priority: NotificationPriority,
 }
 
 pub fn create_notification_events(
@@ -51,10 +69,15 @@ pub fn create_notification_events(
 ) -> Result<(), NotificationServiceError> {
     let repo = NotificationEventRowRepository::new(&ctx.connection);
 
-    // Dedup recipients by to_address
+    // Dedup recipients by notification type and address, a person's email and telegram are both sent to
+    let mut seen = HashSet::new();
     let mut recipients = notification.recipients.clone();
-    recipients.sort_by(|a, b| a.to_address.cmp(&b.to_address));
-    recipients.dedup_by(|a, b| (a.to_address == b.to_address));
+    recipients.retain(|recipient| {
+        seen.insert((
+            recipient.notification_type.clone(),
+            recipient.to_address.clone(),
+        ))
+    });
 
     // Create a tera instance for this notification
     let mut tera = Tera::default();
@@ -87,6 +110,30 @@ pub fn create_notification_events(
     let mut tera_context = Context::from_value(notification.template_data)
         .map_err(|e| create_failed_event_row(e, &config_id, ctx))?;
 
+    let attachments = attachments_to_json(&notification.attachments)?;
+    // Emails are sent from the notification config's sender profile and in its email layout, unless the plugin has chosen them
+    let mut email_options = notification.email_options.clone();
+    let mut priority = notification.priority.clone();
+    let config = match &config_id {
+        Some(config_id) => {
+            NotificationConfigRowRepository::new(&ctx.connection).find_one_by_id(config_id)?
+        }
+        None => None,
+    };
+    if let Some(config) = config {
+        if let Some(config_priority) = config.priority {
+            priority = config_priority;
+        }
+        if email_options.sender_profile.is_none() {
+            email_options.sender_profile = config.sender_profile;
+        }
+        if email_options.layout.is_none() {
+            email_options.layout = email_branding_from_json(&config.email_layout);
+        }
+    }
+    let email_options = email_options_to_json(&email_options)
+        .map_err(|e| NotificationServiceError::InternalError(format!("{:?}", e)))?;
+
     // Loop through recipients and create a notification for each
     for recipient in recipients {
         let notification_type = recipient.notification_type.clone();
@@ -103,7 +150,7 @@ pub fn create_notification_events(
             send_attempts: 0,
             updated_at: Utc::now().naive_utc(),
             notification_config_id: config_id.clone(),
-            notification_type,
+            notification_type: notification_type.clone(),
             retry_at: None,
             context: match serde_json::to_string(&tera_context.clone().into_json()) {
                 Ok(context) => Some(context),
@@ -112,6 +159,12 @@ pub fn create_notification_events(
                     None
                 }
             },
+            attachments: attachments.clone(),
+            email_options: match notification_type {
+                NotificationType::Email => email_options.clone(),
+                _ => None,
+            },
+            priority: priority.clone(),
             ..Default::default()
         };
 
@@ -145,6 +198,29 @@ pub fn create_notification_events(
             }
         };
 
+        // Hold config notifications for recipients who get a digest, system emails such as password resets and critical alerts are sent straight away
+        let notification_queue_row = match notification_queue_row.status {
+            NotificationEventStatus::Queued
+                if config_id.is_some() && priority != NotificationPriority::Critical =>
+            {
+                let digest_frequency = RecipientRowRepository::new(&ctx.connection)
+                    .find_one_by_to_address_and_type(
+                        &notification_queue_row.to_address,
+                        notification_type,
+                    )?
+                    .map(|recipient| recipient.digest_frequency)
+                    .unwrap_or_default();
+                NotificationEventRow {
+                    status: match digest_frequency {
+                        DigestFrequency::Immediate => NotificationEventStatus::Queued,
+                        _ => NotificationEventStatus::Digest,
+                    },
+                    ..notification_queue_row
+                }
+            }
+            _ => notification_queue_row,
+        };
+
         repo.insert_one(&notification_queue_row)
             .map_err(|e| NotificationServiceError::DatabaseError(e))?;
 
@@ -154,26 +230,35 @@ pub fn create_notification_events(
     Ok(())
 }
 
-fn create_failed_event_row(
-    e: Error,
-    config_id: &Option<String>,
+/// Records a notification that couldn't be created (e.g. a datasource query failed) as a failed event,
+/// so the problem is visible in the notification event log rather than only in the server logs
+pub fn create_failed_notification_event(
     ctx: &ServiceContext,
-) -> NotificationServiceError {
+    config_id: Option<String>,
+    error_message: String,
+) -> Result<(), NotificationServiceError> {
     let failed_notification_event_row = NotificationEventRow {
         id: uuid(),
         created_at: Utc::now().naive_utc(),
         updated_at: Utc::now().naive_utc(),
-        notification_config_id: config_id.clone(),
+        notification_config_id: config_id,
         status: NotificationEventStatus::Failed,
-        error_message: Some(format!("{:?}", e)),
+        error_message: Some(error_message),
         notification_type: NotificationType::Unknown,
         ..Default::default()
     };
 
-    match NotificationEventRowRepository::new(&ctx.connection)
+    NotificationEventRowRepository::new(&ctx.connection)
         .insert_one(&failed_notification_event_row)
         .map_err(|e| NotificationServiceError::DatabaseError(e))
-    {
+}
+
+fn create_failed_event_row(
+    e: Error,
+    config_id: &Option<String>,
+    ctx: &ServiceContext,
+) -> NotificationServiceError {
+    match create_failed_notification_event(ctx, config_id.clone(), format!("{:?}", e)) {
         Ok(()) => NotificationServiceError::InternalError(format!(
             "Failed to create notification: {:?}",
             e
@@ -187,12 +272,19 @@ mod test {
     use std::sync::Arc;
 
     use repository::{
-        mock::MockDataInserts, test_db::setup_all, NotificationEventRowRepository, NotificationType,
+        mock::MockDataInserts, test_db::setup_all, NotificationConfigRow,
+        NotificationConfigRowRepository, NotificationEventRowRepository, NotificationPriority,
+        NotificationType,
     };
 
     use crate::{
-        notification::enqueue::{
-            create_notification_events, NotificationContext, NotificationTarget, TemplateDefinition,
+        email::options::{email_options_from_json, EmailOptions},
+        notification::{
+            attachment::{attachments_from_json, NotificationAttachment},
+            enqueue::{
+                create_notification_events, NotificationContext, NotificationTarget,
+                TemplateDefinition,
+            },
         },
         service_provider::{ServiceContext, ServiceProvider},
         test_utils::get_test_settings,
@@ -213,9 +305,19 @@ mod test {
         ));
         let context = ServiceContext::as_server_admin(service_provider).unwrap();
 
+        NotificationConfigRowRepository::new(&connection)
+            .insert_one(&NotificationConfigRow {
+                id: "report_config".to_string(),
+                sender_profile: Some("reports".to_string()),
+                email_layout: Some(r##"{"primary_colour":"#123456"}"##.to_string()),
+                priority: Some(NotificationPriority::Low),
+                ..Default::default()
+            })
+            .unwrap();
+
         let result = create_notification_events(
             &context,
-            None,
+            Some("report_config".to_string()),
             NotificationContext {
                 title_template: Some(TemplateDefinition::TemplateName(
                     "test_message/email_subject.md".to_string(),
@@ -236,6 +338,17 @@ mod test {
                     },
                 ],
                 template_data: serde_json::json!({}),
+                attachments: vec![NotificationAttachment {
+                    filename: "report.csv".to_string(),
+                    content_type: "text/csv".to_string(),
+                    content: b"a,b\n1,2\n".to_vec(),
+                }],
+                email_options: EmailOptions {
+                    reply_to: Some("support@example.com".to_string()),
+                    cc: vec!["manager@example.com".to_string()],
+                    ..Default::default()
+                },
+                priority: NotificationPriority::Normal,
             },
         );
 
@@ -251,6 +364,27 @@ mod test {
             "test@example.com".to_string()
         );
         assert!(notification_event_rows[0].title.is_some());
+        // The config's priority overrides the plugin's
+        assert_eq!(
+            notification_event_rows[0].priority,
+            NotificationPriority::Low
+        );
+        let attachments = attachments_from_json(&notification_event_rows[0].attachments).unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].filename, "report.csv");
+        // Stored with the event so retries send the same email
+        let email_options =
+            email_options_from_json(&notification_event_rows[0].email_options).unwrap();
+        assert_eq!(email_options.cc, vec!["manager@example.com".to_string()]);
+        // Sent from the config's sender profile
+        assert_eq!(email_options.sender_profile, Some("reports".to_string()));
+        // In the config's email layout
+        assert_eq!(
+            email_options
+                .layout
+                .and_then(|layout| layout.primary_colour),
+            Some("#123456".to_string())
+        );
     }
 
     #[actix_rt::test]
@@ -289,6 +423,9 @@ mod test {
                     },
                 ],
                 template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
             },
         );
 
@@ -323,6 +460,9 @@ mod test {
                 body_template: TemplateDefinition::Template("{{bad_template}".to_string()),
                 recipients: vec![],
                 template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
             },
         );
 
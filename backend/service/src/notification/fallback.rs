@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use repository::{
+    ContactFilter, ContactRepository, EqualFilter, NotificationConfigRowRepository,
+    NotificationEventRow, NotificationEventRowRepository, NotificationEventStatus, RecipientFilter,
+    RecipientListContactRowRepository, RecipientListMemberFilter, RecipientListMemberRepository,
+    RecipientRepository, RecipientRow, RepositoryError, StorageConnection,
+};
+use util::uuid::uuid;
+
+use crate::contact::channels::{first_usable_channel, is_usable_channel, next_fallback_channel};
+
+/// Saves the notification's status, a notification that failed is queued again to its fallback.
+/// The notifications in a digest are given the digest's status once it's sent or has failed
+pub fn update_notification_event(
+    connection: &StorageConnection,
+    notification: &NotificationEventRow,
+) -> Result<(), RepositoryError> {
+    // Saved together, so a failed notification isn't left without its fallback
+    connection
+        .transaction_sync(|connection| {
+            let repo = NotificationEventRowRepository::new(connection);
+            repo.update_one(notification)?;
+            if matches!(
+                notification.status,
+                NotificationEventStatus::Sent | NotificationEventStatus::Failed
+            ) {
+                repo.update_digested(notification)?;
+            }
+            if notification.status == NotificationEventStatus::Failed {
+                queue_fallback_notification(connection, notification)?;
+            }
+            Ok(())
+        })
+        .map_err(|error| error.to_inner_error())
+}
+
+/// Queues copies of the failed notification, linked by their `parent_event_id`.
+/// The contact's next channel is tried first, then the notification config's fallback recipient list.
+pub fn queue_fallback_notification(
+    connection: &StorageConnection,
+    failed: &NotificationEventRow,
+) -> Result<Vec<NotificationEventRow>, RepositoryError> {
+    let channels = match next_fallback_channel(
+        connection,
+        &failed.notification_type,
+        &failed.to_address,
+        &failed.priority,
+    )? {
+        Some(channel) => vec![channel],
+        None => fallback_list_channels(connection, failed)?,
+    };
+
+    if channels.is_empty() {
+        log::error!(
+            "Notification {} failed to send to {} and has no fallback",
+            failed.id,
+            failed.to_address
+        );
+        return Ok(Vec::new());
+    }
+
+    let repo = NotificationEventRowRepository::new(connection);
+    let now = Utc::now().naive_utc();
+    let mut fallbacks = Vec::new();
+    for channel in channels {
+        log::info!(
+            "Notification {} failed to send to {}, sending to {:?} {} instead",
+            failed.id,
+            failed.to_address,
+            channel.notification_type,
+            channel.to_address
+        );
+        let fallback = NotificationEventRow {
+            id: uuid(),
+            notification_type: channel.notification_type,
+            to_address: channel.to_address,
+            status: NotificationEventStatus::Queued,
+            created_at: now,
+            updated_at: now,
+            sent_at: None,
+            retry_at: None,
+            send_attempts: 0,
+            error_message: None,
+            parent_event_id: Some(failed.id.clone()),
+            sent_parts: 0,
+            message_id: None,
+            digest_event_id: None,
+            ..failed.clone()
+        };
+        repo.insert_one(&fallback)?;
+        fallbacks.push(fallback);
+    }
+
+    Ok(fallbacks)
+}
+
+/// The usable recipients and contact channels of the config's fallback recipient list, except the one that failed.
+/// A notification that was itself sent as a fallback to the list doesn't fall back to the list again.
+fn fallback_list_channels(
+    connection: &StorageConnection,
+    failed: &NotificationEventRow,
+) -> Result<Vec<RecipientRow>, RepositoryError> {
+    let config = match &failed.notification_config_id {
+        Some(id) => NotificationConfigRowRepository::new(connection).find_one_by_id(id)?,
+        None => None,
+    };
+    let recipient_list_id = match config.and_then(|config| config.fallback_recipient_list_id) {
+        Some(recipient_list_id) => recipient_list_id,
+        None => return Ok(Vec::new()),
+    };
+
+    let recipient_ids = RecipientListMemberRepository::new(connection)
+        .query_by_filter(
+            RecipientListMemberFilter::new()
+                .recipient_list_id(EqualFilter::equal_to(&recipient_list_id)),
+        )?
+        .into_iter()
+        .map(|member| member.recipient_id)
+        .collect();
+    let mut channels: Vec<RecipientRow> = RecipientRepository::new(connection)
+        .query_by_filter(RecipientFilter::new().id(EqualFilter::equal_any(recipient_ids)))?
+        .into_iter()
+        .filter(|channel| is_usable_channel(channel, &failed.priority))
+        .collect();
+
+    let contact_ids = RecipientListContactRowRepository::new(connection)
+        .find_all_for_recipient_lists(&[recipient_list_id])?
+        .into_iter()
+        .map(|row| row.contact_id)
+        .collect();
+    let contacts = ContactRepository::new(connection)
+        .query_by_filter(ContactFilter::new().id(EqualFilter::equal_any(contact_ids)))?;
+    for contact in contacts {
+        channels.extend(first_usable_channel(
+            connection,
+            &contact.id,
+            &failed.priority,
+        )?);
+    }
+
+    let is_channel = |channel: &RecipientRow, event: &NotificationEventRow| {
+        channel.notification_type == event.notification_type
+            && channel.to_address == event.to_address
+    };
+
+    // Walk back through the fallbacks that led to this notification, the first notification isn't a fallback
+    let repo = NotificationEventRowRepository::new(connection);
+    let mut event = Some(failed.clone());
+    while let Some(current) = event {
+        let parent = match &current.parent_event_id {
+            Some(parent_event_id) => repo.find_one_by_id(parent_event_id)?,
+            None => break,
+        };
+        if channels.iter().any(|channel| is_channel(channel, &current)) {
+            return Ok(Vec::new());
+        }
+        event = parent;
+    }
+
+    channels.retain(|channel| !is_channel(channel, failed));
+    let mut seen = HashSet::new();
+    channels.retain(|channel| {
+        seen.insert((
+            channel.notification_type.clone(),
+            channel.to_address.clone(),
+        ))
+    });
+    Ok(channels)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use repository::{
+        mock::{
+            mock_coldchain_notification_config_a, mock_recipient_a, mock_recipient_b,
+            mock_recipient_list_with_recipient_members_a_and_b, MockDataInserts,
+        },
+        test_db::setup_all,
+        ContactRow, ContactRowRepository, NotificationConfigRow, NotificationType, RecipientRow,
+        RecipientRowRepository,
+    };
+
+    use crate::{
+        contact::channels::SetContactChannels,
+        service_provider::{ServiceContext, ServiceProvider},
+        test_utils::get_test_settings,
+    };
+
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_fallback_to_next_channel() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_fallback_to_next_channel",
+            MockDataInserts::none().recipients(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        // Telegram first, email if telegram fails
+        let telegram = RecipientRow {
+            id: "nurse_telegram".to_string(),
+            name: "Nurse".to_string(),
+            notification_type: NotificationType::Telegram,
+            to_address: "-12345".to_string(),
+            ..Default::default()
+        };
+        RecipientRowRepository::new(&context.connection)
+            .insert_one(&telegram)
+            .unwrap();
+        ContactRowRepository::new(&context.connection)
+            .insert_one(&ContactRow {
+                id: "nurse".to_string(),
+                name: "Nurse".to_string(),
+                deleted_datetime: None,
+            })
+            .unwrap();
+        context
+            .service_provider
+            .contact_service
+            .set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "nurse".to_string(),
+                    recipient_ids: vec![telegram.id.clone(), mock_recipient_a().id],
+                },
+            )
+            .unwrap();
+
+        let repo = NotificationEventRowRepository::new(&context.connection);
+        let now = Utc::now().naive_utc();
+        let failed = NotificationEventRow {
+            id: "telegram_event".to_string(),
+            notification_config_id: None,
+            notification_type: NotificationType::Telegram,
+            to_address: telegram.to_address.clone(),
+            title: Some("Fridge alarm".to_string()),
+            message: "Fridge is too hot".to_string(),
+            status: NotificationEventStatus::Queued,
+            created_at: now,
+            updated_at: now,
+            send_attempts: 1,
+            ..Default::default()
+        };
+        repo.insert_one(&failed).unwrap();
+
+        // Errors that will be retried aren't sent to the next channel
+        update_notification_event(
+            &context.connection,
+            &NotificationEventRow {
+                status: NotificationEventStatus::Errored,
+                ..failed.clone()
+            },
+        )
+        .unwrap();
+        assert!(repo.un_sent().unwrap().is_empty());
+
+        update_notification_event(
+            &context.connection,
+            &NotificationEventRow {
+                status: NotificationEventStatus::Failed,
+                sent_parts: 1,
+                ..failed.clone()
+            },
+        )
+        .unwrap();
+        let queued = repo.un_sent().unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].notification_type, NotificationType::Email);
+        assert_eq!(queued[0].to_address, mock_recipient_a().to_address);
+        assert_eq!(queued[0].message, failed.message);
+        assert_eq!(queued[0].send_attempts, 0);
+        // The whole message is sent to the next channel, not just the parts the last channel missed
+        assert_eq!(queued[0].sent_parts, 0);
+        assert_eq!(queued[0].parent_event_id, Some(failed.id.clone()));
+
+        // Email is the contact's last channel
+        assert!(queue_fallback_notification(&context.connection, &queued[0])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_fallback_to_recipient_list() {
+        let (_, connection, _, _) = setup_all(
+            "test_fallback_to_recipient_list",
+            MockDataInserts::none()
+                .recipient_list_members()
+                .notification_configs(),
+        )
+        .await;
+
+        let config = NotificationConfigRow {
+            fallback_recipient_list_id: Some(
+                mock_recipient_list_with_recipient_members_a_and_b().id,
+            ),
+            ..mock_coldchain_notification_config_a()
+        };
+        NotificationConfigRowRepository::new(&connection)
+            .update_one(&config)
+            .unwrap();
+
+        let repo = NotificationEventRowRepository::new(&connection);
+        let now = Utc::now().naive_utc();
+        let failed = NotificationEventRow {
+            id: "critical_event".to_string(),
+            notification_config_id: Some(config.id.clone()),
+            notification_type: NotificationType::Telegram,
+            to_address: "-12345".to_string(),
+            message: "Fridge is too hot".to_string(),
+            status: NotificationEventStatus::Queued,
+            created_at: now,
+            updated_at: now,
+            ..Default::default()
+        };
+        repo.insert_one(&failed).unwrap();
+
+        update_notification_event(
+            &connection,
+            &NotificationEventRow {
+                status: NotificationEventStatus::Failed,
+                ..failed.clone()
+            },
+        )
+        .unwrap();
+        let mut queued = repo.un_sent().unwrap();
+        queued.sort_by(|a, b| a.to_address.cmp(&b.to_address));
+        let mut expected = vec![mock_recipient_a().to_address, mock_recipient_b().to_address];
+        expected.sort();
+        assert_eq!(
+            queued
+                .iter()
+                .map(|event| event.to_address.clone())
+                .collect::<Vec<String>>(),
+            expected
+        );
+        assert!(queued
+            .iter()
+            .all(|event| event.parent_event_id == Some(failed.id.clone())
+                && event.message == failed.message));
+
+        // A notification sent to the fallback list doesn't fall back to the list again
+        assert!(queue_fallback_notification(&connection, &queued[0])
+            .unwrap()
+            .is_empty());
+
+        // Nor does a notification without a config
+        assert!(queue_fallback_notification(
+            &connection,
+            &NotificationEventRow {
+                notification_config_id: None,
+                ..failed
+            }
+        )
+        .unwrap()
+        .is_empty());
+    }
+}
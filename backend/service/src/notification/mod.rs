@@ -1,20 +1,41 @@
+use crate::email::layout::{markdown_to_html, render_notification_email};
+use crate::email::options::email_options_from_json;
+use crate::email::unsubscribe::add_unsubscribe_headers;
+use crate::push::PushPriority;
+use crate::recipient::telegram::migrate_telegram_recipient;
 use crate::service_provider::ServiceContext;
 use crate::settings::Settings;
 use async_trait::async_trait;
-use chrono::{ Utc, Duration };
+use chrono::Utc;
+use futures_util::{future::join_all, stream, StreamExt};
 use lettre::address::AddressError;
 use repository::{
-    NotificationEventRowRepository, NotificationEventStatus, NotificationType, RepositoryError,
+    NotificationEventRow, NotificationEventRowRepository, NotificationEventStatus,
+    NotificationPriority, NotificationType, RecipientRowRepository, RepositoryError,
 };
 use serde_json::json;
-use telegram::TelegramError;
+use std::convert::TryFrom;
+use telegram::{MessageOptions, TelegramClient, TelegramError};
 use tera::Tera;
 
+use self::attachment::{attachments_from_json, NotificationAttachment};
+use self::dispatch::send_queues;
+use self::fallback::update_notification_event;
+use self::retry::record_send_result;
+
+pub mod attachment;
+pub mod digest;
+pub mod dispatch;
 pub mod enqueue;
+pub mod fallback;
 pub mod renderer;
+pub mod retry;
 
 pub static MAX_SEND_ATTEMPTS: i32 = 3;
 pub static RETRY_DELAY_MINUTES: i64 = 15; // Doubles each retry
+pub static CRITICAL_MAX_SEND_ATTEMPTS: i32 = 6;
+pub static CRITICAL_RETRY_DELAY_MINUTES: i64 = 1;
+pub static MAX_RATE_LIMITED_HOURS: i64 = 24; // Rate limited retries don't use up attempts, so they stop after this long
 
 // We use a trait for NotificationService to allow mocking in tests
 #[async_trait(?Send)]
@@ -106,186 +127,908 @@ impl NotificationServiceTrait for NotificationService {
         log::debug!("Sending queued notifications");
 
         let repo = NotificationEventRowRepository::new(&ctx.connection);
-        let queued_notifications = repo.un_sent()?;
+        let queues = send_queues(repo.un_sent()?);
+
+        // Each channel's workers send a queue at a time, the channels are sent at the same time
+        let settings = &ctx.service_provider.settings.sending;
+        let channels = queues.into_iter().map(|(channel, queues)| {
+            stream::iter(queues)
+                .map(|queue| send_queue(ctx, queue))
+                .buffer_unordered(channel.workers(settings))
+                .collect::<Vec<_>>()
+        });
+        let results = join_all(channels).await;
 
-        let mut error_count = 0;
         let mut sent_count = 0;
+        let mut error_count = 0;
+        let mut first_error = None;
+        for result in results.into_iter().flatten() {
+            match result {
+                Ok((sent, errors)) => {
+                    sent_count += sent;
+                    error_count += errors;
+                }
+                Err(error) => {
+                    log::error!("Error sending notifications: {:?}", error);
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        log::debug!("Sent {} notifications, {} errors", sent_count, error_count);
+        if let Some(error) = first_error {
+            return Err(error);
+        }
+
+        Ok(sent_count)
+    }
+}
 
-        for mut notification in queued_notifications {
-            match notification.notification_type {
-                NotificationType::Unknown => {
-                    // This should only happen with a misconfigured sql recipient list query.
-                    // If you get this error in the logs you need to fix the sql query.
-                    error_count += 1;
+/// Sends the queue's notifications one at a time, returning how many were sent and how many weren't.
+/// Each is claimed before it's sent, so a notification that's interrupted while sending isn't sent again
+async fn send_queue(
+    ctx: &ServiceContext,
+    queue: Vec<NotificationEventRow>,
+) -> Result<(usize, usize), NotificationServiceError> {
+    let repo = NotificationEventRowRepository::new(&ctx.connection);
+    let mut sent_count = 0;
+    let mut error_count = 0;
+    for notification in queue {
+        if !repo.claim_for_sending(&notification)? {
+            continue;
+        }
+        match send_notification(ctx, notification).await? {
+            true => sent_count += 1,
+            false => error_count += 1,
+        }
+    }
+    Ok((sent_count, error_count))
+}
+
+/// Sends the notification and saves whether it was sent, returns true if it was sent
+async fn send_notification(
+    ctx: &ServiceContext,
+    mut notification: NotificationEventRow,
+) -> Result<bool, NotificationServiceError> {
+    let attachments = match attachments_from_json(&notification.attachments) {
+        Ok(attachments) => attachments,
+        Err(e) => {
+            log::error!(
+                "Unable to read attachments for notification {} - {:?}",
+                notification.id,
+                e
+            );
+            notification.error_message = Some(format!("{:?}", e));
+            notification.status = NotificationEventStatus::Failed;
+            notification.updated_at = Utc::now().naive_utc();
+            update_notification_event(&ctx.connection, &notification)?;
+            return Ok(false);
+        }
+    };
+
+    // Addresses that have hard bounced won't ever be delivered to, so aren't sent to
+    let undeliverable_recipient = RecipientRowRepository::new(&ctx.connection)
+        .find_one_by_to_address_and_type(
+            &notification.to_address,
+            notification.notification_type.clone(),
+        )?
+        .filter(|recipient| recipient.undeliverable_datetime.is_some());
+    if let Some(recipient) = undeliverable_recipient {
+        log::info!(
+            "Not sending notification {} to undeliverable recipient {}",
+            notification.id,
+            notification.to_address
+        );
+        notification.error_message = Some(format!(
+            "Not sent as {} is undeliverable - {}",
+            notification.to_address,
+            recipient.undeliverable_reason.unwrap_or_default()
+        ));
+        notification.status = NotificationEventStatus::Failed;
+        notification.updated_at = Utc::now().naive_utc();
+        update_notification_event(&ctx.connection, &notification)?;
+        return Ok(false);
+    }
+
+    match notification.notification_type {
+        NotificationType::Unknown => {
+            // This should only happen with a misconfigured sql recipient list query.
+            // If you get this error in the logs you need to fix the sql query.
+            log::error!(
+                "Unknown Notification Type {} to {} !!!!!",
+                notification.id,
+                notification.to_address,
+            );
+            notification.error_message = Some(format!(
+                "Unknown Notification Type for address {}",
+                notification.to_address,
+            ));
+            notification.status = NotificationEventStatus::Failed;
+
+            update_notification_event(&ctx.connection, &notification)?;
+        }
+        NotificationType::Email => {
+            let mut email_options = match email_options_from_json(&notification.email_options) {
+                Ok(email_options) => email_options,
+                Err(e) => {
                     log::error!(
-                        "Unknown Notification Type {} to {} !!!!!",
+                        "Unable to read email options for notification {} - {:?}",
                         notification.id,
-                        notification.to_address,
+                        e
                     );
-                    notification.error_message = Some(format!(
-                        "Unknown Notification Type for address {}",
-                        notification.to_address,
-                    ));
+                    notification.error_message = Some(format!("{:?}", e));
                     notification.status = NotificationEventStatus::Failed;
-
-                    repo.update_one(&notification)?;
+                    notification.updated_at = Utc::now().naive_utc();
+                    update_notification_event(&ctx.connection, &notification)?;
+                    return Ok(false);
                 }
-                NotificationType::Email => {
-                    // Try to send via email
-                    let text_body = notification.message.clone();
-                    let parser = pulldown_cmark::Parser::new(&notification.message);
-                    let mut email_body = String::new();
-                    pulldown_cmark::html::push_html(&mut email_body, parser);
-
-                    let result = ctx.service_provider.email_service.send_email(
-                        notification.to_address.clone(),
-                        notification
-                            .title
-                            .clone()
-                            .unwrap_or("Notification".to_string()),
-                        email_body,
-                        text_body,
-                    );
+            };
 
-                    match result {
-                        Ok(_) => {
-                            // Successfully Sent
-                            notification.error_message = None;
-                            notification.status = NotificationEventStatus::Sent;
-                            notification.send_attempts += 1;
-                            notification.sent_at = Some(Utc::now().naive_utc());
-                            notification.updated_at = Utc::now().naive_utc();
-                            repo.update_one(&notification)?;
-                            sent_count += 1;
-                        }
-                        Err(send_error) => {
-                            // Failed to send
-                            notification.updated_at = Utc::now().naive_utc();
-                            notification.send_attempts += 1;
-                            if notification.send_attempts >= MAX_SEND_ATTEMPTS {
-                                log::error!(
-                                    "Failed to send email {} to {} after {} attempts - {:?}",
-                                    notification.id,
-                                    notification.to_address,
-                                    MAX_SEND_ATTEMPTS,
-                                    send_error
-                                );
-                                notification.error_message = Some(format!(
-                                    "Failed to send email after {} attempts - {:?}",
-                                    MAX_SEND_ATTEMPTS, send_error
-                                ));
-                                notification.status = NotificationEventStatus::Failed;
-                            } else if send_error.is_permanent() {
-                                log::error!(
-                                    "Permanently failed to send email {} to {}",
-                                    notification.id,
-                                    notification.to_address,
-                                );
-                                notification.error_message = Some(format!("{:?}", send_error));
-                                notification.status = NotificationEventStatus::Failed;
-                            } else {
-                                log::error!(
-                                    "Temporarily unable to send email {} to {} - {:?}",
-                                    notification.id,
-                                    notification.to_address,
-                                    send_error
-                                );
-                                notification.error_message = Some(format!("{:?}", send_error));
-                                notification.status = NotificationEventStatus::Errored;
-                                notification.retry_at = Some(
-                                    Utc::now().naive_utc() +
-                                    Duration::minutes(
-                                        RETRY_DELAY_MINUTES *
-                                        i64::pow(2, notification.send_attempts as u32 - 1)
-                                    )
-                                )
-                            }
-                            error_count += 1;
-                            repo.update_one(&notification)?;
-                            continue;
+            // Try to send via email, system emails already have their html body
+            let text_body = notification.message.clone();
+            let subject = notification
+                .title
+                .clone()
+                .unwrap_or("Notification".to_string());
+            let email_body = match notification.html_message.clone() {
+                Some(html_message) => html_message,
+                None => {
+                    // System emails such as password resets can't be unsubscribed from
+                    add_unsubscribe_headers(ctx, &notification.to_address, &mut email_options);
+                    match render_notification_email(
+                        &ctx.service_provider.settings.mail,
+                        &subject,
+                        &notification.message,
+                        &email_options,
+                    ) {
+                        Ok(email_body) => email_body,
+                        Err(e) => {
+                            log::error!(
+                                "Unable to render the email layout for notification {}, sending without it - {:?}",
+                                notification.id,
+                                e
+                            );
+                            markdown_to_html(&notification.message)
                         }
                     }
                 }
-                NotificationType::Telegram => {
-                    // Try to send via telegram
-                    if let Some(telegram) = &ctx.service_provider.telegram {
-                        let telegram_markdown_v2 =
-                            telegram::service::markdown::cmark_to_telegram_v2(
-                                &notification.message,
-                            );
+            };
 
-                        let result = telegram
-                            .send_markdown_message(&notification.to_address, &telegram_markdown_v2)
-                            .await;
-
-                        match result {
-                            Ok(_) => {
-                                log::info!("Sent telegram message to {}", notification.to_address);
-                                notification.error_message = None;
-                                notification.status = NotificationEventStatus::Sent;
-                                notification.send_attempts += 1;
-                                notification.sent_at = Some(Utc::now().naive_utc());
-                                notification.updated_at = Utc::now().naive_utc();
-                                repo.update_one(&notification)?;
-                                sent_count += 1;
-                            }
-                            Err(TelegramError::Fatal(e)) => {
-                                log::error!(
-                                    "Permanently fail to send telegram message to {}: {:?}",
-                                    notification.to_address,
-                                    e
-                                );
-                                notification.send_attempts += 1;
-                                notification.error_message = Some(format!("{:?}", e));
-                                notification.status = NotificationEventStatus::Failed;
-                                notification.updated_at = Utc::now().naive_utc();
-                                repo.update_one(&notification)?;
-                                error_count += 1;
-                            }
-                            Err(TelegramError::Temporary(e)) => {
-                                notification.send_attempts += 1;
-                                if notification.send_attempts >= MAX_SEND_ATTEMPTS {
-                                    log::error!(
-                                    "Failed to send telegram message {} to {} after {} attempts - {:?}",
-                                    notification.id,
-                                    notification.to_address,
-                                    MAX_SEND_ATTEMPTS,
-                                    e
-                                );
-                                    notification.error_message = Some(format!("{:?}", e));
-                                    notification.status = NotificationEventStatus::Failed;
-                                } else {
-                                    log::error!(
-                                    "Temporarily unable to send telegram message {} to {} - {:?}",
-                                    notification.id,
-                                    notification.to_address,
-                                    e
-                                );
-                                    notification.error_message = Some(format!("{:?}", e));
-                                    notification.status = NotificationEventStatus::Errored;
-                                }
-
-                                notification.updated_at = Utc::now().naive_utc();
-                                repo.update_one(&notification)?;
-                                error_count += 1;
-                            }
-                        }
-                    } else {
-                        log::error!(
-                            "Telegram not configured, you are missing telegram notifications!!!!"
+            // Smtp blocks, so emails are sent on a blocking thread while the other workers carry on
+            let service_provider = ctx.service_provider.clone();
+            let to_address = notification.to_address.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                service_provider.email_service.send_email(
+                    to_address,
+                    subject,
+                    email_body,
+                    text_body,
+                    attachments,
+                    email_options,
+                )
+            })
+            .await
+            .map_err(|e| {
+                NotificationServiceError::InternalError(format!(
+                    "Sending email {} stopped - {:?}",
+                    notification.id, e
+                ))
+            })?;
+
+            if let Ok(message_id) = &result {
+                notification.message_id = Some(message_id.clone());
+            }
+            record_send_result(&mut notification, result);
+            update_notification_event(&ctx.connection, &notification)?;
+        }
+        NotificationType::Teams | NotificationType::Slack | NotificationType::Mattermost => {
+            if !attachments.is_empty() {
+                log::warn!(
+                    "Attachments can't be sent to {:?} webhooks, sending notification {} without them",
+                    notification.notification_type,
+                    notification.id
+                );
+            }
+
+            let result = ctx
+                .service_provider
+                .chat_webhook
+                .send_message(
+                    &notification.notification_type,
+                    &notification.to_address,
+                    notification.title.as_deref(),
+                    &notification.message,
+                )
+                .await;
+
+            record_send_result(&mut notification, result);
+            update_notification_event(&ctx.connection, &notification)?;
+        }
+        NotificationType::NtfyPush => {
+            if !attachments.is_empty() {
+                log::warn!(
+                    "Attachments can't be sent as push notifications, sending notification {} without them",
+                    notification.id
+                );
+            }
+
+            let priority = PushPriority::from_notification(
+                &notification.priority,
+                notification.context.as_deref(),
+            );
+            let result = ctx
+                .service_provider
+                .push
+                .send_message(
+                    &notification.to_address,
+                    notification.title.as_deref(),
+                    &notification.message,
+                    priority,
+                )
+                .await;
+
+            record_send_result(&mut notification, result);
+            update_notification_event(&ctx.connection, &notification)?;
+        }
+        NotificationType::Sms => {
+            let Some(sms) = &ctx.service_provider.sms else {
+                log::error!("SMS not configured, you are missing SMS notifications!!!!");
+                notification.error_message = Some("SMS Not Configured".to_string());
+                notification.status = NotificationEventStatus::Errored;
+                notification.updated_at = Utc::now().naive_utc();
+                update_notification_event(&ctx.connection, &notification)?;
+                return Ok(false);
+            };
+            if !attachments.is_empty() {
+                log::warn!(
+                    "Attachments can't be sent by SMS, sending notification {} without them",
+                    notification.id
+                );
+            }
+
+            let result = sms
+                .send(&notification.to_address, &notification.message)
+                .await;
+
+            record_send_result(&mut notification, result);
+            update_notification_event(&ctx.connection, &notification)?;
+        }
+        NotificationType::Telegram => {
+            // Try to send via telegram
+            if let Some(telegram) = &ctx.service_provider.telegram {
+                // Low priority messages are delivered silently, so reports don't wake anyone up
+                let options = MessageOptions {
+                    disable_notification: notification.priority == NotificationPriority::Low,
+                };
+                let mut sent_parts = usize::try_from(notification.sent_parts).unwrap_or_default();
+                let result = send_telegram_notification(
+                    telegram,
+                    &notification.to_address,
+                    &notification.message,
+                    attachments,
+                    options,
+                    &mut sent_parts,
+                )
+                .await;
+                notification.sent_parts = sent_parts as i32;
+
+                match result {
+                    Err(TelegramError::ChatMigrated { migrate_to_chat_id }) => {
+                        // The group was upgraded to a supergroup, move the recipient and send to the new chat next time
+                        let to_chat_id = migrate_to_chat_id.to_string();
+                        log::warn!(
+                            "Telegram chat {} was upgraded to {}, retrying notification {}",
+                            notification.to_address,
+                            to_chat_id,
+                            notification.id
                         );
-                        notification.error_message = Some("Telegram Not Configured".to_string());
+                        if let Err(e) =
+                            migrate_telegram_recipient(ctx, &notification.to_address, &to_chat_id)
+                        {
+                            log::error!("Error migrating telegram recipient: {:?}", e);
+                        }
+                        notification.to_address = to_chat_id;
+                        notification.error_message = Some(format!(
+                            "Telegram chat was upgraded to {}",
+                            migrate_to_chat_id
+                        ));
                         notification.status = NotificationEventStatus::Errored;
+                        notification.retry_at = Some(Utc::now().naive_utc());
                         notification.updated_at = Utc::now().naive_utc();
-                        repo.update_one(&notification)?;
-                        error_count += 1;
                     }
+                    result => record_send_result(&mut notification, result),
                 }
+                update_notification_event(&ctx.connection, &notification)?;
+            } else {
+                log::error!("Telegram not configured, you are missing telegram notifications!!!!");
+                notification.error_message = Some("Telegram Not Configured".to_string());
+                notification.status = NotificationEventStatus::Errored;
+                notification.updated_at = Utc::now().naive_utc();
+                update_notification_event(&ctx.connection, &notification)?;
             }
         }
+    }
 
-        log::debug!("Sent {} notifications, {} errors", sent_count, error_count);
+    Ok(notification.status == NotificationEventStatus::Sent)
+}
 
-        Ok(sent_count)
+/// Sends the telegram message, split into parts if it's too long, followed by each attachment as a document.
+/// Each message part and attachment is a part, the `sent_parts` delivered by an earlier attempt aren't sent again
+async fn send_telegram_notification(
+    telegram: &TelegramClient,
+    chat_id: &str,
+    common_markdown: &str,
+    attachments: Vec<NotificationAttachment>,
+    options: MessageOptions,
+    sent_parts: &mut usize,
+) -> Result<(), TelegramError> {
+    let message_parts = telegram
+        .send_common_markdown(chat_id, common_markdown, options, sent_parts)
+        .await?;
+    for (index, attachment) in attachments.into_iter().enumerate() {
+        if message_parts + index < *sent_parts {
+            continue;
+        }
+        telegram
+            .send_document(
+                chat_id,
+                &attachment.filename,
+                &attachment.content_type,
+                attachment.content,
+                None,
+                options,
+            )
+            .await?;
+        *sent_parts += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use repository::{
+        mock::{mock_recipient_a, MockDataInserts},
+        test_db::setup_all,
+        NotificationEventFilter, NotificationEventRepository,
+    };
+
+    use super::*;
+    use crate::{
+        chat_webhook::stub::WebhookStub,
+        notification::{
+            dispatch::fail_interrupted_sends,
+            enqueue::{
+                create_notification_events, NotificationContext, NotificationTarget,
+                TemplateDefinition,
+            },
+        },
+        service_provider::ServiceProvider,
+        settings::{SmppSettings, SmsSettings},
+        sms::stub::SmppStub,
+        test_utils::{
+            get_test_settings, service_provider_with_mock_email_service,
+            telegram_test::{mock_telegram_api, mock_telegram_settings},
+        },
+    };
+
+    #[actix_rt::test]
+    async fn test_send_queued_telegram_notifications() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_send_queued_telegram_notifications",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let mut settings = get_test_settings("");
+        settings.telegram = mock_telegram_settings();
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        // The mock is shared between tests, so this test uses its own chat
+        let chat_id = "-2001";
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: None,
+                body_template: TemplateDefinition::TemplateName(
+                    "test_message/telegram.html".to_string(),
+                ),
+                recipients: vec![NotificationTarget {
+                    name: "telegram".to_string(),
+                    to_address: chat_id.to_string(),
+                    notification_type: NotificationType::Telegram,
+                }],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 1);
+
+        let sent_messages = mock_telegram_api().sent_messages_to(chat_id);
+        assert_eq!(sent_messages.len(), 1);
+        assert_eq!(sent_messages[0].parse_mode, Some("MarkdownV2".to_string()));
+        assert!(NotificationEventRowRepository::new(&context.connection)
+            .un_sent()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_send_long_telegram_notification() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_send_long_telegram_notification",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let mut settings = get_test_settings("");
+        settings.telegram = mock_telegram_settings();
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        // A report with a row per sensor, too long for one telegram message
+        let chat_id = "2002";
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: None,
+                body_template: TemplateDefinition::Template(
+                    "{% for i in range(end=400) %}**Sensor {{ i }}**: 5.1°C\n\n{% endfor %}"
+                        .to_string(),
+                ),
+                recipients: vec![NotificationTarget {
+                    name: "telegram".to_string(),
+                    to_address: chat_id.to_string(),
+                    notification_type: NotificationType::Telegram,
+                }],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 1);
+
+        let sent_messages = mock_telegram_api().sent_messages_to(chat_id);
+        assert!(sent_messages.len() > 1);
+        assert!(sent_messages
+            .iter()
+            .all(|message| message.text.chars().count() <= 4096));
+        assert!(sent_messages.last().unwrap().text.contains("*Sensor 399*"));
+    }
+
+    #[actix_rt::test]
+    async fn test_telegram_parts_not_sent_again() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_telegram_parts_not_sent_again",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let mut settings = get_test_settings("");
+        settings.telegram = mock_telegram_settings();
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let chat_id = "-2003";
+        let attachment = |filename: &str| NotificationAttachment {
+            filename: filename.to_string(),
+            content_type: "text/csv".to_string(),
+            content: b"a,b\n1,2\n".to_vec(),
+        };
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: None,
+                body_template: TemplateDefinition::Template("Weekly report".to_string()),
+                recipients: vec![NotificationTarget {
+                    name: "telegram".to_string(),
+                    to_address: chat_id.to_string(),
+                    notification_type: NotificationType::Telegram,
+                }],
+                template_data: serde_json::json!({}),
+                attachments: vec![attachment("first.csv"), attachment("second.csv")],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        // The message is sent, then telegram is unavailable when the first attachment is sent
+        mock_telegram_api().push_chat_error(
+            chat_id,
+            "sendDocument",
+            telegram::mock::MockError {
+                error_code: 502,
+                description: "Bad Gateway".to_string(),
+                parameters: None,
+            },
+        );
+        let service = &context.service_provider.notification_service;
+        assert_eq!(
+            service.send_queued_notifications(&context).await.unwrap(),
+            0
+        );
+
+        let repo = NotificationEventRowRepository::new(&context.connection);
+        let mut notification = NotificationEventRepository::new(&context.connection)
+            .query_by_filter(NotificationEventFilter::new())
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(notification.status, NotificationEventStatus::Errored);
+        assert_eq!(notification.sent_parts, 1);
+
+        // The retry sends the attachments without sending the message again
+        notification.retry_at = Some(Utc::now().naive_utc() - chrono::Duration::minutes(1));
+        repo.update_one(&notification).unwrap();
+        assert_eq!(
+            service.send_queued_notifications(&context).await.unwrap(),
+            1
+        );
+
+        let sent: Vec<String> = mock_telegram_api()
+            .sent_messages_to(chat_id)
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[0].contains("Weekly report"));
+        assert_eq!(sent[1..], ["first.csv", "second.csv"]);
+    }
+
+    #[actix_rt::test]
+    async fn test_send_queued_chat_webhook_notifications() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_send_queued_chat_webhook_notifications",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let stub = WebhookStub::start();
+        let target = |notification_type: NotificationType, path: &str| NotificationTarget {
+            name: format!("{:?}", notification_type),
+            to_address: stub.url(path),
+            notification_type,
+        };
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: Some(TemplateDefinition::Template("Sensor alert".to_string())),
+                body_template: TemplateDefinition::Template("**Sensor** is too hot".to_string()),
+                recipients: vec![
+                    target(NotificationType::Teams, "/teams"),
+                    target(NotificationType::Slack, "/slack"),
+                    target(NotificationType::Mattermost, "/mattermost"),
+                ],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        stub.push_response("/teams", 503, &[], "Service Unavailable");
+        // The webhook has been removed
+        stub.push_response("/slack", 404, &[], "no_service");
+
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 1);
+        assert_eq!(stub.requests().len(), 3);
+
+        let events = NotificationEventRepository::new(&context.connection)
+            .query_by_filter(NotificationEventFilter::new())
+            .unwrap();
+        let event = |notification_type: NotificationType| {
+            events
+                .iter()
+                .find(|event| event.notification_type == notification_type)
+                .unwrap()
+        };
+
+        let teams = event(NotificationType::Teams);
+        assert_eq!(teams.status, NotificationEventStatus::Errored);
+        assert_eq!(teams.send_attempts, 1);
+        assert!(teams.retry_at.is_some());
+
+        let slack = event(NotificationType::Slack);
+        assert_eq!(slack.status, NotificationEventStatus::Failed);
+
+        let mattermost = event(NotificationType::Mattermost);
+        assert_eq!(mattermost.status, NotificationEventStatus::Sent);
+        assert_eq!(mattermost.send_attempts, 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_send_queued_push_notifications() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_send_queued_push_notifications",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let stub = WebhookStub::start();
+        let target = |path: &str| NotificationTarget {
+            name: path.to_string(),
+            to_address: stub.url(path),
+            notification_type: NotificationType::NtfyPush,
+        };
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: Some(TemplateDefinition::Template("Sensor alert".to_string())),
+                body_template: TemplateDefinition::Template(
+                    "**{{ sensor_name }}** is too hot".to_string(),
+                ),
+                recipients: vec![target("/on-call"), target("/message?token=abc")],
+                // A cold chain high temperature alert
+                template_data: serde_json::json!({ "sensor_name": "Fridge 1", "alert_type": "High" }),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 2);
+
+        let requests = stub.requests();
+        let ntfy = requests.iter().find(|request| request.path == "/").unwrap();
+        assert_eq!(ntfy.body["topic"], "on-call");
+        assert_eq!(ntfy.body["title"], "Sensor alert");
+        assert_eq!(ntfy.body["message"], "**Fridge 1** is too hot");
+        assert_eq!(ntfy.body["priority"], 5);
+        let gotify = requests
+            .iter()
+            .find(|request| request.path == "/message?token=abc")
+            .unwrap();
+        assert_eq!(gotify.body["priority"], 10);
+    }
+
+    #[actix_rt::test]
+    async fn test_send_queued_sms_notifications() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_send_queued_sms_notifications",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let stub = SmppStub::start();
+        let mut settings = get_test_settings("");
+        settings.sms = Some(SmsSettings {
+            sender: Some("+6421000000".to_string()),
+            max_parts: None,
+            smpp: Some(SmppSettings {
+                host: "127.0.0.1".to_string(),
+                port: stub.port(),
+                system_id: "dashboard".to_string(),
+                password: "secret".to_string(),
+                system_type: String::new(),
+            }),
+            http: None,
+        });
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let target = |to_address: &str| NotificationTarget {
+            name: to_address.to_string(),
+            to_address: to_address.to_string(),
+            notification_type: NotificationType::Sms,
+        };
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: None,
+                body_template: TemplateDefinition::Template(
+                    "**Sensor** is too hot, see [details](http://example.com/)".to_string(),
+                ),
+                recipients: vec![target("+64 21 123 4567"), target("021 123 4567")],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 1);
+
+        let submits = stub.submits();
+        assert_eq!(submits.len(), 1);
+        assert_eq!(submits[0].destination_addr, "64211234567");
+        assert_eq!(submits[0].source_addr, "6421000000");
+        assert_eq!(
+            String::from_utf8(submits[0].short_message.clone()).unwrap(),
+            "Sensor is too hot, see details (http://example.com/)"
+        );
+
+        // Not an E.164 number, so retrying won't help
+        let events = NotificationEventRepository::new(&context.connection)
+            .query_by_filter(NotificationEventFilter::new())
+            .unwrap();
+        let invalid = events
+            .iter()
+            .find(|event| event.to_address == "021 123 4567")
+            .unwrap();
+        assert_eq!(invalid.status, NotificationEventStatus::Failed);
+    }
+
+    #[actix_rt::test]
+    async fn test_undeliverable_recipient_not_sent() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_undeliverable_recipient_not_sent",
+            MockDataInserts::none().recipients(),
+        )
+        .await;
+
+        let service_provider = Arc::new(service_provider_with_mock_email_service(
+            &connection_manager,
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        RecipientRowRepository::new(&context.connection)
+            .set_undeliverable(
+                &mock_recipient_a().id,
+                Some((
+                    Utc::now().naive_utc(),
+                    "Bounced with status 5.1.1 - User unknown".to_string(),
+                )),
+            )
+            .unwrap();
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: Some(TemplateDefinition::Template("Sensor alert".to_string())),
+                body_template: TemplateDefinition::Template("Sensor is too hot".to_string()),
+                recipients: vec![NotificationTarget::from(mock_recipient_a())],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 0);
+
+        // Failed straight away, rather than being retried
+        let events = NotificationEventRepository::new(&context.connection)
+            .query_by_filter(NotificationEventFilter::new())
+            .unwrap();
+        assert_eq!(events[0].status, NotificationEventStatus::Failed);
+        assert_eq!(
+            events[0].error_message,
+            Some(
+                "Not sent as a@openmsupply.foundation is undeliverable - Bounced with status 5.1.1 - User unknown"
+                    .to_string()
+            )
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_interrupted_notification_not_sent_again() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_interrupted_notification_not_sent_again",
+            MockDataInserts::none(),
+        )
+        .await;
+
+        let mut settings = get_test_settings("");
+        settings.telegram = mock_telegram_settings();
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+
+        let chat_id = "-2004";
+        let target = |to_address: &str| NotificationTarget {
+            name: "telegram".to_string(),
+            to_address: to_address.to_string(),
+            notification_type: NotificationType::Telegram,
+        };
+        create_notification_events(
+            &context,
+            None,
+            NotificationContext {
+                title_template: None,
+                body_template: TemplateDefinition::Template("Sensor is too hot".to_string()),
+                recipients: vec![target(chat_id)],
+                template_data: serde_json::json!({}),
+                attachments: vec![],
+                email_options: Default::default(),
+                priority: Default::default(),
+            },
+        )
+        .unwrap();
+
+        // The server stopped while the notification was being sent
+        let repo = NotificationEventRowRepository::new(&context.connection);
+        let interrupted = repo.un_sent().unwrap().pop().unwrap();
+        assert!(repo.claim_for_sending(&interrupted).unwrap());
+
+        // A notification that's sending isn't sent again, or failed, by another pass
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 0);
+        let sending = repo.find_one_by_id(&interrupted.id).unwrap().unwrap();
+        assert_eq!(sending.status, NotificationEventStatus::Sending);
+
+        // It's failed when the server starts again
+        assert_eq!(fail_interrupted_sends(&context.connection).unwrap(), 1);
+        let sent_count = context
+            .service_provider
+            .notification_service
+            .send_queued_notifications(&context)
+            .await
+            .unwrap();
+        assert_eq!(sent_count, 0);
+        assert!(mock_telegram_api().sent_messages_to(chat_id).is_empty());
+        let interrupted = repo.find_one_by_id(&interrupted.id).unwrap().unwrap();
+        assert_eq!(interrupted.status, NotificationEventStatus::Failed);
     }
 }
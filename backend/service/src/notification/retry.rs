@@ -0,0 +1,261 @@
+use chrono::{Duration, Utc};
+use repository::{NotificationEventRow, NotificationEventStatus, NotificationPriority};
+use telegram::TelegramError;
+
+use crate::{
+    chat_webhook::ChatWebhookError, email::send::EmailSendError, push::PushError, sms::SmsError,
+};
+
+use super::{
+    CRITICAL_MAX_SEND_ATTEMPTS, CRITICAL_RETRY_DELAY_MINUTES, MAX_RATE_LIMITED_HOURS,
+    MAX_SEND_ATTEMPTS, RETRY_DELAY_MINUTES,
+};
+
+/// An error from sending a notification on one of the channels
+pub trait SendError: std::fmt::Debug {
+    /// Retrying won't help, e.g. the address doesn't exist
+    fn is_permanent(&self) -> bool;
+
+    /// How long the channel has asked us to wait when we're rate limited
+    fn retry_after(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+/// Updates the notification with the result of sending it, the caller saves it.
+/// Temporary errors are retried until the notification runs out of attempts. Being rate limited isn't a failed attempt,
+/// but a destination that's still rate limiting us once the notification is MAX_RATE_LIMITED_HOURS old is given up on
+pub fn record_send_result<T, E: SendError>(
+    notification: &mut NotificationEventRow,
+    result: Result<T, E>,
+) {
+    notification.updated_at = Utc::now().naive_utc();
+    let send_error = match result {
+        Ok(_) => {
+            log::info!(
+                "Sent {:?} notification {}",
+                notification.notification_type,
+                notification.id
+            );
+            notification.error_message = None;
+            notification.status = NotificationEventStatus::Sent;
+            notification.send_attempts += 1;
+            notification.sent_at = Some(Utc::now().naive_utc());
+            return;
+        }
+        Err(send_error) => send_error,
+    };
+
+    let retry_after = send_error.retry_after();
+    if retry_after.is_none() {
+        notification.send_attempts += 1;
+    }
+    notification.error_message = Some(format!("{:?}", send_error));
+    let rate_limited_too_long = retry_after.is_some()
+        && Utc::now().naive_utc() - notification.created_at
+            >= Duration::hours(MAX_RATE_LIMITED_HOURS);
+    if send_error.is_permanent()
+        || notification.send_attempts >= max_send_attempts(&notification.priority)
+        || rate_limited_too_long
+    {
+        log::error!(
+            "Failed to send {:?} notification {} to {} after {} attempts - {:?}",
+            notification.notification_type,
+            notification.id,
+            notification.to_address,
+            notification.send_attempts,
+            send_error
+        );
+        notification.status = NotificationEventStatus::Failed;
+    } else {
+        log::error!(
+            "Temporarily unable to send {:?} notification {} to {} - {:?}",
+            notification.notification_type,
+            notification.id,
+            notification.to_address,
+            send_error
+        );
+        notification.status = NotificationEventStatus::Errored;
+        notification.retry_at = Some(
+            Utc::now().naive_utc()
+                + retry_after_delay(
+                    retry_after,
+                    notification.send_attempts,
+                    &notification.priority,
+                ),
+        );
+    }
+}
+
+/// Critical notifications get more attempts before they're given up on
+pub fn max_send_attempts(priority: &NotificationPriority) -> i32 {
+    match priority {
+        NotificationPriority::Critical => CRITICAL_MAX_SEND_ATTEMPTS,
+        NotificationPriority::Normal | NotificationPriority::Low => MAX_SEND_ATTEMPTS,
+    }
+}
+
+/// How long to wait before retrying a notification that failed to send, doubling after each attempt.
+/// Critical notifications are retried within minutes, low priority ones can wait an hour
+pub fn retry_delay(send_attempts: i32, priority: &NotificationPriority) -> Duration {
+    let minutes = match priority {
+        NotificationPriority::Critical => CRITICAL_RETRY_DELAY_MINUTES,
+        NotificationPriority::Normal => RETRY_DELAY_MINUTES,
+        NotificationPriority::Low => RETRY_DELAY_MINUTES * 4,
+    };
+    Duration::minutes(minutes * i64::pow(2, (send_attempts.max(1) - 1) as u32))
+}
+
+/// Telegram, the chat webhooks and push servers tell us how long to wait when we're rate limited, otherwise the usual retry delay is used
+pub fn retry_after_delay(
+    retry_after: Option<std::time::Duration>,
+    send_attempts: i32,
+    priority: &NotificationPriority,
+) -> Duration {
+    retry_after
+        .and_then(|retry_after| Duration::from_std(retry_after).ok())
+        .unwrap_or_else(|| retry_delay(send_attempts, priority))
+}
+
+impl SendError for EmailSendError {
+    fn is_permanent(&self) -> bool {
+        EmailSendError::is_permanent(self)
+    }
+}
+
+impl SendError for ChatWebhookError {
+    fn is_permanent(&self) -> bool {
+        ChatWebhookError::is_permanent(self)
+    }
+
+    fn retry_after(&self) -> Option<std::time::Duration> {
+        ChatWebhookError::retry_after(self)
+    }
+}
+
+impl SendError for PushError {
+    fn is_permanent(&self) -> bool {
+        PushError::is_permanent(self)
+    }
+
+    fn retry_after(&self) -> Option<std::time::Duration> {
+        PushError::retry_after(self)
+    }
+}
+
+impl SendError for SmsError {
+    fn is_permanent(&self) -> bool {
+        SmsError::is_permanent(self)
+    }
+}
+
+/// Chat migrations aren't send errors, they're handled before the result is recorded
+impl SendError for TelegramError {
+    fn is_permanent(&self) -> bool {
+        matches!(self, TelegramError::Fatal(_))
+    }
+
+    fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            TelegramError::Temporary(e) => e.retry_after(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use repository::NotificationType;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let normal = NotificationPriority::Normal;
+        assert_eq!(retry_delay(1, &normal), Duration::minutes(15));
+        assert_eq!(retry_delay(2, &normal), Duration::minutes(30));
+        assert_eq!(
+            retry_delay(2, &NotificationPriority::Critical),
+            Duration::minutes(2)
+        );
+        assert_eq!(
+            retry_delay(1, &NotificationPriority::Low),
+            Duration::hours(1)
+        );
+        assert_eq!(max_send_attempts(&NotificationPriority::Critical), 6);
+        assert_eq!(max_send_attempts(&NotificationPriority::Low), 3);
+
+        // Telegram's retry_after is used instead of the backoff when we're rate limited
+        assert_eq!(
+            retry_after_delay(Some(std::time::Duration::from_secs(5)), 2, &normal),
+            Duration::seconds(5)
+        );
+        assert_eq!(retry_after_delay(None, 2, &normal), Duration::minutes(30));
+    }
+
+    #[test]
+    fn test_record_send_result() {
+        let mut notification = NotificationEventRow {
+            id: "push".to_string(),
+            notification_type: NotificationType::NtfyPush,
+            created_at: Utc::now().naive_utc(),
+            ..Default::default()
+        };
+
+        // Rate limited, waits as long as it's told to without using an attempt
+        record_send_result::<(), _>(
+            &mut notification,
+            Err(PushError::RateLimited {
+                description: "Too many requests".to_string(),
+                retry_after: std::time::Duration::from_secs(30),
+            }),
+        );
+        assert_eq!(notification.status, NotificationEventStatus::Errored);
+        assert_eq!(notification.send_attempts, 0);
+        let retry_in = notification.retry_at.unwrap() - Utc::now().naive_utc();
+        assert!(retry_in <= Duration::seconds(30) && retry_in > Duration::seconds(20));
+
+        // Temporary errors are retried until the attempts run out
+        for _ in 0..MAX_SEND_ATTEMPTS - 1 {
+            record_send_result::<(), _>(
+                &mut notification,
+                Err(PushError::Temporary("Unavailable".to_string())),
+            );
+            assert_eq!(notification.status, NotificationEventStatus::Errored);
+        }
+        record_send_result::<(), _>(
+            &mut notification,
+            Err(PushError::Temporary("Unavailable".to_string())),
+        );
+        assert_eq!(notification.status, NotificationEventStatus::Failed);
+        assert_eq!(notification.send_attempts, MAX_SEND_ATTEMPTS);
+
+        // A destination that never stops rate limiting us doesn't hold the notification forever
+        let mut notification = NotificationEventRow {
+            created_at: Utc::now().naive_utc() - Duration::hours(MAX_RATE_LIMITED_HOURS),
+            ..Default::default()
+        };
+        record_send_result::<(), _>(
+            &mut notification,
+            Err(PushError::RateLimited {
+                description: "Too many requests".to_string(),
+                retry_after: std::time::Duration::from_secs(30),
+            }),
+        );
+        assert_eq!(notification.status, NotificationEventStatus::Failed);
+        assert_eq!(notification.send_attempts, 0);
+
+        // Permanent errors aren't retried
+        let mut notification = NotificationEventRow::default();
+        record_send_result::<(), _>(
+            &mut notification,
+            Err(PushError::Permanent("Forbidden".to_string())),
+        );
+        assert_eq!(notification.status, NotificationEventStatus::Failed);
+
+        record_send_result::<(), PushError>(&mut notification, Ok(()));
+        assert_eq!(notification.status, NotificationEventStatus::Sent);
+        assert!(notification.sent_at.is_some());
+        assert_eq!(notification.error_message, None);
+    }
+}
@@ -72,5 +72,10 @@ pub fn generate(
         sql_recipient_list_ids: "[]".to_string(),
         last_run_datetime: None,
         next_due_datetime: None,
+        sender_profile: None,
+        email_layout: None,
+        contact_ids: "[]".to_string(),
+        fallback_recipient_list_id: None,
+        priority: None,
     })
 }
@@ -1,7 +1,8 @@
 use super::validate::check_notification_config_exists;
 use crate::service_provider::ServiceContext;
 use repository::{
-    NotificationConfigRow, NotificationConfigRowRepository, RepositoryError, StorageConnection,
+    NotificationConfigRow, NotificationConfigRowRepository, RecipientOptOutRowRepository,
+    RepositoryError, StorageConnection,
 };
 
 #[derive(PartialEq, Debug)]
@@ -19,6 +20,8 @@ pub fn delete_notification_config(
         .transaction_sync(|connection| {
             let notification_config_row = validate(connection, notification_config_id)?;
 
+            RecipientOptOutRowRepository::new(connection)
+                .delete_all_for_notification_config(notification_config_id)?;
             let notification_config_repo = NotificationConfigRowRepository::new(connection);
             match notification_config_repo.delete(notification_config_id) {
                 Ok(_) => {}
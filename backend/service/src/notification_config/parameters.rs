@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 
-use crate::service_provider::ServiceContext;
-use crate::notification::NotificationServiceError;
 use super::query::NotificationConfig;
+use crate::datasource::notification_query_limits;
+use crate::notification::NotificationServiceError;
+use crate::service_provider::ServiceContext;
 use repository::NotificationQueryRowRepository;
 
 pub fn get_notification_parameters(
@@ -47,22 +48,38 @@ fn get_sql_parameters(
     let repository = NotificationQueryRowRepository::new(&ctx.connection);
     let query_record = repository.find_one_by_id(&parameter_query_id)?;
 
-    let sql_query = match query_record {
-        None => return Err(NotificationServiceError::InternalError(format!(
-                    "No query found for parameter_query_id: {}", parameter_query_id)
-                )),
-        Some(record) => record.query,
+    let query_record = match query_record {
+        None => {
+            return Err(NotificationServiceError::InternalError(format!(
+                "No query found for parameter_query_id: {}",
+                parameter_query_id
+            )))
+        }
+        Some(record) => record,
     };
 
     let query_result = ctx
         .service_provider
         .datasource_service
-        .run_sql_query(sql_query)
+        .run_sql_query(
+            query_record.datasource_id.clone(),
+            query_record.query.clone(),
+            Some(notification_query_limits(&query_record)),
+        )
         .map_err(|e| {
-            NotificationServiceError::InternalError(format!("Error when fetching parameter_query_id: {} - {:?}",
-                        parameter_query_id, e
-                    ))
+            NotificationServiceError::InternalError(format!(
+                "Error when fetching parameter_query_id: {} - {:?}",
+                parameter_query_id, e
+            ))
         })?;
 
+    // An errored query returns no rows, we don't want to carry on as if there were no parameters
+    if let Some(query_error) = query_result.query_error {
+        return Err(NotificationServiceError::InternalError(format!(
+            "Error running parameter query {}: {}",
+            parameter_query_id, query_error
+        )));
+    }
+
     return Ok(query_result.results);
 }
@@ -2,13 +2,15 @@ use chrono::NaiveDateTime;
 use repository::{
     EqualFilter, NotificationConfigFilter, NotificationConfigKind, NotificationConfigRepository,
     NotificationConfigRow, NotificationConfigRowRepository, NotificationConfigSort,
-    NotificationConfigStatus, PaginationOption,
+    NotificationConfigStatus, NotificationPriority, PaginationOption,
 };
 use util::i64_to_u32;
 
 use crate::{
-    get_default_pagination, service_provider::ServiceContext, ListError, ListResult,
-    SingleRecordError,
+    email::layout::{email_branding_from_json, EmailBranding},
+    get_default_pagination,
+    service_provider::ServiceContext,
+    ListError, ListResult, SingleRecordError,
 };
 
 pub const MAX_LIMIT: u32 = 1000;
@@ -28,6 +30,12 @@ pub struct NotificationConfig {
     pub sql_recipient_list_ids: Vec<String>,
     pub last_run_datetime: Option<NaiveDateTime>,
     pub next_due_datetime: Option<NaiveDateTime>,
+    pub sender_profile: Option<String>,
+    pub email_layout: Option<EmailBranding>,
+    pub contact_ids: Vec<String>,
+    pub fallback_recipient_list_id: Option<String>,
+    /// The priority of the config's notifications, when it's not set the plugin chooses
+    pub priority: Option<NotificationPriority>,
 }
 
 impl From<NotificationConfigRow> for NotificationConfig {
@@ -45,6 +53,11 @@ impl From<NotificationConfigRow> for NotificationConfig {
             sql_recipient_list_ids,
             last_run_datetime,
             next_due_datetime,
+            sender_profile,
+            email_layout,
+            contact_ids,
+            fallback_recipient_list_id,
+            priority,
         }: NotificationConfigRow,
     ) -> Self {
         NotificationConfig {
@@ -61,6 +74,11 @@ impl From<NotificationConfigRow> for NotificationConfig {
                 .unwrap_or_default(),
             last_run_datetime,
             next_due_datetime,
+            sender_profile,
+            email_layout: email_branding_from_json(&email_layout),
+            contact_ids: serde_json::from_str(&contact_ids).unwrap_or_default(),
+            fallback_recipient_list_id,
+            priority,
         }
     }
 }
@@ -1,23 +1,32 @@
 use std::str::FromStr;
 
+use chrono::Utc;
 use repository::{
-    EqualFilter, RecipientFilter, RecipientListMemberFilter, RecipientListMemberRepository,
-    RecipientRepository,
+    ContactFilter, ContactRepository, EqualFilter, NotificationPriority, RecipientFilter,
+    RecipientListContactRowRepository, RecipientListMemberFilter, RecipientListMemberRepository,
+    RecipientOptOutRowRepository, RecipientRepository, RecipientRow, RecipientRowRepository,
 };
 
 use crate::{
-    notification::enqueue::NotificationTarget, service_provider::ServiceContext,
+    contact::channels::{first_usable_channel, get_contact_channels},
+    notification::enqueue::NotificationTarget,
+    service_provider::ServiceContext,
     sql_recipient_list::query::get_sql_recipients,
 };
 
 use super::{query::NotificationConfig, ModifyNotificationConfigError};
 
+/// The recipients of a notification from the config, `priority` is the plugin's priority for the notification.
+/// Muted recipients only get critical notifications
 pub fn get_notification_targets(
     ctx: &ServiceContext,
     notification_config: &NotificationConfig,
     parameters: serde_json::Value,
+    priority: NotificationPriority,
 ) -> Result<Vec<NotificationTarget>, ModifyNotificationConfigError> {
     let mut notification_targets: Vec<NotificationTarget> = Vec::new();
+    // The config's priority overrides the plugin's, the same as when the notification is queued
+    let priority = notification_config.priority.clone().unwrap_or(priority);
 
     // // Get the recipients based on recipient list ids
     let recipient_list_member_repository = RecipientListMemberRepository::new(&ctx.connection);
@@ -48,6 +57,22 @@ pub fn get_notification_targets(
         .collect();
     notification_targets.extend(recipients);
 
+    // Contacts get the notification on the first of their channels that can be sent to
+    let mut all_contact_ids: Vec<String> = RecipientListContactRowRepository::new(&ctx.connection)
+        .find_all_for_recipient_lists(&notification_config.recipient_list_ids)?
+        .into_iter()
+        .map(|row| row.contact_id)
+        .collect();
+    all_contact_ids.extend(notification_config.contact_ids.clone());
+    let contacts = ContactRepository::new(&ctx.connection)
+        .query_by_filter(ContactFilter::new().id(EqualFilter::equal_any(all_contact_ids)))?;
+    for contact in contacts {
+        match first_usable_channel(&ctx.connection, &contact.id, &priority)? {
+            Some(channel) => notification_targets.push(NotificationTarget::from(channel)),
+            None => log::info!("Skipping contact {} with no usable channels", contact.name),
+        }
+    }
+
     // loop through all the sql recipient lists
     for sql_recipient_list_id in &notification_config.sql_recipient_list_ids {
         // Run the query
@@ -79,6 +104,107 @@ pub fn get_notification_targets(
         }
     }
 
+    // Sql recipient lists can include recipients too, so recipient preferences are matched by address
+    let recipient_repository = RecipientRowRepository::new(&ctx.connection);
+    let is_recipient = |recipient: &RecipientRow, target: &NotificationTarget| {
+        recipient.notification_type == target.notification_type
+            && recipient.to_address == target.to_address
+    };
+
+    // Skip recipients that have opted out of this config
+    let opted_out_recipient_ids: Vec<String> = RecipientOptOutRowRepository::new(&ctx.connection)
+        .find_all_for_notification_config(&notification_config.id)?
+        .into_iter()
+        .map(|row| row.recipient_id)
+        .collect();
+    let mut opted_out_recipients = RecipientRepository::new(&ctx.connection).query_by_filter(
+        RecipientFilter::new().id(EqualFilter::equal_any(opted_out_recipient_ids)),
+    )?;
+    // Opting out on one of a contact's channels opts the contact out
+    let opted_out_contact_ids: Vec<String> = opted_out_recipients
+        .iter()
+        .filter_map(|recipient| recipient.contact_id.clone())
+        .collect();
+    for contact_id in opted_out_contact_ids {
+        opted_out_recipients.extend(get_contact_channels(&ctx.connection, &contact_id)?);
+    }
+    notification_targets.retain(|target| {
+        let is_opted_out = opted_out_recipients
+            .iter()
+            .any(|recipient| is_recipient(recipient, target));
+        if is_opted_out {
+            log::info!("Skipping recipient {} who opted out", target.name);
+        }
+        !is_opted_out
+    });
+
+    // Send to the person's preferred recipient instead, if it can be sent to.
+    // Contacts' channels don't have preferred recipients, their contact's channel order is used instead,
+    // and a preferred recipient that's a contact's channel is swapped for the contact's first usable channel below
+    for recipient in recipient_repository.find_all_with_preferred_recipient()? {
+        let preferred_recipient = match &recipient.preferred_recipient_id {
+            Some(id) => recipient_repository.find_one_by_id(id)?,
+            None => None,
+        };
+        let preferred_recipient = match preferred_recipient {
+            Some(preferred_recipient)
+                if preferred_recipient.deleted_datetime.is_none()
+                    && preferred_recipient.deactivated_datetime.is_none()
+                    && preferred_recipient.undeliverable_datetime.is_none() =>
+            {
+                preferred_recipient
+            }
+            _ => continue,
+        };
+        for target in notification_targets.iter_mut() {
+            if is_recipient(&recipient, target) {
+                *target = NotificationTarget::from(preferred_recipient.clone());
+            }
+        }
+    }
+
+    // A recipient that's one of a contact's channels is sent to on the contact's first usable channel
+    for target in notification_targets.iter_mut() {
+        let contact_id = match recipient_repository
+            .find_one_by_to_address_and_type(&target.to_address, target.notification_type.clone())?
+            .and_then(|recipient| recipient.contact_id)
+        {
+            Some(contact_id) => contact_id,
+            None => continue,
+        };
+        if let Some(channel) = first_usable_channel(&ctx.connection, &contact_id, &priority)? {
+            *target = NotificationTarget::from(channel);
+        }
+    }
+
+    // A person on a list under several of their addresses only gets the notification once
+    let mut unique_targets: Vec<NotificationTarget> = Vec::new();
+    for target in notification_targets {
+        if !unique_targets.iter().any(|unique| {
+            unique.notification_type == target.notification_type
+                && unique.to_address == target.to_address
+        }) {
+            unique_targets.push(target);
+        }
+    }
+    let mut notification_targets = unique_targets;
+
+    // Skip deactivated recipients, and muted recipients unless the notification is critical
+    let mut skipped_recipients = recipient_repository.find_all_deactivated()?;
+    if priority != NotificationPriority::Critical {
+        skipped_recipients.extend(recipient_repository.find_all_muted(Utc::now().naive_utc())?);
+    }
+    notification_targets.retain(|target| {
+        let is_skipped = skipped_recipients.iter().any(|recipient| {
+            recipient.notification_type == target.notification_type
+                && recipient.to_address == target.to_address
+        });
+        if is_skipped {
+            log::info!("Skipping muted or deactivated recipient {}", target.name);
+        }
+        !is_skipped
+    });
+
     Ok(notification_targets)
 }
 
@@ -88,7 +214,8 @@ mod tests {
 
     use repository::{
         mock::{
-            mock_recipient_a, mock_recipient_b, mock_recipient_list_with_recipient_members_a_and_b,
+            mock_coldchain_notification_config_a, mock_recipient_a, mock_recipient_b,
+            mock_recipient_c, mock_recipient_list_with_recipient_members_a_and_b,
             mock_sql_recipient_list_with_no_param, mock_sql_recipient_list_with_param,
             MockDataInserts,
         },
@@ -97,7 +224,12 @@ mod tests {
     };
     use util::uuid::uuid;
 
-    use crate::{service_provider::ServiceProvider, test_utils::get_test_settings};
+    use crate::{
+        contact::{channels::SetContactChannels, create::CreateContact},
+        recipient::{preferences::RecipientPreferences, ModifyRecipientError},
+        service_provider::ServiceProvider,
+        test_utils::get_test_settings,
+    };
 
     use super::*;
 
@@ -138,8 +270,13 @@ mod tests {
         let parameters = serde_json::Value::Null;
 
         // Call the function being tested
-        let notification_targets =
-            get_notification_targets(&context, &notification_config, parameters.clone()).unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            parameters.clone(),
+            NotificationPriority::Normal,
+        )
+        .unwrap();
 
         // Check that the correct recipients were returned
         assert_eq!(notification_targets.len(), 2); // Recipient A & B
@@ -158,8 +295,13 @@ mod tests {
         };
 
         // Call the function being tested
-        let notification_targets =
-            get_notification_targets(&context, &notification_config, parameters.clone()).unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            parameters.clone(),
+            NotificationPriority::Normal,
+        )
+        .unwrap();
 
         // Check that the correct recipients were returned
         assert_eq!(notification_targets.len(), 2); // Recipient A & B
@@ -178,14 +320,389 @@ mod tests {
         };
 
         // Call the function being tested
-        let notification_targets =
-            get_notification_targets(&context, &notification_config, parameters).unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            parameters,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
 
         // Check that the correct recipients were returned
         assert_eq!(notification_targets.len(), 2); // Recipient A & B
 
         assert!(notification_targets.contains(&NotificationTarget::from(recipient1.clone())));
         assert!(notification_targets.contains(&NotificationTarget::from(recipient2.clone())));
+
+        // 4. Check muted recipients are skipped, until their mute expires
+        let recipient_repository = RecipientRowRepository::new(&context.connection);
+        let now = Utc::now().naive_utc();
+        recipient_repository
+            .set_muted_until(&recipient1.id, Some(now + chrono::Duration::hours(1)))
+            .unwrap();
+        recipient_repository
+            .set_muted_until(&recipient2.id, Some(now - chrono::Duration::hours(1)))
+            .unwrap();
+
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(notification_targets.len(), 1); // Recipient B
+        assert!(notification_targets.contains(&NotificationTarget::from(recipient2.clone())));
+
+        // Muted recipients still get critical notifications
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Critical,
+        )
+        .unwrap();
+        assert_eq!(notification_targets.len(), 2); // Recipient A & B
+
+        // 5. Check deactivated recipients are skipped
+        recipient_repository
+            .set_deactivated_datetime(&recipient2.id, Some(now))
+            .unwrap();
+
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(notification_targets.len(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_get_notification_targets_preferences() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_get_notification_targets_preferences",
+            MockDataInserts::none().recipients().notification_configs(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let recipient_service = &context.service_provider.recipient_service;
+
+        let (recipient_a, recipient_b, recipient_c) =
+            (mock_recipient_a(), mock_recipient_b(), mock_recipient_c());
+        let notification_config = NotificationConfig {
+            id: mock_coldchain_notification_config_a().id,
+            recipient_ids: vec![recipient_a.id.clone(), recipient_b.id.clone()],
+            status: NotificationConfigStatus::Enabled,
+            ..Default::default()
+        };
+
+        // 1. Recipients that opted out of the config are skipped
+        recipient_service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient_a.id.clone(),
+                    opted_out_notification_config_ids: vec![notification_config.id.clone()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_b.clone())]
+        );
+
+        // 2. Notifications go to the preferred recipient instead
+        recipient_service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient_b.id.clone(),
+                    preferred_recipient_id: Some(recipient_c.id.clone()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_c.clone())]
+        );
+
+        // 3. Unless the preferred recipient can't be sent to
+        RecipientRowRepository::new(&context.connection)
+            .set_deactivated_datetime(&recipient_c.id, Some(Utc::now().naive_utc()))
+            .unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_b)]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_get_notification_targets_contacts() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_get_notification_targets_contacts",
+            MockDataInserts::none().recipients().notification_configs(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let contact_service = &context.service_provider.contact_service;
+
+        let (recipient_a, recipient_c) = (mock_recipient_a(), mock_recipient_c());
+        contact_service
+            .create_contact(
+                &context,
+                CreateContact {
+                    id: "contact_nurse".to_string(),
+                    name: "Nurse".to_string(),
+                },
+            )
+            .unwrap();
+        contact_service
+            .set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "contact_nurse".to_string(),
+                    recipient_ids: vec![recipient_c.id.clone(), recipient_a.id.clone()],
+                },
+            )
+            .unwrap();
+        let notification_config = NotificationConfig {
+            id: mock_coldchain_notification_config_a().id,
+            recipient_ids: vec![recipient_a.id.clone()],
+            contact_ids: vec!["contact_nurse".to_string()],
+            status: NotificationConfigStatus::Enabled,
+            ..Default::default()
+        };
+
+        // 1. The contact only gets the notification once, on their first channel
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_c.clone())]
+        );
+
+        // 2. Muted channels are skipped, unless the notification is critical
+        let recipient_repository = RecipientRowRepository::new(&context.connection);
+        recipient_repository
+            .set_muted_until(
+                &recipient_c.id,
+                Some(Utc::now().naive_utc() + chrono::Duration::hours(1)),
+            )
+            .unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_a.clone())]
+        );
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Critical,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_c.clone())]
+        );
+        recipient_repository
+            .set_muted_until(&recipient_c.id, None)
+            .unwrap();
+
+        // 3. Channels that can't be sent to are skipped
+        recipient_repository
+            .set_undeliverable(
+                &recipient_c.id,
+                Some((Utc::now().naive_utc(), "Mailbox not found".to_string())),
+            )
+            .unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_a.clone())]
+        );
+
+        // 4. Opting out on any of the contact's channels opts the contact out
+        context
+            .service_provider
+            .recipient_service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient_c.id.clone(),
+                    opted_out_notification_config_ids: vec![notification_config.id.clone()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert!(notification_targets.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_get_notification_targets_contacts_and_preferred_recipients() {
+        let (_, _, connection_manager, _) = setup_all(
+            "test_get_notification_targets_contacts_and_preferred_recipients",
+            MockDataInserts::none().recipients().notification_configs(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let contact_service = &context.service_provider.contact_service;
+        let recipient_service = &context.service_provider.recipient_service;
+
+        let (recipient_a, recipient_b, recipient_c) =
+            (mock_recipient_a(), mock_recipient_b(), mock_recipient_c());
+        recipient_service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient_a.id.clone(),
+                    preferred_recipient_id: Some(recipient_b.id.clone()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        contact_service
+            .create_contact(
+                &context,
+                CreateContact {
+                    id: "contact_nurse".to_string(),
+                    name: "Nurse".to_string(),
+                },
+            )
+            .unwrap();
+        contact_service
+            .set_contact_channels(
+                &context,
+                SetContactChannels {
+                    contact_id: "contact_nurse".to_string(),
+                    recipient_ids: vec![recipient_c.id.clone(), recipient_a.id.clone()],
+                },
+            )
+            .unwrap();
+
+        // 1. Adding a recipient to a contact clears its preferred recipient, the contact's channel order is used instead
+        let preferences = recipient_service
+            .get_recipient_preferences(&context, &recipient_a.id)
+            .unwrap();
+        assert_eq!(preferences.preferred_recipient_id, None);
+        let notification_config = NotificationConfig {
+            id: mock_coldchain_notification_config_a().id,
+            recipient_ids: vec![recipient_a.id.clone()],
+            status: NotificationConfigStatus::Enabled,
+            ..Default::default()
+        };
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_c.clone())]
+        );
+
+        // 2. A contact's channel can't have a preferred recipient
+        assert!(matches!(
+            recipient_service.set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient_a.id.clone(),
+                    preferred_recipient_id: Some(recipient_b.id.clone()),
+                    ..Default::default()
+                },
+            ),
+            Err(ModifyRecipientError::GenericError(_))
+        ));
+
+        // 3. A preferred recipient that's a contact's channel is sent to in the contact's channel order
+        recipient_service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: recipient_b.id.clone(),
+                    preferred_recipient_id: Some(recipient_a.id.clone()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let notification_config = NotificationConfig {
+            recipient_ids: vec![recipient_b.id.clone()],
+            ..notification_config
+        };
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            serde_json::Value::Null,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            notification_targets,
+            vec![NotificationTarget::from(recipient_c.clone())]
+        );
     }
 
     // Test SQL Recipients
@@ -228,8 +745,13 @@ mod tests {
         };
 
         // Call the function being tested
-        let notification_targets =
-            get_notification_targets(&context, &notification_config, parameters).unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            parameters,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
 
         // Check that the correct recipients were returned
         assert_eq!(notification_targets.len(), 1);
@@ -259,8 +781,13 @@ mod tests {
         };
 
         // Call the function being tested
-        let notification_targets =
-            get_notification_targets(&context, &notification_config, parameters).unwrap();
+        let notification_targets = get_notification_targets(
+            &context,
+            &notification_config,
+            parameters,
+            NotificationPriority::Normal,
+        )
+        .unwrap();
 
         // Check that the correct recipients were returned
         assert_eq!(notification_targets.len(), 1);
@@ -9,7 +9,8 @@ mod notification_config_query_test {
         NotificationConfigSortField,
     };
     use repository::{
-        EqualFilter, NotificationConfigRow, NotificationConfigRowRepository, PaginationOption, Sort, NotificationConfigStatus,
+        EqualFilter, NotificationConfigRow, NotificationConfigRowRepository,
+        NotificationConfigStatus, PaginationOption, Sort,
     };
     use util::uuid::uuid;
 
@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod notification_config_update_tests {
+    use crate::email::layout::EmailBranding;
     use crate::notification_config::{
         update::UpdateNotificationConfig, ModifyNotificationConfigError,
     };
@@ -10,6 +11,7 @@ mod notification_config_update_tests {
         mock::{mock_coldchain_notification_config_a, MockDataInserts},
         test_db::setup_all,
     };
+    use std::collections::HashMap;
     use std::sync::Arc;
 
     #[actix_rt::test]
@@ -41,6 +43,105 @@ mod notification_config_update_tests {
         );
     }
 
+    #[actix_rt::test]
+    async fn notification_config_service_update_sender_profile() {
+        let (_, _, connection_manager, _) = setup_all(
+            "notification_config_service_update_sender_profile",
+            MockDataInserts::none().notification_configs(),
+        )
+        .await;
+
+        let mut settings = get_test_settings("");
+        settings.mail.sender_profiles =
+            HashMap::from([("alerts".to_string(), "alerts@example.com".to_string())]);
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.notification_config_service;
+        let update = |sender_profile: &str| {
+            service.update_notification_config(
+                &context,
+                UpdateNotificationConfig {
+                    id: mock_coldchain_notification_config_a().id.clone(),
+                    sender_profile: Some(sender_profile.to_string()),
+                    ..Default::default()
+                },
+            )
+        };
+
+        // Profiles have to be in the mail settings
+        assert!(matches!(
+            update("reports"),
+            Err(ModifyNotificationConfigError::BadUserInput(_))
+        ));
+
+        let updated_notification_config = update("alerts").unwrap();
+        assert_eq!(
+            updated_notification_config.sender_profile,
+            Some("alerts".to_string())
+        );
+
+        // An empty profile goes back to the default from address
+        let updated_notification_config = update("").unwrap();
+        assert_eq!(updated_notification_config.sender_profile, None);
+    }
+
+    #[actix_rt::test]
+    async fn notification_config_service_update_email_layout() {
+        let (_, _, connection_manager, _) = setup_all(
+            "notification_config_service_update_email_layout",
+            MockDataInserts::none().notification_configs(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.notification_config_service;
+        let update = |email_layout: EmailBranding| {
+            service.update_notification_config(
+                &context,
+                UpdateNotificationConfig {
+                    id: mock_coldchain_notification_config_a().id.clone(),
+                    email_layout: Some(email_layout),
+                    ..Default::default()
+                },
+            )
+        };
+
+        // Colours have to be hex colours
+        assert!(matches!(
+            update(EmailBranding {
+                primary_colour: Some("red".to_string()),
+                ..Default::default()
+            }),
+            Err(ModifyNotificationConfigError::BadUserInput(_))
+        ));
+
+        let updated_notification_config = update(EmailBranding {
+            primary_colour: Some("#123456".to_string()),
+            footer_text: Some("".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            updated_notification_config.email_layout,
+            Some(EmailBranding {
+                primary_colour: Some("#123456".to_string()),
+                ..Default::default()
+            })
+        );
+
+        // Empty values go back to the mail layout settings
+        let updated_notification_config = update(EmailBranding {
+            primary_colour: Some("".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(updated_notification_config.email_layout, None);
+    }
+
     #[actix_rt::test]
     async fn notification_config_service_update_success() {
         let (_, _, connection_manager, _) = setup_all(
@@ -1,13 +1,17 @@
 use super::{
     query::{get_notification_config, NotificationConfig},
-    validate::check_notification_config_exists,
+    validate::{check_notification_config_exists, check_sender_profile_exists},
     ModifyNotificationConfigError,
 };
-use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use crate::{
+    audit_log::audit_log_entry, email::layout::EmailBranding,
+    recipient_list::validate::check_recipient_list_exists, service_provider::ServiceContext,
+    settings::MailSettings,
+};
 use chrono::Utc;
 use repository::{
     LogType, NotificationConfigRow, NotificationConfigRowRepository, NotificationConfigStatus,
-    StorageConnection,
+    NotificationPriority, StorageConnection,
 };
 
 #[derive(Clone, Default)]
@@ -22,6 +26,15 @@ pub struct UpdateNotificationConfig {
     pub recipient_list_ids: Option<Vec<String>>,
     pub sql_recipient_list_ids: Option<Vec<String>>,
     pub next_due_datetime: Option<chrono::NaiveDateTime>,
+    /// An empty string clears the sender profile, so emails are sent from the default from address
+    pub sender_profile: Option<String>,
+    /// Replaces the config's email layout branding, empty values use the mail layout settings
+    pub email_layout: Option<EmailBranding>,
+    pub contact_ids: Option<Vec<String>>,
+    /// An empty string clears the fallback recipient list
+    pub fallback_recipient_list_id: Option<String>,
+    /// `Some(None)` clears the priority, so the plugin chooses each notification's priority again
+    pub priority: Option<Option<NotificationPriority>>,
 }
 
 pub fn update_notification_config(
@@ -31,7 +44,11 @@ pub fn update_notification_config(
     let notification_config = ctx
         .connection
         .transaction_sync(|connection| {
-            let notification_config_row = validate(connection, &updated_notification_config)?;
+            let notification_config_row = validate(
+                connection,
+                &ctx.service_provider.settings.mail,
+                &updated_notification_config,
+            )?;
             let updated_notification_config_row =
                 generate(updated_notification_config.clone(), notification_config_row)?;
             NotificationConfigRowRepository::new(connection)
@@ -54,6 +71,7 @@ pub fn update_notification_config(
 
 pub fn validate(
     connection: &StorageConnection,
+    mail_settings: &MailSettings,
     new_notification_config: &UpdateNotificationConfig,
 ) -> Result<NotificationConfigRow, ModifyNotificationConfigError> {
     let notification_config_row =
@@ -62,6 +80,34 @@ pub fn validate(
             None => return Err(ModifyNotificationConfigError::NotificationConfigDoesNotExist),
         };
 
+    if let Some(sender_profile) = &new_notification_config.sender_profile {
+        if !check_sender_profile_exists(sender_profile, mail_settings) {
+            return Err(ModifyNotificationConfigError::BadUserInput(format!(
+                "Sender profile {} is not configured",
+                sender_profile
+            )));
+        }
+    }
+
+    if let Some(fallback_recipient_list_id) = &new_notification_config.fallback_recipient_list_id {
+        if !fallback_recipient_list_id.is_empty()
+            && check_recipient_list_exists(fallback_recipient_list_id, connection)?.is_none()
+        {
+            return Err(ModifyNotificationConfigError::BadUserInput(format!(
+                "Fallback recipient list {} does not exist",
+                fallback_recipient_list_id
+            )));
+        }
+    }
+
+    if let Some(email_layout) = &new_notification_config.email_layout {
+        email_layout
+            .clone()
+            .non_empty()
+            .validate()
+            .map_err(ModifyNotificationConfigError::BadUserInput)?;
+    }
+
     Ok(notification_config_row)
 }
 
@@ -77,6 +123,11 @@ pub fn generate(
         recipient_list_ids,
         sql_recipient_list_ids,
         next_due_datetime,
+        sender_profile,
+        email_layout,
+        contact_ids,
+        fallback_recipient_list_id,
+        priority,
     }: UpdateNotificationConfig,
     current_notification_config_row: NotificationConfigRow,
 ) -> Result<NotificationConfigRow, ModifyNotificationConfigError> {
@@ -123,6 +174,46 @@ pub fn generate(
         new_notification_config_row.sql_recipient_list_ids = recipient_json;
     }
 
+    if let Some(contact_ids) = contact_ids {
+        let contact_json = serde_json::to_string(&contact_ids).map_err(|_| {
+            ModifyNotificationConfigError::BadUserInput(
+                "Could not convert contacts to JSON".to_string(),
+            )
+        })?;
+        new_notification_config_row.contact_ids = contact_json;
+    }
+
+    if let Some(sender_profile) = sender_profile {
+        new_notification_config_row.sender_profile = match sender_profile.is_empty() {
+            true => None,
+            false => Some(sender_profile),
+        };
+    }
+
+    if let Some(fallback_recipient_list_id) = fallback_recipient_list_id {
+        new_notification_config_row.fallback_recipient_list_id =
+            match fallback_recipient_list_id.is_empty() {
+                true => None,
+                false => Some(fallback_recipient_list_id),
+            };
+    }
+
+    if let Some(priority) = priority {
+        new_notification_config_row.priority = priority;
+    }
+
+    if let Some(email_layout) = email_layout {
+        let email_layout = email_layout.non_empty();
+        new_notification_config_row.email_layout = match email_layout.is_empty() {
+            true => None,
+            false => Some(serde_json::to_string(&email_layout).map_err(|_| {
+                ModifyNotificationConfigError::BadUserInput(
+                    "Could not convert email layout to JSON".to_string(),
+                )
+            })?),
+        };
+    }
+
     // Note: We usually reset the next check datetime in case the schedule has changed, or something needs to be recalculated
     new_notification_config_row.next_due_datetime = next_due_datetime;
 
@@ -1,3 +1,4 @@
+use crate::settings::MailSettings;
 use repository::{
     NotificationConfigRow, NotificationConfigRowRepository, RepositoryError, StorageConnection,
 };
@@ -17,3 +18,8 @@ pub fn check_notification_config_does_not_exist(
 
     Ok(notification_config.is_none())
 }
+
+/// An empty sender profile is allowed, it means the default from address
+pub fn check_sender_profile_exists(sender_profile: &str, mail_settings: &MailSettings) -> bool {
+    sender_profile.is_empty() || mail_settings.sender_profiles.contains_key(sender_profile)
+}
@@ -110,6 +110,15 @@ mod notification_event_query_test {
         };
         repo.insert_one(&notification_event).unwrap();
 
+        // System emails' messages aren't searched as they can contain password reset links
+        let notification_event = NotificationEventRow {
+            id: "id5-system-email".to_string(),
+            message: searched_string.clone(),
+            html_message: Some(searched_string.clone()),
+            ..Default::default()
+        };
+        repo.insert_one(&notification_event).unwrap();
+
         // Query to find the new records
         let db_notification_events = service
             .get_notification_events(
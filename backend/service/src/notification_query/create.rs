@@ -8,12 +8,13 @@ use super::{
     ModifyNotificationQueryError,
 };
 use crate::audit_log::audit_log_entry;
+use crate::datasource::{check_datasource_id_is_valid, datasource_id_from_input};
 use crate::service_provider::ServiceContext;
 
 use chrono::Utc;
 use repository::{
-    LogType, NotificationQuery, NotificationQueryRow, NotificationQueryRowRepository,
-    StorageConnection,
+    AttachmentFormat, LogType, NotificationQuery, NotificationQueryRow,
+    NotificationQueryRowRepository, StorageConnection,
 };
 
 #[derive(Clone, Default)]
@@ -21,12 +22,23 @@ pub struct CreateNotificationQuery {
     pub id: String,
     pub name: String,
     pub reference_name: String,
+    /// Named datasource to run the query against, None or an empty string uses the default datasource
+    pub datasource_id: Option<String>,
 }
 
 pub fn create_notification_query(
     ctx: &ServiceContext,
     new_notification_query: CreateNotificationQuery,
 ) -> Result<NotificationQuery, ModifyNotificationQueryError> {
+    if let Some(datasource_id) = &new_notification_query.datasource_id {
+        if !check_datasource_id_is_valid(ctx, datasource_id) {
+            return Err(ModifyNotificationQueryError::BadUserInput(format!(
+                "No datasource configured with id: {}",
+                datasource_id
+            )));
+        }
+    }
+
     let notification_query = ctx
         .connection
         .transaction_sync(|connection| {
@@ -91,6 +103,7 @@ pub fn generate(
         id,
         name,
         reference_name,
+        datasource_id,
     }: CreateNotificationQuery,
 ) -> Result<NotificationQueryRow, ModifyNotificationQueryError> {
     Ok(NotificationQueryRow {
@@ -102,5 +115,10 @@ pub fn generate(
         required_parameters: "[]".to_string(),
         created_at: Utc::now().naive_utc(),
         updated_at: Utc::now().naive_utc(),
+        timeout_seconds: None,
+        max_rows: None,
+        max_result_bytes: None,
+        datasource_id: datasource_id.and_then(datasource_id_from_input),
+        attachment_format: AttachmentFormat::None,
     })
 }
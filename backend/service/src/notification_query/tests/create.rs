@@ -104,6 +104,22 @@ mod notification_query_create_test {
             ),
             Err(ModifyNotificationQueryError::InvalidNotificationQueryName)
         );
+
+        // Trying to use a datasource that isn't configured should fail
+        assert_eq!(
+            service.create_notification_query(
+                &context,
+                CreateNotificationQuery {
+                    id: "some-new-id".to_string(),
+                    name: "new_notification_query".to_string(),
+                    datasource_id: Some("not_configured".to_string()),
+                    ..Default::default()
+                },
+            ),
+            Err(ModifyNotificationQueryError::BadUserInput(
+                "No datasource configured with id: not_configured".to_string()
+            ))
+        );
     }
 
     #[actix_rt::test]
@@ -116,10 +132,11 @@ mod notification_query_create_test {
 
         let connection = connection_manager.connection().unwrap();
         let notification_query_row_repository = NotificationQueryRowRepository::new(&connection);
-        let service_provider = Arc::new(ServiceProvider::new(
-            connection_manager,
-            get_test_settings(""),
-        ));
+        let mut settings = get_test_settings("");
+        settings
+            .datasources
+            .insert("country_a".to_string(), settings.datasource.clone());
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
         let context = ServiceContext::as_server_admin(service_provider).unwrap();
         let service = &context.service_provider.notification_query_service;
 
@@ -142,5 +159,26 @@ mod notification_query_create_test {
 
         // NotificationQuery now exists
         assert_eq!(result.name, "new_notification_query");
+        assert_eq!(result.datasource_id, None);
+
+        // Create a query for a named datasource
+        let country_query_id = uuid();
+        service
+            .create_notification_query(
+                &context,
+                CreateNotificationQuery {
+                    id: country_query_id.clone(),
+                    name: "country_a_query".to_string(),
+                    reference_name: "country_a_query".to_string(),
+                    datasource_id: Some("country_a".to_string()),
+                },
+            )
+            .unwrap();
+
+        let result = notification_query_row_repository
+            .find_one_by_id(&country_query_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.datasource_id, Some("country_a".to_string()));
     }
 }
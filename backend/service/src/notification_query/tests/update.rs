@@ -127,6 +127,36 @@ mod notification_query_update_tests {
             ),
             Err(ModifyNotificationQueryError::InvalidNotificationQueryName)
         );
+
+        // Trying to set a query limit that isn't positive should fail
+        assert_eq!(
+            service.update_notification_query(
+                &context,
+                UpdateNotificationQuery {
+                    id: id1.clone(),
+                    max_rows: Some(Some(0)),
+                    ..Default::default()
+                },
+            ),
+            Err(ModifyNotificationQueryError::BadUserInput(
+                "max_rows must be greater than 0".to_string()
+            ))
+        );
+
+        // Trying to use a datasource that isn't configured should fail
+        assert_eq!(
+            service.update_notification_query(
+                &context,
+                UpdateNotificationQuery {
+                    id: id1.clone(),
+                    datasource_id: Some("not_configured".to_string()),
+                    ..Default::default()
+                },
+            ),
+            Err(ModifyNotificationQueryError::BadUserInput(
+                "No datasource configured with id: not_configured".to_string()
+            ))
+        );
     }
     #[actix_rt::test]
     async fn notification_query_service_update_success() {
@@ -136,10 +166,11 @@ mod notification_query_update_tests {
         )
         .await;
 
-        let service_provider = Arc::new(ServiceProvider::new(
-            connection_manager,
-            get_test_settings(""),
-        ));
+        let mut settings = get_test_settings("");
+        settings
+            .datasources
+            .insert("country_a".to_string(), settings.datasource.clone());
+        let service_provider = Arc::new(ServiceProvider::new(connection_manager, settings));
         let context = ServiceContext::as_server_admin(service_provider).unwrap();
 
         // Create a notification_query to update
@@ -195,5 +226,76 @@ mod notification_query_update_tests {
             updated_notification_query.description,
             "A nice new description".to_string()
         );
+
+        // Update query limits
+        let updated_notification_query = context
+            .service_provider
+            .notification_query_service
+            .update_notification_query(
+                &context,
+                UpdateNotificationQuery {
+                    id: "id1".to_string(),
+                    timeout_seconds: Some(Some(5)),
+                    max_rows: Some(Some(100)),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(updated_notification_query.timeout_seconds, Some(5));
+        assert_eq!(updated_notification_query.max_rows, Some(100));
+        assert_eq!(updated_notification_query.max_result_bytes, None);
+
+        // Clear a limit to go back to the datasource's default
+        let updated_notification_query = context
+            .service_provider
+            .notification_query_service
+            .update_notification_query(
+                &context,
+                UpdateNotificationQuery {
+                    id: "id1".to_string(),
+                    timeout_seconds: Some(None),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(updated_notification_query.timeout_seconds, None);
+        assert_eq!(updated_notification_query.max_rows, Some(100));
+
+        // Update datasource, surrounding whitespace is trimmed before it's checked and saved
+        let updated_notification_query = context
+            .service_provider
+            .notification_query_service
+            .update_notification_query(
+                &context,
+                UpdateNotificationQuery {
+                    id: "id1".to_string(),
+                    datasource_id: Some(" country_a ".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            updated_notification_query.datasource_id,
+            Some("country_a".to_string())
+        );
+
+        // An empty datasource_id goes back to the default datasource
+        let updated_notification_query = context
+            .service_provider
+            .notification_query_service
+            .update_notification_query(
+                &context,
+                UpdateNotificationQuery {
+                    id: "id1".to_string(),
+                    datasource_id: Some("".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(updated_notification_query.datasource_id, None);
     }
 }
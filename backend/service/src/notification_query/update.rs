@@ -3,15 +3,19 @@ use super::{
     validate::{
         check_list_name_doesnt_contain_special_characters, check_list_name_is_appropriate_length,
         check_notification_query_exists, check_notification_query_name_is_unique,
-        check_notification_query_reference_name_is_unique,
+        check_notification_query_reference_name_is_unique, check_query_limit_is_valid,
     },
     ModifyNotificationQueryError,
 };
-use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use crate::{
+    audit_log::audit_log_entry,
+    datasource::{check_datasource_id_is_valid, datasource_id_from_input},
+    service_provider::ServiceContext,
+};
 use chrono::Utc;
 use repository::{
-    LogType, NotificationQuery, NotificationQueryRow, NotificationQueryRowRepository,
-    StorageConnection,
+    AttachmentFormat, LogType, NotificationQuery, NotificationQueryRow,
+    NotificationQueryRowRepository, StorageConnection,
 };
 
 #[derive(Clone, Default)]
@@ -22,12 +26,28 @@ pub struct UpdateNotificationQuery {
     pub description: Option<String>,
     pub query: Option<String>,
     pub required_parameters: Option<Vec<String>>,
+    /// `Some(None)` clears the limit so the datasource's default is used
+    pub timeout_seconds: Option<Option<i32>>,
+    pub max_rows: Option<Option<i32>>,
+    pub max_result_bytes: Option<Option<i32>>,
+    /// An empty string resets the query to use the default datasource
+    pub datasource_id: Option<String>,
+    pub attachment_format: Option<AttachmentFormat>,
 }
 
 pub fn update_notification_query(
     ctx: &ServiceContext,
     updated_notification_query: UpdateNotificationQuery,
 ) -> Result<NotificationQuery, ModifyNotificationQueryError> {
+    if let Some(datasource_id) = &updated_notification_query.datasource_id {
+        if !check_datasource_id_is_valid(ctx, datasource_id) {
+            return Err(ModifyNotificationQueryError::BadUserInput(format!(
+                "No datasource configured with id: {}",
+                datasource_id
+            )));
+        }
+    }
+
     let notification_query = ctx
         .connection
         .transaction_sync(|connection| {
@@ -66,6 +86,19 @@ pub fn validate(
         }
     }
 
+    for (limit_name, limit) in [
+        ("timeout_seconds", new_notification_query.timeout_seconds),
+        ("max_rows", new_notification_query.max_rows),
+        ("max_result_bytes", new_notification_query.max_result_bytes),
+    ] {
+        if !check_query_limit_is_valid(limit.flatten()) {
+            return Err(ModifyNotificationQueryError::BadUserInput(format!(
+                "{} must be greater than 0",
+                limit_name
+            )));
+        }
+    }
+
     let notification_query_row =
         match check_notification_query_exists(&new_notification_query.id, connection)? {
             Some(notification_query_row) => notification_query_row,
@@ -99,6 +132,11 @@ pub fn generate(
         description,
         query,
         required_parameters,
+        timeout_seconds,
+        max_rows,
+        max_result_bytes,
+        datasource_id,
+        attachment_format,
     }: UpdateNotificationQuery,
     current_notification_query_row: NotificationQueryRow,
 ) -> Result<NotificationQueryRow, ModifyNotificationQueryError> {
@@ -132,6 +170,21 @@ pub fn generate(
 
         new_notification_query_row.required_parameters = json_parameters;
     }
+    if let Some(timeout_seconds) = timeout_seconds {
+        new_notification_query_row.timeout_seconds = timeout_seconds;
+    }
+    if let Some(max_rows) = max_rows {
+        new_notification_query_row.max_rows = max_rows;
+    }
+    if let Some(max_result_bytes) = max_result_bytes {
+        new_notification_query_row.max_result_bytes = max_result_bytes;
+    }
+    if let Some(datasource_id) = datasource_id {
+        new_notification_query_row.datasource_id = datasource_id_from_input(datasource_id);
+    }
+    if let Some(attachment_format) = attachment_format {
+        new_notification_query_row.attachment_format = attachment_format;
+    }
 
     Ok(new_notification_query_row)
 }
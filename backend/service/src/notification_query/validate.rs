@@ -64,6 +64,13 @@ pub fn check_notification_query_reference_name_is_unique(
     Ok(notification_queries.is_empty())
 }
 
+pub fn check_query_limit_is_valid(limit: Option<i32>) -> bool {
+    match limit {
+        Some(limit) => limit > 0,
+        None => true,
+    }
+}
+
 // TODO: Refactor as part of https://github.com/openmsupply/notify/issues/140
 pub fn check_list_name_is_appropriate_length(name: &str) -> Result<bool, RepositoryError> {
     Ok(name.trim().len() >= 3 && name.len() <= 70)
@@ -1,8 +1,20 @@
-use crate::service_provider::ServiceContext;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{notification_config::query::NotificationConfig, service_provider::ServiceContext};
 
 #[derive(Debug)]
 pub enum PluginError {
     UnableToProcessTick(String),
+    UnableToGetAlarms(String),
+}
+
+/// Something a plugin is currently alerting about, e.g. a sensor that's too hot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PluginAlarm {
+    pub name: String,
+    pub description: String,
+    pub since_utc: NaiveDateTime,
 }
 
 pub trait PluginTrait: Send + Sync {
@@ -16,4 +28,13 @@ pub trait PluginTrait: Send + Sync {
         // Plugins should process their work here
         Ok(())
     }
+
+    /// Current alarms for a notification config, plugins should return none for configs of other kinds
+    fn current_alarms(
+        &self,
+        _ctx: &ServiceContext,
+        _config: &NotificationConfig,
+    ) -> Result<Vec<PluginAlarm>, PluginError> {
+        Ok(vec![])
+    }
 }
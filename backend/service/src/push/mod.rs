@@ -0,0 +1,319 @@
+/*
+   Publishes notifications to a self hosted push server, for phones running the ntfy or Gotify app.
+   The recipient's to_address is the server url and topic, e.g. https://ntfy.example.com/on-call,
+   or a Gotify server's message url with an application token, e.g. https://gotify.example.com/message?token=XXXX
+   https://docs.ntfy.sh/publish/#publish-as-json
+   https://gotify.net/api-docs#/message/createMessage
+*/
+
+use std::time::Duration;
+
+use repository::NotificationPriority;
+use reqwest::{header::RETRY_AFTER, StatusCode, Url};
+use serde_json::{json, Value};
+
+const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+// Used if the server says we've sent too many messages without saying how long to wait
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+const GOTIFY_MESSAGE_PATH: &str = "/message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushPriority {
+    Min,
+    Low,
+    Default,
+    High,
+    Urgent,
+}
+
+impl PushPriority {
+    /// Cold chain alerts set alert_type in the template data, a High or Low temperature is urgent,
+    /// and sensors that have stopped sending data are high priority. Anything else gets the default priority.
+    pub fn from_context(context: Option<&str>) -> PushPriority {
+        let alert_type = context
+            .and_then(|context| serde_json::from_str::<Value>(context).ok())
+            .and_then(|context| context["alert_type"].as_str().map(str::to_string));
+        match alert_type.as_deref() {
+            Some("High") | Some("Low") => PushPriority::Urgent,
+            Some("NoData") => PushPriority::High,
+            _ => PushPriority::Default,
+        }
+    }
+
+    /// Critical notifications are always urgent and low priority ones are low, otherwise the priority comes from the context
+    pub fn from_notification(
+        priority: &NotificationPriority,
+        context: Option<&str>,
+    ) -> PushPriority {
+        match priority {
+            NotificationPriority::Critical => PushPriority::Urgent,
+            NotificationPriority::Low => PushPriority::Low,
+            NotificationPriority::Normal => PushPriority::from_context(context),
+        }
+    }
+
+    /// ntfy priorities go from 1 (min) to 5 (urgent)
+    pub fn ntfy(&self) -> u8 {
+        match self {
+            PushPriority::Min => 1,
+            PushPriority::Low => 2,
+            PushPriority::Default => 3,
+            PushPriority::High => 4,
+            PushPriority::Urgent => 5,
+        }
+    }
+
+    /// Gotify priorities go from 0 to 10, the android app only makes a sound from 4 and pops up from 8
+    pub fn gotify(&self) -> u8 {
+        match self {
+            PushPriority::Min => 0,
+            PushPriority::Low => 2,
+            PushPriority::Default => 5,
+            PushPriority::High => 8,
+            PushPriority::Urgent => 10,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PushClient {
+    http_client: reqwest::Client,
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    /// The server won't accept the message, e.g. the topic needs a token we don't have, retrying won't help
+    Permanent(String),
+    Temporary(String),
+    /// Too many messages have been sent to the server, try again after retry_after
+    RateLimited {
+        description: String,
+        retry_after: Duration,
+    },
+}
+
+impl PushError {
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, PushError::Permanent(_))
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            PushError::RateLimited { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for PushError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_builder() {
+            return PushError::Permanent(error.to_string());
+        }
+        PushError::Temporary(error.to_string())
+    }
+}
+
+impl Default for PushClient {
+    fn default() -> Self {
+        PushClient::new()
+    }
+}
+
+impl PushClient {
+    pub fn new() -> PushClient {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DEFAULT_REQUEST_TIMEOUT))
+            .build()
+            .expect("Something went unexpectedly wrong building the push reqwest client");
+        PushClient { http_client }
+    }
+
+    /// Publishes the title and markdown body to the ntfy topic or Gotify application
+    pub async fn send_message(
+        &self,
+        url: &str,
+        title: Option<&str>,
+        common_markdown: &str,
+        priority: PushPriority,
+    ) -> Result<(), PushError> {
+        let (publish_url, payload) = push_message(url, title, common_markdown, priority)?;
+
+        let response = self
+            .http_client
+            .post(publish_url)
+            .json(&payload)
+            .send()
+            .await?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let description = format!("{} - {}", status, response.text().await.unwrap_or_default());
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(PushError::RateLimited {
+                description,
+                retry_after: retry_after.unwrap_or(DEFAULT_RETRY_AFTER),
+            });
+        }
+        if status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT {
+            return Err(PushError::Temporary(description));
+        }
+        if !status.is_success() {
+            return Err(PushError::Permanent(description));
+        }
+        Ok(())
+    }
+}
+
+/// Works out where to post the message and builds the json for ntfy or Gotify
+pub fn push_message(
+    url: &str,
+    title: Option<&str>,
+    common_markdown: &str,
+    priority: PushPriority,
+) -> Result<(Url, Value), PushError> {
+    let mut url = Url::parse(url.trim())
+        .map_err(|e| PushError::Permanent(format!("Invalid push url {} - {}", url, e)))?;
+
+    if url
+        .path()
+        .trim_end_matches('/')
+        .ends_with(GOTIFY_MESSAGE_PATH)
+    {
+        let payload = json!({
+            "title": title,
+            "message": common_markdown,
+            "priority": priority.gotify(),
+            "extras": { "client::display": { "contentType": "text/markdown" } },
+        });
+        return Ok((url, payload));
+    }
+
+    // ntfy takes json posted to the server's root url, with the topic in the body
+    let topic = topic_from_url(&url)
+        .ok_or_else(|| PushError::Permanent(format!("No ntfy topic in the url {}", url)))?;
+    let path = url.path().trim_end_matches('/');
+    let root_path = path
+        .strip_suffix(topic.as_str())
+        .unwrap_or(path)
+        .to_string();
+    url.set_path(&root_path);
+    let mut payload = json!({
+        "topic": topic,
+        "message": common_markdown,
+        "priority": priority.ntfy(),
+        "markdown": true,
+    });
+    if let Some(title) = title {
+        payload["title"] = json!(title);
+    }
+    Ok((url, payload))
+}
+
+/// The last segment of the path is the ntfy topic
+pub fn topic_from_url(url: &Url) -> Option<String> {
+    url.path_segments()?
+        .rev()
+        .find(|segment| !segment.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chat_webhook::stub::WebhookStub;
+
+    use super::*;
+
+    #[test]
+    fn test_push_priority() {
+        let context = |alert_type: &str| format!(r#"{{"alert_type":"{}"}}"#, alert_type);
+        assert_eq!(
+            PushPriority::from_context(Some(&context("High"))),
+            PushPriority::Urgent
+        );
+        assert_eq!(
+            PushPriority::from_context(Some(&context("NoData"))),
+            PushPriority::High
+        );
+        assert_eq!(
+            PushPriority::from_context(Some(&context("Ok"))),
+            PushPriority::Default
+        );
+        assert_eq!(PushPriority::from_context(None), PushPriority::Default);
+        assert_eq!(
+            PushPriority::from_notification(&NotificationPriority::Critical, None),
+            PushPriority::Urgent
+        );
+        assert_eq!(
+            PushPriority::from_notification(&NotificationPriority::Low, Some(&context("NoData"))),
+            PushPriority::Low
+        );
+        assert_eq!(PushPriority::Urgent.ntfy(), 5);
+        assert_eq!(PushPriority::Urgent.gotify(), 10);
+    }
+
+    #[test]
+    fn test_push_message() {
+        let (url, payload) = push_message(
+            "https://ntfy.example.com/alerts/on-call?auth=abc",
+            Some("Sensor alert"),
+            "**Sensor** is too hot",
+            PushPriority::Urgent,
+        )
+        .unwrap();
+        assert_eq!(url.as_str(), "https://ntfy.example.com/alerts/?auth=abc");
+        assert_eq!(
+            payload,
+            json!({
+                "topic": "on-call",
+                "title": "Sensor alert",
+                "message": "**Sensor** is too hot",
+                "priority": 5,
+                "markdown": true,
+            })
+        );
+
+        let (url, payload) = push_message(
+            "https://gotify.example.com/message?token=abc",
+            None,
+            "**Sensor** is too hot",
+            PushPriority::High,
+        )
+        .unwrap();
+        assert_eq!(url.as_str(), "https://gotify.example.com/message?token=abc");
+        assert_eq!(payload["priority"], 8);
+        assert_eq!(payload["title"], Value::Null);
+
+        let result = push_message("https://ntfy.example.com/", None, "", PushPriority::Default);
+        assert!(result.unwrap_err().is_permanent());
+    }
+
+    #[actix_rt::test]
+    async fn test_send_push_message() {
+        let stub = WebhookStub::start();
+        let client = PushClient::new();
+        let url = stub.url("/on-call");
+        let send = || client.send_message(&url, None, "Sensor is too hot", PushPriority::Default);
+
+        send().await.unwrap();
+        let requests = stub.requests();
+        assert_eq!(requests[0].path, "/");
+        assert_eq!(requests[0].body["topic"], "on-call");
+
+        stub.push_response("/", 429, &[("Retry-After", "10")], "Too Many Requests");
+        let result = send().await;
+        assert_eq!(
+            result.unwrap_err().retry_after(),
+            Some(Duration::from_secs(10))
+        );
+
+        stub.push_response("/", 403, &[], "forbidden");
+        let result = send().await;
+        assert!(result.unwrap_err().is_permanent());
+    }
+}
@@ -1,6 +1,9 @@
 use super::{
     query::get_recipient,
-    validate::{check_recipient_does_not_exist, check_to_address_is_unique},
+    validate::{
+        check_recipient_does_not_exist, check_recipient_exists, check_to_address_is_unique,
+        check_to_address_is_valid, normalise_to_address,
+    },
     ModifyRecipientError,
 };
 use crate::audit_log::audit_log_entry;
@@ -8,7 +11,8 @@ use crate::service_provider::ServiceContext;
 
 use chrono::Utc;
 use repository::{
-    LogType, NotificationType, Recipient, RecipientRow, RecipientRowRepository, StorageConnection,
+    DigestFrequency, LogType, NotificationType, Recipient, RecipientRow, RecipientRowRepository,
+    StorageConnection,
 };
 
 #[derive(Clone)]
@@ -34,7 +38,14 @@ pub fn upsert_recipient(
                     new_recipient_row
                 }
                 Err(ModifyRecipientError::RecipientAlreadyExists) => {
-                    let new_recipient_row = generate(new_recipient.clone())?;
+                    // Keep the recipient's preferences, None values such as muted_until aren't updated
+                    let existing =
+                        check_recipient_exists(&new_recipient.id, connection)?.unwrap_or_default();
+                    let new_recipient_row = RecipientRow {
+                        digest_frequency: existing.digest_frequency,
+                        fallback_order: existing.fallback_order,
+                        ..generate(new_recipient.clone())?
+                    };
                     RecipientRowRepository::new(connection).update_one(&new_recipient_row)?;
                     new_recipient_row
                 }
@@ -88,6 +99,12 @@ pub fn validate(
     new_recipient: &CreateRecipient,
     connection: &StorageConnection,
 ) -> Result<(), ModifyRecipientError> {
+    if !check_to_address_is_valid(&new_recipient.to_address, &new_recipient.notification_type) {
+        return Err(ModifyRecipientError::InvalidToAddress(
+            new_recipient.to_address.clone(),
+        ));
+    }
+
     if !check_recipient_does_not_exist(&new_recipient.id, connection)? {
         return Err(ModifyRecipientError::RecipientAlreadyExists);
     }
@@ -114,9 +131,17 @@ pub fn generate(
 ) -> Result<RecipientRow, ModifyRecipientError> {
     Ok(RecipientRow {
         id,
-        notification_type,
         name: name.trim().to_string(),
-        to_address: to_address.trim().to_ascii_lowercase(),
+        to_address: normalise_to_address(&to_address, &notification_type),
+        notification_type,
         deleted_datetime: None,
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     })
 }
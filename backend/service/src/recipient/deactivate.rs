@@ -0,0 +1,38 @@
+use super::{query::get_recipient, validate::check_recipient_exists, ModifyRecipientError};
+use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use chrono::{NaiveDateTime, Utc};
+use repository::{LogType, Recipient, RecipientRowRepository};
+
+/// Flags a recipient that can't be sent to any more, e.g. after the bot is removed from a telegram chat.
+/// Notifications aren't sent to deactivated recipients, None reactivates the recipient.
+pub fn deactivate_recipient(
+    ctx: &ServiceContext,
+    recipient_id: &str,
+    deactivated_datetime: Option<NaiveDateTime>,
+) -> Result<Recipient, ModifyRecipientError> {
+    let recipient = ctx
+        .connection
+        .transaction_sync(|connection| {
+            if check_recipient_exists(recipient_id, connection)?.is_none() {
+                return Err(ModifyRecipientError::RecipientDoesNotExist);
+            }
+            RecipientRowRepository::new(connection)
+                .set_deactivated_datetime(recipient_id, deactivated_datetime)?;
+
+            get_recipient(ctx, recipient_id.to_string()).map_err(ModifyRecipientError::from)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    // Audit logging
+    let log_type = match deactivated_datetime {
+        Some(_) => LogType::RecipientDeactivated,
+        None => LogType::RecipientReactivated,
+    };
+    audit_log_entry(
+        ctx,
+        log_type,
+        Some(recipient_id.to_string()),
+        Utc::now().naive_utc(),
+    )?;
+    Ok(recipient)
+}
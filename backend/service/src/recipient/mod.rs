@@ -1,18 +1,25 @@
 use self::{
     create::{create_recipient, CreateRecipient},
+    deactivate::deactivate_recipient,
     delete::{delete_recipient, DeleteRecipientError},
+    mute::mute_recipient,
+    preferences::{get_recipient_preferences, set_recipient_preferences, RecipientPreferences},
     query::{get_recipient, get_recipients},
     update::{update_recipient, UpdateRecipient},
 };
 
 use super::{ListError, ListResult};
 use crate::{service_provider::ServiceContext, SingleRecordError};
+use chrono::NaiveDateTime;
 use repository::{PaginationOption, Recipient, RecipientFilter, RecipientSort, RepositoryError};
 
 mod tests;
 
 pub mod create;
+pub mod deactivate;
 pub mod delete;
+pub mod mute;
+pub mod preferences;
 pub mod query;
 pub mod telegram;
 pub mod update;
@@ -60,6 +67,40 @@ pub trait RecipientServiceTrait: Sync + Send {
     ) -> Result<Recipient, ModifyRecipientError> {
         update_recipient(ctx, input)
     }
+
+    fn mute_recipient(
+        &self,
+        ctx: &ServiceContext,
+        recipient_id: &str,
+        muted_until: Option<NaiveDateTime>,
+    ) -> Result<Recipient, ModifyRecipientError> {
+        mute_recipient(ctx, recipient_id, muted_until)
+    }
+
+    fn deactivate_recipient(
+        &self,
+        ctx: &ServiceContext,
+        recipient_id: &str,
+        deactivated_datetime: Option<NaiveDateTime>,
+    ) -> Result<Recipient, ModifyRecipientError> {
+        deactivate_recipient(ctx, recipient_id, deactivated_datetime)
+    }
+
+    fn get_recipient_preferences(
+        &self,
+        ctx: &ServiceContext,
+        recipient_id: &str,
+    ) -> Result<RecipientPreferences, ModifyRecipientError> {
+        get_recipient_preferences(&ctx.connection, recipient_id)
+    }
+
+    fn set_recipient_preferences(
+        &self,
+        ctx: &ServiceContext,
+        preferences: RecipientPreferences,
+    ) -> Result<Recipient, ModifyRecipientError> {
+        set_recipient_preferences(ctx, preferences)
+    }
 }
 
 pub struct RecipientService {}
@@ -71,6 +112,7 @@ pub enum ModifyRecipientError {
     ModifiedRecordNotFound,
     DatabaseError(RepositoryError),
     RecipientDoesNotExist,
+    InvalidToAddress(String),
     GenericError(String),
 }
 
@@ -0,0 +1,32 @@
+use super::{query::get_recipient, validate::check_recipient_exists, ModifyRecipientError};
+use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use chrono::{NaiveDateTime, Utc};
+use repository::{LogType, Recipient, RecipientRowRepository};
+
+/// Notifications, other than critical alerts, aren't sent to the recipient until `muted_until`, None unmutes the recipient
+pub fn mute_recipient(
+    ctx: &ServiceContext,
+    recipient_id: &str,
+    muted_until: Option<NaiveDateTime>,
+) -> Result<Recipient, ModifyRecipientError> {
+    let recipient = ctx
+        .connection
+        .transaction_sync(|connection| {
+            if check_recipient_exists(recipient_id, connection)?.is_none() {
+                return Err(ModifyRecipientError::RecipientDoesNotExist);
+            }
+            RecipientRowRepository::new(connection).set_muted_until(recipient_id, muted_until)?;
+
+            get_recipient(ctx, recipient_id.to_string()).map_err(ModifyRecipientError::from)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    // Audit logging
+    audit_log_entry(
+        ctx,
+        LogType::RecipientUpdated,
+        Some(recipient_id.to_string()),
+        Utc::now().naive_utc(),
+    )?;
+    Ok(recipient)
+}
@@ -0,0 +1,151 @@
+use super::{query::get_recipient, validate::check_recipient_exists, ModifyRecipientError};
+use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use chrono::Utc;
+use repository::{
+    DigestFrequency, LogType, NotificationConfigRowRepository, Recipient, RecipientOptOutRow,
+    RecipientOptOutRowRepository, RecipientRowRepository, RepositoryError, StorageConnection,
+};
+use util::uuid::uuid;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecipientPreferences {
+    pub recipient_id: String,
+    pub digest_frequency: DigestFrequency,
+    /// Another of the person's recipients that notifications are sent to instead of this one
+    pub preferred_recipient_id: Option<String>,
+    /// Notification configs the recipient doesn't receive
+    pub opted_out_notification_config_ids: Vec<String>,
+}
+
+pub fn get_recipient_preferences(
+    connection: &StorageConnection,
+    recipient_id: &str,
+) -> Result<RecipientPreferences, ModifyRecipientError> {
+    let recipient = check_recipient_exists(recipient_id, connection)?
+        .ok_or(ModifyRecipientError::RecipientDoesNotExist)?;
+    let opted_out_notification_config_ids = RecipientOptOutRowRepository::new(connection)
+        .find_all_for_recipient(recipient_id)?
+        .into_iter()
+        .map(|row| row.notification_config_id)
+        .collect();
+
+    Ok(RecipientPreferences {
+        recipient_id: recipient.id,
+        digest_frequency: recipient.digest_frequency,
+        preferred_recipient_id: recipient.preferred_recipient_id,
+        opted_out_notification_config_ids,
+    })
+}
+
+/// Replaces the recipient's preferences, used by admins and the recipient's own manage preferences page
+pub fn set_recipient_preferences(
+    ctx: &ServiceContext,
+    preferences: RecipientPreferences,
+) -> Result<Recipient, ModifyRecipientError> {
+    let recipient_id = preferences.recipient_id.clone();
+    let recipient = ctx
+        .connection
+        .transaction_sync(|connection| {
+            validate(connection, &preferences)?;
+
+            RecipientRowRepository::new(connection).set_preferences(
+                &recipient_id,
+                preferences.digest_frequency,
+                preferences.preferred_recipient_id,
+            )?;
+            set_opted_out_configs(
+                connection,
+                &recipient_id,
+                &preferences.opted_out_notification_config_ids,
+            )?;
+
+            get_recipient(ctx, recipient_id.clone()).map_err(ModifyRecipientError::from)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    // Audit logging
+    audit_log_entry(
+        ctx,
+        LogType::RecipientPreferencesUpdated,
+        Some(recipient_id),
+        Utc::now().naive_utc(),
+    )?;
+    Ok(recipient)
+}
+
+fn validate(
+    connection: &StorageConnection,
+    preferences: &RecipientPreferences,
+) -> Result<(), ModifyRecipientError> {
+    let recipient = check_recipient_exists(&preferences.recipient_id, connection)?
+        .ok_or(ModifyRecipientError::RecipientDoesNotExist)?;
+
+    if let Some(preferred_recipient_id) = &preferences.preferred_recipient_id {
+        // A contact's channel order decides which of its channels is sent to
+        if recipient.contact_id.is_some() {
+            return Err(ModifyRecipientError::GenericError(format!(
+                "{} is one of a contact's channels, so notifications are sent in the contact's channel order instead of to a preferred recipient",
+                recipient.name
+            )));
+        }
+        if *preferred_recipient_id == preferences.recipient_id {
+            return Err(ModifyRecipientError::GenericError(
+                "A recipient can't be its own preferred recipient".to_string(),
+            ));
+        }
+        let preferred_recipient = check_recipient_exists(preferred_recipient_id, connection)?
+            .filter(|recipient| recipient.deleted_datetime.is_none())
+            .ok_or(ModifyRecipientError::RecipientDoesNotExist)?;
+        // Only one hop, so notifications can't be passed around in a loop
+        if preferred_recipient.preferred_recipient_id.is_some() {
+            return Err(ModifyRecipientError::GenericError(format!(
+                "{} sends its notifications to another recipient, so can't be a preferred recipient",
+                preferred_recipient.name
+            )));
+        }
+    }
+
+    let config_repo = NotificationConfigRowRepository::new(connection);
+    for config_id in &preferences.opted_out_notification_config_ids {
+        if config_repo.find_one_by_id(config_id)?.is_none() {
+            return Err(ModifyRecipientError::GenericError(format!(
+                "Notification config {} does not exist",
+                config_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn set_opted_out_configs(
+    connection: &StorageConnection,
+    recipient_id: &str,
+    notification_config_ids: &[String],
+) -> Result<(), RepositoryError> {
+    let repo = RecipientOptOutRowRepository::new(connection);
+    let existing = repo.find_all_for_recipient(recipient_id)?;
+    let mut notification_config_ids = notification_config_ids.to_vec();
+    notification_config_ids.sort();
+    notification_config_ids.dedup();
+
+    for row in &existing {
+        if !notification_config_ids.contains(&row.notification_config_id) {
+            repo.delete(recipient_id, &row.notification_config_id)?;
+        }
+    }
+    for config_id in &notification_config_ids {
+        if existing
+            .iter()
+            .any(|row| row.notification_config_id == *config_id)
+        {
+            continue;
+        }
+        repo.insert_one(&RecipientOptOutRow {
+            id: uuid(),
+            recipient_id: recipient_id.to_string(),
+            notification_config_id: config_id.clone(),
+            created_datetime: Utc::now().naive_utc(),
+        })?;
+    }
+    Ok(())
+}
@@ -1,10 +1,17 @@
 use std::collections::HashMap;
 
-use repository::{NotificationType, Recipient, RecipientRow, RecipientRowRepository};
+use chrono::Utc;
+use repository::{
+    DigestFrequency, LogType, NotificationType, Recipient, RecipientRow, RecipientRowRepository,
+};
 use telegram::TelegramUpdate;
 use util::uuid::uuid;
 
+use crate::audit_log::audit_log_entry;
 use crate::recipient::create::{upsert_recipient, CreateRecipient};
+use crate::recipient::deactivate::deactivate_recipient;
+use crate::recipient::query::get_recipient;
+use crate::recipient::ModifyRecipientError;
 use crate::service_provider::ServiceContext;
 
 fn blank_telegram_recipient() -> RecipientRow {
@@ -14,6 +21,14 @@ fn blank_telegram_recipient() -> RecipientRow {
         notification_type: NotificationType::Telegram,
         to_address: "".to_string(),
         deleted_datetime: None,
+        muted_until: None,
+        deactivated_datetime: None,
+        undeliverable_datetime: None,
+        undeliverable_reason: None,
+        digest_frequency: DigestFrequency::Immediate,
+        preferred_recipient_id: None,
+        contact_id: None,
+        fallback_order: 0,
     }
 }
 
@@ -46,6 +61,62 @@ pub async fn update_telegram_recipients(
         };
         log::debug!("Received Telegram Update: {:?}", update);
 
+        if let Some(message) = &update.message {
+            // The old group gets a message with the new chat id, and the new supergroup a message with the old one.
+            // Either could arrive first, so the recipient is moved by whichever we see.
+            let migration = match (message.migrate_to_chat_id, message.migrate_from_chat_id) {
+                (Some(to_chat_id), _) => Some((message.chat.id, to_chat_id)),
+                (None, Some(from_chat_id)) => Some((from_chat_id, message.chat.id)),
+                (None, None) => None,
+            };
+            if let Some((from_chat_id, to_chat_id)) = migration {
+                let from_chat_id = from_chat_id.to_string();
+                let to_chat_id = to_chat_id.to_string();
+                recipient_cache.remove(&from_chat_id);
+                recipient_cache.remove(&to_chat_id);
+
+                if let Err(e) = migrate_telegram_recipient(&ctx, &from_chat_id, &to_chat_id) {
+                    log::error!(
+                        "Error migrating telegram recipient from {} to {}: {:?}",
+                        from_chat_id,
+                        to_chat_id,
+                        e
+                    );
+                }
+                // The old group can't be sent to anymore, so we don't want a recipient for it
+                if message.migrate_to_chat_id.is_some() {
+                    continue;
+                }
+            }
+        }
+
+        if let Some(my_chat_member) = &update.my_chat_member {
+            if my_chat_member.new_chat_member.has_left() {
+                let chat_id = my_chat_member.chat.id.to_string();
+                recipient_cache.remove(&chat_id);
+
+                match recipient_repo
+                    .find_one_by_to_address_and_type(&chat_id, NotificationType::Telegram)
+                {
+                    Ok(Some(recipient)) if recipient.deactivated_datetime.is_none() => {
+                        log::warn!(
+                            "Bot was removed from telegram chat {}, deactivating recipient {}",
+                            chat_id,
+                            recipient.name
+                        );
+                        if let Err(e) =
+                            deactivate_recipient(&ctx, &recipient.id, Some(Utc::now().naive_utc()))
+                        {
+                            log::error!("Error deactivating recipient {:?}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::error!("Error looking up recipient in database {}", e),
+                }
+                continue;
+            }
+        }
+
         if let Some(chat) = update.chat() {
             let chat_id = chat.id.to_string();
             let cached_recipient = recipient_cache.entry(chat_id.clone()).or_insert_with(|| {
@@ -66,6 +137,24 @@ pub async fn update_telegram_recipients(
                 }
             });
 
+            // The bot has been added back to a chat it was removed from
+            let rejoined = update
+                .my_chat_member
+                .as_ref()
+                .map(|my_chat_member| my_chat_member.new_chat_member.is_member())
+                .unwrap_or(false);
+            if rejoined && cached_recipient.deactivated_datetime.is_some() {
+                log::info!(
+                    "Bot was added back to telegram chat {}, reactivating recipient {}",
+                    chat_id,
+                    cached_recipient.name
+                );
+                match deactivate_recipient(&ctx, &cached_recipient.id, None) {
+                    Ok(_) => cached_recipient.deactivated_datetime = None,
+                    Err(e) => log::error!("Error reactivating recipient {:?}", e),
+                }
+            }
+
             // Check if we need to update the recipient name (e.g if the chat title has changed or if we just created the recipient)
             if cached_recipient.name != chat.name() {
                 log::debug!(
@@ -94,6 +183,59 @@ pub async fn update_telegram_recipients(
     }
 }
 
+/// Moves the recipient for a telegram group to its new chat id, after the group is upgraded to a supergroup
+pub fn migrate_telegram_recipient(
+    ctx: &ServiceContext,
+    from_chat_id: &str,
+    to_chat_id: &str,
+) -> Result<Option<Recipient>, ModifyRecipientError> {
+    let recipient_id = ctx
+        .connection
+        .transaction_sync(|connection| {
+            let repo = RecipientRowRepository::new(connection);
+            let mut recipient = match repo
+                .find_one_by_to_address_and_type(from_chat_id, NotificationType::Telegram)?
+            {
+                Some(recipient) => recipient,
+                None => return Ok(None),
+            };
+
+            // A message from the new chat may have arrived first, and created a recipient for it
+            if let Some(new_chat_recipient) =
+                repo.find_one_by_to_address_and_type(to_chat_id, NotificationType::Telegram)?
+            {
+                repo.mark_deleted(&new_chat_recipient.id)?;
+            }
+
+            recipient.to_address = to_chat_id.to_string();
+            repo.update_one(&recipient)?;
+            Ok(Some(recipient.id))
+        })
+        .map_err(
+            |error: repository::TransactionError<ModifyRecipientError>| error.to_inner_error(),
+        )?;
+
+    let recipient_id = match recipient_id {
+        Some(recipient_id) => recipient_id,
+        None => return Ok(None),
+    };
+    log::info!(
+        "Telegram chat {} was upgraded to {}, updated recipient {}",
+        from_chat_id,
+        to_chat_id,
+        recipient_id
+    );
+
+    // Audit logging
+    audit_log_entry(
+        ctx,
+        LogType::RecipientChatMigrated,
+        Some(recipient_id.clone()),
+        Utc::now().naive_utc(),
+    )?;
+    Ok(Some(get_recipient(ctx, recipient_id)?))
+}
+
 #[cfg(test)]
 mod test {
     use std::sync::Arc;
@@ -167,6 +309,8 @@ mod test {
                     ..Default::default()
                 },
                 text: None,
+                migrate_to_chat_id: None,
+                migrate_from_chat_id: None,
             }),
             my_chat_member: None,
         };
@@ -201,6 +345,8 @@ mod test {
                     ..Default::default()
                 },
                 text: None,
+                migrate_to_chat_id: None,
+                migrate_from_chat_id: None,
             }),
             my_chat_member: None,
         };
@@ -300,6 +446,8 @@ mod test {
                     ..Default::default()
                 },
                 text: None,
+                migrate_to_chat_id: None,
+                migrate_from_chat_id: None,
             }),
             my_chat_member: None,
         };
@@ -317,6 +465,102 @@ mod test {
         assert_eq!(recipients.count, 1);
         assert_eq!(recipients.rows[0].name, "Notification Group 1a");
 
+        // When the group is upgraded to a supergroup, the recipient follows it to the new chat id
+        let migrate_update = TelegramUpdate {
+            update_id: 5,
+            message: Some(TelegramMessage {
+                message_id: 2,
+                from: None,
+                chat: telegram::TelegramChat {
+                    id: -9999,
+                    title: Some("Notification Group 1a".to_string()),
+                    r#type: "group".to_string(),
+                    ..Default::default()
+                },
+                text: None,
+                migrate_to_chat_id: Some(-1009999),
+                migrate_from_chat_id: None,
+            }),
+            my_chat_member: None,
+        };
+
+        tx.send(migrate_update).unwrap();
+
+        // wait 10ms to allow processing to happen
+        tokio::time::sleep(tokio::time::Duration::from_millis(ASYNC_WAIT_MS)).await;
+
+        let recipient = service_provider
+            .recipient_service
+            .get_recipient(&send_ctx, telegram_recipient.id.clone())
+            .unwrap();
+        assert_eq!(recipient.to_address, "-1009999");
+        // No recipient is created for the old chat
+        let recipients = service_provider
+            .recipient_service
+            .get_recipients(&send_ctx, None, None, None)
+            .unwrap();
+        assert_eq!(recipients.count, 2);
+
+        // When the bot is removed from the chat, the recipient is deactivated
+        let removed_update = TelegramUpdate {
+            update_id: 6,
+            message: None,
+            my_chat_member: Some(telegram::TelegramMyChatMember {
+                chat: telegram::TelegramChat {
+                    id: -1009999,
+                    title: Some("Notification Group 1a".to_string()),
+                    r#type: "supergroup".to_string(),
+                    ..Default::default()
+                },
+                new_chat_member: telegram::TelegramChatMember {
+                    status: "kicked".to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        };
+
+        tx.send(removed_update).unwrap();
+
+        // wait 10ms to allow processing to happen
+        tokio::time::sleep(tokio::time::Duration::from_millis(ASYNC_WAIT_MS)).await;
+
+        let recipient = service_provider
+            .recipient_service
+            .get_recipient(&send_ctx, telegram_recipient.id.clone())
+            .unwrap();
+        assert!(recipient.deactivated_datetime.is_some());
+
+        // Adding the bot back reactivates it
+        let added_update = TelegramUpdate {
+            update_id: 7,
+            message: None,
+            my_chat_member: Some(telegram::TelegramMyChatMember {
+                chat: telegram::TelegramChat {
+                    id: -1009999,
+                    title: Some("Notification Group 1a".to_string()),
+                    r#type: "supergroup".to_string(),
+                    ..Default::default()
+                },
+                new_chat_member: telegram::TelegramChatMember {
+                    status: "member".to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        };
+
+        tx.send(added_update).unwrap();
+
+        // wait 10ms to allow processing to happen
+        tokio::time::sleep(tokio::time::Duration::from_millis(ASYNC_WAIT_MS)).await;
+
+        let recipient = service_provider
+            .recipient_service
+            .get_recipient(&send_ctx, telegram_recipient.id.clone())
+            .unwrap();
+        assert_eq!(recipient.deactivated_datetime, None);
+
         update_handler.abort();
     }
 }
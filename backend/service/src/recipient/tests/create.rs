@@ -152,4 +152,103 @@ mod recipient_create_test {
         // Recipient now exists
         assert!(result.is_some());
     }
+    #[actix_rt::test]
+    async fn create_webhook_recipient() {
+        let (_, _, connection_manager, _) =
+            setup_all("create_webhook_recipient", MockDataInserts::none()).await;
+
+        let connection = connection_manager.connection().unwrap();
+        let recipient_row_repository = RecipientRowRepository::new(&connection);
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.recipient_service;
+
+        // Webhook recipients need a url
+        assert_eq!(
+            service.create_recipient(
+                &context,
+                CreateRecipient {
+                    id: uuid(),
+                    name: "Slack".to_string(),
+                    to_address: "#general".to_string(),
+                    notification_type: NotificationType::Slack,
+                },
+            ),
+            Err(ModifyRecipientError::InvalidToAddress("#general".to_string()))
+        );
+
+        let new_recipient_id = uuid();
+        let url = "https://hooks.slack.com/services/T000/B000/XXXXxxxx";
+        service
+            .create_recipient(
+                &context,
+                CreateRecipient {
+                    id: new_recipient_id.clone(),
+                    name: "Slack".to_string(),
+                    to_address: format!(" {} ", url),
+                    notification_type: NotificationType::Slack,
+                },
+            )
+            .unwrap();
+
+        // Webhook urls are case sensitive, so aren't lowercased
+        let result = recipient_row_repository
+            .find_one_by_id(&new_recipient_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.to_address, url);
+    }
+
+    #[actix_rt::test]
+    async fn create_sms_recipient() {
+        let (_, _, connection_manager, _) =
+            setup_all("create_sms_recipient", MockDataInserts::none()).await;
+
+        let connection = connection_manager.connection().unwrap();
+        let recipient_row_repository = RecipientRowRepository::new(&connection);
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.recipient_service;
+
+        // SMS recipients need an E.164 number, including the country code
+        assert_eq!(
+            service.create_recipient(
+                &context,
+                CreateRecipient {
+                    id: uuid(),
+                    name: "Phone".to_string(),
+                    to_address: "021 123 4567".to_string(),
+                    notification_type: NotificationType::Sms,
+                },
+            ),
+            Err(ModifyRecipientError::InvalidToAddress(
+                "021 123 4567".to_string()
+            ))
+        );
+
+        let new_recipient_id = uuid();
+        service
+            .create_recipient(
+                &context,
+                CreateRecipient {
+                    id: new_recipient_id.clone(),
+                    name: "Phone".to_string(),
+                    to_address: "+64 (21) 123-4567".to_string(),
+                    notification_type: NotificationType::Sms,
+                },
+            )
+            .unwrap();
+
+        let result = recipient_row_repository
+            .find_one_by_id(&new_recipient_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.to_address, "+64211234567");
+    }
 }
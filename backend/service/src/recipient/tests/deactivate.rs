@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod recipient_deactivate_tests {
+
+    use std::sync::Arc;
+
+    use chrono::NaiveDateTime;
+    use repository::mock::mock_recipient_c;
+    use repository::{mock::MockDataInserts, test_db::setup_all};
+    use repository::{AuditLogFilter, AuditLogRepository, EqualFilter, LogType};
+
+    use crate::recipient::ModifyRecipientError;
+    use crate::service_provider::ServiceContext;
+    use crate::service_provider::ServiceProvider;
+    use crate::test_utils::get_test_settings;
+
+    #[actix_rt::test]
+    async fn recipient_service_deactivate() {
+        let (_, _, connection_manager, _) = setup_all(
+            "recipient_service_deactivate",
+            MockDataInserts::none().recipients().permissions(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.recipient_service;
+
+        // Deactivating a recipient that does not exist should fail
+        assert_eq!(
+            service.deactivate_recipient(&context, "new_id", None),
+            Err(ModifyRecipientError::RecipientDoesNotExist)
+        );
+
+        let deactivated_datetime =
+            NaiveDateTime::parse_from_str("2024-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let recipient = service
+            .deactivate_recipient(&context, &mock_recipient_c().id, Some(deactivated_datetime))
+            .unwrap();
+        assert_eq!(recipient.deactivated_datetime, Some(deactivated_datetime));
+
+        let recipient = service
+            .deactivate_recipient(&context, &mock_recipient_c().id, None)
+            .unwrap();
+        assert_eq!(recipient.deactivated_datetime, None);
+
+        // Admins can see when the recipient was deactivated and reactivated in the audit log
+        let logs = AuditLogRepository::new(&context.connection)
+            .query_by_filter(
+                AuditLogFilter::new().record_id(EqualFilter::equal_to(&mock_recipient_c().id)),
+            )
+            .unwrap();
+        let log_types: Vec<LogType> = logs
+            .into_iter()
+            .map(|log| log.log_row.record_type)
+            .collect();
+        assert!(log_types.contains(&LogType::RecipientDeactivated));
+        assert!(log_types.contains(&LogType::RecipientReactivated));
+    }
+}
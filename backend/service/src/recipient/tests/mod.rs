@@ -1,8 +1,14 @@
 #[cfg(test)]
 mod create;
 #[cfg(test)]
+mod deactivate;
+#[cfg(test)]
 mod delete;
 #[cfg(test)]
+mod mute;
+#[cfg(test)]
+mod preferences;
+#[cfg(test)]
 mod query;
 #[cfg(test)]
 mod update;
@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod recipient_mute_tests {
+
+    use std::sync::Arc;
+
+    use chrono::NaiveDateTime;
+    use repository::mock::mock_recipient_c;
+    use repository::{mock::MockDataInserts, test_db::setup_all};
+
+    use crate::recipient::create::{upsert_recipient, CreateRecipient};
+    use crate::recipient::ModifyRecipientError;
+    use crate::service_provider::ServiceContext;
+    use crate::service_provider::ServiceProvider;
+    use crate::test_utils::get_test_settings;
+
+    #[actix_rt::test]
+    async fn recipient_service_mute() {
+        let (_, _, connection_manager, _) = setup_all(
+            "recipient_service_mute",
+            MockDataInserts::none().recipients().permissions(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.recipient_service;
+
+        // Muting a recipient that does not exist should fail
+        assert_eq!(
+            service.mute_recipient(&context, "new_id", None),
+            Err(ModifyRecipientError::RecipientDoesNotExist)
+        );
+
+        let muted_until =
+            NaiveDateTime::parse_from_str("2030-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let recipient = service
+            .mute_recipient(&context, &mock_recipient_c().id, Some(muted_until))
+            .unwrap();
+        assert_eq!(recipient.muted_until, Some(muted_until));
+
+        // Updating the recipient, e.g. when the telegram chat is renamed, keeps it muted
+        let recipient = upsert_recipient(
+            &context,
+            CreateRecipient {
+                id: mock_recipient_c().id,
+                name: "renamed_chat".to_string(),
+                notification_type: mock_recipient_c().notification_type,
+                to_address: mock_recipient_c().to_address,
+            },
+        )
+        .unwrap();
+        assert_eq!(recipient.name, "renamed_chat");
+        assert_eq!(recipient.muted_until, Some(muted_until));
+
+        let recipient = service
+            .mute_recipient(&context, &mock_recipient_c().id, None)
+            .unwrap();
+        assert_eq!(recipient.muted_until, None);
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod recipient_preferences_tests {
+
+    use std::sync::Arc;
+
+    use repository::mock::{
+        mock_coldchain_notification_config_a, mock_recipient_a, mock_recipient_b, mock_recipient_c,
+    };
+    use repository::{mock::MockDataInserts, test_db::setup_all, DigestFrequency};
+
+    use crate::recipient::preferences::RecipientPreferences;
+    use crate::recipient::ModifyRecipientError;
+    use crate::service_provider::ServiceContext;
+    use crate::service_provider::ServiceProvider;
+    use crate::test_utils::get_test_settings;
+
+    #[actix_rt::test]
+    async fn recipient_service_preferences() {
+        let (_, _, connection_manager, _) = setup_all(
+            "recipient_service_preferences",
+            MockDataInserts::none()
+                .recipients()
+                .notification_configs()
+                .permissions(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let service = &context.service_provider.recipient_service;
+
+        // Recipient does not exist
+        assert_eq!(
+            service.set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: "new_id".to_string(),
+                    ..Default::default()
+                }
+            ),
+            Err(ModifyRecipientError::RecipientDoesNotExist)
+        );
+
+        // Can't opt out of a config that doesn't exist
+        assert!(matches!(
+            service.set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: mock_recipient_a().id,
+                    opted_out_notification_config_ids: vec!["no_such_config".to_string()],
+                    ..Default::default()
+                }
+            ),
+            Err(ModifyRecipientError::GenericError(_))
+        ));
+
+        let preferences = RecipientPreferences {
+            recipient_id: mock_recipient_a().id,
+            digest_frequency: DigestFrequency::Weekly,
+            preferred_recipient_id: Some(mock_recipient_c().id),
+            opted_out_notification_config_ids: vec![mock_coldchain_notification_config_a().id],
+        };
+        let recipient = service
+            .set_recipient_preferences(&context, preferences.clone())
+            .unwrap();
+        assert_eq!(recipient.digest_frequency, DigestFrequency::Weekly);
+        assert_eq!(
+            recipient.preferred_recipient_id,
+            Some(mock_recipient_c().id)
+        );
+        assert_eq!(
+            service
+                .get_recipient_preferences(&context, &mock_recipient_a().id)
+                .unwrap(),
+            preferences
+        );
+
+        // A recipient that sends to another recipient can't be a preferred recipient
+        assert!(matches!(
+            service.set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: mock_recipient_b().id,
+                    preferred_recipient_id: Some(mock_recipient_a().id),
+                    ..Default::default()
+                }
+            ),
+            Err(ModifyRecipientError::GenericError(_))
+        ));
+
+        // Setting the preferences again replaces them
+        service
+            .set_recipient_preferences(
+                &context,
+                RecipientPreferences {
+                    recipient_id: mock_recipient_a().id,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            service
+                .get_recipient_preferences(&context, &mock_recipient_a().id)
+                .unwrap(),
+            RecipientPreferences {
+                recipient_id: mock_recipient_a().id,
+                ..Default::default()
+            }
+        );
+    }
+}
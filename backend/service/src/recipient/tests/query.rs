@@ -8,6 +8,10 @@ mod recipient_query_test {
     };
     use repository::{EqualFilter, PaginationOption, Sort};
 
+    use chrono::Utc;
+    use repository::RecipientRowRepository;
+
+    use crate::recipient::update::UpdateRecipient;
     use crate::service_provider::ServiceContext;
     use crate::test_utils::get_test_settings;
     use crate::{service_provider::ServiceProvider, ListError, SingleRecordError};
@@ -269,4 +273,69 @@ mod recipient_query_test {
 
         assert_eq!(result_names, sorted_names);
     }
+
+    #[actix_rt::test]
+    async fn recipient_service_delivery_problem_filter() {
+        let (_, _, connection_manager, _) = setup_all(
+            "recipient_service_delivery_problem_filter",
+            MockDataInserts::none().recipients(),
+        )
+        .await;
+
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::new(service_provider).unwrap();
+        let service = &context.service_provider.recipient_service;
+        let now = Utc::now().naive_utc();
+
+        let recipient_repository = RecipientRowRepository::new(&context.connection);
+        recipient_repository
+            .set_undeliverable(
+                &mock_recipient_a().id,
+                Some((now, "Bounced with status 5.1.1".to_string())),
+            )
+            .unwrap();
+        recipient_repository
+            .set_deactivated_datetime(&mock_recipient_aa().id, Some(now))
+            .unwrap();
+
+        let problem_recipients = service
+            .get_recipients(
+                &context,
+                None,
+                Some(RecipientFilter::new().has_delivery_problem(true)),
+                None,
+            )
+            .unwrap();
+        let mut ids: Vec<String> = problem_recipients
+            .rows
+            .into_iter()
+            .map(|recipient| recipient.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![mock_recipient_a().id, mock_recipient_aa().id]);
+
+        // Changing the address clears the bounce
+        service
+            .update_recipient(
+                &context,
+                UpdateRecipient {
+                    id: mock_recipient_a().id,
+                    name: None,
+                    to_address: Some("new-a@openmsupply.foundation".to_string()),
+                },
+            )
+            .unwrap();
+        let problem_recipients = service
+            .get_recipients(
+                &context,
+                None,
+                Some(RecipientFilter::new().has_delivery_problem(true)),
+                None,
+            )
+            .unwrap();
+        assert_eq!(problem_recipients.count, 1);
+    }
 }
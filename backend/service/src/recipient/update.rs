@@ -1,6 +1,9 @@
 use super::{
     query::get_recipient,
-    validate::{check_recipient_exists, check_to_address_is_unique},
+    validate::{
+        check_recipient_exists, check_to_address_is_unique, check_to_address_is_valid,
+        normalise_to_address,
+    },
     ModifyRecipientError,
 };
 use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
@@ -22,8 +25,14 @@ pub fn update_recipient(
         .connection
         .transaction_sync(|connection| {
             let recipient_row = validate(connection, &updated_recipient)?;
+            let previous_to_address = recipient_row.to_address.clone();
             let updated_recipient_row = generate(updated_recipient.clone(), recipient_row)?;
-            RecipientRowRepository::new(connection).update_one(&updated_recipient_row)?;
+            let repo = RecipientRowRepository::new(connection);
+            repo.update_one(&updated_recipient_row)?;
+            // A new address hasn't bounced
+            if updated_recipient_row.to_address != previous_to_address {
+                repo.set_undeliverable(&updated_recipient_row.id, None)?;
+            }
 
             get_recipient(ctx, updated_recipient_row.id).map_err(ModifyRecipientError::from)
         })
@@ -48,6 +57,12 @@ pub fn validate(
         None => return Err(ModifyRecipientError::RecipientDoesNotExist),
     };
 
+    if let Some(to_address) = &new_recipient.to_address {
+        if !check_to_address_is_valid(to_address, &recipient_row.notification_type) {
+            return Err(ModifyRecipientError::InvalidToAddress(to_address.clone()));
+        }
+    }
+
     if !check_to_address_is_unique(
         &new_recipient.id,
         new_recipient.to_address.clone(),
@@ -73,7 +88,8 @@ pub fn generate(
         new_recipient_row.name = name.trim().to_string();
     }
     if let Some(to_address) = to_address {
-        new_recipient_row.to_address = to_address.trim().to_ascii_lowercase();
+        new_recipient_row.to_address =
+            normalise_to_address(&to_address, &new_recipient_row.notification_type);
     }
 
     Ok(new_recipient_row)
@@ -1,3 +1,7 @@
+use crate::{
+    push::topic_from_url,
+    sms::{is_e164, normalise_phone_number},
+};
 use repository::{
     EqualFilter, NotificationType, RecipientFilter, RecipientRepository, RecipientRow,
     RecipientRowRepository, RepositoryError, StorageConnection, StringFilter,
@@ -19,6 +23,43 @@ pub fn check_recipient_does_not_exist(
     Ok(recipient.is_none())
 }
 
+/// Webhook and push urls are case sensitive, phone numbers are stored without spaces or punctuation,
+/// other addresses are stored in lower case
+pub fn normalise_to_address(to_address: &str, notification_type: &NotificationType) -> String {
+    if notification_type.is_chat_webhook() || *notification_type == NotificationType::NtfyPush {
+        to_address.trim().to_string()
+    } else if *notification_type == NotificationType::Sms {
+        normalise_phone_number(to_address.trim())
+    } else {
+        to_address.trim().to_ascii_lowercase()
+    }
+}
+
+/// Teams, Slack and Mattermost recipients need to be a http(s) webhook url,
+/// SMS recipients need to be a phone number in E.164 format e.g. +64211234567,
+/// and push recipients need a http(s) url including the ntfy topic or Gotify message path
+pub fn check_to_address_is_valid(to_address: &str, notification_type: &NotificationType) -> bool {
+    if *notification_type == NotificationType::Sms {
+        return is_e164(&normalise_phone_number(to_address.trim()));
+    }
+    if *notification_type == NotificationType::NtfyPush {
+        return match reqwest::Url::parse(to_address.trim()) {
+            Ok(url) => {
+                (url.scheme() == "https" || url.scheme() == "http")
+                    && topic_from_url(&url).is_some()
+            }
+            Err(_) => false,
+        };
+    }
+    if !notification_type.is_chat_webhook() {
+        return true;
+    }
+    match reqwest::Url::parse(to_address.trim()) {
+        Ok(url) => url.scheme() == "https" || url.scheme() == "http",
+        Err(_) => false,
+    }
+}
+
 pub fn check_to_address_is_unique(
     id: &str,
     to_address: Option<String>,
@@ -30,7 +71,10 @@ pub fn check_to_address_is_unique(
         Some(to_address) => {
             let recipients = RecipientRepository::new(connection).query_by_filter(
                 RecipientFilter::new()
-                    .to_address(StringFilter::equal_to(&to_address.trim().to_lowercase()))
+                    .to_address(StringFilter::equal_to(&normalise_to_address(
+                        &to_address,
+                        &notification_type,
+                    )))
                     .notification_type(NotificationType::equal_to(notification_type))
                     .id(EqualFilter::not_equal_to(id)),
             )?;
@@ -0,0 +1,80 @@
+use super::{validate::check_recipient_list_exists, ModifyRecipientListError};
+use crate::{
+    audit_log::audit_log_entry, contact::validate::check_contact_exists,
+    service_provider::ServiceContext,
+};
+
+use chrono::Utc;
+use repository::{
+    LogType, RecipientListContactRow, RecipientListContactRowRepository, StorageConnection,
+    TransactionError,
+};
+use util::uuid::uuid;
+
+#[derive(Clone)]
+pub struct AddContactToList {
+    pub contact_id: String,
+    pub recipient_list_id: String,
+}
+
+pub fn add_contact_to_list(
+    ctx: &ServiceContext,
+    new_list_contact: AddContactToList,
+) -> Result<RecipientListContactRow, ModifyRecipientListError> {
+    let list_contact = ctx
+        .connection
+        .transaction_sync(|connection| {
+            validate(&new_list_contact, connection)?;
+            let list_contact_row = generate(new_list_contact.clone());
+            RecipientListContactRowRepository::new(connection).insert_one(&list_contact_row)?;
+            Ok(list_contact_row)
+        })
+        .map_err(|error: TransactionError<ModifyRecipientListError>| error.to_inner_error())?;
+
+    audit_log_entry(
+        ctx,
+        LogType::ContactAddedToList,
+        Some(list_contact.recipient_list_id.clone()),
+        Utc::now().naive_utc(),
+    )?;
+
+    Ok(list_contact)
+}
+
+pub fn validate(
+    new_list_contact: &AddContactToList,
+    connection: &StorageConnection,
+) -> Result<(), ModifyRecipientListError> {
+    if check_contact_exists(&new_list_contact.contact_id, connection)?.is_none() {
+        return Err(ModifyRecipientListError::ContactDoesNotExist);
+    }
+
+    if check_recipient_list_exists(&new_list_contact.recipient_list_id, connection)?.is_none() {
+        return Err(ModifyRecipientListError::RecipientListDoesNotExist);
+    }
+
+    if RecipientListContactRowRepository::new(connection)
+        .find_one(
+            &new_list_contact.recipient_list_id,
+            &new_list_contact.contact_id,
+        )?
+        .is_some()
+    {
+        return Err(ModifyRecipientListError::RecipientListContactAlreadyExists);
+    }
+
+    Ok(())
+}
+
+pub fn generate(
+    AddContactToList {
+        contact_id,
+        recipient_list_id,
+    }: AddContactToList,
+) -> RecipientListContactRow {
+    RecipientListContactRow {
+        id: uuid(),
+        recipient_list_id,
+        contact_id,
+    }
+}
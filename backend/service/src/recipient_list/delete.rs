@@ -1,8 +1,8 @@
 use super::validate::check_recipient_list_exists;
 use crate::service_provider::ServiceContext;
 use repository::{
-    RecipientListMemberRowRepository, RecipientListRow, RecipientListRowRepository,
-    RepositoryError, StorageConnection,
+    RecipientListContactRowRepository, RecipientListMemberRowRepository, RecipientListRow,
+    RecipientListRowRepository, RepositoryError, StorageConnection,
 };
 
 #[derive(PartialEq, Debug)]
@@ -20,6 +20,9 @@ pub fn delete_recipient_list(
         .transaction_sync(|connection| {
             let recipient_list_row = validate(connection, recipient_list_id)?;
 
+            RecipientListContactRowRepository::new(connection)
+                .delete_all_for_recipient_list_id(recipient_list_id)?;
+
             let member_repo = RecipientListMemberRowRepository::new(connection);
             let recipient_list_repo = RecipientListRowRepository::new(connection);
 
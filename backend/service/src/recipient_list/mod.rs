@@ -1,8 +1,10 @@
 use self::{
+    add_contact::{add_contact_to_list, AddContactToList},
     add_member::{add_recipient_to_list, AddRecipientToList},
     create::{create_recipient_list, CreateRecipientList},
     delete::{delete_recipient_list, DeleteRecipientListError},
     query::{get_recipient_list, get_recipient_lists},
+    remove_contact::{remove_contact_from_list, RemoveContactFromList},
     remove_member::{remove_recipient_from_list, RemoveRecipientFromList},
     update::{update_recipient_list, UpdateRecipientList},
 };
@@ -10,16 +12,18 @@ use self::{
 use super::{ListError, ListResult};
 use crate::{service_provider::ServiceContext, SingleRecordError};
 use repository::{
-    PaginationOption, RecipientList, RecipientListFilter, RecipientListMember, RecipientListSort,
-    RepositoryError,
+    PaginationOption, RecipientList, RecipientListContactRow, RecipientListFilter,
+    RecipientListMember, RecipientListSort, RepositoryError,
 };
 
 mod tests;
 
+pub mod add_contact;
 pub mod add_member;
 pub mod create;
 pub mod delete;
 pub mod query;
+pub mod remove_contact;
 pub mod remove_member;
 pub mod update;
 pub mod validate;
@@ -82,6 +86,22 @@ pub trait RecipientListServiceTrait: Sync + Send {
     ) -> Result<RecipientListMember, ModifyRecipientListError> {
         remove_recipient_from_list(ctx, input)
     }
+
+    fn add_contact_to_list(
+        &self,
+        ctx: &ServiceContext,
+        input: AddContactToList,
+    ) -> Result<RecipientListContactRow, ModifyRecipientListError> {
+        add_contact_to_list(ctx, input)
+    }
+
+    fn remove_contact_from_list(
+        &self,
+        ctx: &ServiceContext,
+        input: RemoveContactFromList,
+    ) -> Result<RecipientListContactRow, ModifyRecipientListError> {
+        remove_contact_from_list(ctx, input)
+    }
 }
 
 pub struct RecipientListService {}
@@ -97,6 +117,9 @@ pub enum ModifyRecipientListError {
     RecipientListMemberAlreadyExists,
     RecipientListMemberDoesNotExist,
     RecipientDoesNotExist,
+    RecipientListContactAlreadyExists,
+    RecipientListContactDoesNotExist,
+    ContactDoesNotExist,
     GenericError(String),
 }
 
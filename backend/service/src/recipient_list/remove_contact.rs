@@ -0,0 +1,41 @@
+use super::ModifyRecipientListError;
+use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+
+use chrono::Utc;
+use repository::{LogType, RecipientListContactRow, RecipientListContactRowRepository};
+
+#[derive(Clone)]
+pub struct RemoveContactFromList {
+    pub contact_id: String,
+    pub recipient_list_id: String,
+}
+
+pub fn remove_contact_from_list(
+    ctx: &ServiceContext,
+    remove_contact: RemoveContactFromList,
+) -> Result<RecipientListContactRow, ModifyRecipientListError> {
+    let list_contact = ctx
+        .connection
+        .transaction_sync(|connection| {
+            let repo = RecipientListContactRowRepository::new(connection);
+            let list_contact = match repo.find_one(
+                &remove_contact.recipient_list_id,
+                &remove_contact.contact_id,
+            )? {
+                Some(list_contact) => list_contact,
+                None => return Err(ModifyRecipientListError::RecipientListContactDoesNotExist),
+            };
+            repo.delete(&list_contact.id)?;
+            Ok(list_contact)
+        })
+        .map_err(|error| error.to_inner_error())?;
+
+    audit_log_entry(
+        ctx,
+        LogType::ContactRemovedFromList,
+        Some(list_contact.recipient_list_id.clone()),
+        Utc::now().naive_utc(),
+    )?;
+
+    Ok(list_contact)
+}
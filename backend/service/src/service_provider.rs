@@ -5,6 +5,8 @@ use telegram::TelegramClient;
 
 use crate::{
     auth::{AuthService, AuthServiceTrait},
+    chat_webhook::ChatWebhookClient,
+    contact::{ContactService, ContactServiceTrait},
     datasource::{DatasourceService, DatasourceServiceTrait},
     email::{EmailService, EmailServiceTrait},
     log_service::{LogService, LogServiceTrait},
@@ -13,9 +15,11 @@ use crate::{
     notification_event::{NotificationEventService, NotificationEventServiceTrait},
     notification_query::{NotificationQueryService, NotificationQueryServiceTrait},
     plugin_store::{PluginService, PluginServiceTrait},
+    push::PushClient,
     recipient::{RecipientService, RecipientServiceTrait},
     recipient_list::{RecipientListService, RecipientListServiceTrait},
     settings::Settings,
+    sms::SmsService,
     sql_recipient_list::{SqlRecipientListService, SqlRecipientListServiceTrait},
     user_account::{UserAccountService, UserAccountServiceTrait},
 };
@@ -28,6 +32,7 @@ pub struct ServiceProvider {
     pub user_account_service: Box<dyn UserAccountServiceTrait>,
     pub notification_config_service: Box<dyn NotificationConfigServiceTrait>,
     pub recipient_service: Box<dyn RecipientServiceTrait>,
+    pub contact_service: Box<dyn ContactServiceTrait>,
     pub recipient_list_service: Box<dyn RecipientListServiceTrait>,
     pub sql_recipient_list_service: Box<dyn SqlRecipientListServiceTrait>,
     pub notification_query_service: Box<dyn NotificationQueryServiceTrait>,
@@ -36,6 +41,9 @@ pub struct ServiceProvider {
     pub plugin_service: Box<dyn PluginServiceTrait>,
     pub settings: Settings,
     pub telegram: Option<TelegramClient>,
+    pub chat_webhook: ChatWebhookClient,
+    pub push: PushClient,
+    pub sms: Option<SmsService>,
     pub log_service: Box<dyn LogServiceTrait>,
 }
 
@@ -81,10 +89,21 @@ impl ServiceContext {
 
 impl ServiceProvider {
     pub fn new(connection_manager: StorageConnectionManager, settings: Settings) -> Self {
-        let telegram = match &settings.telegram.token {
-            Some(token) => Some(TelegramClient::new(token.clone())),
-            None => None,
+        let telegram = match (&settings.telegram.token, &settings.telegram.base_url) {
+            (Some(token), Some(base_url)) => {
+                Some(TelegramClient::new_with_api_url(token.clone(), base_url))
+            }
+            (Some(token), None) => Some(TelegramClient::new(token.clone())),
+            (None, _) => None,
         };
+        let telegram = match settings.telegram.max_message_parts {
+            Some(max_message_parts) => {
+                telegram.map(|telegram| telegram.with_max_message_parts(max_message_parts))
+            }
+            None => telegram,
+        };
+
+        let sms = settings.sms.as_ref().and_then(SmsService::new);
 
         ServiceProvider {
             connection_manager,
@@ -94,6 +113,7 @@ impl ServiceProvider {
             user_account_service: Box::new(UserAccountService {}),
             notification_config_service: Box::new(NotificationConfigService {}),
             recipient_service: Box::new(RecipientService {}),
+            contact_service: Box::new(ContactService {}),
             recipient_list_service: Box::new(RecipientListService {}),
             sql_recipient_list_service: Box::new(SqlRecipientListService {}),
             notification_query_service: Box::new(NotificationQueryService {}),
@@ -102,6 +122,9 @@ impl ServiceProvider {
             plugin_service: Box::new(PluginService {}),
             settings,
             telegram,
+            chat_webhook: ChatWebhookClient::new(),
+            push: PushClient::new(),
+            sms,
             log_service: Box::new(LogService {}),
         }
     }
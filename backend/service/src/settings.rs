@@ -1,17 +1,33 @@
-use std::fmt::{Display, Formatter, Result};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result},
+};
 
-use datasource::database_settings::PostgresSettings;
+use datasource::database_settings::DatasourceSettings;
 use repository::database_settings::SqliteSettings;
+
+use crate::email::layout::EmailBranding;
+
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
     pub server: ServerSettings,
     pub database: SqliteSettings,
     pub mail: MailSettings,
     pub telegram: TelegramSettings,
-    pub datasource: PostgresSettings,
+    /// Default datasource, used when a query doesn't specify a datasource_id
+    pub datasource: DatasourceSettings,
+    /// Additional datasources, keyed by the datasource_id used to select them
+    #[serde(default)]
+    pub datasources: HashMap<String, DatasourceSettings>,
     pub logging: Option<LoggingSettings>,
     #[serde(default)]
     pub backup: BackupSettings,
+    #[serde(default)]
+    pub query_cache: QueryCacheSettings,
+    #[serde(default)]
+    pub sms: Option<SmsSettings>,
+    #[serde(default)]
+    pub sending: SendingSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -106,10 +122,189 @@ pub struct MailSettings {
     pub username: String,
     pub password: String,
     pub from: String,
+    /// Named from addresses, e.g. alerts: "Cold Chain Alerts <alerts@example.com>", selected by a notification config's sender_profile
+    #[serde(default)]
+    pub sender_profiles: HashMap<String, String>,
+    /// Relays tried in order when the relay above has a temporary failure
+    #[serde(default)]
+    pub fallback_relays: Vec<SmtpRelaySettings>,
+    /// Most connections kept open to each relay, 10 if not set
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+    /// Signs outgoing emails so receiving servers can check they came from the domain
+    #[serde(default)]
+    pub dkim: Option<DkimSettings>,
+    /// Where bounced emails are read from, so addresses that don't exist are marked undeliverable
+    #[serde(default)]
+    pub bounces: Option<BounceSettings>,
+    /// The logo, colours and footer of the html email layout
+    #[serde(default)]
+    pub layout: Option<EmailLayoutSettings>,
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct EmailLayoutSettings {
+    /// A tera template to use instead of the built in notification layout, it can extend "base.html"
+    pub template_file: Option<String>,
+    /// Notification configs can change these for their emails
+    #[serde(flatten)]
+    pub branding: EmailBranding,
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct BounceSettings {
+    /// The mailbox bounces are sent to, usually the mailbox of the from address. Bounces are deleted once read, other emails are left.
+    pub pop3: Option<Pop3Settings>,
+    /// How often the mailbox is checked, 300 seconds if not set
+    pub poll_interval_seconds: Option<u64>,
+    /// Enables the /email/bounce endpoint, bounces are posted to it with this secret in the X-Notify-Secret header
+    pub webhook_secret: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct Pop3Settings {
+    pub host: String,
+    /// 995 for tls, otherwise 110
+    pub port: u16,
+    /// Connect with tls, defaults to true
+    #[serde(default = "default_true")]
+    pub tls: bool,
+    pub username: String,
+    pub password: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(serde::Deserialize, Clone, Default, Debug, PartialEq)]
+pub enum DkimAlgorithm {
+    #[default]
+    Rsa,
+    Ed25519,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct DkimSettings {
+    /// The domain the public key is published for, usually the domain of the from address
+    pub domain: String,
+    /// The public key is published in DNS as a TXT record for <selector>._domainkey.<domain>
+    pub selector: String,
+    #[serde(default)]
+    pub algorithm: DkimAlgorithm,
+    /// A PKCS1 PEM for rsa, or the base64 encoded 32 byte key for ed25519
+    pub private_key: Option<String>,
+    /// Read the private key from this file, rather than having it in the settings
+    pub private_key_file: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpRelaySettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub starttls: bool,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+impl MailSettings {
+    /// The relay configured by host, port, starttls, username and password
+    pub fn primary_relay(&self) -> SmtpRelaySettings {
+        SmtpRelaySettings {
+            host: self.host.clone(),
+            port: self.port,
+            starttls: self.starttls,
+            username: self.username.clone(),
+            password: self.password.clone(),
+        }
+    }
 }
+
 #[derive(serde::Deserialize, Clone)]
 pub struct TelegramSettings {
     pub token: Option<String>,
+    /// Bot API server to use instead of https://api.telegram.org, e.g. a local Bot API server
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Messages too long for this many telegram messages are sent as a document instead, defaults to 4
+    #[serde(default)]
+    pub max_message_parts: Option<usize>,
+    /// Receive updates from telegram via a webhook rather than polling for them
+    #[serde(default)]
+    pub webhook: Option<TelegramWebhookSettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct TelegramWebhookSettings {
+    /// Public url telegram sends updates to, should end in /telegram/webhook
+    pub url: String,
+    /// Sent by telegram with every update, so we know the requests come from telegram.
+    /// 1-256 characters, only A-Z, a-z, 0-9, _ and - are allowed
+    pub secret_token: String,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmsSettings {
+    /// Sender id or number the messages come from, if the gateway allows it to be set
+    #[serde(default)]
+    pub sender: Option<String>,
+    /// Long messages are split into at most this many SMS, and truncated after that, defaults to 3
+    #[serde(default)]
+    pub max_parts: Option<usize>,
+    /// Send using an SMPP connection to an SMSC, this is used if both smpp and http are set
+    #[serde(default)]
+    pub smpp: Option<SmppSettings>,
+    /// Send by posting each message to a SMS gateway's http api
+    #[serde(default)]
+    pub http: Option<HttpSmsGatewaySettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmppSettings {
+    pub host: String,
+    #[serde(default = "default_smpp_port")]
+    pub port: u16,
+    pub system_id: String,
+    pub password: String,
+    #[serde(default)]
+    pub system_type: String,
+}
+
+fn default_smpp_port() -> u16 {
+    2775
+}
+
+#[derive(serde::Deserialize, Clone, Default, PartialEq, Debug)]
+pub enum HttpSmsFormat {
+    #[default]
+    Json,
+    Form,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct HttpSmsGatewaySettings {
+    pub url: String,
+    /// Json (default) | Form
+    #[serde(default)]
+    pub format: HttpSmsFormat,
+    /// Names of the fields the number, text and sender are sent in, "to", "message" and "from" by default
+    #[serde(default)]
+    pub to_field: Option<String>,
+    #[serde(default)]
+    pub message_field: Option<String>,
+    #[serde(default)]
+    pub sender_field: Option<String>,
+    /// Extra headers to send, e.g. an api key
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Basic auth credentials, if the gateway needs them
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
 }
 
 #[derive(serde::Deserialize, Clone, Default)]
@@ -119,3 +314,25 @@ pub struct BackupSettings {
     pub path: String,
     pub filename: String,
 }
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct QueryCacheSettings {
+    /// How long notification query results are shared between scheduled notifications (and runs).
+    /// Results are always reused within a single run, the shared cache is disabled when this isn't set
+    pub shared_ttl_seconds: Option<u64>,
+}
+
+/// How many notifications are sent at the same time on each channel, notifications to the same address are always sent one at a time
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct SendingSettings {
+    /// Defaults to 4, the smtp connection pool's max_connections should be at least this
+    pub email_workers: Option<usize>,
+    /// Defaults to 4, telegram's rate limits still apply
+    pub telegram_workers: Option<usize>,
+    /// Teams, Slack and Mattermost webhooks, defaults to 4
+    pub chat_webhook_workers: Option<usize>,
+    /// Defaults to 4
+    pub push_workers: Option<usize>,
+    /// Defaults to 1, SMS gateways often limit how many messages can be submitted at once
+    pub sms_workers: Option<usize>,
+}
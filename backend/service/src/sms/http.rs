@@ -0,0 +1,157 @@
+/*
+   Sends SMS using a gateway's http api, most gateways accept a POST with the number and text in a json or form body.
+   The field names, headers and credentials are configurable to suit the gateway.
+*/
+
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+
+use crate::settings::{HttpSmsFormat, HttpSmsGatewaySettings};
+
+use super::{message::SmsMessage, SmsError, SmsGateway};
+
+const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+const DEFAULT_TO_FIELD: &str = "to";
+const DEFAULT_MESSAGE_FIELD: &str = "message";
+const DEFAULT_SENDER_FIELD: &str = "from";
+
+pub struct HttpSmsGateway {
+    http_client: reqwest::Client,
+    settings: HttpSmsGatewaySettings,
+    sender: Option<String>,
+}
+
+impl From<reqwest::Error> for SmsError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_builder() {
+            // e.g. the url isn't valid
+            return SmsError::Permanent(error.to_string());
+        }
+        SmsError::Temporary(error.to_string())
+    }
+}
+
+impl HttpSmsGateway {
+    pub fn new(settings: HttpSmsGatewaySettings, sender: Option<String>) -> HttpSmsGateway {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DEFAULT_REQUEST_TIMEOUT))
+            .build()
+            .expect("Something went unexpectedly wrong building the sms gateway reqwest client");
+        HttpSmsGateway {
+            http_client,
+            settings,
+            sender,
+        }
+    }
+
+    fn fields(&self, to: &str, message: &SmsMessage) -> Vec<(String, String)> {
+        let field = |name: &Option<String>, default: &str| {
+            name.clone().unwrap_or_else(|| default.to_string())
+        };
+        let mut fields = vec![
+            (
+                field(&self.settings.to_field, DEFAULT_TO_FIELD),
+                to.to_string(),
+            ),
+            (
+                field(&self.settings.message_field, DEFAULT_MESSAGE_FIELD),
+                message.text.clone(),
+            ),
+        ];
+        if let Some(sender) = &self.sender {
+            fields.push((
+                field(&self.settings.sender_field, DEFAULT_SENDER_FIELD),
+                sender.clone(),
+            ));
+        }
+        fields
+    }
+}
+
+#[async_trait(?Send)]
+impl SmsGateway for HttpSmsGateway {
+    /// The whole text is posted, the gateway splits it into parts if it needs to
+    async fn send_sms(&self, to: &str, message: &SmsMessage) -> Result<(), SmsError> {
+        let fields = self.fields(to, message);
+        let mut request = self.http_client.post(&self.settings.url);
+        request = match self.settings.format {
+            HttpSmsFormat::Json => {
+                let body: Map<String, Value> = fields
+                    .into_iter()
+                    .map(|(name, value)| (name, Value::String(value)))
+                    .collect();
+                request.json(&body)
+            }
+            HttpSmsFormat::Form => {
+                let body: HashMap<String, String> = fields.into_iter().collect();
+                request.form(&body)
+            }
+        };
+        for (name, value) in &self.settings.headers {
+            request = request.header(name, value);
+        }
+        if let Some(username) = &self.settings.username {
+            request = request.basic_auth(username, self.settings.password.as_ref());
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let description = format!("{} - {}", status, response.text().await.unwrap_or_default());
+        if status.is_server_error()
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT
+        {
+            return Err(SmsError::Temporary(description));
+        }
+        Err(SmsError::Permanent(description))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::chat_webhook::stub::WebhookStub;
+
+    use super::*;
+
+    fn settings(url: String) -> HttpSmsGatewaySettings {
+        HttpSmsGatewaySettings {
+            url,
+            format: HttpSmsFormat::Json,
+            to_field: Some("number".to_string()),
+            message_field: None,
+            sender_field: None,
+            headers: HashMap::from([("X-Api-Key".to_string(), "secret".to_string())]),
+            username: None,
+            password: None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_http_sms_gateway() {
+        let stub = WebhookStub::start();
+        let gateway = HttpSmsGateway::new(settings(stub.url("/sms")), Some("Alerts".to_string()));
+        let message = SmsMessage::new("Sensor is too hot", 3);
+
+        gateway.send_sms("+64211234567", &message).await.unwrap();
+        assert_eq!(
+            stub.requests()[0].body,
+            json!({ "number": "+64211234567", "message": "Sensor is too hot", "from": "Alerts" })
+        );
+
+        stub.push_response("/sms", 503, &[], "Service Unavailable");
+        let result = gateway.send_sms("+64211234567", &message).await;
+        assert!(matches!(result, Err(SmsError::Temporary(_))));
+
+        stub.push_response("/sms", 400, &[], "Invalid number");
+        let result = gateway.send_sms("+64211234567", &message).await;
+        assert!(result.unwrap_err().is_permanent());
+    }
+}
@@ -0,0 +1,262 @@
+/*
+   Renders notifications as plain text and splits them into SMS parts.
+
+   An SMS is 140 bytes, which is 160 characters from the GSM 7 bit alphabet, or 70 UCS-2 (UTF-16) characters if the text
+   has any other characters. Longer messages are sent as several parts with a header so the phone can put them back
+   together, which leaves room for 153 GSM characters or 67 UCS-2 characters in each part.
+   https://en.wikipedia.org/wiki/GSM_03.38
+*/
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+const GSM_SINGLE_LENGTH: usize = 160;
+const GSM_PART_LENGTH: usize = 153;
+const UCS2_SINGLE_LENGTH: usize = 70;
+const UCS2_PART_LENGTH: usize = 67;
+const TRUNCATED: &str = "...";
+
+/// The GSM 7 bit default alphabet, in order of their septet value. 0x1B is the escape to the extension table.
+pub(crate) const GSM_ALPHABET: [char; 128] = [
+    '@', '£', '$', '¥', 'è', 'é', 'ù', 'ì', 'ò', 'Ç', '\n', 'Ø', 'ø', '\r', 'Å', 'å', //
+    'Δ', '_', 'Φ', 'Γ', 'Λ', 'Ω', 'Π', 'Ψ', 'Σ', 'Θ', 'Ξ', '\u{1b}', 'Æ', 'æ', 'ß', 'É', //
+    ' ', '!', '"', '#', '¤', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', //
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?', //
+    '¡', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', //
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'Ä', 'Ö', 'Ñ', 'Ü', '§', //
+    '¿', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', //
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', 'ä', 'ö', 'ñ', 'ü', 'à', //
+];
+
+/// Characters from the GSM extension table, sent as the escape followed by their septet value
+pub(crate) const GSM_EXTENSION: [(char, u8); 10] = [
+    ('\u{c}', 0x0A),
+    ('^', 0x14),
+    ('{', 0x28),
+    ('}', 0x29),
+    ('\\', 0x2F),
+    ('[', 0x3C),
+    ('~', 0x3D),
+    (']', 0x3E),
+    ('|', 0x40),
+    ('€', 0x65),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmsEncoding {
+    Gsm7,
+    Ucs2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmsMessage {
+    /// The whole text, truncated if it didn't fit in the allowed number of parts
+    pub text: String,
+    pub encoding: SmsEncoding,
+    /// The text split into parts that each fit in one SMS, a single part if it isn't split
+    pub parts: Vec<String>,
+}
+
+impl SmsMessage {
+    /// Renders the markdown as plain text, split into at most max_parts parts
+    pub fn from_markdown(common_markdown: &str, max_parts: usize) -> SmsMessage {
+        SmsMessage::new(&sms_text(common_markdown), max_parts)
+    }
+
+    pub fn new(text: &str, max_parts: usize) -> SmsMessage {
+        let text = replace_typographic_characters(text);
+        let encoding = if text.chars().all(|c| gsm_length(c).is_some()) {
+            SmsEncoding::Gsm7
+        } else {
+            SmsEncoding::Ucs2
+        };
+        let (single_length, part_length) = match encoding {
+            SmsEncoding::Gsm7 => (GSM_SINGLE_LENGTH, GSM_PART_LENGTH),
+            SmsEncoding::Ucs2 => (UCS2_SINGLE_LENGTH, UCS2_PART_LENGTH),
+        };
+        let length = |c: char| match encoding {
+            SmsEncoding::Gsm7 => gsm_length(c).unwrap_or(1),
+            SmsEncoding::Ucs2 => c.len_utf16(),
+        };
+
+        if text.chars().map(length).sum::<usize>() <= single_length {
+            return SmsMessage {
+                parts: vec![text.clone()],
+                text,
+                encoding,
+            };
+        }
+
+        // Leave room for the "..." at the end if it doesn't all fit
+        let max_length = part_length * max_parts.max(1);
+        let text = if text.chars().map(length).sum::<usize>() > max_length {
+            let mut truncated = String::new();
+            let mut truncated_length = 0;
+            for c in text.chars() {
+                if truncated_length + length(c) > max_length - TRUNCATED.len() {
+                    break;
+                }
+                truncated_length += length(c);
+                truncated.push(c);
+            }
+            format!("{}{}", truncated.trim_end(), TRUNCATED)
+        } else {
+            text
+        };
+
+        let mut parts = Vec::new();
+        let mut part = String::new();
+        let mut current_length = 0;
+        for c in text.chars() {
+            if current_length + length(c) > part_length {
+                parts.push(std::mem::take(&mut part));
+                current_length = 0;
+            }
+            current_length += length(c);
+            part.push(c);
+        }
+        if !part.is_empty() {
+            parts.push(part);
+        }
+
+        SmsMessage {
+            text,
+            encoding,
+            parts,
+        }
+    }
+}
+
+/// How many septets the character takes in the GSM 7 bit alphabet, None if it isn't in it
+fn gsm_length(c: char) -> Option<usize> {
+    if c != '\u{1b}' && GSM_ALPHABET.contains(&c) {
+        return Some(1);
+    }
+    if GSM_EXTENSION.iter().any(|(extension, _)| *extension == c) {
+        return Some(2);
+    }
+    None
+}
+
+// Templates often include characters that would need UCS-2, halving the length of the SMS, when there's a close GSM character
+fn replace_typographic_characters(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '‘' | '’' | '′' => "'".to_string(),
+            '“' | '”' | '″' => "\"".to_string(),
+            '–' | '—' | '‐' | '−' => "-".to_string(),
+            '…' => TRUNCATED.to_string(),
+            '\u{a0}' => " ".to_string(),
+            '•' => "-".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Converts common markdown to plain text for an SMS, without blank lines and with links written out
+pub fn sms_text(common_markdown: &str) -> String {
+    let mut text = String::new();
+    let mut list_number: Option<u64> = None;
+    let mut link_text_start = 0;
+
+    for event in Parser::new(common_markdown) {
+        match event {
+            Event::Text(value) | Event::Code(value) | Event::Html(value) => text.push_str(&value),
+            Event::SoftBreak | Event::HardBreak | Event::Rule => text.push('\n'),
+            Event::End(Tag::Paragraph)
+            | Event::End(Tag::Heading(..))
+            | Event::End(Tag::CodeBlock(_))
+            | Event::End(Tag::Item) => text.push('\n'),
+            Event::Start(Tag::Link(..)) => link_text_start = text.len(),
+            // Autolinks already show the url
+            Event::End(Tag::Link(_link_type, url, _title)) if text[link_text_start..] != *url => {
+                text.push_str(&format!(" ({})", url));
+            }
+            Event::Start(Tag::List(number)) => list_number = number,
+            Event::End(Tag::List(_)) => list_number = None,
+            Event::Start(Tag::Item) => match list_number {
+                Some(number) => {
+                    text.push_str(&format!("{}. ", number));
+                    list_number = Some(number + 1);
+                }
+                None => text.push_str("- "),
+            },
+            _ => {}
+        }
+    }
+
+    text.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sms_text() {
+        let cmarkdown = r#"# Sensor alert
+
+**Sensor** is _too hot_, see [details](http://example.com/)
+1. Freezer 1
+1. <http://example.com/fridge>
+"#; // Don't indent this!
+        assert_eq!(
+            sms_text(cmarkdown),
+            "Sensor alert\nSensor is too hot, see details (http://example.com/)\n1. Freezer 1\n2. http://example.com/fridge"
+        );
+    }
+
+    #[test]
+    fn test_sms_message_encoding() {
+        let message = SmsMessage::new("Temperature – 5.1C, it’s ok", 3);
+        assert_eq!(message.encoding, SmsEncoding::Gsm7);
+        assert_eq!(message.text, "Temperature - 5.1C, it's ok");
+        assert_eq!(message.parts, vec![message.text.clone()]);
+
+        // ° isn't in the GSM alphabet
+        let message = SmsMessage::new("Temperature 5.1°C", 3);
+        assert_eq!(message.encoding, SmsEncoding::Ucs2);
+        assert_eq!(message.parts.len(), 1);
+    }
+
+    #[test]
+    fn test_sms_message_parts() {
+        // Exactly fits in one SMS
+        let message = SmsMessage::new(&"a".repeat(160), 3);
+        assert_eq!(message.parts.len(), 1);
+
+        let message = SmsMessage::new(&"a".repeat(161), 3);
+        assert_eq!(message.parts.len(), 2);
+        assert_eq!(message.parts[0].len(), 153);
+        assert_eq!(message.parts.concat(), message.text);
+
+        // Extension characters take two septets
+        let message = SmsMessage::new(&"[".repeat(80), 3);
+        assert_eq!(message.parts.len(), 1);
+        let message = SmsMessage::new(&"€".repeat(81), 3);
+        assert_eq!(message.parts.len(), 2);
+        assert_eq!(message.parts[0].chars().count(), 76);
+
+        let message = SmsMessage::new(&"°".repeat(71), 3);
+        assert_eq!(message.encoding, SmsEncoding::Ucs2);
+        assert_eq!(message.parts.len(), 2);
+        assert_eq!(message.parts[0].chars().count(), 67);
+
+        // Too long for the allowed parts
+        let message = SmsMessage::new(&"word ".repeat(200), 2);
+        assert_eq!(message.parts.len(), 2);
+        assert!(message.text.ends_with("..."));
+        assert!(message.text.len() <= 306);
+    }
+
+    #[test]
+    fn test_gsm_alphabet() {
+        assert_eq!(GSM_ALPHABET[0x24], '¤');
+        assert_eq!(GSM_ALPHABET[0x41], 'A');
+        assert_eq!(GSM_ALPHABET[0x7F], 'à');
+        assert_eq!(gsm_length('\u{1b}'), None);
+    }
+}
@@ -0,0 +1,109 @@
+use async_trait::async_trait;
+
+use crate::settings::SmsSettings;
+
+use self::{http::HttpSmsGateway, message::SmsMessage, smpp::SmppGateway};
+
+pub mod http;
+pub mod message;
+pub mod smpp;
+#[cfg(test)]
+pub(crate) mod stub;
+
+const DEFAULT_MAX_PARTS: usize = 3;
+
+/// Sends an SMS to a phone number in E.164 format, e.g. +64211234567
+#[async_trait(?Send)]
+pub trait SmsGateway: Send + Sync {
+    async fn send_sms(&self, to: &str, message: &SmsMessage) -> Result<(), SmsError>;
+}
+
+#[derive(Debug)]
+pub enum SmsError {
+    InvalidNumber(String),
+    /// The gateway won't accept the message, retrying won't help
+    Permanent(String),
+    Temporary(String),
+}
+
+impl SmsError {
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, SmsError::InvalidNumber(_) | SmsError::Permanent(_))
+    }
+}
+
+pub struct SmsService {
+    gateway: Box<dyn SmsGateway>,
+    max_parts: usize,
+}
+
+impl SmsService {
+    /// Uses the SMPP gateway if it's configured, otherwise the HTTP gateway, None if neither are configured
+    pub fn new(settings: &SmsSettings) -> Option<SmsService> {
+        let gateway: Box<dyn SmsGateway> = match (&settings.smpp, &settings.http) {
+            (Some(smpp), _) => Box::new(SmppGateway::new(smpp.clone(), settings.sender.clone())),
+            (None, Some(http)) => {
+                Box::new(HttpSmsGateway::new(http.clone(), settings.sender.clone()))
+            }
+            (None, None) => return None,
+        };
+        Some(SmsService::with_gateway(
+            gateway,
+            settings.max_parts.unwrap_or(DEFAULT_MAX_PARTS),
+        ))
+    }
+
+    pub fn with_gateway(gateway: Box<dyn SmsGateway>, max_parts: usize) -> SmsService {
+        SmsService {
+            gateway,
+            max_parts: max_parts.max(1),
+        }
+    }
+
+    /// Sends the markdown as plain text, split into several parts if it's too long for one SMS
+    pub async fn send(&self, to: &str, common_markdown: &str) -> Result<(), SmsError> {
+        let to = normalise_phone_number(to);
+        if !is_e164(&to) {
+            return Err(SmsError::InvalidNumber(to));
+        }
+        let message = SmsMessage::from_markdown(common_markdown, self.max_parts);
+        self.gateway.send_sms(&to, &message).await
+    }
+}
+
+/// Removes the spaces, dashes, dots and brackets people often write phone numbers with
+pub fn normalise_phone_number(phone_number: &str) -> String {
+    phone_number
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect()
+}
+
+/// A + followed by the country code and number, up to 15 digits, e.g. +64211234567
+/// https://en.wikipedia.org/wiki/E.164
+pub fn is_e164(phone_number: &str) -> bool {
+    match phone_number.strip_prefix('+') {
+        Some(digits) => {
+            (2..=15).contains(&digits.len())
+                && !digits.starts_with('0')
+                && digits.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_e164() {
+        assert!(is_e164("+64211234567"));
+        assert_eq!(normalise_phone_number("+64 (21) 123-4567"), "+64211234567");
+        assert!(!is_e164("0211234567"));
+        assert!(!is_e164("+0211234567"));
+        assert!(!is_e164("+6421123456789012"));
+        assert!(!is_e164("+64 21 123 4567"));
+        assert!(!is_e164("+64abc"));
+    }
+}
@@ -0,0 +1,342 @@
+/*
+   A minimal SMPP 3.4 client, it binds as a transmitter, submits each part of the message and unbinds.
+   A new connection is used for each message, notifications aren't sent often enough to keep a session open.
+   https://smpp.org/SMPP_v3_4_Issue1_2.pdf
+*/
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+use crate::settings::SmppSettings;
+
+use super::{
+    message::{SmsEncoding, SmsMessage, GSM_ALPHABET, GSM_EXTENSION},
+    SmsError, SmsGateway,
+};
+
+pub(crate) const BIND_TRANSMITTER: u32 = 0x00000002;
+pub(crate) const SUBMIT_SM: u32 = 0x00000004;
+pub(crate) const UNBIND: u32 = 0x00000006;
+pub(crate) const ENQUIRE_LINK: u32 = 0x00000015;
+pub(crate) const GENERIC_NACK: u32 = 0x80000000;
+pub(crate) const RESPONSE: u32 = 0x80000000;
+
+const HEADER_LENGTH: usize = 16;
+const INTERFACE_VERSION: u8 = 0x34;
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+const TON_UNKNOWN: u8 = 0;
+const TON_INTERNATIONAL: u8 = 1;
+const TON_ALPHANUMERIC: u8 = 5;
+const NPI_UNKNOWN: u8 = 0;
+const NPI_ISDN: u8 = 1;
+
+pub(crate) const DATA_CODING_DEFAULT: u8 = 0x00;
+pub(crate) const DATA_CODING_UCS2: u8 = 0x08;
+// The short_message starts with a user data header, used to join the parts of a long message
+pub(crate) const ESM_CLASS_UDHI: u8 = 0x40;
+
+// The SMSC is busy or limiting how fast we send, trying again later should work
+const ESME_RMSGQFUL: u32 = 0x14;
+const ESME_RTHROTTLED: u32 = 0x58;
+const ESME_RSYSERR: u32 = 0x08;
+
+pub struct SmppGateway {
+    settings: SmppSettings,
+    sender: Option<String>,
+}
+
+impl SmppGateway {
+    pub fn new(settings: SmppSettings, sender: Option<String>) -> SmppGateway {
+        SmppGateway { settings, sender }
+    }
+
+    async fn submit(&self, to: &str, message: &SmsMessage) -> Result<(), SmsError> {
+        let mut session = SmppSession::connect(&self.settings).await?;
+
+        let mut bind = Vec::new();
+        put_c_string(&mut bind, &self.settings.system_id);
+        put_c_string(&mut bind, &self.settings.password);
+        put_c_string(&mut bind, &self.settings.system_type);
+        bind.extend_from_slice(&[INTERFACE_VERSION, TON_UNKNOWN, NPI_UNKNOWN]);
+        put_c_string(&mut bind, "");
+        session.request(BIND_TRANSMITTER, &bind).await?;
+
+        let reference = rand::random::<u8>();
+        let total = message.parts.len();
+        for (index, part) in message.parts.iter().enumerate() {
+            let mut short_message = Vec::new();
+            if total > 1 {
+                // Concatenated message header, with 8 bit reference number
+                short_message.extend_from_slice(&[
+                    0x05,
+                    0x00,
+                    0x03,
+                    reference,
+                    total as u8,
+                    index as u8 + 1,
+                ]);
+            }
+            let data_coding = match message.encoding {
+                SmsEncoding::Gsm7 => {
+                    short_message.extend(gsm_septets(part));
+                    DATA_CODING_DEFAULT
+                }
+                SmsEncoding::Ucs2 => {
+                    for unit in part.encode_utf16() {
+                        short_message.extend_from_slice(&unit.to_be_bytes());
+                    }
+                    DATA_CODING_UCS2
+                }
+            };
+
+            let mut submit = Vec::new();
+            put_c_string(&mut submit, ""); // service_type
+            let (source_ton, source_npi, source) = source_address(self.sender.as_deref());
+            submit.extend_from_slice(&[source_ton, source_npi]);
+            put_c_string(&mut submit, &source);
+            submit.extend_from_slice(&[TON_INTERNATIONAL, NPI_ISDN]);
+            put_c_string(&mut submit, to.trim_start_matches('+'));
+            let esm_class = if total > 1 { ESM_CLASS_UDHI } else { 0 };
+            submit.extend_from_slice(&[esm_class, 0, 0]); // esm_class, protocol_id, priority_flag
+            put_c_string(&mut submit, ""); // schedule_delivery_time
+            put_c_string(&mut submit, ""); // validity_period
+            submit.extend_from_slice(&[0, 0, data_coding, 0, short_message.len() as u8]);
+            submit.extend_from_slice(&short_message);
+            session.request(SUBMIT_SM, &submit).await?;
+        }
+
+        // The message has been accepted, so don't fail if the SMSC doesn't reply to the unbind
+        if let Err(e) = session.request(UNBIND, &[]).await {
+            log::warn!("SMPP unbind failed: {:?}", e);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl SmsGateway for SmppGateway {
+    async fn send_sms(&self, to: &str, message: &SmsMessage) -> Result<(), SmsError> {
+        match timeout(SEND_TIMEOUT, self.submit(to, message)).await {
+            Ok(result) => result,
+            Err(_) => Err(SmsError::Temporary(format!(
+                "Timed out sending SMS to {}:{}",
+                self.settings.host, self.settings.port
+            ))),
+        }
+    }
+}
+
+struct SmppSession {
+    stream: TcpStream,
+    sequence_number: u32,
+}
+
+impl SmppSession {
+    async fn connect(settings: &SmppSettings) -> Result<SmppSession, SmsError> {
+        let stream = TcpStream::connect((settings.host.as_str(), settings.port))
+            .await
+            .map_err(|e| {
+                SmsError::Temporary(format!(
+                    "Unable to connect to {}:{} - {}",
+                    settings.host, settings.port, e
+                ))
+            })?;
+        Ok(SmppSession {
+            stream,
+            sequence_number: 0,
+        })
+    }
+
+    /// Sends the pdu and waits for its response, replying to any enquire_link from the SMSC while waiting
+    async fn request(&mut self, command_id: u32, body: &[u8]) -> Result<(), SmsError> {
+        self.sequence_number += 1;
+        let sequence_number = self.sequence_number;
+        self.write_pdu(command_id, 0, sequence_number, body).await?;
+
+        loop {
+            let (response_id, status, response_sequence) = self.read_pdu().await?;
+            if response_id == ENQUIRE_LINK {
+                self.write_pdu(ENQUIRE_LINK | RESPONSE, 0, response_sequence, &[])
+                    .await?;
+                continue;
+            }
+            if response_id == GENERIC_NACK
+                || (response_id == command_id | RESPONSE && response_sequence == sequence_number)
+            {
+                return check_status(command_id, status);
+            }
+            log::debug!("Ignoring unexpected SMPP pdu {:#010x}", response_id);
+        }
+    }
+
+    async fn write_pdu(
+        &mut self,
+        command_id: u32,
+        status: u32,
+        sequence_number: u32,
+        body: &[u8],
+    ) -> Result<(), SmsError> {
+        let mut pdu = Vec::with_capacity(HEADER_LENGTH + body.len());
+        pdu.extend_from_slice(&((HEADER_LENGTH + body.len()) as u32).to_be_bytes());
+        pdu.extend_from_slice(&command_id.to_be_bytes());
+        pdu.extend_from_slice(&status.to_be_bytes());
+        pdu.extend_from_slice(&sequence_number.to_be_bytes());
+        pdu.extend_from_slice(body);
+        self.stream.write_all(&pdu).await.map_err(io_error)
+    }
+
+    /// Returns the command_id, command_status and sequence_number, the body isn't needed
+    async fn read_pdu(&mut self) -> Result<(u32, u32, u32), SmsError> {
+        let mut header = [0u8; HEADER_LENGTH];
+        self.stream
+            .read_exact(&mut header)
+            .await
+            .map_err(io_error)?;
+        let field = |index: usize| {
+            u32::from_be_bytes([
+                header[index],
+                header[index + 1],
+                header[index + 2],
+                header[index + 3],
+            ])
+        };
+        let length = field(0) as usize;
+        let mut body = vec![0u8; length.saturating_sub(HEADER_LENGTH)];
+        self.stream.read_exact(&mut body).await.map_err(io_error)?;
+        Ok((field(4), field(8), field(12)))
+    }
+}
+
+fn io_error(error: std::io::Error) -> SmsError {
+    SmsError::Temporary(format!("SMPP connection error - {}", error))
+}
+
+fn check_status(command_id: u32, status: u32) -> Result<(), SmsError> {
+    let description = format!(
+        "SMPP command {:#010x} failed with status {:#010x}",
+        command_id, status
+    );
+    match status {
+        0 => Ok(()),
+        ESME_RMSGQFUL | ESME_RTHROTTLED | ESME_RSYSERR => Err(SmsError::Temporary(description)),
+        _ => Err(SmsError::Permanent(description)),
+    }
+}
+
+fn put_c_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(value.as_bytes());
+    buffer.push(0);
+}
+
+// A sender made of digits is a phone number, anything else is an alphanumeric sender id
+fn source_address(sender: Option<&str>) -> (u8, u8, String) {
+    match sender {
+        None => (TON_UNKNOWN, NPI_UNKNOWN, String::new()),
+        Some(sender) => {
+            let number = sender.trim_start_matches('+');
+            if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+                (TON_INTERNATIONAL, NPI_ISDN, number.to_string())
+            } else {
+                (TON_ALPHANUMERIC, NPI_UNKNOWN, sender.to_string())
+            }
+        }
+    }
+}
+
+/// The text as unpacked GSM 03.38 septets, one per byte, which is what SMSCs expect for data_coding 0
+pub(crate) fn gsm_septets(text: &str) -> Vec<u8> {
+    let mut septets = Vec::new();
+    for c in text.chars() {
+        if let Some(position) = GSM_ALPHABET.iter().position(|gsm| *gsm == c) {
+            septets.push(position as u8);
+        } else if let Some((_, value)) = GSM_EXTENSION.iter().find(|(gsm, _)| *gsm == c) {
+            septets.extend_from_slice(&[0x1B, *value]);
+        } else {
+            // Only happens if the message wasn't checked for the encoding, shown as a ?
+            septets.push(0x3F);
+        }
+    }
+    septets
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sms::stub::SmppStub;
+
+    use super::*;
+
+    fn settings(port: u16) -> SmppSettings {
+        SmppSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            system_id: "dashboard".to_string(),
+            password: "secret".to_string(),
+            system_type: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_gsm_septets() {
+        assert_eq!(gsm_septets("A@£"), vec![0x41, 0x00, 0x01]);
+        assert_eq!(gsm_septets("€"), vec![0x1B, 0x65]);
+    }
+
+    #[actix_rt::test]
+    async fn test_smpp_gateway() {
+        let stub = SmppStub::start();
+        let gateway = SmppGateway::new(settings(stub.port()), Some("Alerts".to_string()));
+
+        let message = SmsMessage::new("Sensor is too hot", 3);
+        gateway.send_sms("+64211234567", &message).await.unwrap();
+        let submits = stub.submits();
+        assert_eq!(stub.binds(), vec!["dashboard".to_string()]);
+        assert_eq!(submits.len(), 1);
+        assert_eq!(submits[0].source_addr, "Alerts");
+        assert_eq!(submits[0].destination_addr, "64211234567");
+        assert_eq!(submits[0].data_coding, DATA_CODING_DEFAULT);
+        assert_eq!(submits[0].esm_class, 0);
+        assert_eq!(submits[0].short_message, b"Sensor is too hot".to_vec());
+
+        // Sent as two parts with a header so they are joined back together
+        let message = SmsMessage::new(&"°".repeat(100), 3);
+        gateway.send_sms("+64211234567", &message).await.unwrap();
+        let submits = stub.submits();
+        assert_eq!(submits.len(), 3);
+        assert_eq!(submits[1].data_coding, DATA_CODING_UCS2);
+        assert_eq!(submits[1].esm_class, ESM_CLASS_UDHI);
+        assert_eq!(submits[1].short_message[..3], [0x05, 0x00, 0x03]);
+        assert_eq!(submits[1].short_message[4..6], [2, 1]);
+        assert_eq!(submits[2].short_message[4..6], [2, 2]);
+        assert_eq!(submits[1].short_message.len(), 6 + 67 * 2);
+        assert_eq!(submits[1].short_message[3], submits[2].short_message[3]);
+
+        stub.set_submit_status(ESME_RTHROTTLED);
+        let result = gateway.send_sms("+64211234567", &message).await;
+        assert!(matches!(result, Err(SmsError::Temporary(_))));
+
+        // Invalid destination address
+        stub.set_submit_status(0x0B);
+        let result = gateway.send_sms("+64211234567", &message).await;
+        assert!(result.unwrap_err().is_permanent());
+    }
+
+    #[actix_rt::test]
+    async fn test_smpp_gateway_unavailable() {
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let gateway = SmppGateway::new(settings(port), None);
+        let result = gateway
+            .send_sms("+64211234567", &SmsMessage::new("Sensor is too hot", 3))
+            .await;
+        assert!(matches!(result, Err(SmsError::Temporary(_))));
+    }
+}
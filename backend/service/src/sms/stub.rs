@@ -0,0 +1,189 @@
+/*
+   A local SMSC standing in for an SMPP gateway in tests.
+   It accepts any bind, records each submit_sm and replies with the status set by set_submit_status, 0 by default.
+*/
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::smpp::{BIND_TRANSMITTER, RESPONSE, SUBMIT_SM, UNBIND};
+
+#[derive(Debug, Clone)]
+pub struct StubSubmit {
+    pub source_addr: String,
+    pub destination_addr: String,
+    pub esm_class: u8,
+    pub data_coding: u8,
+    pub short_message: Vec<u8>,
+}
+
+#[derive(Default)]
+struct StubState {
+    binds: Vec<String>,
+    submits: Vec<StubSubmit>,
+    submit_status: u32,
+}
+
+pub struct SmppStub {
+    port: u16,
+    state: Arc<Mutex<StubState>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl SmppStub {
+    pub fn start() -> SmppStub {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to start SMPP stub");
+        listener
+            .set_nonblocking(true)
+            .expect("Unable to start SMPP stub");
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_state = state.clone();
+        let thread_stopped = stopped.clone();
+        thread::spawn(move || {
+            while !thread_stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => handle_connection(stream, &thread_state),
+                    Err(_) => thread::sleep(Duration::from_millis(5)),
+                }
+            }
+        });
+
+        SmppStub {
+            port,
+            state,
+            stopped,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn set_submit_status(&self, status: u32) {
+        self.state.lock().unwrap().submit_status = status;
+    }
+
+    /// The system_id of each bind
+    pub fn binds(&self) -> Vec<String> {
+        self.state.lock().unwrap().binds.clone()
+    }
+
+    pub fn submits(&self) -> Vec<StubSubmit> {
+        self.state.lock().unwrap().submits.clone()
+    }
+}
+
+impl Drop for SmppStub {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &Mutex<StubState>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+
+    loop {
+        let mut header = [0u8; 16];
+        if stream.read_exact(&mut header).is_err() {
+            return;
+        }
+        let field = |index: usize| {
+            u32::from_be_bytes([
+                header[index],
+                header[index + 1],
+                header[index + 2],
+                header[index + 3],
+            ])
+        };
+        let (length, command_id, sequence_number) = (field(0) as usize, field(4), field(12));
+        let mut body = vec![0u8; length.saturating_sub(16)];
+        if stream.read_exact(&mut body).is_err() {
+            return;
+        }
+
+        let mut reader = PduReader { body: &body, at: 0 };
+        let (status, response_body) = match command_id {
+            BIND_TRANSMITTER => {
+                let system_id = reader.c_string();
+                state.lock().unwrap().binds.push(system_id);
+                (0, b"stub\0".to_vec())
+            }
+            SUBMIT_SM => {
+                let _service_type = reader.c_string();
+                reader.skip(2);
+                let source_addr = reader.c_string();
+                reader.skip(2);
+                let destination_addr = reader.c_string();
+                let esm_class = reader.byte();
+                reader.skip(2);
+                let _schedule_delivery_time = reader.c_string();
+                let _validity_period = reader.c_string();
+                reader.skip(2);
+                let data_coding = reader.byte();
+                reader.skip(1);
+                let sm_length = reader.byte() as usize;
+                let short_message = body[reader.at..reader.at + sm_length].to_vec();
+
+                let mut state = state.lock().unwrap();
+                state.submits.push(StubSubmit {
+                    source_addr,
+                    destination_addr,
+                    esm_class,
+                    data_coding,
+                    short_message,
+                });
+                (state.submit_status, b"1\0".to_vec())
+            }
+            _ => (0, Vec::new()),
+        };
+
+        let mut reply = Vec::new();
+        reply.extend_from_slice(&(16 + response_body.len() as u32).to_be_bytes());
+        reply.extend_from_slice(&(command_id | RESPONSE).to_be_bytes());
+        reply.extend_from_slice(&status.to_be_bytes());
+        reply.extend_from_slice(&sequence_number.to_be_bytes());
+        reply.extend_from_slice(&response_body);
+        if stream.write_all(&reply).is_err() || command_id == UNBIND {
+            return;
+        }
+    }
+}
+
+struct PduReader<'a> {
+    body: &'a [u8],
+    at: usize,
+}
+
+impl PduReader<'_> {
+    fn c_string(&mut self) -> String {
+        let end = self.body[self.at..]
+            .iter()
+            .position(|b| *b == 0)
+            .map(|position| self.at + position)
+            .unwrap_or(self.body.len());
+        let value = String::from_utf8_lossy(&self.body[self.at..end]).to_string();
+        self.at = end + 1;
+        value
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.at += 1;
+        self.body[self.at - 1]
+    }
+
+    fn skip(&mut self, count: usize) {
+        self.at += count;
+    }
+}
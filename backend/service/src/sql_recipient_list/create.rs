@@ -7,6 +7,7 @@ use super::{
     ModifySqlRecipientListError,
 };
 use crate::audit_log::audit_log_entry;
+use crate::datasource::{check_datasource_id_is_valid, datasource_id_from_input};
 use crate::service_provider::ServiceContext;
 
 use chrono::Utc;
@@ -22,12 +23,22 @@ pub struct CreateSqlRecipientList {
     pub description: String,
     pub query: String,
     pub required_parameters: Vec<String>,
+    pub datasource_id: Option<String>,
 }
 
 pub fn create_sql_recipient_list(
     ctx: &ServiceContext,
     new_sql_recipient_list: CreateSqlRecipientList,
 ) -> Result<SqlRecipientList, ModifySqlRecipientListError> {
+    if let Some(datasource_id) = &new_sql_recipient_list.datasource_id {
+        if !check_datasource_id_is_valid(ctx, datasource_id) {
+            return Err(ModifySqlRecipientListError::BadUserInput(format!(
+                "No datasource configured with id: {}",
+                datasource_id
+            )));
+        }
+    }
+
     let sql_recipient_list = ctx
         .connection
         .transaction_sync(|connection| {
@@ -86,6 +97,7 @@ pub fn generate(
         description,
         query,
         required_parameters,
+        datasource_id,
     }: CreateSqlRecipientList,
 ) -> Result<SqlRecipientListRow, ModifySqlRecipientListError> {
     let json_parameters = serde_json::to_value(required_parameters).map_err(|e| {
@@ -109,5 +121,6 @@ pub fn generate(
         required_parameters: json_parameters,
         created_at: Utc::now().naive_utc(),
         updated_at: Utc::now().naive_utc(),
+        datasource_id: datasource_id.and_then(datasource_id_from_input),
     })
 }
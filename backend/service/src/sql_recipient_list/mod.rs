@@ -68,6 +68,7 @@ pub trait SqlRecipientListServiceTrait: Sync + Send {
     fn get_recipients_by_sql_query(
         &self,
         ctx: &ServiceContext,
+        datasource_id: Option<String>,
         query: String,
         params: String,
     ) -> Result<ListResult<BasicRecipientRow>, ListError> {
@@ -76,7 +77,7 @@ pub trait SqlRecipientListServiceTrait: Sync + Send {
             ListError::InvalidRequest(format!("Invalid params string: {}. Error: {}", params, err))
         })?;
 
-        get_sql_recipients_by_sql_query(ctx, query, params)
+        get_sql_recipients_by_sql_query(ctx, datasource_id, query, params)
     }
 }
 
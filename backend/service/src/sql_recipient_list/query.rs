@@ -65,11 +65,12 @@ pub fn get_sql_recipients(
         }
     };
 
-    get_sql_recipients_by_sql_query(ctx, sql_list.query, params)
+    get_sql_recipients_by_sql_query(ctx, sql_list.datasource_id, sql_list.query, params)
 }
 
 pub fn get_sql_recipients_by_sql_query(
     ctx: &ServiceContext,
+    datasource_id: Option<String>,
     query: String,
     params: serde_json::Value,
 ) -> Result<ListResult<BasicRecipientRow>, ListError> {
@@ -92,7 +93,7 @@ pub fn get_sql_recipients_by_sql_query(
     let result = ctx
         .service_provider
         .datasource_service
-        .run_recipient_query(full_query)
+        .run_recipient_query(datasource_id, full_query)
         .map_err(|e| {
             ListError::InvalidRequest(format!("Failed to run query on datasource: {:?}", e))
         })?;
@@ -107,6 +107,22 @@ mod sql_recipient_list_create_test {
             ),
             Err(ModifySqlRecipientListError::InvalidSqlRecipientListName)
         );
+
+        // Create using a datasource that isn't configured
+        assert_eq!(
+            service.create_sql_recipient_list(
+                &context,
+                CreateSqlRecipientList {
+                    id: "some-new-id".to_string(),
+                    name: "some-new-name".to_string(),
+                    datasource_id: Some("not_configured".to_string()),
+                    ..Default::default()
+                },
+            ),
+            Err(ModifySqlRecipientListError::BadUserInput(
+                "No datasource configured with id: not_configured".to_string()
+            ))
+        );
     }
 
     #[actix_rt::test]
@@ -6,7 +6,11 @@ use super::{
     },
     ModifySqlRecipientListError,
 };
-use crate::{audit_log::audit_log_entry, service_provider::ServiceContext};
+use crate::{
+    audit_log::audit_log_entry,
+    datasource::{check_datasource_id_is_valid, datasource_id_from_input},
+    service_provider::ServiceContext,
+};
 use chrono::Utc;
 use repository::{
     LogType, SqlRecipientList, SqlRecipientListRow, SqlRecipientListRowRepository,
@@ -20,12 +24,23 @@ pub struct UpdateSqlRecipientList {
     pub description: Option<String>,
     pub query: Option<String>,
     pub required_parameters: Option<Vec<String>>,
+    /// An empty string resets the list to use the default datasource
+    pub datasource_id: Option<String>,
 }
 
 pub fn update_sql_recipient_list(
     ctx: &ServiceContext,
     updated_sql_recipient_list: UpdateSqlRecipientList,
 ) -> Result<SqlRecipientList, ModifySqlRecipientListError> {
+    if let Some(datasource_id) = &updated_sql_recipient_list.datasource_id {
+        if !check_datasource_id_is_valid(ctx, datasource_id) {
+            return Err(ModifySqlRecipientListError::BadUserInput(format!(
+                "No datasource configured with id: {}",
+                datasource_id
+            )));
+        }
+    }
+
     let sql_recipient_list = ctx
         .connection
         .transaction_sync(|connection| {
@@ -88,6 +103,7 @@ pub fn generate(
         description,
         query,
         required_parameters,
+        datasource_id,
     }: UpdateSqlRecipientList,
     current_sql_recipient_list_row: SqlRecipientListRow,
 ) -> Result<SqlRecipientListRow, ModifySqlRecipientListError> {
@@ -118,6 +134,9 @@ pub fn generate(
 
         new_sql_recipient_list_row.required_parameters = json_parameters;
     }
+    if let Some(datasource_id) = datasource_id {
+        new_sql_recipient_list_row.datasource_id = datasource_id_from_input(datasource_id);
+    }
 
     Ok(new_sql_recipient_list_row)
 }
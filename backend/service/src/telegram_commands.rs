@@ -0,0 +1,512 @@
+use chrono::{NaiveDateTime, Utc};
+use repository::{
+    EqualFilter, NotificationConfigKind, NotificationConfigRepository, NotificationType,
+    Pagination, Recipient, RecipientListFilter, RecipientListMemberFilter,
+    RecipientListMemberRepository, RecipientListRepository, RecipientRowRepository,
+    RepositoryError,
+};
+use serde::Serialize;
+use telegram::{TelegramChat, TelegramClient, TelegramCommand, TelegramUpdate};
+
+use crate::{
+    notification::renderer::render_template,
+    notification_config::query::NotificationConfig,
+    plugin::{PluginAlarm, PluginTrait},
+    recipient::ModifyRecipientError,
+    service_provider::ServiceContext,
+};
+
+#[derive(Debug)]
+pub enum TelegramCommandError {
+    DatabaseError(RepositoryError),
+    RenderError(tera::Error),
+    InternalError(String),
+}
+
+impl From<RepositoryError> for TelegramCommandError {
+    fn from(error: RepositoryError) -> Self {
+        TelegramCommandError::DatabaseError(error)
+    }
+}
+
+impl From<tera::Error> for TelegramCommandError {
+    fn from(error: tera::Error) -> Self {
+        TelegramCommandError::RenderError(error)
+    }
+}
+
+impl From<ModifyRecipientError> for TelegramCommandError {
+    fn from(error: ModifyRecipientError) -> Self {
+        match error {
+            ModifyRecipientError::DatabaseError(error) => {
+                TelegramCommandError::DatabaseError(error)
+            }
+            error => TelegramCommandError::InternalError(format!("{:?}", error)),
+        }
+    }
+}
+
+/// Replies to commands sent to the bot, the replies are rendered from the templates in `templates/telegram`
+pub async fn handle_telegram_commands(
+    ctx: ServiceContext,
+    client: TelegramClient,
+    channel: &tokio::sync::broadcast::Sender<TelegramUpdate>,
+    plugins: Vec<Box<dyn PluginTrait>>,
+) {
+    let mut rx = channel.subscribe();
+
+    loop {
+        let update = match rx.recv().await {
+            Ok(update) => update,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                log::error!("Telegram update lagged behind, something is delaying processing off telegram commands!");
+                continue;
+            }
+        };
+
+        let message = match update.message {
+            Some(message) => message,
+            None => continue,
+        };
+        let command = match message.text.as_deref().and_then(TelegramCommand::parse) {
+            Some(command) => command,
+            None => continue,
+        };
+        log::debug!("Received Telegram Command: {:?}", command);
+
+        let chat_id = message.chat.id.to_string();
+        let reply = match command_reply(
+            &ctx,
+            &plugins,
+            &message.chat,
+            command.clone(),
+            Utc::now().naive_utc(),
+        ) {
+            Ok(reply) => reply,
+            Err(e) => {
+                log::error!(
+                    "Failed to respond to {:?} in chat {}: {:?}",
+                    command,
+                    chat_id,
+                    e
+                );
+                "Sorry, something went wrong, please try again later.".to_string()
+            }
+        };
+
+        match client.send_html_message(&chat_id, &reply).await {
+            Ok(_) => log::debug!("Sent {:?} reply to chat id {}", command, chat_id),
+            Err(e) => log::error!(
+                "Failed to send {:?} reply to chat id {}: {:?}",
+                command,
+                chat_id,
+                e
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatParams {
+    chat_id: String,
+}
+
+#[derive(Serialize)]
+struct RecipientParams {
+    /// Whether the chat has been added as a recipient
+    is_recipient: bool,
+    muted_until: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+struct StatusConfigParams {
+    title: String,
+    alarms: Vec<PluginAlarm>,
+}
+
+#[derive(Serialize)]
+struct StatusParams {
+    #[serde(flatten)]
+    recipient: RecipientParams,
+    configs: Vec<StatusConfigParams>,
+}
+
+#[derive(Serialize)]
+struct SubscriptionConfigParams {
+    title: String,
+    status: String,
+}
+
+#[derive(Serialize)]
+struct SubscriptionsParams {
+    #[serde(flatten)]
+    recipient: RecipientParams,
+    configs: Vec<SubscriptionConfigParams>,
+    recipient_lists: Vec<String>,
+}
+
+/// Renders the reply to a command sent in `chat`, making any changes the command asks for (e.g. muting the chat)
+pub fn command_reply(
+    ctx: &ServiceContext,
+    plugins: &[Box<dyn PluginTrait>],
+    chat: &TelegramChat,
+    command: TelegramCommand,
+    now: NaiveDateTime,
+) -> Result<String, TelegramCommandError> {
+    let tera = ctx.service_provider.notification_service.tera();
+    let chat_id = chat.id.to_string();
+
+    let recipient = RecipientRowRepository::new(&ctx.connection)
+        .find_one_by_to_address_and_type(&chat_id, NotificationType::Telegram)?;
+
+    let reply = match command {
+        TelegramCommand::Hello => {
+            render_template(tera, "telegram/hello.html", ChatParams { chat_id })?
+        }
+        TelegramCommand::Help => {
+            render_template(tera, "telegram/help.html", ChatParams { chat_id })?
+        }
+        TelegramCommand::Status => {
+            let mut configs = Vec::new();
+            if let Some(recipient) = &recipient {
+                for config in recipient_notification_configs(ctx, recipient)? {
+                    if config.kind != NotificationConfigKind::ColdChain {
+                        continue;
+                    }
+                    let mut alarms = Vec::new();
+                    for plugin in plugins {
+                        let plugin_alarms = plugin.current_alarms(ctx, &config).map_err(|e| {
+                            TelegramCommandError::InternalError(format!(
+                                "{} plugin: {:?}",
+                                plugin.name(),
+                                e
+                            ))
+                        })?;
+                        alarms.extend(plugin_alarms);
+                    }
+                    configs.push(StatusConfigParams {
+                        title: config.title,
+                        alarms,
+                    });
+                }
+            }
+            let params = StatusParams {
+                recipient: recipient_params(recipient, now),
+                configs,
+            };
+            render_template(tera, "telegram/status.html", params)?
+        }
+        TelegramCommand::Subscriptions => {
+            let (configs, recipient_lists) = match &recipient {
+                Some(recipient) => (
+                    recipient_notification_configs(ctx, recipient)?
+                        .into_iter()
+                        .map(|config| SubscriptionConfigParams {
+                            title: config.title,
+                            status: format!("{:?}", config.status),
+                        })
+                        .collect(),
+                    recipient_lists(ctx, recipient)?
+                        .into_iter()
+                        .map(|recipient_list| recipient_list.name)
+                        .collect(),
+                ),
+                None => (vec![], vec![]),
+            };
+            let params = SubscriptionsParams {
+                recipient: recipient_params(recipient, now),
+                configs,
+                recipient_lists,
+            };
+            render_template(tera, "telegram/subscriptions.html", params)?
+        }
+        TelegramCommand::Mute(duration) => {
+            let muted_until = duration
+                .and_then(|duration| chrono::Duration::from_std(duration).ok())
+                .and_then(|duration| now.checked_add_signed(duration));
+            let recipient = match (recipient, muted_until) {
+                (Some(recipient), Some(muted_until)) => {
+                    Some(ctx.service_provider.recipient_service.mute_recipient(
+                        ctx,
+                        &recipient.id,
+                        Some(muted_until),
+                    )?)
+                }
+                // Without a valid duration the reply explains how to use the command
+                (recipient, _) => recipient.map(|recipient| Recipient {
+                    muted_until: None,
+                    ..recipient
+                }),
+            };
+            render_template(tera, "telegram/mute.html", recipient_params(recipient, now))?
+        }
+        TelegramCommand::Unmute => {
+            let recipient = match recipient {
+                Some(recipient) => Some(ctx.service_provider.recipient_service.mute_recipient(
+                    ctx,
+                    &recipient.id,
+                    None,
+                )?),
+                None => None,
+            };
+            render_template(
+                tera,
+                "telegram/unmute.html",
+                recipient_params(recipient, now),
+            )?
+        }
+    };
+
+    Ok(reply)
+}
+
+fn recipient_params(recipient: Option<Recipient>, now: NaiveDateTime) -> RecipientParams {
+    // A mute that has expired doesn't need mentioning
+    let muted_until = recipient
+        .as_ref()
+        .and_then(|recipient| recipient.muted_until)
+        .filter(|muted_until| *muted_until > now);
+    RecipientParams {
+        is_recipient: recipient.is_some(),
+        muted_until,
+    }
+}
+
+fn recipient_lists(
+    ctx: &ServiceContext,
+    recipient: &Recipient,
+) -> Result<Vec<repository::RecipientList>, RepositoryError> {
+    let recipient_list_ids = RecipientListMemberRepository::new(&ctx.connection)
+        .query_by_filter(
+            RecipientListMemberFilter::new().recipient_id(EqualFilter::equal_to(&recipient.id)),
+        )?
+        .into_iter()
+        .map(|member| member.recipient_list_id)
+        .collect();
+
+    RecipientListRepository::new(&ctx.connection)
+        .query_by_filter(RecipientListFilter::new().id(EqualFilter::equal_any(recipient_list_ids)))
+}
+
+/// Notification configs that include the recipient directly or through a recipient list.
+/// SQL recipient lists depend on the config's parameters, so configs only using those aren't included
+fn recipient_notification_configs(
+    ctx: &ServiceContext,
+    recipient: &Recipient,
+) -> Result<Vec<NotificationConfig>, RepositoryError> {
+    let recipient_list_ids: Vec<String> = recipient_lists(ctx, recipient)?
+        .into_iter()
+        .map(|recipient_list| recipient_list.id)
+        .collect();
+
+    let configs = NotificationConfigRepository::new(&ctx.connection)
+        .query(Pagination::all(), None, None)?
+        .into_iter()
+        .map(NotificationConfig::from)
+        .filter(|config| {
+            config.recipient_ids.contains(&recipient.id)
+                || config
+                    .recipient_list_ids
+                    .iter()
+                    .any(|id| recipient_list_ids.contains(id))
+        })
+        .collect();
+
+    Ok(configs)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use chrono::NaiveDateTime;
+    use repository::{
+        mock::{mock_recipient_c, MockDataInserts},
+        test_db::setup_all,
+        NotificationConfigKind, NotificationConfigRow, NotificationConfigRowRepository,
+        NotificationConfigStatus, RecipientListMemberRow, RecipientListMemberRowRepository,
+        RecipientListRow, RecipientListRowRepository, RecipientRowRepository,
+    };
+    use telegram::{TelegramChat, TelegramCommand};
+
+    use crate::{
+        notification_config::query::NotificationConfig,
+        plugin::{PluginAlarm, PluginError, PluginTrait},
+        service_provider::{ServiceContext, ServiceProvider},
+        test_utils::get_test_settings,
+    };
+
+    use super::command_reply;
+
+    struct TestPlugin {}
+
+    impl PluginTrait for TestPlugin {
+        fn new() -> Self {
+            TestPlugin {}
+        }
+
+        fn name(&self) -> String {
+            "Test".to_string()
+        }
+
+        fn current_alarms(
+            &self,
+            _ctx: &ServiceContext,
+            config: &NotificationConfig,
+        ) -> Result<Vec<PluginAlarm>, PluginError> {
+            if config.id != "coldchain_config" {
+                return Ok(vec![]);
+            }
+            Ok(vec![PluginAlarm {
+                name: "Fridge 1".to_string(),
+                description: "High temperature (9.5°C)".to_string(),
+                since_utc: NaiveDateTime::parse_from_str(
+                    "2024-03-25 01:30:00",
+                    "%Y-%m-%d %H:%M:%S",
+                )
+                .unwrap(),
+            }])
+        }
+    }
+
+    fn chat(id: &str) -> TelegramChat {
+        TelegramChat {
+            id: id.parse().unwrap(),
+            title: Some("Cold chain alerts".to_string()),
+            r#type: "group".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_telegram_command_replies() {
+        let (_, connection, connection_manager, _) = setup_all(
+            "test_telegram_command_replies",
+            MockDataInserts::none().recipients().permissions(),
+        )
+        .await;
+        let service_provider = Arc::new(ServiceProvider::new(
+            connection_manager,
+            get_test_settings(""),
+        ));
+        let context = ServiceContext::as_server_admin(service_provider).unwrap();
+        let plugins: Vec<Box<dyn PluginTrait>> = vec![Box::new(TestPlugin::new())];
+        let now =
+            NaiveDateTime::parse_from_str("2024-03-25 02:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        // Telegram chat ids are numbers
+        let chat_id = "-1001234";
+        let mut recipient = mock_recipient_c();
+        recipient.to_address = chat_id.to_string();
+        RecipientRowRepository::new(&connection)
+            .update_one(&recipient)
+            .unwrap();
+
+        RecipientListRowRepository::new(&connection)
+            .insert_one(&RecipientListRow {
+                id: "telegram_list".to_string(),
+                name: "Telegram chats".to_string(),
+                description: "".to_string(),
+            })
+            .unwrap();
+        RecipientListMemberRowRepository::new(&connection)
+            .insert_one(&RecipientListMemberRow {
+                id: "telegram_list_member".to_string(),
+                recipient_list_id: "telegram_list".to_string(),
+                recipient_id: recipient.id.clone(),
+            })
+            .unwrap();
+
+        let config_repository = NotificationConfigRowRepository::new(&connection);
+        config_repository
+            .insert_one(&NotificationConfigRow {
+                id: "coldchain_config".to_string(),
+                title: "Fridges".to_string(),
+                kind: NotificationConfigKind::ColdChain,
+                status: NotificationConfigStatus::Enabled,
+                recipient_ids: format!("[\"{}\"]", recipient.id),
+                recipient_list_ids: "[]".to_string(),
+                sql_recipient_list_ids: "[]".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        config_repository
+            .insert_one(&NotificationConfigRow {
+                id: "scheduled_config".to_string(),
+                title: "Weekly report".to_string(),
+                kind: NotificationConfigKind::Scheduled,
+                status: NotificationConfigStatus::Disabled,
+                recipient_ids: "[]".to_string(),
+                recipient_list_ids: "[\"telegram_list\"]".to_string(),
+                sql_recipient_list_ids: "[]".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        config_repository
+            .insert_one(&NotificationConfigRow {
+                id: "other_config".to_string(),
+                title: "Someone else's notification".to_string(),
+                kind: NotificationConfigKind::ColdChain,
+                recipient_ids: "[]".to_string(),
+                recipient_list_ids: "[]".to_string(),
+                sql_recipient_list_ids: "[]".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let reply = |chat_id: &str, command: TelegramCommand| {
+            command_reply(&context, &plugins, &chat(chat_id), command, now).unwrap()
+        };
+
+        assert!(reply(chat_id, TelegramCommand::Hello).contains("This chat_id is -1001234"));
+        assert!(reply(chat_id, TelegramCommand::Help).contains("/mute"));
+
+        let status = reply(chat_id, TelegramCommand::Status);
+        assert!(status.contains("<b>Fridges</b>"));
+        assert!(status.contains("Fridge 1: High temperature (9.5°C) since 25 Mar 2024 01:30 UTC"));
+        assert!(!status.contains("Weekly report"));
+        assert!(!status.contains("Someone else"));
+
+        let subscriptions = reply(chat_id, TelegramCommand::Subscriptions);
+        assert!(subscriptions.contains("- Fridges\n"));
+        assert!(subscriptions.contains("- Weekly report (Disabled)"));
+        assert!(subscriptions.contains("- Telegram chats"));
+        assert!(!subscriptions.contains("Someone else"));
+
+        // Muting
+        let mute = reply(chat_id, TelegramCommand::Mute(None));
+        assert!(mute.contains("Please tell me how long"));
+        let mute = reply(
+            chat_id,
+            TelegramCommand::Mute(Some(std::time::Duration::from_secs(2 * 60 * 60))),
+        );
+        assert!(mute.contains("muted until 25 Mar 2024 04:00 UTC"));
+        let muted_recipient = RecipientRowRepository::new(&connection)
+            .find_one_by_id(&recipient.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            muted_recipient.muted_until,
+            Some(now + chrono::Duration::hours(2))
+        );
+        assert!(reply(chat_id, TelegramCommand::Status).contains("muted until"));
+
+        let unmute = reply(chat_id, TelegramCommand::Unmute);
+        assert!(unmute.contains("no longer muted"));
+        let unmuted_recipient = RecipientRowRepository::new(&connection)
+            .find_one_by_id(&recipient.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(unmuted_recipient.muted_until, None);
+
+        // Chats that aren't recipients yet
+        let status = reply("999", TelegramCommand::Status);
+        assert!(status.contains("isn't receiving any notifications"));
+        let mute = reply(
+            "999",
+            TelegramCommand::Mute(Some(std::time::Duration::from_secs(60))),
+        );
+        assert!(mute.contains("isn't receiving any notifications"));
+    }
+}
@@ -0,0 +1,66 @@
+use repository::{
+    KeyValueStoreRepository, KeyValueType, RepositoryError, StorageConnectionManager,
+};
+use telegram::service::TelegramUpdateIdStore;
+
+/// Persists the last telegram update id in the key value store, so a restarted server
+/// picks up from where it left off rather than handling updates again
+pub struct KeyValueTelegramUpdateIdStore {
+    connection_manager: StorageConnectionManager,
+}
+
+impl KeyValueTelegramUpdateIdStore {
+    pub fn new(connection_manager: StorageConnectionManager) -> Self {
+        KeyValueTelegramUpdateIdStore { connection_manager }
+    }
+
+    fn get(&self) -> Result<Option<i64>, RepositoryError> {
+        let connection = self.connection_manager.connection()?;
+        KeyValueStoreRepository::new(&connection).get_i64(KeyValueType::TelegramLastUpdateId)
+    }
+
+    fn set(&self, update_id: i64) -> Result<(), RepositoryError> {
+        let connection = self.connection_manager.connection()?;
+        KeyValueStoreRepository::new(&connection)
+            .set_i64(KeyValueType::TelegramLastUpdateId, Some(update_id))
+    }
+}
+
+impl TelegramUpdateIdStore for KeyValueTelegramUpdateIdStore {
+    fn last_update_id(&self) -> Option<i64> {
+        self.get().unwrap_or_else(|e| {
+            log::error!("Failed to get last telegram update id: {:?}", e);
+            None
+        })
+    }
+
+    fn set_last_update_id(&self, update_id: i64) {
+        if let Err(e) = self.set(update_id) {
+            log::error!("Failed to save last telegram update id: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use repository::{mock::MockDataInserts, test_db::setup_all};
+    use telegram::service::TelegramUpdateIdStore;
+
+    use super::KeyValueTelegramUpdateIdStore;
+
+    #[actix_rt::test]
+    async fn test_telegram_update_id_store() {
+        let (_, _, connection_manager, _) =
+            setup_all("test_telegram_update_id_store", MockDataInserts::none()).await;
+
+        let store = KeyValueTelegramUpdateIdStore::new(connection_manager.clone());
+        assert_eq!(store.last_update_id(), None);
+
+        store.set_last_update_id(794348052);
+        assert_eq!(store.last_update_id(), Some(794348052));
+
+        // A new store, e.g. after a restart, reads the saved id
+        let store = KeyValueTelegramUpdateIdStore::new(connection_manager);
+        assert_eq!(store.last_update_id(), Some(794348052));
+    }
+}
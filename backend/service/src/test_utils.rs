@@ -1,20 +1,23 @@
-use datasource::{BasicRecipientRow, PostgresSettings};
+use datasource::{BasicRecipientRow, DatasourceBackend, DatasourceSettings, QueryLimits};
 use std::{
+    collections::HashMap,
     env,
     path::{Path, PathBuf},
 };
 
 use repository::{test_db::get_test_db_settings, StorageConnectionManager};
+use util::uuid::uuid;
 
 use crate::{
     datasource::DatasourceServiceTrait,
     datasource::QueryResult,
-    email::{send::EmailSendError, EmailServiceError, EmailServiceTrait},
-    service_provider::{ServiceContext, ServiceProvider},
-    settings::{MailSettings, ServerSettings, Settings, TelegramSettings},
+    email::{options::EmailOptions, send::EmailSendError, EmailServiceError, EmailServiceTrait},
+    notification::attachment::NotificationAttachment,
+    service_provider::ServiceProvider,
+    settings::{MailSettings, ServerSettings, Settings},
 };
 
-use self::telegram_test::get_telegram_token_from_env;
+use self::telegram_test::get_test_telegram_settings;
 
 pub fn find_base_dir() -> PathBuf {
     // Assume the base path is the base path of one of the project crates:
@@ -36,7 +39,6 @@ pub fn search_for_base_dir(path: &Path) -> Result<PathBuf, String> {
 // The following settings work for PG and Sqlite (username, password, host and port are
 // ignored for the later)
 pub fn get_test_settings(db_name: &str) -> Settings {
-    let telegram_token = get_telegram_token_from_env();
     Settings {
         server: ServerSettings {
             port: 5432,
@@ -52,19 +54,29 @@ pub fn get_test_settings(db_name: &str) -> Settings {
             username: "".to_string(),
             password: "".to_string(),
             from: "no-reply@msupply.foundation".to_string(),
+            sender_profiles: HashMap::new(),
+            fallback_relays: vec![],
+            max_connections: None,
+            dkim: None,
+            bounces: None,
+            layout: None,
         },
-        telegram: TelegramSettings {
-            token: telegram_token,
-        },
-        datasource: PostgresSettings {
+        telegram: get_test_telegram_settings(),
+        datasource: DatasourceSettings {
+            backend: DatasourceBackend::Postgres,
             username: String::from("postgres"),
             password: String::from("password"),
             port: 5432,
             host: "localhost".to_string(),
             database_name: String::from("dashboard"),
+            query_limits: QueryLimits::default(),
         },
+        datasources: HashMap::new(),
         logging: None,
         backup: Default::default(),
+        query_cache: Default::default(),
+        sms: None,
+        sending: Default::default(),
     }
 }
 
@@ -75,18 +87,16 @@ impl EmailServiceTrait for MockEmailService {
         Ok(true)
     }
 
-    fn send_queued_emails(&self, _ctx: &ServiceContext) -> Result<usize, EmailServiceError> {
-        Ok(0)
-    }
-
     fn send_email(
         &self,
         _to: String,
         _subject: String,
         _html_body: String,
         _text_body: String,
-    ) -> Result<(), EmailSendError> {
-        Ok(())
+        _attachments: Vec<NotificationAttachment>,
+        _options: EmailOptions,
+    ) -> Result<String, EmailSendError> {
+        Ok(format!("<{}@example.com>", uuid()))
     }
 }
 
@@ -94,29 +104,41 @@ pub struct MockDatasourceService {}
 impl DatasourceServiceTrait for MockDatasourceService {
     fn run_sql_query(
         &self,
+        _datasource_id: Option<String>,
         _sql_query: String,
+        _query_limits: Option<QueryLimits>,
     ) -> Result<QueryResult, crate::datasource::DatasourceServiceError> {
         todo!()
     }
 
     fn run_sql_query_with_parameters(
         &self,
+        _datasource_id: Option<String>,
         _sql_query: String,
         _parameters: serde_json::Value,
+        _query_limits: Option<QueryLimits>,
     ) -> Result<QueryResult, crate::datasource::DatasourceServiceError> {
         todo!()
     }
 
     fn run_recipient_query(
         &self,
+        _datasource_id: Option<String>,
         _sql_query: String,
     ) -> Result<Vec<BasicRecipientRow>, crate::datasource::DatasourceServiceError> {
         todo!()
     }
 
-    fn get_connection_pool(&self) -> datasource::DatasourcePool {
+    fn get_connection_pool(
+        &self,
+        _datasource_id: Option<String>,
+    ) -> Result<datasource::DatasourcePool, crate::datasource::DatasourceServiceError> {
         todo!()
     }
+
+    fn datasource_ids(&self) -> Vec<String> {
+        vec![]
+    }
 }
 
 // Create a service provider with a dummy email service
@@ -133,27 +155,86 @@ pub mod email_test {
     use crate::service_provider::ServiceContext;
 
     #[cfg(feature = "email-tests")]
-    pub fn send_test_emails(context: &ServiceContext) {
+    pub async fn send_test_emails(context: &ServiceContext) {
         context
             .service_provider
-            .email_service
-            .send_queued_emails(&context)
+            .notification_service
+            .send_queued_notifications(context)
+            .await
             .unwrap();
     }
 
     #[cfg(not(feature = "email-tests"))]
-    pub fn send_test_emails(_context: &ServiceContext) {
+    pub async fn send_test_emails(_context: &ServiceContext) {
         println!("Skipping email sending");
     }
 }
 
 pub mod telegram_test {
-    use crate::service_provider::ServiceContext;
+    #[cfg(any(test, feature = "telegram-mock"))]
+    use std::sync::OnceLock;
+
+    #[cfg(any(test, feature = "telegram-mock"))]
+    use telegram::{
+        mock::{MockTelegramApi, MOCK_TELEGRAM_TOKEN},
+        TelegramChat,
+    };
+
+    use crate::{service_provider::ServiceContext, settings::TelegramSettings};
+
+    /// A group the mock telegram api knows about, used when TELEGRAM_CHAT_ID isn't set
+    pub const MOCK_TELEGRAM_CHAT_ID: i64 = -1001;
+
+    /// The mock telegram api shared by tests, started the first time it's needed.
+    /// Other crates' tests get it with the telegram-mock feature
+    #[cfg(any(test, feature = "telegram-mock"))]
+    pub fn mock_telegram_api() -> &'static MockTelegramApi {
+        static MOCK_TELEGRAM_API: OnceLock<MockTelegramApi> = OnceLock::new();
+        MOCK_TELEGRAM_API.get_or_init(|| {
+            let mock_telegram_api = MockTelegramApi::start();
+            mock_telegram_api.add_chat(TelegramChat {
+                id: MOCK_TELEGRAM_CHAT_ID,
+                title: Some("Notify test group".to_string()),
+                r#type: "group".to_string(),
+                ..Default::default()
+            });
+            mock_telegram_api
+        })
+    }
+
+    #[cfg(any(test, feature = "telegram-mock"))]
+    pub fn mock_telegram_settings() -> TelegramSettings {
+        TelegramSettings {
+            token: Some(MOCK_TELEGRAM_TOKEN.to_string()),
+            base_url: Some(mock_telegram_api().url().to_string()),
+            max_message_parts: None,
+            webhook: None,
+        }
+    }
+
+    /// Uses telegram if TELEGRAM_TOKEN is set, otherwise the mock telegram api if it's available
+    pub fn get_test_telegram_settings() -> TelegramSettings {
+        match get_telegram_token_from_env() {
+            Some(token) => TelegramSettings {
+                token: Some(token),
+                base_url: None,
+                max_message_parts: None,
+                webhook: None,
+            },
+            #[cfg(any(test, feature = "telegram-mock"))]
+            None => mock_telegram_settings(),
+            #[cfg(not(any(test, feature = "telegram-mock")))]
+            None => TelegramSettings {
+                token: None,
+                base_url: None,
+                max_message_parts: None,
+                webhook: None,
+            },
+        }
+    }
 
     pub fn get_default_telegram_chat_id() -> String {
-        std::env::var("TELEGRAM_CHAT_ID").expect(
-            "Please set the TELEGRAM_CHAT_ID environment variable to run the telegram tests",
-        )
+        std::env::var("TELEGRAM_CHAT_ID").unwrap_or_else(|_| MOCK_TELEGRAM_CHAT_ID.to_string())
     }
 
     pub fn get_telegram_token_from_env() -> Option<String> {
@@ -161,25 +242,34 @@ pub mod telegram_test {
             Ok(token) => Some(token),
             Err(_) => {
                 println!(
-                    "Please set the TELEGRAM_TOKEN environment variable to run the telegram tests"
+                    "TELEGRAM_TOKEN environment variable isn't set, using the mock telegram api"
                 );
                 None
             }
         }
     }
 
-    #[cfg(feature = "telegram-tests")]
-    pub async fn send_test_notifications(context: &ServiceContext) {
-        context
-            .service_provider
-            .notification_service
-            .send_queued_notifications(&context)
-            .await
-            .unwrap();
+    #[cfg(any(test, feature = "telegram-mock"))]
+    fn is_using_mock_telegram_api(context: &ServiceContext) -> bool {
+        context.service_provider.settings.telegram.token.as_deref() == Some(MOCK_TELEGRAM_TOKEN)
     }
 
-    #[cfg(not(feature = "telegram-tests"))]
-    pub async fn send_test_notifications(_context: &ServiceContext) {
-        println!("Skipping notification sending");
+    #[cfg(not(any(test, feature = "telegram-mock")))]
+    fn is_using_mock_telegram_api(_context: &ServiceContext) -> bool {
+        false
+    }
+
+    /// Sends the queued notifications, to telegram only with the telegram-tests feature
+    pub async fn send_test_notifications(context: &ServiceContext) {
+        if cfg!(feature = "telegram-tests") || is_using_mock_telegram_api(context) {
+            context
+                .service_provider
+                .notification_service
+                .send_queued_notifications(context)
+                .await
+                .unwrap();
+        } else {
+            println!("Skipping notification sending");
+        }
     }
 }
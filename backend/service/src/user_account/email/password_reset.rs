@@ -1,6 +1,8 @@
 use crate::{
     email::{
         enqueue::{enqueue_email, EnqueueEmailData},
+        layout::{inline_css, EmailLayout},
+        options::EmailOptions,
         EmailServiceError,
     },
     service_provider::ServiceContext,
@@ -37,6 +39,7 @@ fn create_password_reset_email(
     to: &str,
     users: Vec<PasswordResetUser>,
     password_reset_base_url: &str,
+    layout: &EmailLayout,
 ) -> Result<EnqueueEmailData, EmailServiceError> {
     // Even though this takes an array of users, it should only ever be called for a single email address as only 1 email will be sent
 
@@ -50,6 +53,7 @@ fn create_password_reset_email(
     context.insert("title_text", &title_text);
     context.insert("button_text", &button_text);
     context.insert("url", &password_reset_base_url);
+    context.insert("layout", layout);
 
     let base_html_template = include_str!("../../email/base.html");
     let html_template = include_str!("templates/password_reset.html");
@@ -61,7 +65,7 @@ fn create_password_reset_email(
     .unwrap();
     let html = tera.render("password_reset.html", &context);
     let html = match html {
-        Ok(html) => html,
+        Ok(html) => inline_css(&html),
         Err(err) => {
             log::error!("Failed to render user invite email template: {}", err);
             return Err(EmailServiceError::GenericError(err.to_string()));
@@ -75,6 +79,8 @@ fn create_password_reset_email(
         subject: subject,
         html_body: html.to_string(),
         text_body: text,
+        attachments: vec![],
+        options: EmailOptions::default(),
     };
 
     Ok(email)
@@ -91,7 +97,12 @@ pub fn queue_password_reset_email(
 
     let users: Vec<PasswordResetUser> = users.into_iter().map(|u| u.into()).collect();
 
-    let email = create_password_reset_email(to, users, &password_reset_base_url)?;
+    let email = create_password_reset_email(
+        to,
+        users,
+        &password_reset_base_url,
+        &EmailLayout::from_settings(&ctx.service_provider.settings.mail),
+    )?;
     let enqueue = enqueue_email(ctx, email);
 
     //TODO: Trigger email sending
@@ -113,10 +124,11 @@ mod email_password_reset_test {
     use std::sync::Arc;
 
     use repository::{
-        mock::MockDataInserts, test_db::setup_all, EmailQueueRowRepository, UserAccount,
+        mock::MockDataInserts, test_db::setup_all, NotificationEventRowRepository, UserAccount,
     };
 
     use crate::{
+        email::layout::EmailLayout,
         service_provider::{ServiceContext, ServiceProvider},
         test_utils::{email_test::send_test_emails, get_test_settings},
         user_account::email::password_reset::{queue_password_reset_email, PasswordResetUser},
@@ -137,7 +149,12 @@ mod email_password_reset_test {
         };
 
         // Check that we can create a message with a single user
-        let message = super::create_password_reset_email(&to, vec![user1.clone()], &url);
+        let message = super::create_password_reset_email(
+            &to,
+            vec![user1.clone()],
+            &url,
+            &EmailLayout::default(),
+        );
         if message.is_err() {
             log::error!("Failed to create email: {:?}", message);
         }
@@ -151,7 +168,12 @@ mod email_password_reset_test {
             reset_token: "reset_token2".to_string(),
         };
 
-        let message = super::create_password_reset_email(&to, vec![user1, user2], &url);
+        let message = super::create_password_reset_email(
+            &to,
+            vec![user1, user2],
+            &url,
+            &EmailLayout::default(),
+        );
         if message.is_err() {
             log::error!("Failed to create email: {:?}", message);
         }
@@ -194,10 +216,10 @@ mod email_password_reset_test {
         assert!(result.is_ok());
 
         // Check that the email was queued
-        let repo = EmailQueueRowRepository::new(&context.connection);
+        let repo = NotificationEventRowRepository::new(&context.connection);
         let unsent = repo.un_sent().unwrap();
         assert_eq!(unsent.len(), 1);
         assert_eq!(unsent[0].to_address, "test@example.com");
-        send_test_emails(&context);
+        send_test_emails(&context).await;
     }
 }
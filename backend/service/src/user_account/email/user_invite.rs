@@ -1,6 +1,8 @@
 use crate::{
     email::{
         enqueue::{enqueue_email, EnqueueEmailData},
+        layout::{inline_css, EmailLayout},
+        options::EmailOptions,
         EmailServiceError,
     },
     service_provider::ServiceContext,
@@ -20,6 +22,7 @@ pub fn create_user_invite_email(
     to: &str,
     url: &str,
     params: &UserInviteParams,
+    layout: &EmailLayout,
 ) -> Result<EnqueueEmailData, EmailServiceError> {
     // TODO - put in an invite name/application name?
     let subject = format!(
@@ -32,6 +35,7 @@ pub fn create_user_invite_email(
     context.insert("inviteParams", &params);
     context.insert("title_text", &title_text);
     context.insert("button_text", "Accept Invitation");
+    context.insert("layout", layout);
 
     let base_html_template = include_str!("../../email/base.html");
     let html_template = include_str!("templates/user_invite.html");
@@ -43,7 +47,7 @@ pub fn create_user_invite_email(
     .unwrap();
     let html = tera.render("user_invite.html", &context);
     let html = match html {
-        Ok(html) => html,
+        Ok(html) => inline_css(&html),
         Err(err) => {
             log::error!("Failed to render user invite email template: {}", err);
             return Err(EmailServiceError::GenericError(err.to_string()));
@@ -57,6 +61,8 @@ pub fn create_user_invite_email(
         subject: subject,
         html_body: html.to_string(),
         text_body: text,
+        attachments: vec![],
+        options: EmailOptions::default(),
     };
 
     Ok(email)
@@ -72,7 +78,12 @@ pub fn queue_user_invite_email(
 
     let verify_account_url = format!("{}/verify-account?token={}", server_url, reset_token);
 
-    let email = create_user_invite_email(to, &verify_account_url, &params)?;
+    let email = create_user_invite_email(
+        to,
+        &verify_account_url,
+        &params,
+        &EmailLayout::from_settings(&ctx.service_provider.settings.mail),
+    )?;
     let enqueue = enqueue_email(ctx, email);
 
     match enqueue {
@@ -91,9 +102,10 @@ pub fn queue_user_invite_email(
 mod email_user_invite_test {
     use std::sync::Arc;
 
-    use repository::{mock::MockDataInserts, test_db::setup_all, EmailQueueRowRepository};
+    use repository::{mock::MockDataInserts, test_db::setup_all, NotificationEventRowRepository};
 
     use crate::{
+        email::layout::EmailLayout,
         service_provider::{ServiceContext, ServiceProvider},
         test_utils::{email_test::send_test_emails, get_test_settings},
         user_account::email::user_invite::{queue_user_invite_email, UserInviteParams},
@@ -113,7 +125,8 @@ mod email_user_invite_test {
         };
 
         // Check that we can create a message with a single user
-        let message = super::create_user_invite_email(&to, &url, &invite_params);
+        let message =
+            super::create_user_invite_email(&to, &url, &invite_params, &EmailLayout::default());
         if message.is_err() {
             log::error!("Failed to create email: {:?}", message);
         }
@@ -147,10 +160,10 @@ mod email_user_invite_test {
         assert!(result.is_ok());
 
         // Check that the email was queued
-        let repo = EmailQueueRowRepository::new(&context.connection);
+        let repo = NotificationEventRowRepository::new(&context.connection);
         let unsent = repo.un_sent().unwrap();
         assert_eq!(unsent.len(), 1);
         assert_eq!(unsent[0].to_address, "test@example.com");
-        send_test_emails(&context);
+        send_test_emails(&context).await;
     }
 }
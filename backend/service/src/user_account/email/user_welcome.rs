@@ -1,6 +1,8 @@
 use crate::{
     email::{
         enqueue::{enqueue_email, EnqueueEmailData},
+        layout::{inline_css, EmailLayout},
+        options::EmailOptions,
         EmailServiceError,
     },
     service_provider::ServiceContext,
@@ -21,6 +23,7 @@ pub fn create_welcome_email(
     to: &str,
     url: &str,
     params: &UserWelcomeParams,
+    layout: &EmailLayout,
 ) -> Result<EnqueueEmailData, EmailServiceError> {
     let subject = format!("Welcome to Notify");
     let title_text = "Welcome to Notify!";
@@ -30,6 +33,7 @@ pub fn create_welcome_email(
     context.insert("welcomeParams", &params);
     context.insert("title_text", &title_text);
     context.insert("button_text", &button_text);
+    context.insert("layout", layout);
 
     let base_html_template = include_str!("../../email/base.html");
     let html_template = include_str!("templates/user_welcome.html");
@@ -42,7 +46,7 @@ pub fn create_welcome_email(
 
     let html = tera.render("user_invite.html", &context);
     let html = match html {
-        Ok(html) => html,
+        Ok(html) => inline_css(&html),
         Err(err) => {
             log::error!("Failed to render user invite email template: {}", err);
             return Err(EmailServiceError::GenericError(err.to_string()));
@@ -55,6 +59,8 @@ pub fn create_welcome_email(
         subject: subject,
         html_body: html.to_string(),
         text_body: text,
+        attachments: vec![],
+        options: EmailOptions::default(),
     };
 
     Ok(email)
@@ -68,7 +74,12 @@ pub fn queue_user_welcome_email(
     let server_url = ctx.service_provider.settings.server.app_url.clone();
     let url = format!("{}/login", server_url);
 
-    let email = create_welcome_email(to, &url, params)?;
+    let email = create_welcome_email(
+        to,
+        &url,
+        params,
+        &EmailLayout::from_settings(&ctx.service_provider.settings.mail),
+    )?;
     let enqueue = enqueue_email(ctx, email);
 
     match enqueue {
@@ -88,9 +99,10 @@ pub fn queue_user_welcome_email(
 mod email_user_welcome_test {
     use std::sync::Arc;
 
-    use repository::{mock::MockDataInserts, test_db::setup_all, EmailQueueRowRepository};
+    use repository::{mock::MockDataInserts, test_db::setup_all, NotificationEventRowRepository};
 
     use crate::{
+        email::layout::EmailLayout,
         service_provider::{ServiceContext, ServiceProvider},
         test_utils::{email_test::send_test_emails, get_test_settings},
         user_account::email::user_welcome::{queue_user_welcome_email, UserWelcomeParams},
@@ -105,7 +117,8 @@ mod email_user_welcome_test {
             username: "supplier".to_string(),
         };
 
-        let message = super::create_welcome_email(&to, &url, &welcome_params);
+        let message =
+            super::create_welcome_email(&to, &url, &welcome_params, &EmailLayout::default());
         if message.is_err() {
             println!("Error: {:?}", message);
         }
@@ -132,10 +145,10 @@ mod email_user_welcome_test {
         assert!(result.is_ok());
 
         // Check that the email was queued
-        let repo = EmailQueueRowRepository::new(&context.connection);
+        let repo = NotificationEventRowRepository::new(&context.connection);
         let unsent = repo.un_sent().unwrap();
         assert_eq!(unsent.len(), 1);
         assert_eq!(unsent[0].to_address, "test@example.com");
-        send_test_emails(&context);
+        send_test_emails(&context).await;
     }
 }
@@ -1,16 +1,42 @@
 use http::StatusCode;
+use reqwest::multipart::{Form, Part};
 use serde::Serialize;
 use serde_json::{self, Value};
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use crate::{TelegramApiResponse, TelegramChat, TelegramMessage};
+use crate::{
+    service::markdown::{
+        cmark_to_plain_text, cmark_to_telegram_html, split_common_markdown, truncate_utf16,
+        MessagePart, MAX_MESSAGE_LENGTH,
+    },
+    TelegramApiResponse, TelegramChat, TelegramMessage, TelegramUser,
+};
+
+mod rate_limit;
+use rate_limit::RateLimiter;
+pub use rate_limit::RateLimits;
 
 const DEFAULT_REQUEST_TIMEOUT: u64 = 60;
+const DEFAULT_API_URL: &str = "https://api.telegram.org";
+const DEFAULT_MAX_MESSAGE_PARTS: usize = 4;
+// Telegram only shows the first 1024 UTF-16 code units of a document's caption
+const MAX_CAPTION_LENGTH: usize = 1024;
+const LONG_MESSAGE_FILENAME: &str = "message.txt";
+
+/// Options for the messages making up a notification
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MessageOptions {
+    /// Delivers the message silently, users get a notification without a sound
+    pub disable_notification: bool,
+}
 
 #[derive(Clone)]
 pub struct TelegramClient {
     http_client: reqwest::Client,
     base_url: String,
+    // Shared by clones of the client, so all messages from the bot count towards the limits
+    rate_limiter: Arc<RateLimiter>,
+    max_message_parts: usize,
 }
 
 #[derive(Debug)]
@@ -19,13 +45,70 @@ pub enum TemporaryErrorType {
     ConnectionError(String),
     InternalServerError(String),
     Flood(String),
+    /// Telegram (or our own rate limiting) says not to send to the chat until retry_after has passed
+    RateLimited {
+        description: String,
+        retry_after: Duration,
+    },
     Other(String),
 }
 
+impl TemporaryErrorType {
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            TemporaryErrorType::RateLimited { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum TelegramError {
     Fatal(String),
     Temporary(TemporaryErrorType),
+    /// The group was upgraded to a supergroup, messages need to be sent to the new chat id
+    ChatMigrated {
+        migrate_to_chat_id: i64,
+    },
+}
+
+impl TelegramError {
+    /// Works out the error from a response that isn't ok
+    /// https://core.telegram.org/bots/api#making-requests
+    fn from_response(response: &TelegramApiResponse, response_text: String) -> TelegramError {
+        if let Some(migrate_to_chat_id) = response
+            .parameters
+            .as_ref()
+            .and_then(|parameters| parameters.migrate_to_chat_id)
+        {
+            return TelegramError::ChatMigrated { migrate_to_chat_id };
+        }
+        match response.error_code {
+            Some(429) => {
+                let retry_after = response
+                    .parameters
+                    .as_ref()
+                    .and_then(|parameters| parameters.retry_after)
+                    .unwrap_or(1);
+                TelegramError::Temporary(TemporaryErrorType::RateLimited {
+                    description: response_text,
+                    retry_after: Duration::from_secs(retry_after.max(0) as u64),
+                })
+            }
+            Some(error_code) if error_code >= 500 => {
+                TelegramError::Temporary(TemporaryErrorType::InternalServerError(response_text))
+            }
+            _ => TelegramError::Fatal(response_text),
+        }
+    }
+
+    /// Telegram couldn't parse the formatting in a message, e.g. "Bad Request: can't parse entities: ..."
+    pub fn is_parse_error(&self) -> bool {
+        match self {
+            TelegramError::Fatal(text) => text.contains("can't parse entities"),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -66,17 +149,55 @@ impl From<reqwest::Error> for TelegramError {
 
 impl TelegramClient {
     pub fn new(token: String) -> TelegramClient {
+        TelegramClient::new_with_api_url(token, DEFAULT_API_URL)
+    }
+
+    /// Uses a different Bot API server, e.g. a local one
+    pub fn new_with_api_url(token: String, api_url: &str) -> TelegramClient {
         let http_client = reqwest::Client::builder()
             .timeout(Duration::from_secs(DEFAULT_REQUEST_TIMEOUT))
             .build()
             .expect("Something went unexpectedly wrong building the telegram reqwest client");
-        let url = format!("https://api.telegram.org/bot{}", token);
+        let url = format!("{}/bot{}", api_url.trim_end_matches('/'), token);
         TelegramClient {
-            http_client: http_client,
+            http_client,
             base_url: url,
+            rate_limiter: Arc::new(RateLimiter::new(RateLimits::default())),
+            max_message_parts: DEFAULT_MAX_MESSAGE_PARTS,
         }
     }
 
+    pub fn with_rate_limits(mut self, rate_limits: RateLimits) -> TelegramClient {
+        self.rate_limiter = Arc::new(RateLimiter::new(rate_limits));
+        self
+    }
+
+    /// Messages that would need to be split into more parts than this are sent as a document instead
+    pub fn with_max_message_parts(mut self, max_message_parts: usize) -> TelegramClient {
+        self.max_message_parts = max_message_parts.max(1);
+        self
+    }
+
+    /// The bot's own user, useful for checking the token works
+    pub async fn get_me(&self) -> Result<TelegramUser, TelegramError> {
+        let url = format!("{}/getMe", self.base_url);
+        let response = self.http_client.get(&url).send().await?;
+        let response_text = response.text().await?;
+
+        let telegram_response: TelegramApiResponse = serde_json::from_str(&response_text)
+            .map_err(|e| TelegramError::Fatal(e.to_string()))?;
+
+        if !telegram_response.ok {
+            return Err(TelegramError::from_response(
+                &telegram_response,
+                response_text,
+            ));
+        }
+
+        serde_json::from_value(telegram_response.result)
+            .map_err(|e| TelegramError::Fatal(e.to_string()))
+    }
+
     pub async fn get_name(&self) -> Result<String, TelegramError> {
         let url = format!("{}/getMyName", self.base_url);
         let response = self.http_client.get(&url).send().await?;
@@ -86,7 +207,10 @@ impl TelegramClient {
             .map_err(|e| TelegramError::Fatal(e.to_string()))?;
 
         if !telegram_response.ok {
-            return Err(TelegramError::Fatal(response_text));
+            return Err(TelegramError::from_response(
+                &telegram_response,
+                response_text,
+            ));
         }
 
         telegram_response
@@ -109,7 +233,10 @@ impl TelegramClient {
             .map_err(|e| TelegramError::Fatal(e.to_string()))?;
 
         if !telegram_response.ok {
-            return Err(TelegramError::Fatal(response_text));
+            return Err(TelegramError::from_response(
+                &telegram_response,
+                response_text,
+            ));
         }
 
         let chat: TelegramChat = serde_json::from_value(telegram_response.result)
@@ -124,45 +251,206 @@ impl TelegramClient {
         chat_id: &str,
         markdown_v2: &str,
     ) -> Result<TelegramMessage, TelegramError> {
-        let params = [
-            ("chat_id", chat_id),
-            ("text", &markdown_v2),
-            ("parse_mode", "MarkdownV2"),
-        ];
+        self.send_message(
+            chat_id,
+            markdown_v2,
+            Some("MarkdownV2"),
+            MessageOptions::default(),
+        )
+        .await
+    }
+
+    pub async fn send_html_message(
+        &self,
+        chat_id: &str,
+        html: &str,
+    ) -> Result<TelegramMessage, TelegramError> {
+        self.send_message(chat_id, html, Some("HTML"), MessageOptions::default())
+            .await
+    }
+
+    pub async fn send_plain_message(
+        &self,
+        chat_id: &str,
+        text: &str,
+    ) -> Result<TelegramMessage, TelegramError> {
+        self.send_message(chat_id, text, None, MessageOptions::default())
+            .await
+    }
+
+    async fn send_message(
+        &self,
+        chat_id: &str,
+        text: &str,
+        parse_mode: Option<&str>,
+        options: MessageOptions,
+    ) -> Result<TelegramMessage, TelegramError> {
+        let mut params = vec![("chat_id", chat_id), ("text", text)];
+        if let Some(parse_mode) = parse_mode {
+            params.push(("parse_mode", parse_mode));
+        }
+        if options.disable_notification {
+            params.push(("disable_notification", "true"));
+        }
         let url = format!("{}/sendMessage", self.base_url);
 
-        let response = self.http_client.post(&url).form(&params).send().await?;
-        let response_text = response.text().await?;
+        self.send_to_chat(chat_id, self.http_client.post(&url).form(&params))
+            .await
+    }
 
-        let telegram_response: TelegramApiResponse = serde_json::from_str(&response_text)
-            .map_err(|e| TelegramError::Fatal(format!("{}-{}", e.to_string(), response_text)))?;
+    /// Sends common markdown as MarkdownV2, split into several messages if it's longer than telegram allows.
+    /// If it would need more than `max_message_parts` messages, it's sent as a text document instead.
+    /// Parts before `sent_parts` were delivered by an earlier attempt and aren't sent again, `sent_parts` is
+    /// incremented as each part is sent. Returns the number of parts the message is sent in.
+    pub async fn send_common_markdown(
+        &self,
+        chat_id: &str,
+        common_markdown: &str,
+        options: MessageOptions,
+        sent_parts: &mut usize,
+    ) -> Result<usize, TelegramError> {
+        let parts = split_common_markdown(common_markdown, MAX_MESSAGE_LENGTH);
+
+        if parts.len() > self.max_message_parts {
+            if *sent_parts >= 1 {
+                return Ok(1);
+            }
+            log::info!(
+                "Message to telegram chat {} needs {} parts, sending it as a document",
+                chat_id,
+                parts.len()
+            );
+            let caption = truncate_utf16(&cmark_to_plain_text(common_markdown), MAX_CAPTION_LENGTH);
+            self.send_document(
+                chat_id,
+                LONG_MESSAGE_FILENAME,
+                "text/plain",
+                common_markdown.as_bytes().to_vec(),
+                Some(&caption),
+                options,
+            )
+            .await?;
+            *sent_parts = 1;
+            return Ok(1);
+        }
 
-        if !telegram_response.ok {
-            return Err(TelegramError::Fatal(response_text));
+        for part in parts.iter().skip(*sent_parts) {
+            self.send_message_part(chat_id, part, options).await?;
+            *sent_parts += 1;
         }
+        Ok(parts.len())
+    }
 
-        let message: TelegramMessage = serde_json::from_value(telegram_response.result)
-            .map_err(|e| TelegramError::Fatal(format!("Unable to interpret message - {:?}", e)))?;
+    // If telegram can't parse the MarkdownV2, try HTML, then plain text
+    async fn send_message_part(
+        &self,
+        chat_id: &str,
+        part: &MessagePart,
+        options: MessageOptions,
+    ) -> Result<TelegramMessage, TelegramError> {
+        match self
+            .send_message(chat_id, &part.markdown_v2, Some("MarkdownV2"), options)
+            .await
+        {
+            Err(error) if error.is_parse_error() => {
+                log::warn!(
+                    "Telegram couldn't parse MarkdownV2 message to {}, sending as HTML - {:?}",
+                    chat_id,
+                    error
+                );
+            }
+            result => return result,
+        }
 
-        Ok(message)
+        let html = cmark_to_telegram_html(&part.common_markdown);
+        match self
+            .send_message(chat_id, &html, Some("HTML"), options)
+            .await
+        {
+            Err(error) if error.is_parse_error() => {
+                log::warn!(
+                    "Telegram couldn't parse HTML message to {}, sending as plain text - {:?}",
+                    chat_id,
+                    error
+                );
+            }
+            result => return result,
+        }
+
+        let text = cmark_to_plain_text(&part.common_markdown);
+        self.send_message(chat_id, &text, None, options).await
     }
 
-    pub async fn send_html_message(
+    /// Sends a file, the caption is plain text and shown below the file
+    pub async fn send_document(
         &self,
         chat_id: &str,
-        html: &str,
+        filename: &str,
+        content_type: &str,
+        content: Vec<u8>,
+        caption: Option<&str>,
+        options: MessageOptions,
     ) -> Result<TelegramMessage, TelegramError> {
-        let params = [("chat_id", chat_id), ("text", html), ("parse_mode", "HTML")];
-        let url = format!("{}/sendMessage", self.base_url);
+        let document = Part::bytes(content)
+            .file_name(filename.to_string())
+            .mime_str(content_type)
+            .map_err(|e| TelegramError::Fatal(format!("Invalid content type - {:?}", e)))?;
+        let mut form = Form::new()
+            .text("chat_id", chat_id.to_string())
+            .part("document", document);
+        if let Some(caption) = caption {
+            form = form.text("caption", caption.to_string());
+        }
+        if options.disable_notification {
+            form = form.text("disable_notification", "true");
+        }
+        let url = format!("{}/sendDocument", self.base_url);
 
-        let response = self.http_client.post(&url).form(&params).send().await?;
+        self.send_to_chat(chat_id, self.http_client.post(&url).multipart(form))
+            .await
+    }
+
+    /// Sends a message once the rate limits allow it.
+    /// If that would take longer than `RateLimits::max_wait`, or telegram says we've sent too many,
+    /// a RateLimited error is returned with how long to wait before trying again.
+    async fn send_to_chat(
+        &self,
+        chat_id: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<TelegramMessage, TelegramError> {
+        match self.rate_limiter.reserve(chat_id) {
+            Ok(wait) if wait.is_zero() => {}
+            Ok(wait) => {
+                log::debug!("Waiting {:?} to send to telegram chat {}", wait, chat_id);
+                tokio::time::sleep(wait).await;
+            }
+            Err(retry_after) => {
+                return Err(TelegramError::Temporary(TemporaryErrorType::RateLimited {
+                    description: format!("Too many messages queued for chat {}", chat_id),
+                    retry_after,
+                }))
+            }
+        }
+
+        let response = request.send().await?;
         let response_text = response.text().await?;
 
         let telegram_response: TelegramApiResponse = serde_json::from_str(&response_text)
-            .map_err(|e| TelegramError::Fatal(format!("{}-{}", e.to_string(), response_text)))?;
+            .map_err(|e| TelegramError::Fatal(format!("{}-{}", e, response_text)))?;
 
         if !telegram_response.ok {
-            return Err(TelegramError::Fatal(response_text));
+            let error = TelegramError::from_response(&telegram_response, response_text);
+            if let TelegramError::Temporary(error) = &error {
+                if let Some(retry_after) = error.retry_after() {
+                    log::warn!(
+                        "Telegram rate limited chat {}, retry after {:?}",
+                        chat_id,
+                        retry_after
+                    );
+                    self.rate_limiter.block_chat(chat_id, retry_after);
+                }
+            }
+            return Err(error);
         }
 
         let message: TelegramMessage = serde_json::from_value(telegram_response.result)
@@ -171,6 +459,44 @@ impl TelegramClient {
         Ok(message)
     }
 
+    /// Telegram will POST updates to the url, with the secret_token in the X-Telegram-Bot-Api-Secret-Token header.
+    /// While a webhook is set `get_updates` can't be used.
+    pub async fn set_webhook(&self, url: &str, secret_token: &str) -> Result<(), TelegramError> {
+        // One connection at a time so updates arrive in order, the webhook ignores updates older than the last one it handled
+        let params = [
+            ("url", url),
+            ("secret_token", secret_token),
+            ("max_connections", "1"),
+        ];
+        let url = format!("{}/setWebhook", self.base_url);
+
+        let response = self.http_client.post(&url).form(&params).send().await?;
+        self.check_response(response).await
+    }
+
+    /// Removes any webhook, so updates can be received with `get_updates` again
+    pub async fn delete_webhook(&self) -> Result<(), TelegramError> {
+        let url = format!("{}/deleteWebhook", self.base_url);
+
+        let response = self.http_client.post(&url).send().await?;
+        self.check_response(response).await
+    }
+
+    async fn check_response(&self, response: reqwest::Response) -> Result<(), TelegramError> {
+        let response_text = response.text().await?;
+
+        let telegram_response: TelegramApiResponse = serde_json::from_str(&response_text)
+            .map_err(|e| TelegramError::Fatal(format!("{}-{}", e, response_text)))?;
+
+        if !telegram_response.ok {
+            return Err(TelegramError::from_response(
+                &telegram_response,
+                response_text,
+            ));
+        }
+        Ok(())
+    }
+
     /// last_update_id +1 maps to "offset" parameter, api description of offset:
     /// Identifier of the first update to be returned. Must be greater by one than the highest among the identifiers
     /// of previously received updates. By default, updates starting with the earliest unconfirmed update are returned.
@@ -196,7 +522,10 @@ impl TelegramClient {
             .map_err(|e| TelegramError::Fatal(e.to_string()))?;
 
         if !telegram_response.ok {
-            return Err(TelegramError::Fatal(response_text));
+            return Err(TelegramError::from_response(
+                &telegram_response,
+                response_text,
+            ));
         }
 
         let updates: Vec<Value> = serde_json::from_value(telegram_response.result)
@@ -265,6 +594,22 @@ mod telegram_test {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_send_document() {
+        let client = TelegramClient::new(get_telegram_token_from_env());
+        client
+            .send_document(
+                &get_telegram_chat_id_from_env(),
+                "test.csv",
+                "text/csv",
+                b"name,value\ntest,1\n".to_vec(),
+                Some("This is a test document from Notify"),
+                MessageOptions::default(),
+            )
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_send_markdown_message() {
         let client = TelegramClient::new(get_telegram_token_from_env());
@@ -277,3 +622,238 @@ mod telegram_test {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod mock_test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mock::{MockError, MockTelegramApi, MOCK_BOT_NAME};
+
+    #[tokio::test]
+    async fn test_mock_get_me_and_chat() {
+        let mock = MockTelegramApi::start();
+        let client = mock.client();
+
+        assert_eq!(
+            client.get_me().await.unwrap().username.unwrap(),
+            MOCK_BOT_NAME
+        );
+        assert_eq!(client.get_name().await.unwrap(), MOCK_BOT_NAME);
+
+        mock.add_chat(TelegramChat {
+            id: -1234,
+            title: Some("Cold chain alerts".to_string()),
+            r#type: "group".to_string(),
+            ..Default::default()
+        });
+        let chat = client.get_chat("-1234").await.unwrap();
+        assert_eq!(chat.title, Some("Cold chain alerts".to_string()));
+
+        assert!(matches!(
+            client.get_chat("5678").await,
+            Err(TelegramError::Fatal(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mock_send_and_receive() {
+        let mock = MockTelegramApi::start();
+        let client = mock.client();
+
+        let message = client
+            .send_html_message("1234", "<b>Hello</b>")
+            .await
+            .unwrap();
+        assert_eq!(message.chat.id, 1234);
+        client
+            .send_document(
+                "1234",
+                "test.csv",
+                "text/csv",
+                b"a,b\n1,2\n".to_vec(),
+                None,
+                MessageOptions {
+                    disable_notification: true,
+                },
+            )
+            .await
+            .unwrap();
+
+        let sent = mock.sent_messages_to("1234");
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].text, "<b>Hello</b>");
+        assert_eq!(sent[0].parse_mode, Some("HTML".to_string()));
+        assert!(!sent[0].disable_notification);
+        assert!(sent[1].is_document);
+        assert_eq!(sent[1].text, "test.csv");
+        assert!(sent[1].disable_notification);
+
+        mock.push_update(json!({
+            "update_id": 10,
+            "message": {
+                "message_id": 1,
+                "chat": {"id": 1234, "type": "private", "first_name": "User1"},
+                "text": "/help"
+            }
+        }));
+        let updates = client.get_updates(None, 0).await.unwrap();
+        assert_eq!(updates.len(), 1);
+        // Confirmed updates aren't returned again
+        let updates = client.get_updates(Some(10), 0).await.unwrap();
+        assert!(updates.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_rate_limited() {
+        let mock = MockTelegramApi::start();
+        let client = mock.client().with_rate_limits(RateLimits {
+            max_wait: Duration::from_secs(1),
+            ..RateLimits::default()
+        });
+        mock.push_error(
+            "sendMessage",
+            MockError {
+                error_code: 429,
+                description: "Too Many Requests: retry after 5".to_string(),
+                parameters: Some(json!({ "retry_after": 5 })),
+            },
+        );
+
+        let result = client.send_html_message("1234", "test").await;
+        match result {
+            Err(TelegramError::Temporary(error)) => {
+                assert_eq!(error.retry_after(), Some(Duration::from_secs(5)))
+            }
+            _ => panic!("Expected a rate limited error, got {:?}", result),
+        }
+
+        // The chat is held until retry_after has passed, without asking telegram again
+        let result = client.send_html_message("1234", "test").await;
+        match result {
+            Err(TelegramError::Temporary(TemporaryErrorType::RateLimited {
+                retry_after, ..
+            })) => assert!(retry_after > Duration::from_secs(4)),
+            _ => panic!("Expected a rate limited error, got {:?}", result),
+        }
+        assert!(mock.sent_messages_to("1234").is_empty());
+
+        // Other chats are still sent to
+        client.send_html_message("5678", "test").await.unwrap();
+        assert_eq!(mock.sent_messages_to("5678").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_chat_migrated() {
+        let mock = MockTelegramApi::start();
+        let client = mock.client();
+        mock.push_error(
+            "sendMessage",
+            MockError {
+                error_code: 400,
+                description: "Bad Request: group chat was upgraded to a supergroup chat"
+                    .to_string(),
+                parameters: Some(json!({ "migrate_to_chat_id": -1001234 })),
+            },
+        );
+
+        let result = client.send_html_message("-1234", "test").await;
+        assert!(matches!(
+            result,
+            Err(TelegramError::ChatMigrated {
+                migrate_to_chat_id: -1001234
+            })
+        ));
+    }
+    #[tokio::test]
+    async fn test_send_common_markdown_falls_back_to_html() {
+        let mock = MockTelegramApi::start();
+        let client = mock.client();
+        let parse_error = || {
+            MockError {
+            error_code: 400,
+            description: "Bad Request: can't parse entities: Can't find end of the entity starting at byte offset 5".to_string(),
+            parameters: None,
+        }
+        };
+        mock.push_error("sendMessage", parse_error());
+
+        client
+            .send_common_markdown(
+                "1234",
+                "**Sensor** is too hot",
+                MessageOptions::default(),
+                &mut 0,
+            )
+            .await
+            .unwrap();
+        let sent = mock.sent_messages_to("1234");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].text, "<b>Sensor</b> is too hot");
+        assert_eq!(sent[0].parse_mode, Some("HTML".to_string()));
+
+        // If the HTML can't be parsed either, it's sent without formatting
+        mock.push_error("sendMessage", parse_error());
+        mock.push_error("sendMessage", parse_error());
+        client
+            .send_common_markdown(
+                "5678",
+                "**Sensor** is too hot",
+                MessageOptions::default(),
+                &mut 0,
+            )
+            .await
+            .unwrap();
+        let sent = mock.sent_messages_to("5678");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].text, "Sensor is too hot");
+        assert_eq!(sent[0].parse_mode, None);
+    }
+
+    #[tokio::test]
+    async fn test_send_long_common_markdown() {
+        let mock = MockTelegramApi::start();
+        let client = mock.client().with_rate_limits(RateLimits {
+            private_chat_interval: Duration::ZERO,
+            ..RateLimits::default()
+        });
+        let report = (1..=400)
+            .map(|i| format!("**Sensor {}**: 5.1°C", i))
+            .collect::<Vec<String>>()
+            .join("\n\n");
+
+        let mut sent_parts = 0;
+        let parts = client
+            .send_common_markdown("1234", &report, MessageOptions::default(), &mut sent_parts)
+            .await
+            .unwrap();
+        let sent = mock.sent_messages_to("1234");
+        assert!(sent.len() > 1);
+        assert_eq!(sent.len(), parts);
+        assert_eq!(sent_parts, parts);
+        assert!(sent.iter().all(|message| !message.is_document
+            && message.text.encode_utf16().count() <= MAX_MESSAGE_LENGTH));
+
+        // A retry carries on from the first part that wasn't sent
+        let mut sent_parts = 2;
+        client
+            .send_common_markdown("9012", &report, MessageOptions::default(), &mut sent_parts)
+            .await
+            .unwrap();
+        let resent = mock.sent_messages_to("9012");
+        assert_eq!(resent.len(), parts - 2);
+        assert_eq!(resent[0].text, sent[2].text);
+        assert_eq!(sent_parts, parts);
+
+        // Too many parts are sent as a document
+        let client = client.with_max_message_parts(1);
+        client
+            .send_common_markdown("5678", &report, MessageOptions::default(), &mut 0)
+            .await
+            .unwrap();
+        let sent = mock.sent_messages_to("5678");
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].is_document);
+        assert_eq!(sent[0].text, LONG_MESSAGE_FILENAME);
+    }
+}
@@ -0,0 +1,184 @@
+/*
+   Telegram limits how quickly a bot can send messages, roughly 30 messages a second overall,
+   one a second to a private chat and 20 a minute to a group.
+   https://core.telegram.org/bots/faq#my-bot-is-hitting-limits-how-do-i-avoid-this
+
+   Going over the limits gets a 429 response with a retry_after, we space out messages so that
+   doesn't happen, and after a 429 hold messages to that chat until retry_after has passed.
+*/
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    /// Minimum time between any two messages sent by the bot
+    pub global_interval: Duration,
+    /// Minimum time between messages to the same private chat
+    pub private_chat_interval: Duration,
+    /// Minimum time between messages to the same group or channel
+    pub group_chat_interval: Duration,
+    /// Longest a message waits to be sent, after that a rate limit error is returned so the message can be retried later
+    pub max_wait: Duration,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            global_interval: Duration::from_millis(34),
+            private_chat_interval: Duration::from_secs(1),
+            group_chat_interval: Duration::from_secs(3),
+            max_wait: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RateLimiterState {
+    next_global_send: Option<Instant>,
+    next_chat_send: HashMap<String, Instant>,
+}
+
+pub(crate) struct RateLimiter {
+    limits: RateLimits,
+    state: Mutex<RateLimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        RateLimiter {
+            limits,
+            state: Mutex::new(RateLimiterState::default()),
+        }
+    }
+
+    /// Reserves the next time a message can be sent to the chat, and returns how long to wait for it.
+    /// If that's longer than `max_wait` nothing is reserved and the wait is returned as the error.
+    pub fn reserve(&self, chat_id: &str) -> Result<Duration, Duration> {
+        let now = Instant::now();
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            // Another send panicked while holding the lock, the timings are still usable
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let send_at = [
+            Some(now),
+            state.next_global_send,
+            state.next_chat_send.get(chat_id).copied(),
+        ]
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(now);
+        let wait = send_at - now;
+        if wait > self.limits.max_wait {
+            return Err(wait);
+        }
+
+        // Forget chats we could send to now, so the map doesn't grow forever
+        state.next_chat_send.retain(|_, next_send| *next_send > now);
+
+        state.next_global_send = Some(send_at + self.limits.global_interval);
+        state
+            .next_chat_send
+            .insert(chat_id.to_string(), send_at + self.chat_interval(chat_id));
+        Ok(wait)
+    }
+
+    /// Holds messages to the chat until `retry_after` has passed, after telegram said we're sending too many
+    pub fn block_chat(&self, chat_id: &str, retry_after: Duration) {
+        let blocked_until = Instant::now() + retry_after;
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let next_send = state
+            .next_chat_send
+            .entry(chat_id.to_string())
+            .or_insert(blocked_until);
+        if *next_send < blocked_until {
+            *next_send = blocked_until;
+        }
+    }
+
+    fn chat_interval(&self, chat_id: &str) -> Duration {
+        // Groups and channels have negative ids
+        if chat_id.starts_with('-') {
+            self.limits.group_chat_interval
+        } else {
+            self.limits.private_chat_interval
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limits() -> RateLimits {
+        RateLimits {
+            global_interval: Duration::from_millis(10),
+            private_chat_interval: Duration::from_secs(1),
+            group_chat_interval: Duration::from_secs(3),
+            max_wait: Duration::from_secs(5),
+        }
+    }
+
+    fn assert_about(actual: Duration, expected: Duration) {
+        // Allow for the time taken to run the test
+        assert!(
+            actual <= expected && actual + Duration::from_millis(100) > expected,
+            "expected about {:?} but got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_spaces_out_messages() {
+        let rate_limiter = RateLimiter::new(limits());
+
+        assert_eq!(rate_limiter.reserve("1"), Ok(Duration::ZERO));
+        // Another chat only waits for the global interval
+        assert_about(
+            rate_limiter.reserve("2").unwrap(),
+            Duration::from_millis(10),
+        );
+        // The same private chat waits a second
+        assert_about(rate_limiter.reserve("1").unwrap(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_rate_limiter_group_chat() {
+        let rate_limiter = RateLimiter::new(limits());
+
+        // Groups wait longer between messages
+        assert_eq!(rate_limiter.reserve("-100"), Ok(Duration::ZERO));
+        assert_about(
+            rate_limiter.reserve("-100").unwrap(),
+            Duration::from_secs(3),
+        );
+        // Until the wait is longer than max_wait
+        assert!(rate_limiter.reserve("-100").is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_blocked_chat() {
+        let rate_limiter = RateLimiter::new(limits());
+
+        rate_limiter.block_chat("1", Duration::from_secs(60));
+        let retry_after = rate_limiter.reserve("1").unwrap_err();
+        assert_about(retry_after, Duration::from_secs(60));
+
+        // Other chats can still be sent to
+        assert_eq!(rate_limiter.reserve("2"), Ok(Duration::ZERO));
+
+        rate_limiter.block_chat("3", Duration::from_secs(2));
+        assert_about(rate_limiter.reserve("3").unwrap(), Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+/// Commands the bot responds to, sent as the first word of a message e.g. `/mute 2h`.
+/// In group chats telegram appends the bot's username, e.g. `/status@notify_bot`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelegramCommand {
+    /// `/hello`, `/start` or `/chatid`
+    Hello,
+    Help,
+    Status,
+    Subscriptions,
+    /// `/mute <duration>`, None if the duration is missing or can't be parsed
+    Mute(Option<Duration>),
+    Unmute,
+}
+
+impl TelegramCommand {
+    /// Returns None if the text isn't a command the bot knows
+    pub fn parse(text: &str) -> Option<TelegramCommand> {
+        let mut words = text.split_whitespace();
+        let command = words.next()?.strip_prefix('/')?;
+        let command = command.split('@').next().unwrap_or_default();
+
+        let command = match command.to_lowercase().as_str() {
+            "hello" | "start" | "chatid" => TelegramCommand::Hello,
+            "help" => TelegramCommand::Help,
+            "status" => TelegramCommand::Status,
+            "subscriptions" => TelegramCommand::Subscriptions,
+            "mute" => TelegramCommand::Mute(words.next().and_then(parse_duration)),
+            "unmute" => TelegramCommand::Unmute,
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+/// Parses durations like `30m`, `2h`, `1d` or `1w`
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    let unit_start = text.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = text.split_at(unit_start);
+    let amount: u64 = amount.parse().ok()?;
+
+    let unit_seconds = match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    if amount == 0 {
+        return None;
+    }
+    amount.checked_mul(unit_seconds).map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            TelegramCommand::parse("/hello"),
+            Some(TelegramCommand::Hello)
+        );
+        assert_eq!(
+            TelegramCommand::parse("/chatid"),
+            Some(TelegramCommand::Hello)
+        );
+        assert_eq!(
+            TelegramCommand::parse("/status@notify_bot"),
+            Some(TelegramCommand::Status)
+        );
+        assert_eq!(
+            TelegramCommand::parse("  /Subscriptions please"),
+            Some(TelegramCommand::Subscriptions)
+        );
+        assert_eq!(
+            TelegramCommand::parse("/mute 2h"),
+            Some(TelegramCommand::Mute(Some(Duration::from_secs(
+                2 * 60 * 60
+            ))))
+        );
+        assert_eq!(
+            TelegramCommand::parse("/mute@notify_bot 30m"),
+            Some(TelegramCommand::Mute(Some(Duration::from_secs(30 * 60))))
+        );
+        assert_eq!(
+            TelegramCommand::parse("/mute"),
+            Some(TelegramCommand::Mute(None))
+        );
+        assert_eq!(
+            TelegramCommand::parse("/mute forever"),
+            Some(TelegramCommand::Mute(None))
+        );
+        assert_eq!(
+            TelegramCommand::parse("/unmute"),
+            Some(TelegramCommand::Unmute)
+        );
+
+        assert_eq!(TelegramCommand::parse("hello"), None);
+        assert_eq!(TelegramCommand::parse("/unknown"), None);
+        assert_eq!(TelegramCommand::parse(""), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("1d"),
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2weeks"),
+            Some(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+        assert_eq!(parse_duration("0h"), None);
+        assert_eq!(parse_duration("5"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("5y"), None);
+    }
+}
@@ -1,7 +1,11 @@
 mod client;
+mod command;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod service;
 
 pub use client::*;
+pub use command::*;
 use serde::{Deserialize, Serialize};
 // Rather than use an existing Telegram Client, we've implemented a minimal one in this crate.
 // If we need more functionality we can flesh out this crate, or refactor using another library.
@@ -92,6 +96,9 @@ pub struct TelegramMessage {
     pub text: Option<String>,
     pub from: Option<TelegramUser>,
     pub chat: TelegramChat,
+    // When a group is upgraded to a supergroup it gets a new id, telegram sends a message to each chat with the other's id
+    pub migrate_to_chat_id: Option<i64>,
+    pub migrate_from_chat_id: Option<i64>,
 }
 
 /*
@@ -136,6 +143,20 @@ pub struct TelegramChatMember {
     pub status: String,
 }
 
+impl TelegramChatMember {
+    /// The member has left, or been removed from, the chat
+    pub fn has_left(&self) -> bool {
+        matches!(self.status.as_str(), "left" | "kicked")
+    }
+
+    pub fn is_member(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "creator" | "administrator" | "member" | "restricted"
+        )
+    }
+}
+
 // Note: TelegramMyChatMember is triggered when the bot is first added to a chat group, and when the bot is removed from a chat group
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub struct TelegramMyChatMember {
@@ -180,8 +201,17 @@ impl TelegramUpdate {
 pub struct TelegramApiResponse {
     pub ok: bool,
     pub description: Option<String>,
+    // Error responses don't include a result
+    #[serde(default)]
     pub result: serde_json::Value,
     pub error_code: Option<i64>,
+    pub parameters: Option<TelegramResponseParameters>,
+}
+
+// https://core.telegram.org/bots/api#responseparameters
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct TelegramResponseParameters {
+    pub migrate_to_chat_id: Option<i64>,
     pub retry_after: Option<i64>,
 }
 
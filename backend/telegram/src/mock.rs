@@ -0,0 +1,459 @@
+/*
+   A small stand in for the Telegram Bot API, so the client and the services using it can be tested without a network connection or a real bot.
+
+   It serves getMe, getMyName, getChat, sendMessage, sendDocument, getUpdates, setWebhook and deleteWebhook for any token,
+   remembers the messages sent so tests can check them, and returns updates added with `push_update` from getUpdates.
+
+   The server runs on its own thread with its own runtime, so it works from sync code and from any async runtime,
+   and stops when the MockTelegramApi is dropped.
+*/
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{oneshot, Notify},
+};
+
+use crate::{TelegramChat, TelegramClient};
+
+pub const MOCK_TELEGRAM_TOKEN: &str = "mock_token";
+pub const MOCK_BOT_NAME: &str = "notify_mock_bot";
+
+/// A message (or document) sent to the mock
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockSentMessage {
+    pub chat_id: String,
+    /// The message text, or for documents the filename
+    pub text: String,
+    pub parse_mode: Option<String>,
+    pub is_document: bool,
+    pub disable_notification: bool,
+}
+
+/// An error response to return instead of the usual result
+#[derive(Debug, Clone)]
+pub struct MockError {
+    pub error_code: i64,
+    pub description: String,
+    pub parameters: Option<Value>,
+}
+
+#[derive(Default)]
+struct MockState {
+    chats: HashMap<String, TelegramChat>,
+    sent_messages: Vec<MockSentMessage>,
+    updates: Vec<Value>,
+    // Returned by the next request to the method, e.g. "sendMessage"
+    errors: HashMap<String, Vec<MockError>>,
+    // Returned by the next request to the method for the chat, so tests sharing the mock don't get each other's errors
+    chat_errors: HashMap<(String, String), Vec<MockError>>,
+    next_message_id: u64,
+}
+
+pub struct MockTelegramApi {
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    new_update: Arc<Notify>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockTelegramApi {
+    pub fn start() -> MockTelegramApi {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")
+            .expect("Unable to bind mock telegram api listener");
+        listener
+            .set_nonblocking(true)
+            .expect("Unable to set mock telegram api listener to non blocking");
+        let url = format!(
+            "http://{}",
+            listener
+                .local_addr()
+                .expect("Unable to get mock telegram api address")
+        );
+
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let new_update = Arc::new(Notify::new());
+        let (shutdown, shutdown_rx) = oneshot::channel();
+
+        let server_state = state.clone();
+        let server_new_update = new_update.clone();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Unable to build mock telegram api runtime");
+            runtime.block_on(async move {
+                let listener = TcpListener::from_std(listener)
+                    .expect("Unable to create mock telegram api listener");
+                tokio::select! {
+                    _ = serve(listener, server_state, server_new_update) => {},
+                    _ = shutdown_rx => {},
+                }
+            });
+        });
+
+        MockTelegramApi {
+            url,
+            state,
+            new_update,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// The api url to give to `TelegramClient::new_with_api_url`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn client(&self) -> TelegramClient {
+        TelegramClient::new_with_api_url(MOCK_TELEGRAM_TOKEN.to_string(), &self.url)
+    }
+
+    /// Chats that getChat knows about, messages can be sent to any chat id
+    pub fn add_chat(&self, chat: TelegramChat) {
+        self.state().chats.insert(chat.id.to_string(), chat);
+    }
+
+    /// Adds an update to be returned by getUpdates
+    pub fn push_update(&self, update: Value) {
+        self.state().updates.push(update);
+        self.new_update.notify_waiters();
+    }
+
+    /// Makes the next request to the method (e.g. "sendMessage") fail with the error
+    pub fn push_error(&self, method: &str, error: MockError) {
+        self.state()
+            .errors
+            .entry(method.to_string())
+            .or_default()
+            .push(error);
+    }
+
+    /// Makes the next request to the method for the chat fail with the error
+    pub fn push_chat_error(&self, chat_id: &str, method: &str, error: MockError) {
+        self.state()
+            .chat_errors
+            .entry((chat_id.to_string(), method.to_string()))
+            .or_default()
+            .push(error);
+    }
+
+    pub fn sent_messages(&self) -> Vec<MockSentMessage> {
+        self.state().sent_messages.clone()
+    }
+
+    pub fn sent_messages_to(&self, chat_id: &str) -> Vec<MockSentMessage> {
+        self.sent_messages()
+            .into_iter()
+            .filter(|message| message.chat_id == chat_id)
+            .collect()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        lock(&self.state)
+    }
+}
+
+impl Drop for MockTelegramApi {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn lock(state: &Mutex<MockState>) -> std::sync::MutexGuard<'_, MockState> {
+    // A panicking test shouldn't break the mock for other tests
+    match state.lock() {
+        Ok(state) => state,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<Mutex<MockState>>, new_update: Arc<Notify>) {
+    loop {
+        let (socket, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                log::error!("Mock telegram api unable to accept connection: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(handle_connection(socket, state.clone(), new_update.clone()));
+    }
+}
+
+struct MockRequest {
+    method: String,
+    params: HashMap<String, String>,
+}
+
+async fn handle_connection(
+    mut socket: TcpStream,
+    state: Arc<Mutex<MockState>>,
+    new_update: Arc<Notify>,
+) {
+    // Connections are closed after each response, so there's one request per connection
+    let request = match read_request(&mut socket).await {
+        Some(request) => request,
+        None => return,
+    };
+
+    let response = match handle_request(&request, &state, &new_update).await {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(error) => {
+            let mut response = json!({
+                "ok": false,
+                "error_code": error.error_code,
+                "description": error.description,
+            });
+            if let Some(parameters) = error.parameters {
+                response["parameters"] = parameters;
+            }
+            response
+        }
+    };
+
+    let body = response.to_string();
+    let status = match response["error_code"].as_i64() {
+        Some(429) => "429 Too Many Requests",
+        Some(_) => "400 Bad Request",
+        None => "200 OK",
+    };
+    let http_response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(http_response.as_bytes()).await;
+    let _ = socket.shutdown().await;
+}
+
+async fn handle_request(
+    request: &MockRequest,
+    state: &Mutex<MockState>,
+    new_update: &Notify,
+) -> Result<Value, MockError> {
+    let param = |name: &str| request.params.get(name).cloned().unwrap_or_default();
+
+    if let Some(error) = lock(state)
+        .errors
+        .get_mut(&request.method)
+        .filter(|errors| !errors.is_empty())
+        .map(|errors| errors.remove(0))
+    {
+        return Err(error);
+    }
+    if let Some(error) = lock(state)
+        .chat_errors
+        .get_mut(&(param("chat_id"), request.method.clone()))
+        .filter(|errors| !errors.is_empty())
+        .map(|errors| errors.remove(0))
+    {
+        return Err(error);
+    }
+
+    match request.method.as_str() {
+        "getMe" => Ok(json!({
+            "id": 1,
+            "is_bot": true,
+            "first_name": MOCK_BOT_NAME,
+            "username": MOCK_BOT_NAME,
+        })),
+        "getMyName" => Ok(json!({ "name": MOCK_BOT_NAME })),
+        "getChat" => match lock(state).chats.get(&param("chat_id")) {
+            Some(chat) => Ok(json!(chat)),
+            None => Err(MockError {
+                error_code: 400,
+                description: "Bad Request: chat not found".to_string(),
+                parameters: None,
+            }),
+        },
+        "sendMessage" | "sendDocument" => {
+            let is_document = request.method == "sendDocument";
+            let chat_id = param("chat_id");
+            let text = if is_document {
+                param("document")
+            } else {
+                param("text")
+            };
+
+            let mut state = lock(state);
+            state.next_message_id += 1;
+            let message_id = state.next_message_id;
+            let chat = state.chats.get(&chat_id).cloned().unwrap_or(TelegramChat {
+                id: chat_id.parse().unwrap_or_default(),
+                r#type: "private".to_string(),
+                ..Default::default()
+            });
+            state.sent_messages.push(MockSentMessage {
+                chat_id,
+                text: text.clone(),
+                parse_mode: request.params.get("parse_mode").cloned(),
+                is_document,
+                disable_notification: request
+                    .params
+                    .get("disable_notification")
+                    .is_some_and(|value| value == "true"),
+            });
+
+            Ok(json!({
+                "message_id": message_id,
+                "chat": chat,
+                "text": text,
+                "date": 0,
+            }))
+        }
+        "getUpdates" => {
+            let offset: i64 = param("offset").parse().unwrap_or_default();
+            let timeout: u64 = param("timeout").parse().unwrap_or_default();
+
+            let pending_updates = || -> Vec<Value> {
+                lock(state)
+                    .updates
+                    .iter()
+                    .filter(|update| update["update_id"].as_i64().unwrap_or_default() >= offset)
+                    .cloned()
+                    .collect()
+            };
+
+            // Like telegram, wait up to timeout seconds for an update to arrive
+            let notified = new_update.notified();
+            let updates = pending_updates();
+            if !updates.is_empty() || timeout == 0 {
+                return Ok(json!(updates));
+            }
+            let _ = tokio::time::timeout(Duration::from_secs(timeout), notified).await;
+            Ok(json!(pending_updates()))
+        }
+        "setWebhook" | "deleteWebhook" => Ok(json!(true)),
+        _ => Err(MockError {
+            error_code: 404,
+            description: "Not Found".to_string(),
+            parameters: None,
+        }),
+    }
+}
+
+/// Reads a request to /bot<token>/<method>, with the parameters from the query string and the form or multipart body
+async fn read_request(socket: &mut TcpStream) -> Option<MockRequest> {
+    let mut data = Vec::new();
+    let mut buffer = [0; 8192];
+
+    let header_end = loop {
+        if let Some(position) = find(&data, b"\r\n\r\n") {
+            break position + 4;
+        }
+        let read = socket.read(&mut buffer).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buffer[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.lines();
+    let path = lines.next()?.split_whitespace().nth(1)?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let mut body = data[header_end..].to_vec();
+    let is_chunked = headers
+        .get("transfer-encoding")
+        .map(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false);
+    if is_chunked {
+        while find(&body, b"0\r\n\r\n").is_none() {
+            let read = socket.read(&mut buffer).await.ok()?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&buffer[..read]);
+        }
+        body = decode_chunked(&body);
+    } else {
+        let content_length: usize = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or_default();
+        while body.len() < content_length {
+            let read = socket.read(&mut buffer).await.ok()?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    let (path, query) = path.split_once('?').unwrap_or((path.as_str(), ""));
+    let method = path.rsplit('/').next()?.to_string();
+
+    let mut params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let content_type = headers.get("content-type").cloned().unwrap_or_default();
+    match content_type.split_once("boundary=") {
+        Some((_, boundary)) => params.extend(parse_multipart(&body, boundary)),
+        None => params.extend(url::form_urlencoded::parse(&body).into_owned()),
+    }
+
+    Some(MockRequest { method, params })
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len())
+        .position(|window| window == pattern)
+}
+
+fn decode_chunked(body: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    while let Some(line_end) = find(rest, b"\r\n") {
+        let size = usize::from_str_radix(String::from_utf8_lossy(&rest[..line_end]).trim(), 16)
+            .unwrap_or_default();
+        if size == 0 {
+            break;
+        }
+        let start = line_end + 2;
+        let end = (start + size).min(rest.len());
+        decoded.extend_from_slice(&rest[start..end]);
+        rest = &rest[(end + 2).min(rest.len())..];
+    }
+    decoded
+}
+
+/// Text fields are returned with their value, and file fields with their filename
+fn parse_multipart(body: &[u8], boundary: &str) -> HashMap<String, String> {
+    let body = String::from_utf8_lossy(body);
+    let delimiter = format!("--{}", boundary.trim_matches('"'));
+
+    body.split(delimiter.as_str())
+        .filter_map(|part| {
+            let (headers, value) = part.split_once("\r\n\r\n")?;
+            let disposition = headers
+                .lines()
+                .find(|line| line.to_ascii_lowercase().starts_with("content-disposition"))?;
+            let attribute = |attribute: &str| {
+                disposition
+                    .split(';')
+                    .filter_map(|item| item.trim().strip_prefix(attribute))
+                    .map(|value| value.trim_matches('"').to_string())
+                    .next()
+            };
+            let name = attribute("name=")?;
+            let value = attribute("filename=")
+                .unwrap_or_else(|| value.trim_end_matches("\r\n").to_string());
+            Some((name, value))
+        })
+        .collect()
+}
@@ -215,6 +215,286 @@ pub fn cmark_to_telegram_v2(common_markdown: &str) -> String {
         .to_string();
 }
 
+/// Telegram won't send messages longer than this, in UTF-16 code units
+pub const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Part of a message that fits in a single telegram message
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessagePart {
+    /// The common markdown this part was rendered from, so it can be rendered again as HTML or plain text
+    pub common_markdown: String,
+    pub markdown_v2: String,
+}
+
+/*
+   Splits common markdown into parts that are each at most max_length once converted to telegram MarkdownV2.
+   Parts are split between top level blocks (paragraphs, lists, code blocks etc), and each part is converted on its own,
+   so bold, links and code blocks are always closed within the part they were opened in.
+   A block that is too long by itself is split between lines, with code blocks re-opened in the next part,
+   and a line that is still too long is split between characters.
+*/
+pub fn split_common_markdown(common_markdown: &str, max_length: usize) -> Vec<MessagePart> {
+    let markdown_v2 = cmark_to_telegram_v2(common_markdown);
+    if fits(&markdown_v2, max_length) {
+        return vec![MessagePart {
+            common_markdown: common_markdown.to_string(),
+            markdown_v2,
+        }];
+    }
+
+    let mut parts = Vec::new();
+    let mut current: Option<MessagePart> = None;
+
+    for block in top_level_blocks(common_markdown) {
+        if let Some(part) = &current {
+            let candidate = format!("{}\n\n{}", part.common_markdown, block);
+            let markdown_v2 = cmark_to_telegram_v2(&candidate);
+            if fits(&markdown_v2, max_length) {
+                current = Some(MessagePart {
+                    common_markdown: candidate,
+                    markdown_v2,
+                });
+                continue;
+            }
+            parts.extend(current.take());
+        }
+
+        let markdown_v2 = cmark_to_telegram_v2(block);
+        if fits(&markdown_v2, max_length) {
+            current = Some(MessagePart {
+                common_markdown: block.to_string(),
+                markdown_v2,
+            });
+        } else {
+            parts.extend(split_block(block, max_length));
+        }
+    }
+    parts.extend(current);
+
+    parts
+}
+
+fn fits(text: &str, max_length: usize) -> bool {
+    text.encode_utf16().count() <= max_length
+}
+
+/// Telegram measures text in UTF-16 code units, so an emoji can count as two
+pub fn truncate_utf16(text: &str, max_length: usize) -> String {
+    let mut length = 0;
+    text.chars()
+        .take_while(|c| {
+            length += c.len_utf16();
+            length <= max_length
+        })
+        .collect()
+}
+
+// The source of each block that isn't inside another block, e.g. each paragraph, heading or list
+fn top_level_blocks(common_markdown: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut depth = 0;
+    for (event, range) in Parser::new(common_markdown).into_offset_iter() {
+        match event {
+            Event::Start(_) => {
+                if depth == 0 {
+                    blocks.push(&common_markdown[range]);
+                }
+                depth += 1;
+            }
+            Event::End(_) => depth -= 1,
+            _ if depth == 0 => blocks.push(&common_markdown[range]),
+            _ => {}
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|block| block.trim_end())
+        .filter(|block| !block.is_empty())
+        .collect()
+}
+
+// Splits a block that doesn't fit in a message between lines
+fn split_block(block: &str, max_length: usize) -> Vec<MessagePart> {
+    // Each part of a fenced code block needs its own fences, the lines are the code inside them
+    let mut code_fence = None;
+    let mut code = String::new();
+    for event in Parser::new(block) {
+        match event {
+            Event::Start(pulldown_cmark::Tag::CodeBlock(
+                pulldown_cmark::CodeBlockKind::Fenced(language),
+            )) => code_fence = Some(format!("```{}", language)),
+            Event::Text(text) if code_fence.is_some() => code.push_str(&text),
+            _ => {}
+        }
+    }
+    let lines = match &code_fence {
+        Some(_) => code.trim_end_matches('\n'),
+        None => block,
+    };
+    let wrap = |lines: &str| match &code_fence {
+        Some(fence) => format!("{}\n{}\n```", fence, lines),
+        None => lines.to_string(),
+    };
+    let to_part = |lines: &str| {
+        let common_markdown = wrap(lines);
+        MessagePart {
+            markdown_v2: cmark_to_telegram_v2(&common_markdown),
+            common_markdown,
+        }
+    };
+
+    let mut parts = Vec::new();
+    let mut current: Option<(String, MessagePart)> = None;
+    for line in lines.lines() {
+        if let Some((current_lines, _)) = &current {
+            let candidate_lines = format!("{}\n{}", current_lines, line);
+            let candidate = to_part(&candidate_lines);
+            if fits(&candidate.markdown_v2, max_length) {
+                current = Some((candidate_lines, candidate));
+                continue;
+            }
+            parts.extend(current.take().map(|(_, part)| part));
+        }
+
+        let part = to_part(line);
+        if fits(&part.markdown_v2, max_length) {
+            current = Some((line.to_string(), part));
+            continue;
+        }
+
+        // Escaping at most doubles the length, so start with half a message's worth of characters
+        let characters: Vec<char> = line.chars().collect();
+        let mut start = 0;
+        while start < characters.len() {
+            let mut length = (max_length / 2).max(1).min(characters.len() - start);
+            loop {
+                let piece: String = characters[start..start + length].iter().collect();
+                let part = to_part(&piece);
+                if fits(&part.markdown_v2, max_length) || length == 1 {
+                    parts.push(part);
+                    break;
+                }
+                length = length * 3 / 4;
+            }
+            start += length;
+        }
+    }
+    parts.extend(current.map(|(_, part)| part));
+
+    parts
+}
+
+/*
+   Converts common markdown to telegram HTML, used if telegram can't parse the MarkdownV2 version of a message.
+   Telegram only supports a few tags, so headings are bold and underlined and lists are written out as text.
+   https://core.telegram.org/bots/api#html-style
+*/
+pub fn cmark_to_telegram_html(common_markdown: &str) -> String {
+    let mut telegram_html = String::new();
+    let mut list_number: Option<u64> = None;
+
+    for event in Parser::new(common_markdown) {
+        match event {
+            Event::Text(text) => telegram_html.push_str(&escape_telegram_html(&text)),
+            Event::Code(text) => {
+                telegram_html.push_str("<code>");
+                telegram_html.push_str(&escape_telegram_html(&text));
+                telegram_html.push_str("</code>");
+            }
+            Event::Html(text) => {
+                telegram_html.push_str("<pre>");
+                telegram_html.push_str(&escape_telegram_html(&text));
+                telegram_html.push_str("</pre>");
+            }
+            Event::FootnoteReference(text) => telegram_html.push_str(&escape_telegram_html(&text)),
+            Event::SoftBreak => telegram_html.push('\n'),
+            Event::HardBreak => telegram_html.push_str("\n\n"),
+            Event::Rule => telegram_html.push_str("\n-----\n"),
+            Event::End(pulldown_cmark::Tag::Paragraph) => telegram_html.push_str("\n\n"),
+            Event::Start(pulldown_cmark::Tag::Emphasis) => telegram_html.push_str("<i>"),
+            Event::End(pulldown_cmark::Tag::Emphasis) => telegram_html.push_str("</i>"),
+            Event::Start(pulldown_cmark::Tag::Strong) => telegram_html.push_str("<b>"),
+            Event::End(pulldown_cmark::Tag::Strong) => telegram_html.push_str("</b>"),
+            Event::Start(pulldown_cmark::Tag::Strikethrough) => telegram_html.push_str("<s>"),
+            Event::End(pulldown_cmark::Tag::Strikethrough) => telegram_html.push_str("</s>"),
+            Event::Start(pulldown_cmark::Tag::CodeBlock(_)) => telegram_html.push_str("<pre>"),
+            Event::End(pulldown_cmark::Tag::CodeBlock(_)) => telegram_html.push_str("</pre>"),
+            Event::Start(pulldown_cmark::Tag::Link(_link_type, url, _title)) => {
+                telegram_html.push_str("<a href=\"");
+                telegram_html.push_str(&escape_telegram_html(&url).replace('"', "&quot;"));
+                telegram_html.push_str("\">");
+            }
+            Event::End(pulldown_cmark::Tag::Link(..)) => telegram_html.push_str("</a>"),
+            Event::Start(pulldown_cmark::Tag::Heading(..)) => telegram_html.push_str("<b><u>"),
+            Event::End(pulldown_cmark::Tag::Heading(..)) => telegram_html.push_str("</u></b>\n"),
+            Event::Start(pulldown_cmark::Tag::BlockQuote) => telegram_html.push_str("<blockquote>"),
+            Event::End(pulldown_cmark::Tag::BlockQuote) => {
+                telegram_html.push_str("</blockquote>\n")
+            }
+            Event::Start(pulldown_cmark::Tag::List(number)) => list_number = number,
+            Event::End(pulldown_cmark::Tag::List(_)) => {
+                list_number = None;
+                telegram_html.push('\n');
+            }
+            Event::Start(pulldown_cmark::Tag::Item) => match list_number {
+                Some(number) => {
+                    telegram_html.push_str(&format!("{}. ", number));
+                    list_number = Some(number + 1);
+                }
+                None => telegram_html.push_str("- "),
+            },
+            Event::End(pulldown_cmark::Tag::Item) => telegram_html.push('\n'),
+            _ => {}
+        }
+    }
+
+    telegram_html
+        .strip_suffix("\n\n")
+        .unwrap_or(&telegram_html)
+        .to_string()
+}
+
+// Only <, > and & need to be escaped in telegram HTML
+fn escape_telegram_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Converts common markdown to text without any formatting, as a last resort if telegram can't parse a message
+pub fn cmark_to_plain_text(common_markdown: &str) -> String {
+    let mut text = String::new();
+    let mut list_number: Option<u64> = None;
+
+    for event in Parser::new(common_markdown) {
+        match event {
+            Event::Text(value) | Event::Code(value) | Event::Html(value) => text.push_str(&value),
+            Event::SoftBreak => text.push('\n'),
+            Event::HardBreak => text.push_str("\n\n"),
+            Event::Rule => text.push_str("\n-----\n"),
+            Event::End(pulldown_cmark::Tag::Paragraph) => text.push_str("\n\n"),
+            Event::End(pulldown_cmark::Tag::Heading(..)) => text.push('\n'),
+            Event::End(pulldown_cmark::Tag::CodeBlock(_)) => text.push('\n'),
+            Event::Start(pulldown_cmark::Tag::List(number)) => list_number = number,
+            Event::End(pulldown_cmark::Tag::List(_)) => {
+                list_number = None;
+                text.push('\n');
+            }
+            Event::Start(pulldown_cmark::Tag::Item) => match list_number {
+                Some(number) => {
+                    text.push_str(&format!("{}. ", number));
+                    list_number = Some(number + 1);
+                }
+                None => text.push_str("- "),
+            },
+            Event::End(pulldown_cmark::Tag::Item) => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    text.trim_end().to_string()
+}
+
 // In all other places characters '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!' must be escaped with the preceding character '\'.
 // https://core.telegram.org/bots/api#markdownv2-style
 fn escape_telegram_markdown(text: &str) -> String {
@@ -417,4 +697,139 @@ Here's another paragraph.
         let escaped_text = escape_telegram_markdown(text);
         assert_eq!(escaped_text, expected);
     }
+    // Counts the formatting characters that haven't been escaped
+    fn unescaped_count(markdown_v2: &str, character: char) -> usize {
+        let mut count = 0;
+        let mut escaped = false;
+        for c in markdown_v2.chars() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == character {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_split_short_message() {
+        let cmarkdown = "**Sensor is now ok!**\n\nLocation: location_name";
+        let parts = split_common_markdown(cmarkdown, MAX_MESSAGE_LENGTH);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].markdown_v2, cmark_to_telegram_v2(cmarkdown));
+        assert_eq!(parts[0].common_markdown, cmarkdown);
+    }
+
+    #[test]
+    fn test_split_between_blocks() {
+        let cmarkdown = (1..=200)
+            .map(|i| {
+                format!(
+                    "**Sensor {}**: _5.1°C_ at [store](http://example.com/{})",
+                    i, i
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        let parts = split_common_markdown(&cmarkdown, MAX_MESSAGE_LENGTH);
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.markdown_v2.encode_utf16().count() <= MAX_MESSAGE_LENGTH);
+            assert_eq!(unescaped_count(&part.markdown_v2, '*') % 2, 0);
+            assert_eq!(unescaped_count(&part.markdown_v2, '_') % 2, 0);
+            assert_eq!(
+                unescaped_count(&part.markdown_v2, '['),
+                unescaped_count(&part.markdown_v2, ']')
+            );
+        }
+        // Nothing is lost, and paragraphs aren't split
+        assert!(parts[0].markdown_v2.starts_with("*Sensor 1*"));
+        assert!(parts.last().unwrap().markdown_v2.contains("*Sensor 200*"));
+        assert_eq!(
+            parts
+                .iter()
+                .map(|part| unescaped_count(&part.markdown_v2, '*'))
+                .sum::<usize>(),
+            400
+        );
+    }
+
+    #[test]
+    fn test_split_long_paragraph_and_code_block() {
+        // A report template usually renders a row per line, all in one paragraph
+        let rows = (1..=300)
+            .map(|i| format!("**Row {}**: value-{}", i, i))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let parts = split_common_markdown(&rows, MAX_MESSAGE_LENGTH);
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.markdown_v2.encode_utf16().count() <= MAX_MESSAGE_LENGTH);
+            assert_eq!(unescaped_count(&part.markdown_v2, '*') % 2, 0);
+        }
+        assert!(parts.last().unwrap().markdown_v2.ends_with("value\\-300"));
+
+        let code = format!("```sql\n{}\n```", "SELECT * FROM sensor;\n".repeat(300));
+        let parts = split_common_markdown(&code, MAX_MESSAGE_LENGTH);
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.markdown_v2.encode_utf16().count() <= MAX_MESSAGE_LENGTH);
+            assert!(part.markdown_v2.starts_with("```sql\nSELECT"));
+            assert!(part.markdown_v2.ends_with("```"));
+        }
+    }
+
+    #[test]
+    fn test_split_long_line() {
+        let cmarkdown = "a.".repeat(5000);
+        let parts = split_common_markdown(&cmarkdown, MAX_MESSAGE_LENGTH);
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.markdown_v2.encode_utf16().count() <= MAX_MESSAGE_LENGTH);
+            assert!(!part.markdown_v2.ends_with('\\'));
+        }
+        assert_eq!(
+            parts
+                .iter()
+                .map(|part| part.common_markdown.len())
+                .sum::<usize>(),
+            cmarkdown.len()
+        );
+    }
+
+    #[test]
+    fn test_cmark_to_telegram_html() {
+        let cmarkdown = r#"# Alert
+
+**Sensor** is _too hot_ <5 & rising, see [details](http://example.com/?a=1&b="2")
+- `code`
+"#; // Don't indent this!
+        let expected = "<b><u>Alert</u></b>\n<b>Sensor</b> is <i>too hot</i> &lt;5 &amp; rising, see <a href=\"http://example.com/?a=1&amp;b=&quot;2&quot;\">details</a>\n\n- <code>code</code>";
+        let result = cmark_to_telegram_html(cmarkdown);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_cmark_to_plain_text() {
+        let cmarkdown = r#"# Alert
+
+**Sensor** is _too hot_, see [details](http://example.com/)
+1. `code`
+"#; // Don't indent this!
+        let expected = "Alert\nSensor is too hot, see details\n\n1. code";
+        let result = cmark_to_plain_text(cmarkdown);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_truncate_utf16() {
+        assert_eq!(truncate_utf16("Sensor", 10), "Sensor");
+        assert_eq!(truncate_utf16("Sensor", 3), "Sen");
+        // 🔥 is two UTF-16 code units, it's left out rather than split
+        assert_eq!(truncate_utf16("Hot🔥", 4), "Hot");
+        assert_eq!(truncate_utf16("Hot🔥", 5), "Hot🔥");
+        assert_eq!(truncate_utf16("°C", 1), "°");
+    }
 }
@@ -1,43 +1,87 @@
+use std::sync::Arc;
+
 use tokio::sync::broadcast::Sender;
 
-use crate::TelegramClient;
+use crate::{TelegramClient, TelegramError};
 
 use self::processor::poll_get_updates;
+pub use self::webhook::{TelegramWebhook, TelegramWebhookError, SECRET_TOKEN_HEADER};
 
 pub mod markdown;
 mod processor;
 mod responder;
+mod webhook;
+
+/// Remembers the id of the last update handled, so updates aren't handled twice after a restart
+pub trait TelegramUpdateIdStore: Send + Sync {
+    fn last_update_id(&self) -> Option<i64>;
+    fn set_last_update_id(&self, update_id: i64);
+}
 
 pub struct TelegramService {
     pub client: TelegramClient,
     pub app_url: String,
     pub updates_channel: Sender<crate::TelegramUpdate>,
+    pub update_id_store: Arc<dyn TelegramUpdateIdStore>,
 }
 
 impl TelegramService {
-    pub fn new(client: TelegramClient, app_url: String) -> Self {
+    pub fn new(
+        client: TelegramClient,
+        app_url: String,
+        update_id_store: Arc<dyn TelegramUpdateIdStore>,
+    ) -> Self {
         let (tx, _) = tokio::sync::broadcast::channel(10);
         TelegramService {
             client,
             app_url,
             updates_channel: tx, // we only need the transmission side because consumers just subscribe via this
+            update_id_store,
         }
     }
 
-    pub async fn init(self) -> Sender<crate::TelegramUpdate> {
-        // let app_url = self.app_url.clone();
+    fn spawn_responder(&self) {
         let responder_channel = self.updates_channel.clone();
         let responder_client = self.client.clone();
         tokio::spawn(async move {
             responder::handle_telegram_updates(responder_client, responder_channel).await;
         });
+    }
+
+    /// Starts polling telegram for updates
+    pub async fn init(self) -> Sender<crate::TelegramUpdate> {
+        // getUpdates doesn't work while a webhook is set, e.g. if the server was previously in webhook mode
+        if let Err(e) = self.client.delete_webhook().await {
+            log::error!("Failed to delete telegram webhook: {:?}", e);
+        }
+
+        self.spawn_responder();
 
         let sender_channel = self.updates_channel.clone();
         let sender_client = self.client.clone();
+        let update_id_store = self.update_id_store.clone();
         tokio::spawn(async move {
-            poll_get_updates(&sender_client, &sender_channel).await;
+            poll_get_updates(&sender_client, &sender_channel, update_id_store.as_ref()).await;
         });
         // Return the channel so consumers can subscribe
         self.updates_channel
     }
+
+    /// Registers `url` as the bot's webhook, updates are then received by the returned TelegramWebhook rather than polled
+    pub async fn init_webhook(
+        self,
+        url: &str,
+        secret_token: &str,
+    ) -> Result<(Sender<crate::TelegramUpdate>, TelegramWebhook), TelegramError> {
+        self.client.set_webhook(url, secret_token).await?;
+
+        self.spawn_responder();
+
+        let webhook = TelegramWebhook::new(
+            secret_token.to_string(),
+            self.updates_channel.clone(),
+            self.update_id_store.clone(),
+        );
+        Ok((self.updates_channel, webhook))
+    }
 }
@@ -190,15 +190,18 @@ https://core.telegram.org/bots/api#getupdates
 use crate::{TelegramClient, TelegramUpdate, TelegramUpdateOrId};
 use log::{self};
 
+use super::TelegramUpdateIdStore;
+
 static TELEGRAM_POLL_TIMEOUT_SECONDS: i64 = 30;
 static ERROR_SLEEP_SECONDS: u64 = 10;
 
 pub async fn poll_get_updates(
     telegram_client: &TelegramClient,
     tx_updates: &tokio::sync::broadcast::Sender<TelegramUpdate>,
+    update_id_store: &dyn TelegramUpdateIdStore,
 ) {
-    // BTW : Might be a good idea to persist last_update_id to the KV store so we can pickup from where we left off on a restart?
-    let mut last_update_id = None;
+    // Pick up from where we left off before a restart
+    let mut last_update_id = update_id_store.last_update_id();
 
     loop {
         let updates = telegram_client
@@ -210,6 +213,9 @@ pub async fn poll_get_updates(
                 log::debug!("Got {} updates", num);
                 if num > 0 {
                     last_update_id = handle_json_updates(updates, tx_updates).await;
+                    if let Some(update_id) = last_update_id {
+                        update_id_store.set_last_update_id(update_id);
+                    }
                 }
             }
             Err(error) => {
@@ -221,7 +227,7 @@ pub async fn poll_get_updates(
     }
 }
 
-async fn handle_json_updates(
+pub(super) async fn handle_json_updates(
     updates: Vec<serde_json::Value>,
     tx_updates: &tokio::sync::broadcast::Sender<TelegramUpdate>,
 ) -> Option<i64> {
@@ -1,8 +1,7 @@
-// The responder should reply to the following...
+// The responder says hello when the bot is added to a chat.
 //
-// Direct messages to the bot
-// Messages to the bot with /hello or /chatid
-// Messages to the bot with /help
+// Commands sent to the bot (/hello, /help, /status etc.) need the database to respond,
+// so they're handled by the service crate, see `service::telegram_commands`
 
 use crate::{TelegramClient, TelegramUpdate};
 
@@ -25,36 +24,6 @@ pub async fn handle_telegram_updates(
             }
         };
 
-        // Respond to messages
-        match update.message {
-            Some(message) => {
-                log::debug!("Received Telegram Message Ready to respond: {:?}", message);
-                let chat_id = message.chat.id.to_string();
-                let text = message.text.unwrap_or_default();
-
-                if text.contains("/hello") || text.contains("/chat") || text.contains("/start") {
-                    //TODO: Render template?
-                    let response = format!("Hello! This chat_id is {}", chat_id);
-                    match client.send_html_message(&chat_id, &response).await {
-                        Ok(_) => log::debug!("Sent hello/chat message to chat id {}", chat_id),
-                        Err(_) => {
-                            log::error!("Failed to send hello message to chat id {}", chat_id)
-                        }
-                    }
-                } else if text == "/help" {
-                    //TODO: Render template?
-                    let response = format!("I can respond to the following commands:\n/hello - I will say hello to you\n/chatid - I will tell you your chat id\n/help - I will show you this help message");
-                    match client.send_html_message(&chat_id, &response).await {
-                        Ok(_) => log::debug!("Sent help message to chat id {}", chat_id),
-                        Err(_) => log::error!("Failed to send help message to chat id {}", chat_id),
-                    }
-                }
-            }
-            None => {
-                log::debug!("No message received!: {:?}", update);
-            }
-        };
-
         // Respond to my chat member updates
         if let Some(chat_member) = update.my_chat_member {
             log::debug!(
@@ -0,0 +1,194 @@
+/*
+   In webhook mode telegram POSTs each update to the server, rather than the server polling /getUpdates.
+   https://core.telegram.org/bots/api#setwebhook
+
+   The request body is a single update, in the same format as the items in the getUpdates result.
+   Telegram includes the secret_token given to setWebhook in the X-Telegram-Bot-Api-Secret-Token header,
+   requests without it didn't come from telegram so they're rejected.
+
+   The webhook is set with max_connections=1, so telegram waits for each update to be handled before sending the next one,
+   and an update with an id at or below the last handled id is one telegram is re-sending.
+*/
+
+use std::sync::Arc;
+
+use subtle::ConstantTimeEq;
+use tokio::sync::broadcast::Sender;
+
+use crate::{TelegramUpdate, TelegramUpdateJustId};
+
+use super::{processor::handle_json_updates, TelegramUpdateIdStore};
+
+pub const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+#[derive(Debug, PartialEq)]
+pub enum TelegramWebhookError {
+    InvalidSecretToken,
+    InvalidUpdate(String),
+}
+
+#[derive(Clone)]
+pub struct TelegramWebhook {
+    secret_token: String,
+    updates_channel: Sender<TelegramUpdate>,
+    update_id_store: Arc<dyn TelegramUpdateIdStore>,
+}
+
+impl TelegramWebhook {
+    pub fn new(
+        secret_token: String,
+        updates_channel: Sender<TelegramUpdate>,
+        update_id_store: Arc<dyn TelegramUpdateIdStore>,
+    ) -> Self {
+        TelegramWebhook {
+            secret_token,
+            updates_channel,
+            update_id_store,
+        }
+    }
+
+    /// Publishes an update received by the webhook to the updates channel, like a polled update.
+    /// Telegram re-sends updates it doesn't get a response for, updates we've already handled are ignored.
+    pub async fn handle_update(
+        &self,
+        secret_token: Option<&str>,
+        update: serde_json::Value,
+    ) -> Result<(), TelegramWebhookError> {
+        // Compared in constant time so the token can't be guessed from how long rejections take
+        let valid_secret_token = secret_token
+            .map(|secret_token| {
+                bool::from(secret_token.as_bytes().ct_eq(self.secret_token.as_bytes()))
+            })
+            .unwrap_or(false);
+        if !valid_secret_token {
+            return Err(TelegramWebhookError::InvalidSecretToken);
+        }
+
+        let update_id = serde_json::from_value::<TelegramUpdateJustId>(update.clone())
+            .map_err(|e| TelegramWebhookError::InvalidUpdate(e.to_string()))?
+            .update_id;
+
+        if let Some(last_update_id) = self.update_id_store.last_update_id() {
+            if update_id <= last_update_id {
+                log::debug!(
+                    "Ignoring telegram update {} as it's already handled",
+                    update_id
+                );
+                return Ok(());
+            }
+        }
+
+        if let Some(update_id) = handle_json_updates(vec![update], &self.updates_channel).await {
+            self.update_id_store.set_last_update_id(update_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestUpdateIdStore {
+        last_update_id: Mutex<Option<i64>>,
+    }
+
+    impl TelegramUpdateIdStore for TestUpdateIdStore {
+        fn last_update_id(&self) -> Option<i64> {
+            *self.last_update_id.lock().unwrap()
+        }
+
+        fn set_last_update_id(&self, update_id: i64) {
+            *self.last_update_id.lock().unwrap() = Some(update_id);
+        }
+    }
+
+    // Recorded from a direct message to the bot
+    const PRIVATE_MESSAGE_UPDATE: &str = r#"
+    {
+        "update_id": 794348052,
+        "message": {
+            "message_id": 33,
+            "from": {
+                "id": 5068627745,
+                "is_bot": false,
+                "first_name": "User1",
+                "last_name": "Last1",
+                "language_code": "en"
+            },
+            "chat": {
+                "id": 5068627745,
+                "first_name": "User1",
+                "last_name": "Last1",
+                "type": "private"
+            },
+            "date": 1691536034,
+            "text": "/chatid",
+            "entities": [{"offset": 0, "length": 7, "type": "bot_command"}]
+        }
+    }"#;
+
+    fn webhook() -> (TelegramWebhook, Arc<TestUpdateIdStore>) {
+        let (tx, _) = tokio::sync::broadcast::channel(8);
+        let store = Arc::new(TestUpdateIdStore::default());
+        (
+            TelegramWebhook::new("secret".to_string(), tx, store.clone()),
+            store,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_webhook_update() {
+        let (webhook, store) = webhook();
+        let mut rx = webhook.updates_channel.subscribe();
+        let update: serde_json::Value = serde_json::from_str(PRIVATE_MESSAGE_UPDATE).unwrap();
+
+        webhook
+            .handle_update(Some("secret"), update.clone())
+            .await
+            .unwrap();
+
+        let received = rx.try_recv().unwrap();
+        assert_eq!(received.update_id, 794348052);
+        assert_eq!(received.chat().unwrap().id, 5068627745);
+        assert_eq!(store.last_update_id(), Some(794348052));
+
+        // Telegram re-sending the same update doesn't publish it again
+        webhook.handle_update(Some("secret"), update).await.unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_invalid_secret_token() {
+        let (webhook, store) = webhook();
+        let mut rx = webhook.updates_channel.subscribe();
+        let update: serde_json::Value = serde_json::from_str(PRIVATE_MESSAGE_UPDATE).unwrap();
+
+        assert_eq!(
+            webhook.handle_update(None, update.clone()).await,
+            Err(TelegramWebhookError::InvalidSecretToken)
+        );
+        assert_eq!(
+            webhook.handle_update(Some("wrong"), update).await,
+            Err(TelegramWebhookError::InvalidSecretToken)
+        );
+        assert!(rx.try_recv().is_err());
+        assert_eq!(store.last_update_id(), None);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_invalid_update() {
+        let (webhook, _) = webhook();
+
+        let result = webhook
+            .handle_update(Some("secret"), serde_json::json!({"message": {}}))
+            .await;
+        assert!(matches!(
+            result,
+            Err(TelegramWebhookError::InvalidUpdate(_))
+        ));
+    }
+}